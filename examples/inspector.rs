@@ -0,0 +1,50 @@
+extern crate abxml;
+extern crate failure;
+extern crate zip;
+
+use std::{env, fs, io::Read};
+
+use failure::{Error, ResultExt};
+
+use abxml::inspector::Inspection;
+
+fn main() {
+    if let Err(ref e) = run() {
+        println!("error: {}", e);
+
+        for e in e.iter_causes() {
+            println!("caused by: {}", e);
+        }
+
+        ::std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            println!("Usage: inspector <file> [<entry on the APK>]");
+            return Ok(());
+        }
+    };
+
+    let content = match env::args().nth(2) {
+        Some(entry) => {
+            let file = fs::File::open(&path).context("could not open the APK")?;
+            let mut archive = zip::ZipArchive::new(file).context("could not read the APK")?;
+            let mut content = Vec::new();
+            archive
+                .by_name(&entry)
+                .context(format!("could not find {} on the APK", entry))?
+                .read_to_end(&mut content)?;
+
+            content
+        }
+        None => fs::read(&path).context("could not read the file")?,
+    };
+
+    print!("{}", Inspection::new(&content));
+
+    Ok(())
+}
@@ -0,0 +1,765 @@
+//! Detection of the quirks used by manipulated files to break analysis tools while they are still
+//! accepted by Android, like misleading chunk sizes, string pools that overlap their header, fake
+//! namespaces or trailing data
+
+use std::{collections::HashSet, fmt, io::Cursor};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::{
+    chunks::{
+        skip_document_header, token_name, Chunk, ChunkHeader, ChunkLoaderStream,
+        StringTableWrapper, XmlNamespaceStartWrapper, XmlTagStartWrapper, TOKEN_PACKAGE,
+        TOKEN_RESOURCE, TOKEN_STRING_TABLE, TOKEN_TABLE, TOKEN_TABLE_SPEC, TOKEN_XML,
+        TOKEN_XML_END_NAMESPACE, TOKEN_XML_START_NAMESPACE, TOKEN_XML_TAG_END, TOKEN_XML_TAG_START,
+        TOKEN_XML_TEXT,
+    },
+    errors::{locate, ChunkContext, Location},
+    model::{AttributeTrait, NamespaceStart, StringTable, TagStart, ANDROID_NAMESPACE},
+};
+
+const NO_INDEX: u32 = 0xFFFF_FFFF;
+
+/// How suspicious an anomaly is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Unusual, but produced by some legitimate tools
+    Info,
+    /// Accepted by Android, but unlikely to be produced by the build tools
+    Warning,
+    /// Meant to break the tools that read the file, or rejected by Android
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+        };
+
+        formatter.write_str(name)
+    }
+}
+
+/// Kind of quirk found on a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnomalyKind {
+    /// The size of a chunk or of its header does not match its type or its parent
+    ChunkSize,
+    /// There are bytes after the end of the document
+    TrailingData,
+    /// The offsets of a string pool point into its header or outside of the chunk
+    StringPoolOffset,
+    /// The name of an attribute is not a valid XML name or it can not be read
+    AttributeName,
+    /// A namespace is used without being declared, or it impersonates the Android one
+    Namespace,
+    /// A chunk has a type that is not known or not expected on the document
+    UnknownChunk,
+    /// A chunk can not be read, so the rest of the document can not be analyzed
+    UnreadableChunk,
+    /// A file of the APK is empty, duplicated or can not be read
+    ApkEntry,
+}
+
+impl fmt::Display for AnomalyKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::ChunkSize => "chunk size",
+            Self::TrailingData => "trailing data",
+            Self::StringPoolOffset => "string pool offset",
+            Self::AttributeName => "attribute name",
+            Self::Namespace => "namespace",
+            Self::UnknownChunk => "unknown chunk",
+            Self::UnreadableChunk => "unreadable chunk",
+            Self::ApkEntry => "APK entry",
+        };
+
+        formatter.write_str(name)
+    }
+}
+
+/// Quirk found on a file, with the location of the bytes that contain it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anomaly {
+    kind: AnomalyKind,
+    severity: Severity,
+    entry: Option<String>,
+    location: Location,
+    description: String,
+}
+
+impl Anomaly {
+    #[must_use]
+    pub fn new(
+        kind: AnomalyKind,
+        severity: Severity,
+        location: Location,
+        description: String,
+    ) -> Self {
+        Self {
+            kind,
+            severity,
+            entry: None,
+            location,
+            description,
+        }
+    }
+
+    /// Marks the anomaly as found on the given file of an APK
+    #[must_use]
+    pub fn with_entry(mut self, entry: &str) -> Self {
+        self.entry = Some(entry.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn get_kind(&self) -> AnomalyKind {
+        self.kind
+    }
+
+    #[must_use]
+    pub fn get_severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Name of the file of the APK that contains the anomaly, if it was found on an APK
+    #[must_use]
+    pub fn get_entry(&self) -> Option<&str> {
+        self.entry.as_deref()
+    }
+
+    #[must_use]
+    pub fn get_location(&self) -> &Location {
+        &self.location
+    }
+
+    #[must_use]
+    pub fn get_description(&self) -> &str {
+        &self.description
+    }
+}
+
+/// Formats the anomaly on a single line (`[warning] trailing data on AndroidManifest.xml at 0x..`)
+impl fmt::Display for Anomaly {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "[{}] {}", self.severity, self.kind)?;
+
+        if let Some(ref entry) = self.entry {
+            write!(formatter, " on {entry}")?;
+        }
+
+        write!(formatter, " at {}: {}", self.location, self.description)
+    }
+}
+
+/// Anomalies found on a binary file or on all the files of an APK
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnomalyReport {
+    anomalies: Vec<Anomaly>,
+}
+
+impl AnomalyReport {
+    /// Analyzes a `resources.arsc` or a binary XML. It never fails: a file that can not be
+    /// read is reported as an anomaly.
+    #[must_use]
+    pub fn analyze(buffer: &[u8]) -> Self {
+        let mut analyzer = Analyzer::default();
+        analyzer.analyze(buffer);
+
+        Self {
+            anomalies: analyzer.anomalies,
+        }
+    }
+
+    #[must_use]
+    pub fn get_anomalies(&self) -> &[Anomaly] {
+        &self.anomalies
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+
+    /// Severity of the most suspicious anomaly
+    pub fn get_max_severity(&self) -> Option<Severity> {
+        self.anomalies.iter().map(Anomaly::get_severity).max()
+    }
+
+    pub fn push(&mut self, anomaly: Anomaly) {
+        self.anomalies.push(anomaly);
+    }
+
+    /// Adds the anomalies of a file of an APK, marking them with its name
+    pub fn append_entry(&mut self, entry: &str, report: Self) {
+        for anomaly in report.anomalies {
+            self.anomalies.push(anomaly.with_entry(entry));
+        }
+    }
+}
+
+/// Prints one anomaly per line
+impl fmt::Display for AnomalyReport {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        for anomaly in &self.anomalies {
+            writeln!(formatter, "{anomaly}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Walks the chunks of a document, keeping what is needed to check the next ones
+#[derive(Debug, Default)]
+struct Analyzer {
+    anomalies: Vec<Anomaly>,
+    path: Vec<ChunkContext>,
+    package_end: Option<u64>,
+    namespaces: Vec<String>,
+}
+
+impl Analyzer {
+    fn report(&mut self, kind: AnomalyKind, severity: Severity, offset: u64, description: String) {
+        let location = Location::new(offset, self.path.clone());
+        self.anomalies
+            .push(Anomaly::new(kind, severity, location, description));
+    }
+
+    fn analyze(&mut self, buffer: &[u8]) {
+        if buffer.len() < 8 {
+            self.report(
+                AnomalyKind::UnreadableChunk,
+                Severity::Critical,
+                0,
+                format!("{} bytes are too few for a binary document", buffer.len()),
+            );
+            return;
+        }
+
+        let token = LittleEndian::read_u16(&buffer[0..2]);
+        let header_size = LittleEndian::read_u16(&buffer[2..4]);
+        let chunk_size = u64::from(LittleEndian::read_u32(&buffer[4..8]));
+        let expected_header = match token {
+            TOKEN_XML => 8,
+            TOKEN_TABLE => 12,
+            _ => {
+                self.report(
+                    AnomalyKind::UnknownChunk,
+                    Severity::Critical,
+                    0,
+                    format!(
+                        "the file starts with {} (0x{:04x}) instead of a document",
+                        token_name(token),
+                        token
+                    ),
+                );
+                return;
+            }
+        };
+        self.path.push(ChunkContext::new(token, 0));
+
+        let mut cursor = Cursor::new(buffer);
+        if let Err(e) = skip_document_header(&mut cursor, token) {
+            let error = locate(&e, 0, &self.path);
+            self.report(
+                AnomalyKind::UnreadableChunk,
+                Severity::Critical,
+                error.get_location().get_offset(),
+                error.to_string(),
+            );
+            return;
+        }
+
+        if header_size != expected_header {
+            self.report(
+                AnomalyKind::ChunkSize,
+                Severity::Warning,
+                2,
+                format!(
+                    "the document header has {header_size} bytes, {expected_header} are expected"
+                ),
+            );
+        }
+
+        let length = buffer.len() as u64;
+        if chunk_size < u64::from(header_size) {
+            self.report(
+                AnomalyKind::ChunkSize,
+                Severity::Critical,
+                4,
+                format!(
+                    "the document declares {chunk_size} bytes, fewer than its {header_size} \
+                    bytes header"
+                ),
+            );
+        } else if chunk_size < length {
+            self.report(
+                AnomalyKind::TrailingData,
+                Severity::Warning,
+                chunk_size,
+                format!(
+                    "{} bytes after the end of the document",
+                    length - chunk_size
+                ),
+            );
+        } else if chunk_size > length {
+            self.report(
+                AnomalyKind::ChunkSize,
+                Severity::Critical,
+                4,
+                format!("the document declares {chunk_size} bytes, but the file has {length}"),
+            );
+        }
+
+        let end = chunk_size.max(u64::from(header_size)).min(length) as usize;
+        self.walk(&buffer[..end], u64::from(header_size), token);
+    }
+
+    fn walk(&mut self, buffer: &[u8], position: u64, document: u16) {
+        let mut string_table = None;
+        let mut cursor = Cursor::new(buffer);
+        cursor.set_position(position);
+        let mut stream = ChunkLoaderStream::new(cursor);
+
+        while let Some(chunk) = stream.next() {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    self.path.truncate(1);
+                    let error = locate(&e, 0, &self.path);
+                    self.report(
+                        AnomalyKind::UnreadableChunk,
+                        Severity::Critical,
+                        error.get_location().get_offset(),
+                        error.to_string(),
+                    );
+                    continue;
+                }
+            };
+            let header = match stream.get_header() {
+                Some(header) => *header,
+                None => continue,
+            };
+            let raw = &buffer[header.get_offset() as usize..header.get_chunk_end() as usize];
+
+            self.enter(&header, document);
+            self.check_header_size(&header);
+
+            match chunk {
+                Chunk::Package(_) => {
+                    self.package_end = Some(header.get_chunk_end());
+                    continue;
+                }
+                Chunk::StringTable(stw) => {
+                    self.check_string_pool(raw, &header);
+                    if document == TOKEN_XML && string_table.is_none() {
+                        string_table = Some(stw);
+                    }
+                }
+                Chunk::XmlNamespaceStart(xnsw) => {
+                    if let Some(ref strings) = string_table {
+                        self.check_namespace_start(&xnsw, strings, &header);
+                    }
+                }
+                Chunk::XmlNamespaceEnd(_) => self.check_namespace_end(&header),
+                Chunk::XmlTagStart(xtsw) => {
+                    if let Some(ref strings) = string_table {
+                        self.check_tag_start(&xtsw, strings, &header);
+                    }
+                }
+                _ => (),
+            }
+
+            self.path.pop();
+        }
+    }
+
+    /// Updates the path with the chunk and checks that it belongs to the document and that it
+    /// fits on its package
+    fn enter(&mut self, header: &ChunkHeader, document: u16) {
+        if let Some(package_end) = self.package_end {
+            if header.get_offset() >= package_end {
+                self.path.truncate(1);
+                self.package_end = None;
+            } else if header.get_chunk_end() > package_end {
+                self.report(
+                    AnomalyKind::ChunkSize,
+                    Severity::Critical,
+                    header.get_offset() + 4,
+                    format!(
+                        "{} ends {} bytes beyond the end of its package",
+                        token_name(header.get_token()),
+                        header.get_chunk_end() - package_end
+                    ),
+                );
+            }
+        }
+
+        if header.get_token() == TOKEN_PACKAGE {
+            self.path.truncate(1);
+        }
+        self.path
+            .push(ChunkContext::new(header.get_token(), header.get_offset()));
+
+        let expected = match header.get_token() {
+            TOKEN_STRING_TABLE => true,
+            TOKEN_RESOURCE
+            | TOKEN_XML_START_NAMESPACE
+            | TOKEN_XML_END_NAMESPACE
+            | TOKEN_XML_TAG_START
+            | TOKEN_XML_TAG_END
+            | TOKEN_XML_TEXT => document == TOKEN_XML,
+            _ if token_name(header.get_token()) != "UNKNOWN" => document == TOKEN_TABLE,
+            _ => {
+                self.report(
+                    AnomalyKind::UnknownChunk,
+                    Severity::Info,
+                    header.get_offset(),
+                    format!("chunk of unknown type 0x{:04x}", header.get_token()),
+                );
+                return;
+            }
+        };
+
+        if !expected {
+            self.report(
+                AnomalyKind::UnknownChunk,
+                Severity::Warning,
+                header.get_offset(),
+                format!(
+                    "{} is not expected on a {}",
+                    token_name(header.get_token()),
+                    token_name(document)
+                ),
+            );
+        }
+    }
+
+    /// Headers bigger than the standard ones move the data away from where most tools read it
+    fn check_header_size(&mut self, header: &ChunkHeader) {
+        let (min, max) = match header.get_token() {
+            TOKEN_STRING_TABLE => (28, 28),
+            TOKEN_RESOURCE => (8, 8),
+            TOKEN_PACKAGE => (284, 288),
+            TOKEN_TABLE_SPEC
+            | TOKEN_XML_START_NAMESPACE
+            | TOKEN_XML_END_NAMESPACE
+            | TOKEN_XML_TAG_START
+            | TOKEN_XML_TAG_END
+            | TOKEN_XML_TEXT => (16, 16),
+            _ => return,
+        };
+        let header_size = header.get_header_size();
+
+        if header_size < min || header_size > max {
+            self.report(
+                AnomalyKind::ChunkSize,
+                Severity::Warning,
+                header.get_offset() + 2,
+                format!(
+                    "the header of {} has {} bytes, {} are expected",
+                    token_name(header.get_token()),
+                    header_size,
+                    max
+                ),
+            );
+        }
+    }
+
+    fn check_string_pool(&mut self, raw: &[u8], header: &ChunkHeader) {
+        let field = |offset: usize| u64::from(LittleEndian::read_u32(&raw[offset..offset + 4]));
+        if raw.len() < 28 {
+            return;
+        }
+
+        let chunk_size = raw.len() as u64;
+        let header_size = u64::from(header.get_header_size());
+        let (strings, styles) = (field(8), field(12));
+        let offsets_end = header_size + (strings + styles) * 4;
+
+        if offsets_end > chunk_size {
+            self.report(
+                AnomalyKind::StringPoolOffset,
+                Severity::Critical,
+                header.get_offset() + 8,
+                format!(
+                    "the offsets of {strings} strings and {styles} styles do not fit on a chunk \
+                    of {chunk_size} bytes"
+                ),
+            );
+            return;
+        }
+
+        for &(count, position, name) in &[(strings, 20, "strings"), (styles, 24, "styles")] {
+            let start = field(position);
+            let description = if count == 0 {
+                continue;
+            } else if start < header_size {
+                format!("{name} start at {start}, inside the header")
+            } else if start < offsets_end {
+                format!("{name} start at {start}, inside the offsets that end at {offsets_end}")
+            } else if start >= chunk_size {
+                format!("{name} start at {start}, beyond the end of the chunk")
+            } else {
+                continue;
+            };
+
+            self.report(
+                AnomalyKind::StringPoolOffset,
+                Severity::Critical,
+                header.get_offset() + position as u64,
+                description,
+            );
+        }
+
+        let strings_start = field(20);
+        let outside = (0..strings)
+            .map(|index| (index, field((header_size + index * 4) as usize)))
+            .filter(|(_, offset)| strings_start + offset >= chunk_size)
+            .collect::<Vec<_>>();
+
+        if let Some(&(first, _)) = outside.first() {
+            self.report(
+                AnomalyKind::StringPoolOffset,
+                Severity::Critical,
+                header.get_offset() + header_size + first * 4,
+                format!(
+                    "{} string offsets point beyond the end of the chunk",
+                    outside.len()
+                ),
+            );
+        }
+    }
+
+    fn check_namespace_start(
+        &mut self,
+        namespace_start: &XmlNamespaceStartWrapper<'_>,
+        strings: &StringTableWrapper<'_>,
+        header: &ChunkHeader,
+    ) {
+        let names = namespace_start
+            .get_prefix(strings)
+            .and_then(|prefix| Ok((prefix, namespace_start.get_namespace(strings)?)));
+
+        match names {
+            Ok((prefix, namespace)) => {
+                if prefix.as_str() == "android" && namespace.as_str() != ANDROID_NAMESPACE {
+                    self.report(
+                        AnomalyKind::Namespace,
+                        Severity::Warning,
+                        header.get_offset(),
+                        format!("the prefix android is bound to {namespace}"),
+                    );
+                }
+
+                self.namespaces.push((*namespace).clone());
+            }
+            Err(e) => self.report(
+                AnomalyKind::Namespace,
+                Severity::Critical,
+                header.get_offset(),
+                format!("the namespace can not be read: {e}"),
+            ),
+        }
+    }
+
+    fn check_namespace_end(&mut self, header: &ChunkHeader) {
+        if self.namespaces.pop().is_none() {
+            self.report(
+                AnomalyKind::Namespace,
+                Severity::Warning,
+                header.get_offset(),
+                "the namespace ends without having started".to_string(),
+            );
+        }
+    }
+
+    fn check_tag_start(
+        &mut self,
+        tag_start: &XmlTagStartWrapper<'_>,
+        strings: &StringTableWrapper<'_>,
+        header: &ChunkHeader,
+    ) {
+        let declared: HashSet<&String> = self.namespaces.iter().collect();
+        let mut found = Vec::new();
+
+        let attributes = (0..tag_start.get_attributes_amount().unwrap_or(0))
+            .map_while(|index| tag_start.get_attribute(index).ok());
+
+        for (index, attribute) in attributes.enumerate() {
+            let offset = header.get_offset() + u64::from(header.get_header_size()) + 20;
+            let offset = offset + index as u64 * 20;
+
+            match attribute
+                .get_name()
+                .and_then(|name| strings.get_string(name))
+            {
+                Ok(ref name) if is_xml_name(name) => (),
+                Ok(name) => found.push((
+                    AnomalyKind::AttributeName,
+                    Severity::Warning,
+                    offset,
+                    format!("the attribute name {name:?} is not a valid XML name"),
+                )),
+                Err(e) => found.push((
+                    AnomalyKind::AttributeName,
+                    Severity::Critical,
+                    offset,
+                    format!("the attribute name can not be read: {e}"),
+                )),
+            }
+
+            let namespace = attribute.get_namespace().unwrap_or(NO_INDEX);
+            if namespace != NO_INDEX {
+                match strings.get_string(namespace) {
+                    Ok(ref uri) if declared.contains(&**uri) => (),
+                    Ok(uri) => found.push((
+                        AnomalyKind::Namespace,
+                        Severity::Warning,
+                        offset,
+                        format!("the attribute uses the undeclared namespace {uri}"),
+                    )),
+                    Err(e) => found.push((
+                        AnomalyKind::Namespace,
+                        Severity::Critical,
+                        offset,
+                        format!("the attribute namespace can not be read: {e}"),
+                    )),
+                }
+            }
+        }
+
+        for (kind, severity, offset, description) in found {
+            self.report(kind, severity, offset, description);
+        }
+    }
+}
+
+/// Checks that the name can be used as the local name of an XML attribute
+fn is_xml_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(first) if first.is_alphabetic() || first == '_' => {
+            chars.all(|character| character.is_alphanumeric() || "_-.".contains(character))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{ByteOrder, LittleEndian};
+
+    use super::{AnomalyKind, AnomalyReport, Severity};
+    use crate::model::{
+        owned::ConfigurationBuf, AttributeValue, BinaryXmlDocument, ResourceId, ResourceTable,
+        TableEntry, XmlAttribute, XmlElement, ANDROID_NAMESPACE,
+    };
+
+    fn manifest(prefix_uri: &str, attribute: &str) -> Vec<u8> {
+        let mut root = XmlElement::new("manifest");
+        root.set_attribute(XmlAttribute::android(
+            "versionCode",
+            ResourceId::from(0x0101_021b),
+            AttributeValue::Integer(3),
+        ));
+        root.set_attribute(XmlAttribute::new(
+            attribute,
+            AttributeValue::String("com.example".to_string()),
+        ));
+
+        let mut document = BinaryXmlDocument::new(root);
+        document.add_namespace("android", prefix_uri);
+
+        document.to_vec().unwrap()
+    }
+
+    fn kinds(buffer: &[u8]) -> Vec<(AnomalyKind, Severity)> {
+        AnomalyReport::analyze(buffer)
+            .get_anomalies()
+            .iter()
+            .map(|anomaly| (anomaly.get_kind(), anomaly.get_severity()))
+            .collect()
+    }
+
+    #[test]
+    fn it_finds_no_anomalies_on_well_formed_files() {
+        let mut table = ResourceTable::default();
+        table.add_package(0x7F, "com.example").unwrap();
+        let title = table.add_resource(0x7F, "string", "title").unwrap();
+        let value = TableEntry::Value(AttributeValue::String("Title".to_string()));
+        table
+            .set_entry(title, &ConfigurationBuf::default(), value)
+            .unwrap();
+
+        assert!(AnomalyReport::analyze(&table.to_vec().unwrap()).is_empty());
+        assert!(AnomalyReport::analyze(&manifest(ANDROID_NAMESPACE, "package")).is_empty());
+    }
+
+    #[test]
+    fn it_reports_trailing_data_and_misleading_sizes() {
+        let mut buffer = manifest(ANDROID_NAMESPACE, "package");
+        let length = buffer.len() as u64;
+        buffer.extend(&[0; 6]);
+
+        let report = AnomalyReport::analyze(&buffer);
+        assert_eq!(
+            vec![(AnomalyKind::TrailingData, Severity::Warning)],
+            kinds(&buffer)
+        );
+        assert_eq!(
+            length,
+            report.get_anomalies()[0].get_location().get_offset()
+        );
+
+        buffer.truncate(length as usize - 4);
+        assert_eq!(
+            Some(&(AnomalyKind::ChunkSize, Severity::Critical)),
+            kinds(&buffer).first()
+        );
+
+        let mut buffer = manifest(ANDROID_NAMESPACE, "package");
+        buffer[4..8].copy_from_slice(&0_u32.to_le_bytes());
+        let report = AnomalyReport::analyze(&buffer);
+        let anomaly = &report.get_anomalies()[0];
+        assert_eq!(AnomalyKind::ChunkSize, anomaly.get_kind());
+        assert_eq!(Severity::Critical, anomaly.get_severity());
+        assert_eq!(4, anomaly.get_location().get_offset());
+        assert!(!kinds(&buffer).contains(&(AnomalyKind::TrailingData, Severity::Warning)));
+    }
+
+    #[test]
+    fn it_reports_string_pools_that_point_into_their_header() {
+        let mut buffer = manifest(ANDROID_NAMESPACE, "package");
+        // The string pool is the first chunk, after the 8 bytes of the document header
+        LittleEndian::write_u32(&mut buffer[8 + 20..8 + 24], 8);
+
+        let report = AnomalyReport::analyze(&buffer);
+        let anomaly = &report.get_anomalies()[0];
+
+        assert_eq!(AnomalyKind::StringPoolOffset, anomaly.get_kind());
+        assert_eq!(Some(Severity::Critical), report.get_max_severity());
+        assert_eq!(28, anomaly.get_location().get_offset());
+        assert_eq!(2, anomaly.get_location().get_path().len());
+    }
+
+    #[test]
+    fn it_reports_invalid_attribute_names_and_fake_namespaces() {
+        // The android attributes use a namespace that is not declared, as the android prefix is
+        // bound to another one
+        let buffer = manifest("http://example.com/android", "1 package");
+
+        assert_eq!(
+            vec![
+                (AnomalyKind::Namespace, Severity::Warning),
+                (AnomalyKind::Namespace, Severity::Warning),
+                (AnomalyKind::AttributeName, Severity::Warning),
+            ],
+            kinds(&buffer)
+        );
+    }
+}
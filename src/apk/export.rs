@@ -0,0 +1,549 @@
+//! Options of `Apk::export_with_options` and the report of what was exported
+
+use std::collections::HashSet;
+
+use crate::errors::Diagnostic;
+
+/// What to do when the output directory already has files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExistingFiles {
+    /// The export fails if the output directory is not empty
+    #[default]
+    Fail,
+    /// The output directory is removed before exporting, including the files that are not on the
+    /// APK
+    Clean,
+    /// The files are exported on the output directory, replacing the ones with the same name and
+    /// keeping the rest
+    Merge,
+}
+
+/// What to do with the files whose names are not safe to write on the output directory: names
+/// that escape it (`../../.bashrc`, `/etc/passwd`, `C:\\evil`) or that collide with another file
+/// on case insensitive file systems
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnsafeNames {
+    /// The files are not exported and they are reported as rejected
+    #[default]
+    Reject,
+    /// The files are exported inside the output directory, with the unsafe parts of the name
+    /// removed and a suffix (`icon~1.png`) if it collides with another file
+    Remap,
+}
+
+/// Which files of an APK are exported and how
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    skip_assets: bool,
+    existing: ExistingFiles,
+    keep_original: bool,
+    unsafe_names: UnsafeNames,
+}
+
+impl ExportOptions {
+    /// Exports only the files that match one of the included globs. `*` and `?` match inside a
+    /// directory and `**` matches any amount of directories (`res/**/*.xml`). If there are no
+    /// included globs, all the files are exported.
+    #[must_use]
+    pub fn with_include(mut self, glob: &str) -> Self {
+        self.include.push(glob.to_string());
+        self
+    }
+
+    /// Does not export the files that match the glob, even if they are included
+    #[must_use]
+    pub fn with_exclude(mut self, glob: &str) -> Self {
+        self.exclude.push(glob.to_string());
+        self
+    }
+
+    /// Does not export the raw assets, stored under `assets/`
+    #[must_use]
+    pub fn with_skip_assets(mut self, skip_assets: bool) -> Self {
+        self.skip_assets = skip_assets;
+        self
+    }
+
+    #[must_use]
+    pub fn with_existing_files(mut self, existing: ExistingFiles) -> Self {
+        self.existing = existing;
+        self
+    }
+
+    /// Writes also the binary version of the decoded XML files, with a `.bin` extension
+    /// (`AndroidManifest.xml.bin`)
+    #[must_use]
+    pub fn with_keep_original(mut self, keep_original: bool) -> Self {
+        self.keep_original = keep_original;
+        self
+    }
+
+    #[must_use]
+    pub fn with_unsafe_names(mut self, unsafe_names: UnsafeNames) -> Self {
+        self.unsafe_names = unsafe_names;
+        self
+    }
+
+    #[must_use]
+    pub fn get_existing_files(&self) -> ExistingFiles {
+        self.existing
+    }
+
+    #[must_use]
+    pub fn is_keep_original(&self) -> bool {
+        self.keep_original
+    }
+
+    #[must_use]
+    pub fn get_unsafe_names(&self) -> UnsafeNames {
+        self.unsafe_names
+    }
+
+    /// Checks if the file with the given name should be exported
+    #[must_use]
+    pub fn is_exported(&self, name: &str) -> bool {
+        if self.skip_assets && name.starts_with("assets/") {
+            return false;
+        }
+
+        let included =
+            self.include.is_empty() || self.include.iter().any(|glob| glob_matches(glob, name));
+
+        included && !self.exclude.iter().any(|glob| glob_matches(glob, name))
+    }
+}
+
+/// File that could not be exported, or that was exported without decoding it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportFailure {
+    name: String,
+    reason: String,
+}
+
+impl ExportFailure {
+    #[must_use]
+    pub fn new(name: String, reason: String) -> Self {
+        Self { name, reason }
+    }
+
+    #[must_use]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn get_reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+/// Outcome of exporting each file of an APK
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    decoded: Vec<String>,
+    copied: Vec<String>,
+    skipped: Vec<String>,
+    failed: Vec<ExportFailure>,
+    rejected: Vec<ExportFailure>,
+    remapped: Vec<(String, String)>,
+    degraded: Vec<(String, Vec<Diagnostic>)>,
+}
+
+impl ExportReport {
+    /// Binary XML files that were written as text
+    #[must_use]
+    pub fn get_decoded(&self) -> &[String] {
+        &self.decoded
+    }
+
+    /// Files that were written as they are on the APK
+    #[must_use]
+    pub fn get_copied(&self) -> &[String] {
+        &self.copied
+    }
+
+    /// Files that were not exported because of the filters of the `ExportOptions`
+    #[must_use]
+    pub fn get_skipped(&self) -> &[String] {
+        &self.skipped
+    }
+
+    /// Files that could not be read or written, and binary XML files that could not be decoded.
+    /// The latter are written as they are on the APK.
+    #[must_use]
+    pub fn get_failed(&self) -> &[ExportFailure] {
+        &self.failed
+    }
+
+    /// Files that were not exported because their names are not safe
+    #[must_use]
+    pub fn get_rejected(&self) -> &[ExportFailure] {
+        &self.rejected
+    }
+
+    /// Names on the APK and the paths, relative to the output directory, where they were
+    /// exported, for the files whose names were not safe
+    #[must_use]
+    pub fn get_remapped(&self) -> &[(String, String)] {
+        &self.remapped
+    }
+
+    /// Files that were decoded skipping some problems, on lenient mode, with those problems. The
+    /// problems of the `resources.arsc`, which affect how the rest of files are decoded, are
+    /// listed under its name.
+    #[must_use]
+    pub fn get_degraded(&self) -> &[(String, Vec<Diagnostic>)] {
+        &self.degraded
+    }
+
+    pub(crate) fn decoded(&mut self, name: &str) {
+        self.decoded.push(name.to_string());
+    }
+
+    pub(crate) fn copied(&mut self, name: &str) {
+        self.copied.push(name.to_string());
+    }
+
+    pub(crate) fn skipped(&mut self, name: &str) {
+        self.skipped.push(name.to_string());
+    }
+
+    pub(crate) fn failed(&mut self, name: &str, reason: String) {
+        self.failed
+            .push(ExportFailure::new(name.to_string(), reason));
+    }
+
+    pub(crate) fn rejected(&mut self, name: &str, reason: String) {
+        self.rejected
+            .push(ExportFailure::new(name.to_string(), reason));
+    }
+
+    pub(crate) fn remapped(&mut self, name: &str, path: &str) {
+        self.remapped.push((name.to_string(), path.to_string()));
+    }
+
+    pub(crate) fn degraded(&mut self, name: &str, diagnostics: Vec<Diagnostic>) {
+        if !diagnostics.is_empty() {
+            self.degraded.push((name.to_string(), diagnostics));
+        }
+    }
+}
+
+/// Paths already used on the output directory, to find the names that collide
+#[derive(Debug, Default)]
+pub(crate) struct OutputPaths {
+    used: HashSet<String>,
+}
+
+impl OutputPaths {
+    /// Safe path, relative to the output directory, for the file with the given name. It fails
+    /// with the reason if the name is rejected.
+    pub(crate) fn claim(
+        &mut self,
+        name: &str,
+        unsafe_names: UnsafeNames,
+    ) -> Result<String, String> {
+        let path = match (check_name(name), unsafe_names) {
+            (Ok(path), _) => path,
+            (Err(reason), UnsafeNames::Reject) => return Err(reason),
+            (Err(_), UnsafeNames::Remap) => remap_name(name)
+                .ok_or_else(|| "the name does not have any safe component".to_string())?,
+        };
+
+        if self.used.insert(path.to_lowercase()) {
+            return Ok(path);
+        }
+
+        match unsafe_names {
+            UnsafeNames::Reject => Err(format!("the name collides with another file: {path}")),
+            UnsafeNames::Remap => {
+                let (stem, extension) = match path.rfind('.') {
+                    Some(dot) if dot > path.rfind('/').map_or(0, |slash| slash + 1) => {
+                        path.split_at(dot)
+                    }
+                    _ => (path.as_str(), ""),
+                };
+
+                let mut suffix = 1;
+                loop {
+                    let candidate = format!("{stem}~{suffix}{extension}");
+                    if self.used.insert(candidate.to_lowercase()) {
+                        return Ok(candidate);
+                    }
+                    suffix += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Normalizes the name of a file of the APK into a relative path: backslashes are taken as
+/// separators and empty and `.` components are removed. It fails if the path is absolute, it has
+/// `..` components, characters that have a special meaning on some file systems or components
+/// that Windows does not allow as file names.
+pub(crate) fn check_name(name: &str) -> Result<String, String> {
+    let name = name.replace('\\', "/");
+
+    if name.starts_with('/') {
+        return Err("the name is an absolute path".to_string());
+    }
+
+    let mut components = Vec::new();
+    for component in name.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => return Err("the name escapes the output directory".to_string()),
+            _ if component.contains(':') => {
+                return Err(format!("the component {component:?} is not valid"))
+            }
+            _ if component.chars().any(char::is_control) => {
+                return Err("the name has control characters".to_string())
+            }
+            _ if is_reserved(component) => {
+                return Err(format!(
+                    "the component {component:?} is reserved on Windows"
+                ))
+            }
+            _ => components.push(component),
+        }
+    }
+
+    if components.is_empty() {
+        return Err("the name is empty".to_string());
+    }
+
+    Ok(components.join("/"))
+}
+
+/// Relative path for a name rejected by `check_name`, resolving `..` inside the output directory
+/// and replacing the characters that are not valid
+fn remap_name(name: &str) -> Option<String> {
+    let name = name.replace('\\', "/");
+    let mut components: Vec<String> = Vec::new();
+
+    for component in name.split('/') {
+        match component {
+            "" | "." => (),
+            ".." => {
+                let _ = components.pop();
+            }
+            _ => {
+                let component: String = component
+                    .chars()
+                    .map(|c| if c == ':' || c.is_control() { '_' } else { c })
+                    .collect();
+
+                components.push(unreserve(component));
+            }
+        }
+    }
+
+    if components.is_empty() {
+        None
+    } else {
+        Some(components.join("/"))
+    }
+}
+
+/// Device names that Windows reserves in every directory, whatever the extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Whether Windows refuses `component` as a file name: a reserved device name, with or without
+/// extension, or a name ending in a dot or a space, which Windows silently strips.
+fn is_reserved(component: &str) -> bool {
+    let stem = component.split('.').next().unwrap_or_default().trim_end();
+
+    component.ends_with('.')
+        || component.ends_with(' ')
+        || RESERVED_NAMES
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+/// Name that Windows accepts for `component`: the trailing dots and spaces become underscores and
+/// reserved device names get an underscore after the stem.
+fn unreserve(component: String) -> String {
+    if !is_reserved(&component) {
+        return component;
+    }
+
+    let kept = component.trim_end_matches(&['.', ' '][..]);
+    let mut name = kept.to_string();
+    name.push_str(&"_".repeat(component.len() - kept.len()));
+
+    if is_reserved(&name) {
+        let stem_end = name.find('.').unwrap_or(name.len());
+        name.insert(stem_end, '_');
+    }
+
+    name
+}
+
+/// Matches a path against a glob where `*` and `?` do not match `/` and `**` matches anything
+fn glob_matches(glob: &str, name: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let mut memo = vec![None; (glob.len() + 1) * (name.len() + 1)];
+
+    matches_from(&glob, &name, 0, 0, &mut memo)
+}
+
+/// Whether `glob[pattern..]` matches `name[start..]`. Each pair of positions is only solved once,
+/// so patterns with many stars do not take exponential time.
+fn matches_from(
+    glob: &[char],
+    name: &[char],
+    pattern: usize,
+    start: usize,
+    memo: &mut [Option<bool>],
+) -> bool {
+    let key = pattern * (name.len() + 1) + start;
+    if let Some(matched) = memo[key] {
+        return matched;
+    }
+
+    let matched = match glob.get(pattern) {
+        None => start == name.len(),
+        Some('*') if glob.get(pattern + 1) == Some(&'*') => {
+            // `**/` also matches no directories at all
+            let rest = pattern + 2;
+            let skipped = if glob.get(rest) == Some(&'/') {
+                rest + 1
+            } else {
+                rest
+            };
+
+            matches_from(glob, name, skipped, start, memo)
+                || (start..=name.len()).any(|next| matches_from(glob, name, rest, next, memo))
+        }
+        Some('*') => (start..=name.len())
+            .take_while(|&end| end == start || name[end - 1] != '/')
+            .any(|next| matches_from(glob, name, pattern + 1, next, memo)),
+        Some('?') => match name.get(start) {
+            Some(&character) if character != '/' => {
+                matches_from(glob, name, pattern + 1, start + 1, memo)
+            }
+            _ => false,
+        },
+        Some(&character) => {
+            name.get(start) == Some(&character)
+                && matches_from(glob, name, pattern + 1, start + 1, memo)
+        }
+    };
+
+    memo[key] = Some(matched);
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_name, glob_matches, ExportOptions, OutputPaths, UnsafeNames};
+
+    #[test]
+    fn it_matches_globs() {
+        assert!(glob_matches("AndroidManifest.xml", "AndroidManifest.xml"));
+        assert!(glob_matches("res/*/*.xml", "res/layout/main.xml"));
+        assert!(!glob_matches("res/*.xml", "res/layout/main.xml"));
+        assert!(glob_matches("res/**/*.xml", "res/layout/main.xml"));
+        assert!(glob_matches("res/**/*.xml", "res/main.xml"));
+        assert!(glob_matches("**", "assets/fonts/a.ttf"));
+        assert!(glob_matches("lib/??/*.so", "lib/x8/libc.so"));
+        assert!(!glob_matches("*.png", "res/icon.png"));
+    }
+
+    #[test]
+    fn it_matches_globs_with_many_stars_quickly() {
+        let name = "a".repeat(200);
+
+        assert!(!glob_matches(&format!("{}b", "*a".repeat(30)), &name));
+        assert!(!glob_matches(&format!("{}b", "**a".repeat(30)), &name));
+        assert!(glob_matches(&"*a".repeat(30), &name));
+    }
+
+    #[test]
+    fn it_filters_the_exported_files() {
+        let options = ExportOptions::default()
+            .with_include("res/**")
+            .with_include("assets/**")
+            .with_exclude("**/*.png")
+            .with_skip_assets(true);
+
+        assert!(options.is_exported("res/layout/main.xml"));
+        assert!(!options.is_exported("res/drawable/icon.png"));
+        assert!(!options.is_exported("assets/data.json"));
+        assert!(!options.is_exported("classes.dex"));
+        assert!(ExportOptions::default().is_exported("classes.dex"));
+    }
+
+    #[test]
+    fn it_rejects_names_that_escape_the_output() {
+        assert_eq!(Ok("res/a.xml".to_string()), check_name("./res//a.xml"));
+        assert_eq!(Ok("res/a.xml".to_string()), check_name("res\\a.xml"));
+        assert!(check_name("../../.bashrc").is_err());
+        assert!(check_name("res/../../a").is_err());
+        assert!(check_name("/etc/passwd").is_err());
+        assert!(check_name("\\etc\\passwd").is_err());
+        assert!(check_name("C:/Windows/a.dll").is_err());
+        assert!(check_name("a\u{0}b").is_err());
+        assert!(check_name("./").is_err());
+        assert!(check_name("res/con").is_err());
+        assert!(check_name("res/Nul.xml").is_err());
+        assert!(check_name("lpt9.tar.gz").is_err());
+        assert!(check_name("res/a.").is_err());
+        assert!(check_name("res /a").is_err());
+        assert_eq!(Ok("res/CONFIG".to_string()), check_name("res/CONFIG"));
+        assert_eq!(Ok("COM10".to_string()), check_name("COM10"));
+    }
+
+    #[test]
+    fn it_remaps_unsafe_and_colliding_names() {
+        let mut paths = OutputPaths::default();
+
+        assert_eq!(
+            Ok("res/icon.png".to_string()),
+            paths.claim("res/icon.png", UnsafeNames::Remap)
+        );
+        assert_eq!(
+            Ok("res/Icon~1.PNG".to_string()),
+            paths.claim("res/Icon.PNG", UnsafeNames::Remap)
+        );
+        assert_eq!(
+            Ok("res/icon~2.png".to_string()),
+            paths.claim("res/icon.png", UnsafeNames::Remap)
+        );
+        assert_eq!(
+            Ok(".bashrc".to_string()),
+            paths.claim("../../.bashrc", UnsafeNames::Remap)
+        );
+        assert_eq!(
+            Ok("etc/passwd".to_string()),
+            paths.claim("/etc/passwd", UnsafeNames::Remap)
+        );
+        assert_eq!(
+            Ok("C_/a.dll".to_string()),
+            paths.claim("C:\\a.dll", UnsafeNames::Remap)
+        );
+        assert_eq!(
+            Ok("res/CON_.xml".to_string()),
+            paths.claim("res/CON.xml", UnsafeNames::Remap)
+        );
+        assert_eq!(
+            Ok("aux_/a__".to_string()),
+            paths.claim("aux /a. ", UnsafeNames::Remap)
+        );
+        assert_eq!(
+            Ok("nul_".to_string()),
+            paths.claim("nul.", UnsafeNames::Remap)
+        );
+        assert!(paths.claim("..", UnsafeNames::Remap).is_err());
+
+        assert!(paths.claim("RES/ICON.png", UnsafeNames::Reject).is_err());
+        assert!(paths.claim("../a", UnsafeNames::Reject).is_err());
+        assert_eq!(Ok("a".to_string()), paths.claim("a", UnsafeNames::Reject));
+    }
+}
@@ -0,0 +1,899 @@
+//! High level abstraction to easy the extraction to file system of APKs
+
+use std::{
+    collections::HashSet,
+    fs,
+    io::{Cursor, Read, Seek, Write},
+    path::{Component, Path},
+};
+
+use failure::{bail, ensure, format_err, Error, ResultExt};
+use log::warn;
+use zip::{read::ZipArchive, result::ZipError, CompressionMethod};
+
+use crate::{
+    anomalies::{Anomaly, AnomalyKind, AnomalyReport, Severity},
+    decoder::BufferedDecoder,
+    errors::{warn_skipped, Location},
+    model::Element,
+    options::{check_limit, ParseOptions},
+    visitor::XmlVisitor,
+};
+
+mod export;
+
+use self::export::OutputPaths;
+pub use self::export::{ExistingFiles, ExportFailure, ExportOptions, ExportReport, UnsafeNames};
+
+/// `resources.arsc` without packages, used on APKs that do not have one. Their references can
+/// only be resolved to the Android framework resources.
+const EMPTY_ARSC: [u8; 12] = [2, 0, 12, 0, 12, 0, 0, 0, 0, 0, 0, 0];
+
+/// How a file is stored on the APK
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Stored,
+    Deflated,
+    /// Any other method, which can not be read
+    Other,
+}
+
+impl From<CompressionMethod> for Compression {
+    fn from(method: CompressionMethod) -> Self {
+        match method {
+            CompressionMethod::Stored => Self::Stored,
+            CompressionMethod::Deflated => Self::Deflated,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// File contained on an APK, as described by the ZIP headers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApkFile {
+    name: String,
+    size: u64,
+    compressed_size: u64,
+    compression: Compression,
+}
+
+impl ApkFile {
+    #[must_use]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Decompressed size, as declared on the ZIP headers
+    #[must_use]
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    #[must_use]
+    pub fn get_compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    #[must_use]
+    pub fn get_compression(&self) -> Compression {
+        self.compression
+    }
+
+    #[must_use]
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+/// Checks if the contents are a binary XML by the type of their first chunk (`0x0003`), instead
+/// of relying on the name of the file
+#[must_use]
+pub fn is_binary_xml(contents: &[u8]) -> bool {
+    contents.len() >= 8 && contents[0..2] == [3, 0]
+}
+
+/// APK read from a file or from any other source that implements `Read` and `Seek`
+#[derive(Debug)]
+pub struct Apk<R = fs::File> {
+    handler: ZipArchive<R>,
+    decoder: BufferedDecoder,
+    options: ParseOptions,
+}
+
+impl Apk<fs::File> {
+    /// Opens the APK on the given path
+    ///
+    /// # Errors
+    ///
+    /// It fails if the file can not be opened or it is not a valid ZIP, or if its
+    /// `resources.arsc` can not be read.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_path_with_options(path, ParseOptions::default())
+    }
+
+    /// Opens the APK to decode its files as `options` says. The size of the files and of the
+    /// exported output are bounded by its limits.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the file can not be opened or it is not a valid ZIP, or if its `resources.arsc`
+    /// can not be read.
+    pub fn from_path_with_options<P: AsRef<Path>>(
+        path: P,
+        options: ParseOptions,
+    ) -> Result<Self, Error> {
+        let file = fs::File::open(&path)?;
+
+        Self::from_reader_with_options(file, options)
+    }
+}
+
+impl Apk<Cursor<Vec<u8>>> {
+    /// Reads the APK from its contents, like an APK received from the network or extracted from
+    /// another archive
+    ///
+    /// # Errors
+    ///
+    /// It fails if the contents are not a valid ZIP or its `resources.arsc` can not be read.
+    pub fn from_bytes<B: Into<Vec<u8>>>(bytes: B) -> Result<Self, Error> {
+        Self::from_bytes_with_options(bytes, ParseOptions::default())
+    }
+
+    /// Reads the APK from its contents, decoding its files as `options` says
+    ///
+    /// # Errors
+    ///
+    /// It fails if the contents are not a valid ZIP or its `resources.arsc` can not be read.
+    pub fn from_bytes_with_options<B: Into<Vec<u8>>>(
+        bytes: B,
+        options: ParseOptions,
+    ) -> Result<Self, Error> {
+        Self::from_reader_with_options(Cursor::new(bytes.into()), options)
+    }
+}
+
+impl<R: Read + Seek> Apk<R> {
+    /// Reads the APK from the given source, like a file or a buffer
+    ///
+    /// # Errors
+    ///
+    /// It fails if the source is not a valid ZIP or its `resources.arsc` can not be read.
+    pub fn from_reader(reader: R) -> Result<Self, Error> {
+        Self::from_reader_with_options(reader, ParseOptions::default())
+    }
+
+    /// Reads the APK from the given source, decoding its files as `options` says. APKs without a
+    /// `resources.arsc` are accepted: their references are resolved only with the Android
+    /// framework resources.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the source is not a valid ZIP, or if it has a `resources.arsc` that can not be
+    /// read or is bigger than the limits allow.
+    pub fn from_reader_with_options(reader: R, options: ParseOptions) -> Result<Self, Error> {
+        let mut zip_handler = ZipArchive::new(reader).context("could not read the ZIP")?;
+        let buffer = match zip_handler.by_name("resources.arsc") {
+            Ok(entry) => read_entry(entry, options.get_limits().get_max_entry_size())
+                .context("could not read resources.arsc")?,
+            Err(ZipError::FileNotFound) => EMPTY_ARSC.to_vec(),
+            Err(e) => {
+                return Err(Error::from(e)
+                    .context("could not read resources.arsc")
+                    .into())
+            }
+        };
+
+        Ok(Self {
+            handler: zip_handler,
+            decoder: buffer.into(),
+            options,
+        })
+    }
+
+    /// Files contained on the APK, on the order of the ZIP central directory
+    ///
+    /// # Errors
+    ///
+    /// It fails if an entry of the ZIP central directory can not be read.
+    pub fn files(&mut self) -> Result<Vec<ApkFile>, Error> {
+        let mut files = Vec::with_capacity(self.handler.len());
+
+        for i in 0..self.handler.len() {
+            let file = self
+                .handler
+                .by_index(i)
+                .context("could not read ZIP entry")?;
+            files.push(ApkFile {
+                name: file.name().to_string(),
+                size: file.size(),
+                compressed_size: file.compressed_size(),
+                compression: file.compression().into(),
+            });
+        }
+
+        Ok(files)
+    }
+
+    /// Contents of the given file, without decoding them
+    ///
+    /// # Errors
+    ///
+    /// It fails if the file is not on the APK, it can not be decompressed or it is bigger than the
+    /// limits allow.
+    pub fn read_raw(&mut self, name: &str) -> Result<Vec<u8>, Error> {
+        let max_size = self.options.get_limits().get_max_entry_size();
+        let file = self
+            .handler
+            .by_name(name)
+            .context(format!("could not find {name} on the APK"))?;
+
+        Ok(read_entry(file, max_size).context(format!("could not read: {name}"))?)
+    }
+
+    /// Decodes the given binary XML file, resolving its references with the resources of the
+    /// APK. The resources are decoded again on each call. The problems skipped on lenient mode
+    /// are logged as warnings.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the file can not be read, the resources of the APK can not be decoded or the
+    /// document is not a binary XML that can be decoded.
+    pub fn decode_xml(&mut self, name: &str) -> Result<Element, Error> {
+        self.visit_xml(name, |visitor| {
+            visitor
+                .into_root()
+                .ok_or_else(|| format_err!("{} does not have a root element", name))
+        })
+    }
+
+    /// Decodes the given binary XML file as `decode_xml` does and encodes it as text
+    // The method path is bound to a single lifetime, so it can not replace the closure
+    /// Decodes the given binary XML file into its text form, as `decode_xml` does
+    ///
+    /// # Errors
+    ///
+    /// It fails if the file can not be decoded, as on `decode_xml`, or the decoded tree can not be
+    /// written as text.
+    #[allow(clippy::redundant_closure_for_method_calls)]
+    pub fn decode_xml_string(&mut self, name: &str) -> Result<String, Error> {
+        self.visit_xml(name, |visitor| visitor.into_string())
+    }
+
+    fn visit_xml<T, F>(&mut self, name: &str, into: F) -> Result<T, Error>
+    where
+        F: FnOnce(XmlVisitor<'_>) -> Result<T, Error>,
+    {
+        let contents = self.read_raw(name)?;
+        ensure!(is_binary_xml(&contents), "{} is not a binary XML", name);
+
+        let decoder = self
+            .decoder
+            .get_decoder_with_options(self.options)
+            .context("could not get the decoder")?;
+        let (visitor, diagnostics) = decoder
+            .xml_visitor_with_options(&contents, self.options)
+            .context(format!("could not decode: {name}"))?;
+        warn_skipped(name, &diagnostics);
+
+        into(visitor)
+    }
+
+    /// Looks for the quirks that manipulated APKs use to break analysis tools: empty, duplicated
+    /// or unreadable entries, a missing `AndroidManifest.xml` and the anomalies of the
+    /// `resources.arsc` and the binary XML files.
+    pub fn analyze(&mut self) -> AnomalyReport {
+        let max_size = self.options.get_limits().get_max_entry_size();
+        let mut report = AnomalyReport::default();
+        let mut names = HashSet::new();
+
+        for i in 0..self.handler.len() {
+            let (name, contents) = match self.handler.by_index(i) {
+                Ok(mut entry) => {
+                    let name = entry.name().to_string();
+                    let contents = read_entry(&mut entry, max_size);
+
+                    (name, contents)
+                }
+                Err(e) => {
+                    let description = format!("the entry {i} can not be read: {e}");
+                    report.push(Self::entry_anomaly(description));
+                    continue;
+                }
+            };
+
+            if !names.insert(name.clone()) {
+                let description = "the name is duplicated on the ZIP".to_string();
+                report.push(Self::entry_anomaly(description).with_entry(&name));
+            }
+
+            let contents = match contents {
+                Ok(contents) => contents,
+                Err(e) => {
+                    let description = format!("the file can not be read: {e}");
+                    report.push(Self::entry_anomaly(description).with_entry(&name));
+                    continue;
+                }
+            };
+
+            if name.trim_end_matches('/') == "AndroidManifest.xml" && contents.is_empty() {
+                let description = "the manifest is empty or a directory".to_string();
+                report.push(Self::entry_anomaly(description).with_entry(&name));
+            } else if name == "resources.arsc" || is_binary_xml(&contents) {
+                report.append_entry(&name, AnomalyReport::analyze(&contents));
+            }
+        }
+
+        if !names.contains("AndroidManifest.xml") {
+            let description = "there is no AndroidManifest.xml".to_string();
+            report.push(Self::entry_anomaly(description));
+        }
+
+        report
+    }
+
+    fn entry_anomaly(description: String) -> Anomaly {
+        Anomaly::new(
+            AnomalyKind::ApkEntry,
+            Severity::Critical,
+            Location::default(),
+            description,
+        )
+    }
+
+    /// It exports to target `output_path` the contents of the APK, transcoding the binary XML files
+    /// found on it. If `force` is set, the exported files replace the ones with the same name on
+    /// the output directory; otherwise, the export fails if it is not empty. The files that could
+    /// not be exported and the problems skipped on lenient mode are logged as warnings.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the APK resources can not be decoded or the output directory can not be
+    /// prepared.
+    pub fn export<P: AsRef<Path>>(&mut self, output_path: P, force: bool) -> Result<(), Error> {
+        let existing = if force {
+            ExistingFiles::Merge
+        } else {
+            ExistingFiles::Fail
+        };
+        let options = ExportOptions::default().with_existing_files(existing);
+        let report = self.export_with_options(output_path, &options)?;
+
+        for failure in report.get_failed().iter().chain(report.get_rejected()) {
+            warn!(
+                "could not export {}: {}",
+                failure.get_name(),
+                failure.get_reason()
+            );
+        }
+
+        for (name, diagnostics) in report.get_degraded() {
+            warn_skipped(name, diagnostics);
+        }
+
+        Ok(())
+    }
+
+    /// Exports the files of the APK selected by `export_options`, transcoding the binary XML
+    /// files. Files that can not be read, decoded or written do not stop the export: they are
+    /// listed as failed on the returned report. Files decoded skipping some problems are listed
+    /// as degraded, with those problems.
+    ///
+    /// The names of the files are not trusted: names that would be written outside of
+    /// `output_path` or that collide with other files are rejected or remapped, as
+    /// `export_options` says.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the APK resources can not be decoded or the output directory can not be
+    /// prepared. Problems with single files do not fail: they are on the report.
+    pub fn export_with_options<P: AsRef<Path>>(
+        &mut self,
+        output_path: P,
+        export_options: &ExportOptions,
+    ) -> Result<ExportReport, Error> {
+        let decoder = self
+            .decoder
+            .get_decoder_with_options(self.options)
+            .context("could not get the decoder")?;
+        let limits = self.options.get_limits();
+        let mut total_output = 0_u64;
+        let mut report = ExportReport::default();
+        let mut paths = OutputPaths::default();
+        let unsafe_names = export_options.get_unsafe_names();
+        report.degraded("resources.arsc", decoder.get_diagnostics().to_vec());
+
+        Self::prepare_output(output_path.as_ref(), export_options.get_existing_files())?;
+
+        // Iterate over all the files on the ZIP and extract them
+        for i in 0..self.handler.len() {
+            let (file_name, path, contents) = {
+                let mut current_file = self
+                    .handler
+                    .by_index(i)
+                    .context("could not read ZIP entry")?;
+                let file_name = current_file.name().to_string();
+
+                if current_file.is_dir() {
+                    continue;
+                }
+                if !export_options.is_exported(&file_name) {
+                    report.skipped(&file_name);
+                    continue;
+                }
+
+                let path = match paths.claim(&file_name, unsafe_names) {
+                    Ok(path) => path,
+                    Err(reason) => {
+                        report.rejected(&file_name, reason);
+                        continue;
+                    }
+                };
+                if path != file_name {
+                    report.remapped(&file_name, &path);
+                }
+
+                match read_entry(&mut current_file, limits.get_max_entry_size()) {
+                    Ok(contents) => (file_name, path, contents),
+                    Err(e) => {
+                        report.failed(&file_name, format!("could not read: {e}"));
+                        continue;
+                    }
+                }
+            };
+
+            // Outputs to write and the problem found while decoding, if any
+            let mut outputs = Vec::new();
+            let mut decode_error = None;
+            let decoded = is_binary_xml(&contents);
+
+            if decoded {
+                let xml = decoder
+                    .xml_visitor_with_options(&contents, self.options)
+                    .and_then(|(visitor, diagnostics)| Ok((visitor.into_string()?, diagnostics)));
+
+                match xml {
+                    Ok((xml, diagnostics)) => {
+                        report.degraded(&file_name, diagnostics);
+                        outputs.push((path.clone(), xml.into_bytes()));
+                        if export_options.is_keep_original() {
+                            let original = format!("{file_name}.bin");
+                            match paths.claim(&format!("{path}.bin"), unsafe_names) {
+                                Ok(original_path) => outputs.push((original_path, contents)),
+                                Err(reason) => report.rejected(&original, reason),
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        outputs.push((path.clone(), contents));
+                        decode_error = Some(e);
+                    }
+                }
+            } else {
+                outputs.push((path.clone(), contents));
+            }
+
+            let mut write_error = None;
+            for (name, contents) in outputs {
+                total_output += contents.len() as u64;
+                check_limit(
+                    "exported output size",
+                    total_output,
+                    limits.get_max_total_output(),
+                    Location::default(),
+                )
+                .context(format!("could not export: {name}"))?;
+
+                if let Err(e) = Self::write_file(&output_path, &name, &contents) {
+                    write_error = Some(format!("could not write {name}: {e}"));
+                }
+            }
+
+            match (write_error, decode_error) {
+                (Some(reason), _) => report.failed(&file_name, reason),
+                (None, Some(e)) => report.failed(
+                    &file_name,
+                    format!("could not decode, it was written as binary: {e}"),
+                ),
+                (None, None) if decoded => report.decoded(&file_name),
+                (None, None) => report.copied(&file_name),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Creates the output directory, handling the existing files as `existing` says
+    fn prepare_output(output_path: &Path, existing: ExistingFiles) -> Result<(), Error> {
+        let not_empty = matches!(
+            fs::read_dir(output_path).map(|mut entries| entries.next().is_some()),
+            Ok(true)
+        );
+
+        if not_empty {
+            match existing {
+                ExistingFiles::Fail => bail!(
+                    "the output directory is not empty: {}",
+                    output_path.display()
+                ),
+                ExistingFiles::Clean => {
+                    fs::remove_dir_all(output_path).context(format_err!(
+                        "could not clean target directory: {}",
+                        output_path.display()
+                    ))?;
+                }
+                ExistingFiles::Merge => (),
+            }
+        }
+
+        fs::create_dir_all(output_path).context(format_err!(
+            "error creating the output folder: {}",
+            output_path.display()
+        ))?;
+
+        Ok(())
+    }
+
+    /// Writes `content` on `relative`, a path already checked by `OutputPaths::claim`
+    fn write_file<B: AsRef<Path>>(
+        base_path: B,
+        relative: &str,
+        content: &[u8],
+    ) -> Result<(), Error> {
+        let relative = Path::new(relative);
+        ensure!(
+            relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_))),
+            "the path is not inside the output directory: {}",
+            relative.display()
+        );
+
+        let full_path = base_path.as_ref().join(relative);
+        let parent = full_path
+            .parent()
+            .ok_or_else(|| format_err!("the path has no parent: {}", full_path.display()))?;
+        fs::create_dir_all(parent).context("could not create the output dir")?;
+
+        let mut descriptor = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(full_path)
+            .context("could not open file to write")?;
+
+        descriptor
+            .write_all(content)
+            .context("could not write to target file")?;
+
+        Ok(())
+    }
+}
+
+/// Reads the whole entry, failing if its decompressed size is above `max_size`. The size on
+/// the ZIP headers is not trusted, as it can be forged.
+fn read_entry<E: Read>(entry: E, max_size: u64) -> Result<Vec<u8>, Error> {
+    let mut contents = Vec::new();
+    entry
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut contents)?;
+    check_limit(
+        "ZIP entry size",
+        contents.len() as u64,
+        max_size,
+        Location::default(),
+    )?;
+
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        io::{Cursor, Write},
+        process,
+    };
+
+    use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    use super::{
+        is_binary_xml, read_entry, Apk, Compression, ExistingFiles, ExportFailure, ExportOptions,
+        UnsafeNames,
+    };
+    use crate::{
+        anomalies::{AnomalyKind, Severity},
+        errors::AbxmlError,
+        model::{
+            AttributeValue, BinaryXmlDocument, ResourceId, XmlAttribute, XmlElement,
+            ANDROID_NAMESPACE,
+        },
+        options::ParseOptions,
+    };
+
+    fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+        for (name, contents) in entries {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(contents).unwrap();
+        }
+
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn it_reads_entries_up_to_the_size_limit() {
+        let entry = vec![0_u8; 16];
+
+        assert_eq!(entry, read_entry(Cursor::new(&entry), 16).unwrap());
+
+        let error = read_entry(Cursor::new(&entry), 15).err().unwrap();
+        match error.downcast::<AbxmlError>().unwrap() {
+            AbxmlError::LimitExceeded { max, found, .. } => assert_eq!((15, 16), (max, found)),
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn it_reports_the_anomalies_of_the_entries() {
+        let path = env::temp_dir().join(format!("abxml-anomalies-{}.apk", process::id()));
+        let arsc = [2, 0, 12, 0, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        fs::write(
+            &path,
+            zip(&[("AndroidManifest.xml", &[]), ("resources.arsc", &arsc)]),
+        )
+        .unwrap();
+
+        let report = Apk::from_path(&path).unwrap().analyze();
+        fs::remove_file(&path).unwrap();
+
+        let anomalies: Vec<_> = report
+            .get_anomalies()
+            .iter()
+            .map(|anomaly| (anomaly.get_entry(), anomaly.get_kind()))
+            .collect();
+        assert_eq!(
+            vec![
+                (Some("AndroidManifest.xml"), AnomalyKind::ApkEntry),
+                (Some("resources.arsc"), AnomalyKind::TrailingData),
+            ],
+            anomalies
+        );
+        assert_eq!(Some(Severity::Critical), report.get_max_severity());
+    }
+
+    #[test]
+    fn it_reads_apks_without_resources_from_memory() {
+        let mut root = XmlElement::new("manifest");
+        root.set_attribute(XmlAttribute::android(
+            "label",
+            ResourceId::from(0x0101_0001),
+            AttributeValue::Reference(ResourceId::from(0x7F01_0000)),
+        ));
+        let mut document = BinaryXmlDocument::new(root);
+        document.add_namespace("android", ANDROID_NAMESPACE);
+        let manifest = document.to_vec().unwrap();
+        let bytes = zip(&[("AndroidManifest.xml", &manifest)]);
+
+        // References to the resources of the app are kept raw, as there are none
+        for options in &[ParseOptions::default(), ParseOptions::strict()] {
+            let mut apk = Apk::from_bytes_with_options(bytes.clone(), *options).unwrap();
+            let xml = apk.decode_xml_string("AndroidManifest.xml").unwrap();
+            assert!(xml.contains("android:label=\"@0x7f010000\""));
+        }
+
+        let apk = Apk::from_bytes(bytes.clone()).unwrap();
+        let decoder = apk.decoder.get_decoder().unwrap();
+        let xml = decoder
+            .xml_visitor(&manifest)
+            .unwrap()
+            .into_string()
+            .unwrap();
+        assert!(xml.contains("<manifest"));
+
+        let mut apk = Apk::from_reader(Cursor::new(&bytes[..])).unwrap();
+        assert!(apk.analyze().is_empty());
+    }
+
+    #[test]
+    fn it_gives_access_to_each_file() {
+        let mut root = XmlElement::new("manifest");
+        root.append(XmlElement::new("application"));
+        let mut document = BinaryXmlDocument::new(root);
+        document.add_namespace("android", ANDROID_NAMESPACE);
+        let manifest = document.to_vec().unwrap();
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("AndroidManifest.xml", stored).unwrap();
+        writer.write_all(&manifest).unwrap();
+        writer
+            .start_file("res/raw/notes.xml", FileOptions::default())
+            .unwrap();
+        writer.write_all(b"<notes/>").unwrap();
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let mut apk = Apk::from_bytes(bytes).unwrap();
+
+        let files = apk.files().unwrap();
+        assert_eq!(2, files.len());
+        assert_eq!("AndroidManifest.xml", files[0].get_name());
+        assert_eq!(manifest.len() as u64, files[0].get_size());
+        assert_eq!(Compression::Stored, files[0].get_compression());
+        assert_eq!(Compression::Deflated, files[1].get_compression());
+
+        assert_eq!(
+            b"<notes/>".to_vec(),
+            apk.read_raw("res/raw/notes.xml").unwrap()
+        );
+        assert!(apk.read_raw("classes.dex").is_err());
+
+        let root = apk.decode_xml("AndroidManifest.xml").unwrap();
+        assert_eq!("manifest", root.get_tag().get_name().as_str());
+        assert_eq!(1, root.get_children().len());
+        assert!(apk
+            .decode_xml_string("AndroidManifest.xml")
+            .unwrap()
+            .contains("<application"));
+        assert!(apk.decode_xml("res/raw/notes.xml").is_err());
+
+        assert!(is_binary_xml(&manifest));
+        assert!(!is_binary_xml(b"<notes/>"));
+    }
+
+    #[test]
+    fn it_exports_with_options() {
+        let manifest = BinaryXmlDocument::new(XmlElement::new("manifest"))
+            .to_vec()
+            .unwrap();
+        let broken = &manifest[..manifest.len() - 4];
+        let bytes = zip(&[
+            ("AndroidManifest.xml", &manifest),
+            ("res/layout/broken.xml", broken),
+            ("res/raw/notes.txt", b"notes"),
+            ("assets/data.json", b"{}"),
+            ("classes.dex", b"dex"),
+        ]);
+        let output = env::temp_dir().join(format!("abxml-export-{}", process::id()));
+        let _ = fs::remove_dir_all(&output);
+        let options = ExportOptions::default()
+            .with_exclude("*.dex")
+            .with_skip_assets(true)
+            .with_keep_original(true);
+
+        let mut apk = Apk::from_bytes(bytes).unwrap();
+        let report = apk.export_with_options(&output, &options).unwrap();
+
+        assert_eq!(&["AndroidManifest.xml".to_string()], report.get_decoded());
+        assert_eq!(&["res/raw/notes.txt".to_string()], report.get_copied());
+        assert_eq!(
+            &["assets/data.json".to_string(), "classes.dex".to_string()],
+            report.get_skipped()
+        );
+        assert_eq!(1, report.get_failed().len());
+        assert_eq!("res/layout/broken.xml", report.get_failed()[0].get_name());
+
+        let xml = fs::read_to_string(output.join("AndroidManifest.xml")).unwrap();
+        assert!(xml.contains("<manifest"));
+        assert_eq!(
+            manifest,
+            fs::read(output.join("AndroidManifest.xml.bin")).unwrap()
+        );
+        assert_eq!(
+            broken,
+            &fs::read(output.join("res/layout/broken.xml")).unwrap()[..]
+        );
+        assert!(!output.join("classes.dex").exists());
+
+        assert!(apk.export_with_options(&output, &options).is_err());
+        let merge = options.clone().with_existing_files(ExistingFiles::Merge);
+        assert!(apk.export_with_options(&output, &merge).is_ok());
+        let clean = ExportOptions::default().with_existing_files(ExistingFiles::Clean);
+        assert!(apk.export_with_options(&output, &clean).is_ok());
+        assert!(output.join("classes.dex").exists());
+        assert!(!output.join("AndroidManifest.xml.bin").exists());
+
+        fs::write(output.join("AndroidManifest.xml"), b"stale").unwrap();
+        fs::write(output.join("unrelated.txt"), b"mine").unwrap();
+        assert!(apk.export(&output, false).is_err());
+        assert!(apk.export(&output, true).is_ok());
+        let xml = fs::read_to_string(output.join("AndroidManifest.xml")).unwrap();
+        assert!(xml.contains("<manifest"));
+        assert_eq!(
+            b"mine",
+            &fs::read(output.join("unrelated.txt")).unwrap()[..]
+        );
+
+        fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn it_reports_the_problems_skipped_while_exporting() {
+        let mut manifest = BinaryXmlDocument::new(XmlElement::new("manifest"))
+            .to_vec()
+            .unwrap();
+        // Chunk with an unknown type at the end of the document
+        manifest.extend(&[0x34, 0x12, 0x08, 0x00, 0x08, 0x00, 0x00, 0x00]);
+        let size = manifest.len() as u32;
+        manifest[4..8].copy_from_slice(&size.to_le_bytes());
+        let bytes = zip(&[("AndroidManifest.xml", &manifest)]);
+        let output = env::temp_dir().join(format!("abxml-degraded-{}", process::id()));
+        let _ = fs::remove_dir_all(&output);
+
+        let mut apk = Apk::from_bytes(bytes.clone()).unwrap();
+        let report = apk
+            .export_with_options(&output, &ExportOptions::default())
+            .unwrap();
+
+        assert_eq!(&["AndroidManifest.xml".to_string()], report.get_decoded());
+        assert_eq!(1, report.get_degraded().len());
+        assert_eq!("AndroidManifest.xml", report.get_degraded()[0].0);
+        match report.get_degraded()[0].1[0].get_error() {
+            AbxmlError::UnknownChunk { token, .. } => assert_eq!(0x1234, *token),
+            error => panic!("unexpected error: {}", error),
+        }
+
+        let mut apk = Apk::from_bytes_with_options(bytes, ParseOptions::strict()).unwrap();
+        let clean = ExportOptions::default().with_existing_files(ExistingFiles::Clean);
+        let report = apk.export_with_options(&output, &clean).unwrap();
+
+        assert!(report.get_degraded().is_empty());
+
+        fs::remove_dir_all(&output).unwrap();
+    }
+
+    #[test]
+    fn it_does_not_export_outside_of_the_output() {
+        let bytes = zip(&[
+            ("../../abxml-evil", b"evil"),
+            ("/tmp/abxml-evil", b"evil"),
+            ("res/raw/A.txt", b"upper"),
+            ("res/raw/a.txt", b"lower"),
+            ("res\\raw\\b.txt", b"b"),
+        ]);
+        let output = env::temp_dir().join(format!("abxml-zip-slip-{}", process::id()));
+        let _ = fs::remove_dir_all(&output);
+
+        let mut apk = Apk::from_bytes(bytes).unwrap();
+        let report = apk
+            .export_with_options(&output, &ExportOptions::default())
+            .unwrap();
+
+        let rejected: Vec<_> = report
+            .get_rejected()
+            .iter()
+            .map(ExportFailure::get_name)
+            .collect();
+        assert_eq!(
+            vec!["../../abxml-evil", "/tmp/abxml-evil", "res/raw/a.txt"],
+            rejected
+        );
+        assert_eq!(
+            &[("res\\raw\\b.txt".to_string(), "res/raw/b.txt".to_string())],
+            report.get_remapped()
+        );
+        assert_eq!(
+            b"upper",
+            &fs::read(output.join("res/raw/A.txt")).unwrap()[..]
+        );
+        assert!(!output.join("../../abxml-evil").exists());
+
+        let remap = ExportOptions::default()
+            .with_existing_files(ExistingFiles::Clean)
+            .with_unsafe_names(UnsafeNames::Remap);
+        let report = apk.export_with_options(&output, &remap).unwrap();
+
+        assert!(report.get_rejected().is_empty());
+        assert_eq!(b"evil", &fs::read(output.join("abxml-evil")).unwrap()[..]);
+        assert_eq!(
+            b"evil",
+            &fs::read(output.join("tmp/abxml-evil")).unwrap()[..]
+        );
+        assert_eq!(
+            b"lower",
+            &fs::read(output.join("res/raw/a~1.txt")).unwrap()[..]
+        );
+        assert!(!output.join("../../abxml-evil").exists());
+
+        fs::remove_dir_all(&output).unwrap();
+    }
+}
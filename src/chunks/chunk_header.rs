@@ -1,5 +1,7 @@
 use std::fmt;
 
+use failure::{ensure, Error};
+
 #[derive(Clone, Copy, Debug)]
 pub struct ChunkHeader {
     offset: u64,
@@ -34,14 +36,20 @@ impl ChunkHeader {
         self.offset + u64::from(self.chunk_size)
     }
 
-    pub fn absolute(&self, relative: u64) -> u64 {
-        let absolute = self.offset + relative;
+    /// Converts an offset relative to the start of the chunk into an absolute offset on the file
+    ///
+    /// # Errors
+    ///
+    /// It fails if the offset is beyond the end of the chunk.
+    pub fn absolute(&self, relative: u64) -> Result<u64, Error> {
+        let absolute = self.offset.saturating_add(relative);
 
-        if absolute > self.get_chunk_end() {
-            panic!("Requested a relative value out of bounds");
-        }
+        ensure!(
+            absolute <= self.get_chunk_end(),
+            "requested a relative value out of bounds"
+        );
 
-        absolute
+        Ok(absolute)
     }
 
     pub fn get_token(&self) -> u16 {
@@ -81,16 +89,16 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    pub fn it_panics_from_relative_out_of_bound() {
+    pub fn it_fails_from_relative_out_of_bound() {
         let chunk = ChunkHeader::new(4000, 8, 500, 0);
-        chunk.absolute(510);
+
+        assert!(chunk.absolute(510).is_err());
     }
 
     #[test]
     pub fn it_returns_absolute_offsets_from_relative_ones() {
         let chunk = ChunkHeader::new(4000, 8, 500, 0);
-        let res = chunk.absolute(490);
+        let res = chunk.absolute(490).unwrap();
 
         assert_eq!(4490, res);
     }
@@ -3,7 +3,11 @@ use std::io::Cursor;
 
 use byteorder::{LittleEndian, ReadBytesExt};
 use failure::Error;
-use log::error;
+
+use crate::{
+    errors::{AbxmlError, Location},
+    options::{check_limit, DecodeLimits},
+};
 
 mod chunk_header;
 mod package;
@@ -27,16 +31,81 @@ pub use self::{
 };
 
 pub const TOKEN_STRING_TABLE: u16 = 0x0001;
+pub const TOKEN_TABLE: u16 = 0x0002;
+pub const TOKEN_XML: u16 = 0x0003;
 pub const TOKEN_RESOURCE: u16 = 0x0180;
 pub const TOKEN_PACKAGE: u16 = 0x0200;
 pub const TOKEN_TABLE_TYPE: u16 = 0x201;
 pub const TOKEN_TABLE_SPEC: u16 = 0x202;
+pub const TOKEN_LIBRARY: u16 = 0x203;
 pub const TOKEN_XML_START_NAMESPACE: u16 = 0x100;
 pub const TOKEN_XML_END_NAMESPACE: u16 = 0x101;
 pub const TOKEN_XML_TAG_START: u16 = 0x102;
 pub const TOKEN_XML_TAG_END: u16 = 0x103;
 pub const TOKEN_XML_TEXT: u16 = 0x104;
 
+const CHUNK_HEADER_SIZE: u16 = 8;
+
+/// Name of the chunk type, as it appears on `ResourceTypes.h`
+#[must_use]
+pub fn token_name(token: u16) -> &'static str {
+    match token {
+        TOKEN_STRING_TABLE => "RES_STRING_POOL_TYPE",
+        TOKEN_TABLE => "RES_TABLE_TYPE",
+        TOKEN_XML => "RES_XML_TYPE",
+        TOKEN_XML_START_NAMESPACE => "RES_XML_START_NAMESPACE_TYPE",
+        TOKEN_XML_END_NAMESPACE => "RES_XML_END_NAMESPACE_TYPE",
+        TOKEN_XML_TAG_START => "RES_XML_START_ELEMENT_TYPE",
+        TOKEN_XML_TAG_END => "RES_XML_END_ELEMENT_TYPE",
+        TOKEN_XML_TEXT => "RES_XML_CDATA_TYPE",
+        TOKEN_RESOURCE => "RES_XML_RESOURCE_MAP_TYPE",
+        TOKEN_PACKAGE => "RES_TABLE_PACKAGE_TYPE",
+        TOKEN_TABLE_TYPE => "RES_TABLE_TYPE_TYPE",
+        TOKEN_TABLE_SPEC => "RES_TABLE_TYPE_SPEC_TYPE",
+        TOKEN_LIBRARY => "RES_TABLE_LIBRARY_TYPE",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Checks that the document starts with the given token and moves the cursor after the header
+/// of the document, where its first chunk starts
+pub(crate) fn skip_document_header(cursor: &mut Cursor<&[u8]>, token: u16) -> Result<(), Error> {
+    let available = cursor.get_ref().len() as u64;
+    let truncated = || AbxmlError::TruncatedChunk {
+        location: Location::default(),
+        needed: u64::from(CHUNK_HEADER_SIZE),
+        available,
+    };
+
+    let found = cursor.read_u16::<LittleEndian>().map_err(|_| truncated())?;
+
+    if found != token {
+        return Err(AbxmlError::BadToken {
+            location: Location::default(),
+            expected: token,
+            found,
+        }
+        .into());
+    }
+
+    let header_size = cursor.read_u16::<LittleEndian>().map_err(|_| truncated())?;
+
+    if header_size < CHUNK_HEADER_SIZE || u64::from(header_size) > available {
+        return Err(AbxmlError::InvalidChunk {
+            location: Location::default(),
+            reason: format!(
+                "header size {header_size} of the document is not valid on a file of \
+                {available} bytes"
+            ),
+        }
+        .into());
+    }
+
+    cursor.set_position(u64::from(header_size));
+
+    Ok(())
+}
+
 #[derive(Debug)]
 pub enum Chunk<'a> {
     StringTable(StringTableWrapper<'a>),
@@ -56,22 +125,55 @@ pub enum Chunk<'a> {
 pub struct ChunkLoaderStream<'a> {
     cursor: Cursor<&'a [u8]>,
     previous: Option<u64>,
+    header: Option<ChunkHeader>,
+    limits: DecodeLimits,
+    count: usize,
 }
 
 impl<'a> ChunkLoaderStream<'a> {
     pub fn new(cursor: Cursor<&'a [u8]>) -> Self {
+        Self::with_limits(cursor, DecodeLimits::default())
+    }
+
+    /// Stream that fails once it has returned `max_chunks` chunks. The limits are also applied to
+    /// the string tables and table types it returns.
+    #[must_use]
+    pub fn with_limits(cursor: Cursor<&'a [u8]>, limits: DecodeLimits) -> Self {
         Self {
             cursor,
             previous: None,
+            header: None,
+            limits,
+            count: 0,
         }
     }
 
+    /// Header of the last chunk returned by the stream
+    #[must_use]
+    pub fn get_header(&self) -> Option<&ChunkHeader> {
+        self.header.as_ref()
+    }
+
     fn read_one(&mut self) -> Result<Chunk<'a>, Error> {
         let initial_position = self.cursor.position();
-        let token = self.cursor.read_u16::<LittleEndian>()?;
-        let header_size = self.cursor.read_u16::<LittleEndian>()?;
-        let chunk_size = self.cursor.read_u32::<LittleEndian>()?;
-        let chunk_header = ChunkHeader::new(initial_position, header_size, chunk_size, token);
+        self.count += 1;
+        let result = check_limit(
+            "chunk count",
+            self.count as u64,
+            self.limits.get_max_chunks() as u64,
+            Location::new(initial_position, Vec::new()),
+        )
+        .and_then(|()| self.read_header(initial_position));
+
+        let chunk_header = match result {
+            Ok(chunk_header) => chunk_header,
+            Err(e) => {
+                // The stream stops on the next iteration, as the position does not change
+                self.cursor.set_position(initial_position);
+                return Err(e.into());
+            }
+        };
+        self.header = Some(chunk_header);
 
         let chunk = self.get_chunk(&chunk_header);
 
@@ -84,17 +186,57 @@ impl<'a> ChunkLoaderStream<'a> {
         Ok(chunk)
     }
 
+    fn read_header(&mut self, offset: u64) -> Result<ChunkHeader, AbxmlError> {
+        let available = (self.cursor.get_ref().len() as u64).saturating_sub(offset);
+        let truncated = |needed| AbxmlError::TruncatedChunk {
+            location: Location::new(offset, Vec::new()),
+            needed,
+            available,
+        };
+
+        let mut read = || -> Result<(u16, u16, u32), std::io::Error> {
+            Ok((
+                self.cursor.read_u16::<LittleEndian>()?,
+                self.cursor.read_u16::<LittleEndian>()?,
+                self.cursor.read_u32::<LittleEndian>()?,
+            ))
+        };
+        let (token, header_size, chunk_size) =
+            read().map_err(|_| truncated(u64::from(CHUNK_HEADER_SIZE)))?;
+        let header = ChunkHeader::new(offset, header_size, chunk_size, token);
+
+        if header_size < CHUNK_HEADER_SIZE || u32::from(header_size) > chunk_size {
+            return Err(AbxmlError::InvalidChunk {
+                location: Location::new(offset, Vec::new()),
+                reason: format!(
+                    "header size {header_size} and chunk size {chunk_size} are not consistent"
+                ),
+            });
+        }
+
+        if u64::from(chunk_size) > available {
+            return Err(truncated(u64::from(chunk_size)));
+        }
+
+        Ok(header)
+    }
+
     fn get_chunk(&self, header: &ChunkHeader) -> Chunk<'a> {
         let raw_data = self.cursor.get_ref();
         let slice = &raw_data[header.get_offset() as usize..header.get_chunk_end() as usize];
 
         match header.get_token() {
-            TOKEN_STRING_TABLE => Chunk::StringTable(StringTableWrapper::new(slice)),
+            TOKEN_STRING_TABLE => {
+                Chunk::StringTable(StringTableWrapper::new(slice).with_limits(self.limits))
+            }
             TOKEN_PACKAGE => Chunk::Package(PackageWrapper::new(slice)),
             TOKEN_TABLE_SPEC => Chunk::TableTypeSpec(TypeSpecWrapper::new(slice)),
             TOKEN_TABLE_TYPE => {
                 let current_chunk_data_offset = header.get_data_offset() - header.get_offset();
-                Chunk::TableType(TableTypeWrapper::new(slice, current_chunk_data_offset))
+                Chunk::TableType(
+                    TableTypeWrapper::new(slice, current_chunk_data_offset)
+                        .with_limits(self.limits),
+                )
             }
             TOKEN_XML_START_NAMESPACE => {
                 Chunk::XmlNamespaceStart(XmlNamespaceStartWrapper::new(slice))
@@ -104,11 +246,7 @@ impl<'a> ChunkLoaderStream<'a> {
             TOKEN_XML_TAG_END => Chunk::XmlTagEnd(XmlTagEndWrapper::new(slice)),
             TOKEN_XML_TEXT => Chunk::XmlText(XmlTextWrapper::new(slice)),
             TOKEN_RESOURCE => Chunk::Resource(ResourceWrapper::new(slice)),
-            t => {
-                error!("Unknown chunk: 0x{:X}", t);
-
-                Chunk::Unknown
-            }
+            _ => Chunk::Unknown,
         }
     }
 }
@@ -21,7 +21,6 @@ impl<'a> ResourceWrapper<'a> {
         cursor.set_position(4);
 
         let count = cursor.read_u32::<LittleEndian>()?;
-        let amount_of_resources = (count / 4) - 2;
 
         ensure!(
             count <= self.raw_data.len() as u32,
@@ -29,6 +28,9 @@ impl<'a> ResourceWrapper<'a> {
             self.raw_data.len(),
             count
         );
+        ensure!(count >= 8, "invalid resources chunk size ({})", count);
+
+        let amount_of_resources = (count / 4) - 2;
 
         let mut resources = Vec::with_capacity(amount_of_resources as usize);
 
@@ -12,19 +12,37 @@ use byteorder::{LittleEndian, ReadBytesExt};
 use encoding::codec::{utf_16, utf_8};
 use failure::{ensure, format_err, Error};
 
-use crate::model::{
-    owned::{Encoding as EncodingType, StringTableBuf},
-    StringTable,
+use crate::{
+    errors::{AbxmlError, Location},
+    model::{
+        owned::{Encoding as EncodingType, SpanBuf, StringTableBuf},
+        StringTable,
+    },
+    options::{check_limit, DecodeLimits},
 };
 
+/// Marks the end of the spans of a style
+const SPAN_END: u32 = 0xFFFF_FFFF;
+
 #[derive(Debug)]
 pub struct StringTableWrapper<'a> {
     raw_data: &'a [u8],
+    max_string_length: usize,
 }
 
 impl<'a> StringTableWrapper<'a> {
     pub fn new(raw_data: &'a [u8]) -> Self {
-        Self { raw_data }
+        Self {
+            raw_data,
+            max_string_length: DecodeLimits::default().get_max_string_length(),
+        }
+    }
+
+    /// Fails to decode the strings longer than the limit
+    #[must_use]
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.max_string_length = limits.get_max_string_length();
+        self
     }
 
     pub fn get_flags(&self) -> u32 {
@@ -46,71 +64,86 @@ impl<'a> StringTableWrapper<'a> {
             owned.add_string(string.clone());
         }
 
+        for i in 0..self.get_styles_len() {
+            owned.add_style(self.get_style(i)?);
+        }
+
         Ok(owned)
     }
 
-    fn get_string_position(&self, idx: u32) -> Result<u64, Error> {
+    /// Returns the spans of the string with the given index. Strings without style have none.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the index is not on the table or the style data is truncated.
+    pub fn get_style(&self, idx: u32) -> Result<Vec<SpanBuf>, Error> {
+        if idx >= self.get_styles_len() {
+            return Ok(Vec::new());
+        }
+
         let mut cursor = Cursor::new(self.raw_data);
-        cursor.set_position(20);
-        let str_offset = cursor.read_u32::<LittleEndian>()?;
+        cursor.set_position(24);
+        let style_offset = cursor.read_u32::<LittleEndian>()?;
 
-        cursor.set_position(28);
+        cursor.set_position(28 + u64::from(self.get_strings_len()) * 4 + u64::from(idx) * 4);
+        let current_offset = cursor.read_u32::<LittleEndian>()?;
+        cursor.set_position(u64::from(style_offset) + u64::from(current_offset));
 
-        let mut position = str_offset;
-        let mut max_offset = 0;
+        let mut spans = Vec::new();
 
-        for _ in 0..=idx {
-            let current_offset = cursor.read_u32::<LittleEndian>()?;
-            position = str_offset.wrapping_add(current_offset);
+        loop {
+            let name = cursor.read_u32::<LittleEndian>()?;
 
-            if current_offset > max_offset {
-                max_offset = current_offset
+            if name == SPAN_END {
+                break;
             }
+
+            let first_char = cursor.read_u32::<LittleEndian>()?;
+            let last_char = cursor.read_u32::<LittleEndian>()?;
+            spans.push(SpanBuf::new(name, first_char, last_char));
         }
 
-        Ok(u64::from(position))
+        Ok(spans)
     }
 
-    fn parse_string(&self, offset: u32) -> Result<String, Error> {
+    fn get_string_position(&self, idx: u32) -> Result<u64, Error> {
         let mut cursor = Cursor::new(self.raw_data);
-        cursor.set_position(u64::from(offset));
+        cursor.set_position(20);
+        let str_offset = cursor.read_u32::<LittleEndian>()?;
 
-        if self.is_utf8() {
-            let mut ini_offset = offset;
-            let v = u32::from(cursor.read_u8()?);
-            if v == 0x80 {
-                ini_offset += 2;
-                cursor.read_u8()?;
-            } else {
-                ini_offset += 1;
-            }
+        cursor.set_position(28 + u64::from(idx) * 4);
+        let current_offset = cursor.read_u32::<LittleEndian>()?;
+        let position = u64::from(str_offset) + u64::from(current_offset);
 
-            let v = u32::from(cursor.read_u8()?);
-            if v == 0x80 {
-                ini_offset += 2;
-                cursor.read_u8()?;
-            } else {
-                ini_offset += 1;
-            }
+        ensure!(
+            position < self.raw_data.len() as u64,
+            "string {} starts out of the string table",
+            idx
+        );
 
-            let mut length = 0;
+        Ok(position)
+    }
 
-            loop {
-                let v = cursor.read_u8()?;
+    fn parse_string(&self, offset: u32) -> Result<String, Error> {
+        let mut cursor = Cursor::new(self.raw_data);
+        cursor.set_position(u64::from(offset));
 
-                if v == 0 {
-                    break;
-                } else {
-                    length += 1;
-                }
-            }
+        if self.is_utf8() {
+            // UTF-8 strings are prefixed by their amount of UTF-16 code units, which is not
+            // needed, and their amount of bytes
+            Self::read_utf8_length(&mut cursor)?;
+            let length = Self::read_utf8_length(&mut cursor)?;
+            self.check_length(offset, u64::from(length))?;
 
-            let a = ini_offset;
-            let b = ini_offset + length;
+            let a = cursor.position();
+            let b = a + u64::from(length);
 
+            // As Android does, the string must end with a terminator right after its declared
+            // length
             ensure!(
-                a <= self.raw_data.len() as u32 && b <= self.raw_data.len() as u32 && a <= b,
-                "sub-slice out of raw_data range"
+                b < self.raw_data.len() as u64 && self.raw_data[b as usize] == 0,
+                "UTF-8 string at {} is not terminated after its length",
+                offset
             );
 
             let subslice: &[u8] = &self.raw_data[a as usize..b as usize];
@@ -126,16 +159,22 @@ impl<'a> StringTableWrapper<'a> {
                 Err(format_err!("error decoding UTF8 string"))
             }
         } else {
-            let size1 = u32::from(cursor.read_u8()?);
-            let size2 = u32::from(cursor.read_u8()?);
-
-            let val = ((size2 & 0xFF) << 8) | size1 & 0xFF;
+            // Strings longer than 0x7FFF units have their length on two words, with the high bit
+            // of the first one set
+            let high = u32::from(cursor.read_u16::<LittleEndian>()?);
+            let units = if high & 0x8000 == 0 {
+                high
+            } else {
+                ((high & 0x7FFF) << 16) | u32::from(cursor.read_u16::<LittleEndian>()?)
+            };
+            let size = u64::from(units) * 2;
+            self.check_length(offset, size)?;
 
-            let a = offset + 2;
-            let b = a + val * 2;
+            let a = cursor.position();
+            let b = a + size;
 
             ensure!(
-                a <= self.raw_data.len() as u32 && b <= self.raw_data.len() as u32 && a <= b,
+                b <= self.raw_data.len() as u64,
                 "sub-slice out of raw_data range"
             );
 
@@ -153,6 +192,27 @@ impl<'a> StringTableWrapper<'a> {
         }
     }
 
+    /// Reads one of the lengths that prefix UTF-8 strings. Lengths longer than 0x7F take two bytes,
+    /// with the high bit of the first one set.
+    fn read_utf8_length(cursor: &mut Cursor<&[u8]>) -> Result<u32, Error> {
+        let first = u32::from(cursor.read_u8()?);
+
+        if first & 0x80 == 0 {
+            Ok(first)
+        } else {
+            Ok(((first & 0x7F) << 8) | u32::from(cursor.read_u8()?))
+        }
+    }
+
+    fn check_length(&self, offset: u32, length: u64) -> Result<(), AbxmlError> {
+        check_limit(
+            "string length",
+            length,
+            self.max_string_length as u64,
+            Location::relative(u64::from(offset)),
+        )
+    }
+
     fn is_utf8(&self) -> bool {
         (self.get_flags() & 0x00000100) == 0x00000100
     }
@@ -174,7 +234,16 @@ impl<'a> StringTable for StringTableWrapper<'a> {
     }
 
     fn get_string(&self, idx: u32) -> Result<Rc<String>, Error> {
-        ensure!(idx <= self.get_strings_len(), "index out of bounds");
+        let len = self.get_strings_len();
+
+        if idx >= len {
+            return Err(AbxmlError::StringIndexOutOfRange {
+                location: Location::default(),
+                index: idx,
+                len,
+            }
+            .into());
+        }
 
         let string = self
             .get_string_position(idx)
@@ -1,9 +1,9 @@
 use std::{io::Cursor, string::ToString};
 
 use byteorder::{LittleEndian, ReadBytesExt};
-use failure::{bail, ensure, Error};
+use failure::{bail, Error};
 
-use crate::model::{owned::ConfigurationBuf, Configuration};
+use crate::model::{owned::ConfigurationBuf, Configuration, MASK_GRAMMATICAL_GENDER};
 
 #[derive(Debug)]
 pub struct ConfigurationWrapper<'a> {
@@ -18,6 +18,64 @@ impl<'a> ConfigurationWrapper<'a> {
     pub fn to_buffer(&self) -> Result<ConfigurationBuf, Error> {
         ConfigurationBuf::from_cursor(self.slice.into())
     }
+
+    /// Returns the script as it is stored, even if it was computed by aapt
+    pub(crate) fn get_raw_locale_script(&self) -> Result<Option<String>, Error> {
+        self.get_string_field(36, 4)
+    }
+
+    pub(crate) fn is_locale_script_computed(&self) -> Result<bool, Error> {
+        Ok(matches!(self.get_field(52, 1)?, Some(field) if field[0] != 0))
+    }
+
+    /// Returns a byte of the fields that are present on every configuration
+    fn get_byte(&self, offset: usize) -> Result<u8, Error> {
+        match self.slice.get(offset) {
+            Some(byte) => Ok(*byte),
+            None => bail!("not enough bytes to retrieve the field"),
+        }
+    }
+
+    /// Returns the bytes of the field, or `None` if the configuration is too old to contain it
+    fn get_field(&self, offset: usize, len: usize) -> Result<Option<&'a [u8]>, Error> {
+        if (self.get_size()? as usize) < offset + len {
+            return Ok(None);
+        }
+
+        match self.slice.get(offset..offset + len) {
+            Some(field) => Ok(Some(field)),
+            None => bail!("not enough bytes to retrieve the field"),
+        }
+    }
+
+    fn get_u8_field(&self, offset: usize) -> Result<u8, Error> {
+        match self.get_field(offset, 1)? {
+            Some(field) => Ok(field[0]),
+            None => bail!("not enough bytes to retrieve the field"),
+        }
+    }
+
+    fn get_u16_field(&self, offset: usize) -> Result<u16, Error> {
+        match self.get_field(offset, 2)? {
+            Some(field) => Ok(u16::from_le_bytes([field[0], field[1]])),
+            None => bail!("not enough bytes to retrieve the field"),
+        }
+    }
+
+    /// Returns the text of a fixed length field padded with zeros, if it is set
+    fn get_string_field(&self, offset: usize, len: usize) -> Result<Option<String>, Error> {
+        let text = self.get_field(offset, len)?.and_then(|field| {
+            let text: String = field
+                .iter()
+                .take_while(|byte| **byte != 0)
+                .map(|byte| char::from(*byte))
+                .collect();
+
+            Some(text).filter(|text| !text.is_empty())
+        });
+
+        Ok(text)
+    }
 }
 
 impl<'a> Configuration for ConfigurationWrapper<'a> {
@@ -43,29 +101,29 @@ impl<'a> Configuration for ConfigurationWrapper<'a> {
     }
 
     fn get_language(&self) -> Result<String, Error> {
-        let lang_low = self.slice[8];
-        let lang_high = self.slice[9];
+        let lang_low = self.get_byte(8)?;
+        let lang_high = self.get_byte(9)?;
 
         let region = Region::from((lang_low, lang_high));
 
-        Ok(region.to_string())
+        Ok(region.unpack(LANGUAGE_BASE))
     }
 
     fn get_region(&self) -> Result<String, Error> {
-        let lang_low = self.slice[10];
-        let lang_high = self.slice[11];
+        let lang_low = self.get_byte(10)?;
+        let lang_high = self.get_byte(11)?;
 
         let region = Region::from((lang_low, lang_high));
 
-        Ok(region.to_string())
+        Ok(region.unpack(REGION_BASE))
     }
 
     fn get_orientation(&self) -> Result<u8, Error> {
-        Ok(self.slice[12])
+        self.get_byte(12)
     }
 
     fn get_touchscreen(&self) -> Result<u8, Error> {
-        Ok(self.slice[13])
+        self.get_byte(13)
     }
 
     fn get_density(&self) -> Result<u16, Error> {
@@ -76,15 +134,15 @@ impl<'a> Configuration for ConfigurationWrapper<'a> {
     }
 
     fn get_keyboard(&self) -> Result<u8, Error> {
-        Ok(self.slice[16])
+        self.get_byte(16)
     }
 
     fn get_navigation(&self) -> Result<u8, Error> {
-        Ok(self.slice[17])
+        self.get_byte(17)
     }
 
     fn get_input_flags(&self) -> Result<u8, Error> {
-        Ok(self.slice[18])
+        self.get_byte(18)
     }
 
     fn get_width(&self) -> Result<u16, Error> {
@@ -116,68 +174,110 @@ impl<'a> Configuration for ConfigurationWrapper<'a> {
     }
 
     fn get_screen_layout(&self) -> Result<u8, Error> {
-        let size = self.get_size()?;
-        ensure!(size >= 28, "not enough bytes to retrieve the field");
-
-        Ok(self.slice[28])
+        self.get_u8_field(28)
     }
 
     fn get_ui_mode(&self) -> Result<u8, Error> {
-        let size = self.get_size()?;
-        ensure!(size >= 29, "not enough bytes to retrieve the field");
-
-        Ok(self.slice[29])
+        self.get_u8_field(29)
     }
 
     fn get_smallest_screen(&self) -> Result<u16, Error> {
-        let size = self.get_size()?;
-        ensure!(size >= 30, "not enough bytes to retrieve the field");
-
-        let mut cursor = Cursor::new(self.slice);
-        cursor.set_position(30);
-
-        Ok(cursor.read_u16::<LittleEndian>()?)
+        self.get_u16_field(30)
     }
 
     fn get_screen_width(&self) -> Result<u16, Error> {
-        let size = self.get_size()?;
-        ensure!(size >= 32, "not enough bytes to retrieve the field");
-
-        let mut cursor = Cursor::new(self.slice);
-        cursor.set_position(32);
-
-        Ok(cursor.read_u16::<LittleEndian>()?)
+        self.get_u16_field(32)
     }
 
     fn get_screen_height(&self) -> Result<u16, Error> {
-        let size = self.get_size()?;
-        ensure!(size >= 34, "not enough bytes to retrieve the field");
-
-        let mut cursor = Cursor::new(self.slice);
-        cursor.set_position(34);
-
-        Ok(cursor.read_u16::<LittleEndian>()?)
+        self.get_u16_field(34)
     }
 
+    /// Returns the script only if it was set on the resources, not if aapt computed it from the
+    /// language
     fn get_locale_script(&self) -> Result<Option<String>, Error> {
-        bail!("not enough bytes to retrieve the field")
+        if self.is_locale_script_computed()? {
+            return Ok(None);
+        }
+
+        self.get_raw_locale_script()
     }
 
     fn get_locale_variant(&self) -> Result<Option<String>, Error> {
-        bail!("not enough bytes to retrieve the field")
+        self.get_string_field(40, 8)
     }
 
     fn get_secondary_layout(&self) -> Result<Option<u8>, Error> {
-        bail!("not enough bytes to retrieve the field")
+        Ok(self.get_field(48, 1)?.map(|field| field[0]))
+    }
+
+    fn get_color_mode(&self) -> Result<u8, Error> {
+        Ok(self.get_field(49, 1)?.map_or(0, |field| field[0]))
+    }
+
+    fn get_grammatical_gender(&self) -> Result<u8, Error> {
+        Ok(self.get_u8_field(19)? & MASK_GRAMMATICAL_GENDER)
+    }
+
+    fn get_numbering_system(&self) -> Result<Option<String>, Error> {
+        self.get_string_field(53, 8)
     }
 }
 
+/// Base used to pack three letters language codes
+pub const LANGUAGE_BASE: u8 = b'a';
+/// Base used to pack three digits region codes (UN M.49, like `419`)
+pub const REGION_BASE: u8 = b'0';
+
 #[derive(Default, Debug, Copy, Clone)]
 pub struct Region {
     low: u8,
     high: u8,
 }
 
+impl Region {
+    /// Encodes a two or three characters code. Three characters codes do not fit on two bytes,
+    /// so they are packed as 5 bit offsets from `base` with the highest bit set, as aapt does.
+    #[must_use]
+    pub fn pack(code: &str, base: u8) -> Self {
+        if code == "any" {
+            return Self::default();
+        }
+
+        match *code.as_bytes() {
+            [low, high] => Self { low, high },
+            [first, second, third] => {
+                let first = first.wrapping_sub(base) & 0x1F;
+                let second = second.wrapping_sub(base) & 0x1F;
+                let third = third.wrapping_sub(base) & 0x1F;
+
+                Self {
+                    low: 0x80 | (third << 2) | (second >> 3),
+                    high: (second << 5) | first,
+                }
+            }
+            _ => Self::default(),
+        }
+    }
+
+    /// Decodes the code, unpacking it if it was a three characters one
+    #[must_use]
+    pub fn unpack(self, base: u8) -> String {
+        if self.low & 0x80 == 0 {
+            return self.to_string();
+        }
+
+        let first = self.high & 0x1F;
+        let second = ((self.high & 0xE0) >> 5) | ((self.low & 0x03) << 3);
+        let third = (self.low & 0x7C) >> 2;
+
+        [first, second, third]
+            .iter()
+            .map(|offset| char::from(base.wrapping_add(*offset)))
+            .collect()
+    }
+}
+
 impl Into<(u8, u8)> for Region {
     fn into(self) -> (u8, u8) {
         (self.low, self.high)
@@ -220,7 +320,9 @@ impl ToString for Region {
 
 #[cfg(test)]
 mod tests {
-    use super::{Configuration, ConfigurationWrapper, Region, ToString};
+    use super::{
+        Configuration, ConfigurationWrapper, Region, ToString, LANGUAGE_BASE, REGION_BASE,
+    };
     use crate::raw_chunks::EXAMPLE_CONFIGURATION;
 
     #[test]
@@ -255,6 +357,20 @@ mod tests {
         assert_eq!(0, high);
     }
 
+    #[test]
+    fn it_can_pack_three_characters_codes() {
+        let language = Region::pack("fil", LANGUAGE_BASE);
+        let region = Region::pack("419", REGION_BASE);
+
+        assert_eq!((0xAD, 0x05), language.into());
+        assert_eq!("fil", language.unpack(LANGUAGE_BASE));
+        assert_eq!("419", region.unpack(REGION_BASE));
+        assert_eq!(
+            "ca",
+            Region::pack("ca", LANGUAGE_BASE).unpack(LANGUAGE_BASE)
+        );
+    }
+
     #[test]
     fn it_can_decode_a_full_configuration_slice() {
         let wrapper = ConfigurationWrapper::new(EXAMPLE_CONFIGURATION);
@@ -272,4 +388,50 @@ mod tests {
         assert_eq!(0, wrapper.get_width().unwrap());
         assert_eq!(0, wrapper.get_height().unwrap());
     }
+
+    #[test]
+    fn it_can_decode_the_fields_of_modern_configurations() {
+        let mut raw = vec![0; 64];
+        raw[0] = 64;
+        raw[8..12].copy_from_slice(b"srRS");
+        raw[19] = 2;
+        raw[36..40].copy_from_slice(b"Latn");
+        raw[40..47].copy_from_slice(b"ekavsk\0");
+        raw[48] = 2;
+        raw[49] = 0x0A;
+        raw[53..57].copy_from_slice(b"latn");
+        let wrapper = ConfigurationWrapper::new(&raw);
+
+        assert_eq!(
+            Some("Latn".to_string()),
+            wrapper.get_locale_script().unwrap()
+        );
+        assert_eq!(
+            Some("ekavsk".to_string()),
+            wrapper.get_locale_variant().unwrap()
+        );
+        assert_eq!(Some(2), wrapper.get_secondary_layout().unwrap());
+        assert_eq!(0x0A, wrapper.get_color_mode().unwrap());
+        assert_eq!(2, wrapper.get_grammatical_gender().unwrap());
+        assert_eq!(
+            Some("latn".to_string()),
+            wrapper.get_numbering_system().unwrap()
+        );
+
+        raw[52] = 1;
+        let wrapper = ConfigurationWrapper::new(&raw);
+        assert_eq!(None, wrapper.get_locale_script().unwrap());
+    }
+
+    #[test]
+    fn it_does_not_decode_fields_newer_than_the_configuration() {
+        let wrapper = ConfigurationWrapper::new(EXAMPLE_CONFIGURATION);
+
+        assert_eq!(None, wrapper.get_locale_script().unwrap());
+        assert_eq!(Some(0), wrapper.get_secondary_layout().unwrap());
+        assert_eq!(None, wrapper.get_numbering_system().unwrap());
+        assert!(ConfigurationWrapper::new(&EXAMPLE_CONFIGURATION[..30])
+            .get_smallest_screen()
+            .is_err());
+    }
 }
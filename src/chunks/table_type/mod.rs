@@ -4,12 +4,16 @@ use byteorder::{LittleEndian, ReadBytesExt};
 use failure::{ensure, format_err, Error};
 use log::debug;
 
-use crate::model::{
-    owned::{ComplexEntry, Entry, EntryHeader, SimpleEntry, TableTypeBuf},
-    TableType,
+use crate::{
+    errors::Location,
+    model::{
+        owned::{ComplexEntry, Entry, EntryHeader, SimpleEntry, TableTypeBuf},
+        TableType,
+    },
+    options::{check_limit, DecodeLimits},
 };
 
-pub use self::configuration::{ConfigurationWrapper, Region};
+pub use self::configuration::{ConfigurationWrapper, Region, LANGUAGE_BASE, REGION_BASE};
 
 mod configuration;
 
@@ -17,6 +21,7 @@ mod configuration;
 pub struct TableTypeWrapper<'a> {
     raw_data: &'a [u8],
     data_offset: u64,
+    max_entries: u32,
 }
 
 impl<'a> TableTypeWrapper<'a> {
@@ -24,17 +29,30 @@ impl<'a> TableTypeWrapper<'a> {
         Self {
             raw_data,
             data_offset,
+            max_entries: DecodeLimits::default().get_max_entries_per_type(),
         }
     }
 
+    /// Fails to decode the types with more entries than the limit
+    #[must_use]
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.max_entries = limits.get_max_entries_per_type();
+        self
+    }
+
     pub fn to_buffer(&self) -> Result<TableTypeBuf, Error> {
         let id = self.get_id()?;
         let amount = self.get_amount()?;
         let config = self.get_configuration()?.to_buffer()?;
-        let mut owned = TableTypeBuf::new(id & 0xF, config);
+        let mut owned = TableTypeBuf::new(id, config);
+        let entries = self.get_entries()?;
+
+        ensure!(
+            entries.len() == amount as usize,
+            "could not decode all the entries of the type"
+        );
 
-        for i in 0..amount {
-            let entry = self.get_entry(i)?;
+        for entry in entries {
             owned.add_entry(entry);
         }
 
@@ -49,17 +67,18 @@ impl<'a> TableTypeWrapper<'a> {
     }
 
     fn decode_entries(&self, cursor: &mut Cursor<&[u8]>) -> Result<Vec<Entry>, Error> {
+        let amount = self.get_amount()?;
         let mut offsets = Vec::new();
         let mut entries = Vec::new();
 
-        for _ in 0..self.get_amount()? {
+        for _ in 0..amount {
             offsets.push(cursor.read_u32::<LittleEndian>()?);
         }
 
-        for i in 0..self.get_amount()? {
+        for (i, offset) in (0..amount).zip(offsets) {
             let id = i & 0xFFFF;
 
-            if offsets[i as usize] == 0xFFFF_FFFF {
+            if offset == 0xFFFF_FFFF {
                 entries.push(Entry::Empty(id, id));
             } else {
                 let maybe_entry = Self::decode_entry(cursor, id)?;
@@ -112,12 +131,14 @@ impl<'a> TableTypeWrapper<'a> {
     ) -> Result<Option<Entry>, Error> {
         let parent_entry = cursor.read_u32::<LittleEndian>()?;
         let value_count = cursor.read_u32::<LittleEndian>()?;
-        let mut entries = Vec::with_capacity(value_count as usize);
 
         if value_count == 0xFFFF_FFFF {
             return Ok(None);
         }
 
+        // The count comes from the file, so the vector is not preallocated with it
+        let mut entries = Vec::new();
+
         for _ in 0..value_count {
             let val_id = cursor.read_u32::<LittleEndian>()?;
             cursor.read_u16::<LittleEndian>()?;
@@ -144,7 +165,7 @@ impl<'a> TableType for TableTypeWrapper<'a> {
     fn get_id(&self) -> Result<u8, Error> {
         let mut cursor = Cursor::new(self.raw_data);
         cursor.set_position(8);
-        let out_value = cursor.read_u32::<LittleEndian>()? & 0xF;
+        let out_value = cursor.read_u32::<LittleEndian>()? & 0xFF;
 
         Ok(out_value as u8)
     }
@@ -152,8 +173,23 @@ impl<'a> TableType for TableTypeWrapper<'a> {
     fn get_amount(&self) -> Result<u32, Error> {
         let mut cursor = Cursor::new(self.raw_data);
         cursor.set_position(12);
+        let amount = cursor.read_u32::<LittleEndian>()?;
+        check_limit(
+            "entries per type",
+            u64::from(amount),
+            u64::from(self.max_entries),
+            Location::relative(12),
+        )?;
+        let available = (self.raw_data.len() as u64).saturating_sub(self.data_offset) / 4;
+
+        ensure!(
+            u64::from(amount) <= available,
+            "the type has {} entries but there is only space for {}",
+            amount,
+            available
+        );
 
-        Ok(cursor.read_u32::<LittleEndian>()?)
+        Ok(amount)
     }
 
     fn get_configuration(&self) -> Result<Self::Configuration, Error> {
@@ -39,8 +39,16 @@ impl<'a> TypeSpec for TypeSpecWrapper<'a> {
     fn get_amount(&self) -> Result<u32, Error> {
         let mut cursor = Cursor::new(self.raw_data);
         cursor.set_position(12);
+        let amount = cursor.read_u32::<LittleEndian>()?;
 
-        Ok(cursor.read_u32::<LittleEndian>()?)
+        ensure!(
+            16 + u64::from(amount) * 4 <= self.raw_data.len() as u64,
+            "the type spec has {} flags but there is only space for {}",
+            amount,
+            self.raw_data.len().saturating_sub(16) / 4
+        );
+
+        Ok(amount)
     }
 
     fn get_flag(&self, index: u32) -> Result<u32, Error> {
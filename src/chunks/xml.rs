@@ -4,7 +4,10 @@ use byteorder::{LittleEndian, ReadBytesExt};
 use failure::{ensure, Error, ResultExt};
 
 use crate::model::{
-    owned::{AttributeBuf, XmlNamespaceEndBuf, XmlNamespaceStartBuf, XmlTagEndBuf, XmlTagStartBuf},
+    owned::{
+        AttributeBuf, XmlNamespaceEndBuf, XmlNamespaceStartBuf, XmlTagEndBuf, XmlTagStartBuf,
+        XmlTextBuf,
+    },
     AttributeTrait, NamespaceEnd, NamespaceStart, StringTable, TagEnd, TagStart,
 };
 
@@ -327,8 +330,36 @@ impl<'a> XmlTagEndWrapper<'a> {
         Self { raw_data }
     }
 
+    /// Returns the line of the source file where the element is closed
+    ///
+    /// # Errors
+    ///
+    /// It fails if the chunk is too short to hold the line.
+    pub fn get_line(&self) -> Result<u32, Error> {
+        let mut cursor = Cursor::new(self.raw_data);
+        cursor.set_position(8);
+
+        Ok(cursor.read_u32::<LittleEndian>()?)
+    }
+
+    /// Return the namespace index. If there is no namespace, it will return `0xFFFF_FFFF`
+    ///
+    /// # Errors
+    ///
+    /// It fails if the chunk is too short to hold the namespace.
+    pub fn get_namespace_index(&self) -> Result<u32, Error> {
+        let mut cursor = Cursor::new(self.raw_data);
+        cursor.set_position(16);
+
+        Ok(cursor.read_u32::<LittleEndian>()?)
+    }
+
     pub fn to_buffer(&self) -> Result<XmlTagEndBuf, Error> {
-        Ok(XmlTagEndBuf::new(self.get_id()?))
+        Ok(XmlTagEndBuf::with_namespace(
+            self.get_line()?,
+            self.get_namespace_index()?,
+            self.get_id()?,
+        ))
     }
 }
 
@@ -351,6 +382,20 @@ impl<'a> XmlTextWrapper<'a> {
         Self { raw_data }
     }
 
+    /// Returns the line of the source file where the text is
+    ///
+    /// # Errors
+    ///
+    /// It fails if the chunk is too short to hold the line.
+    pub fn get_line(&self) -> Result<u32, Error> {
+        let mut cursor = Cursor::new(self.raw_data);
+        cursor.set_position(8);
+
+        Ok(cursor
+            .read_u32::<LittleEndian>()
+            .context("could not get line")?)
+    }
+
     pub fn get_text_index(&self) -> Result<u32, Error> {
         let mut cursor = Cursor::new(self.raw_data);
         cursor.set_position(16);
@@ -359,4 +404,13 @@ impl<'a> XmlTextWrapper<'a> {
             .read_u32::<LittleEndian>()
             .context("could not get data")?)
     }
+
+    /// Copies the chunk into an owned buffer that can be edited
+    ///
+    /// # Errors
+    ///
+    /// It fails if the chunk is too short to hold the line or the text index.
+    pub fn to_buffer(&self) -> Result<XmlTextBuf, Error> {
+        Ok(XmlTextBuf::new(self.get_line()?, self.get_text_index()?))
+    }
 }
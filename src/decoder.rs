@@ -5,6 +5,9 @@ use std::io::{Cursor, Read};
 use failure::{Error, ResultExt};
 
 use crate::{
+    errors::Diagnostic,
+    options::ParseOptions,
+    reader::BinaryXmlReader,
     visitor::{Executor, ModelVisitor, Resources, XmlVisitor},
     STR_ARSC,
 };
@@ -38,6 +41,15 @@ impl BufferedDecoder {
     pub fn get_decoder(&self) -> Result<Decoder, Error> {
         Decoder::from_buffer(&self.buffer)
     }
+
+    /// Decodes the resources of the APK, handling the problems as `options` says
+    ///
+    /// # Errors
+    ///
+    /// It fails if the framework or the APK resources can not be decoded.
+    pub fn get_decoder_with_options(&self, options: ParseOptions) -> Result<Decoder<'_>, Error> {
+        Decoder::from_buffer_with_options(&self.buffer, options)
+    }
 }
 
 #[derive(Debug)]
@@ -45,26 +57,55 @@ pub struct Decoder<'a> {
     visitor: ModelVisitor<'a>,
     buffer_android: &'a [u8],
     buffer_apk: &'a [u8],
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<'a> Decoder<'a> {
     pub fn from_buffer(buffer_apk: &'a [u8]) -> Result<Self, Error> {
-        let visitor = ModelVisitor::default();
+        Self::from_buffer_with_options(buffer_apk, ParseOptions::default())
+    }
+
+    /// Decodes the `resources.arsc` of the APK handling the problems as `options` says. The
+    /// problems skipped on lenient mode are available on `get_diagnostics`.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the framework resources or the `resources.arsc` of the APK can not be decoded.
+    /// On lenient mode, only the problems that can not be skipped fail.
+    pub fn from_buffer_with_options(
+        buffer_apk: &'a [u8],
+        options: ParseOptions,
+    ) -> Result<Self, Error> {
+        let visitor = ModelVisitor::with_options(options);
 
         let mut decoder = Self {
             visitor,
             buffer_android: STR_ARSC,
             buffer_apk,
+            diagnostics: Vec::new(),
         };
 
         Executor::arsc(decoder.buffer_android, &mut decoder.visitor)
             .context("could not read Android lib resources")?;
-        Executor::arsc(decoder.buffer_apk, &mut decoder.visitor)
-            .context("could not read target APK resources")?;
+        let framework_packages = decoder.visitor.get_resources().get_package_count();
+        decoder.diagnostics =
+            Executor::arsc_with_options(decoder.buffer_apk, &mut decoder.visitor, options)
+                .context("could not read target APK resources")?;
+
+        // APKs without a `resources.arsc` only have the framework resources: their references
+        // to their own resources are kept raw instead of failing
+        let missing_app_package =
+            decoder.visitor.get_resources().get_package_count() == framework_packages;
+        decoder.visitor.set_missing_app_package(missing_app_package);
 
         Ok(decoder)
     }
 
+    #[must_use]
+    pub fn get_diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
     pub fn get_resources(&self) -> &'a Resources {
         self.visitor.get_resources()
     }
@@ -77,6 +118,35 @@ impl<'a> Decoder<'a> {
 
         Ok(visitor)
     }
+
+    /// Decodes the binary XML as `xml_visitor` does, handling the problems as `options` says. It
+    /// returns also the problems that were skipped on lenient mode.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the document is not a binary XML or it has problems that can not be skipped.
+    pub fn xml_visitor_with_options<T: AsRef<[u8]>>(
+        &self,
+        content: &'a T,
+        options: ParseOptions,
+    ) -> Result<(XmlVisitor<'_>, Vec<Diagnostic>), Error> {
+        let cursor = Cursor::new(content.as_ref());
+        let mut visitor = XmlVisitor::with_options(self.get_resources(), options);
+
+        let diagnostics = Executor::xml_with_options(cursor, &mut visitor, options)?;
+
+        Ok((visitor, diagnostics))
+    }
+
+    /// Creates a streaming reader over the binary XML, with references resolved against the decoded
+    /// resources
+    ///
+    /// # Errors
+    ///
+    /// It fails if the contents do not start with a binary XML header.
+    pub fn xml_reader<T: AsRef<[u8]>>(&self, content: &'a T) -> Result<BinaryXmlReader<'_>, Error> {
+        BinaryXmlReader::new(content.as_ref(), self.get_resources())
+    }
 }
 
 #[cfg(test)]
@@ -84,6 +154,7 @@ mod tests {
     use std::io::Cursor;
 
     use super::BufferedDecoder;
+    use crate::errors::AbxmlError;
 
     #[test]
     fn it_can_not_decode_an_empty_binary_xml() {
@@ -95,8 +166,12 @@ mod tests {
 
         // Empty binary XML file
         let another = vec![3, 0, 0, 0, 0, 0, 0, 0];
-        let xml_result = decoder.xml_visitor(&another).unwrap().into_string();
-        assert!(xml_result.is_err());
+        let error = decoder.xml_visitor(&another).err().unwrap();
+
+        match error.downcast_ref::<AbxmlError>() {
+            Some(AbxmlError::InvalidChunk { .. }) => {}
+            _ => panic!("unexpected error: {}", error),
+        }
     }
 
     #[test]
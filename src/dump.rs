@@ -0,0 +1,523 @@
+//! Text dumps of resource tables and binary XMLs with the same format as `aapt2 dump resources`
+//! and `aapt2 dump xmltree`
+
+use std::{ffi::OsStr, fmt::Write, io::Cursor, path::Path};
+
+use failure::{format_err, Error, ResultExt};
+use log::warn;
+
+use crate::{
+    chunks::{
+        skip_document_header, Chunk, ChunkLoaderStream, StringTableCache, StringTableWrapper,
+        XmlTagStartWrapper, TOKEN_XML,
+    },
+    model::{
+        owned::{ComplexEntry, Entry},
+        AttributeFormat, AttributeTrait, BagValue, Configuration, Library, NamespaceStart,
+        ResourceId, Resources as ResourcesTrait, StringTable, TagStart, TypeSpec, Value,
+    },
+    visitor::model::{Library as Package, Resources},
+};
+
+const INDENT: &str = "  ";
+const NO_NAMESPACE: u32 = 0xFFFF_FFFF;
+
+/// Dumps the package with the given id as `aapt2 dump resources` does: a header per type and, for
+/// each resource, its value on every configuration. References are printed with the name of the
+/// resource when it is known and with its id otherwise.
+///
+/// # Errors
+///
+/// It fails if there is no package with the given id or some of its values can not be decoded.
+pub fn dump_resources(resources: &Resources, package_id: u8) -> Result<String, Error> {
+    let package = resources
+        .get_package(package_id)
+        .ok_or_else(|| format_err!("could not find package {}", package_id))?;
+    let package_name = package
+        .get_name()
+        .ok_or_else(|| format_err!("package {} does not have a name", package_id))?;
+    let reference = |id: ResourceId| match resources.name_of(id) {
+        Some(name) if name.get_package() == Some(package_name.as_str()) => {
+            format!("{}/{}", name.get_type(), name.get_entry())
+        }
+        Some(name) => name.to_string(),
+        None => id.to_string(),
+    };
+
+    let mut specs: Vec<_> = package.get_type_specs().collect();
+    specs.sort_by_key(|spec| spec.get_id().unwrap_or_default());
+
+    let mut out = String::new();
+    writeln!(out, "Package name={package_name} id={package_id:02x}")?;
+
+    for spec in specs {
+        let type_id = spec.get_id()? as u8;
+        let amount = spec.get_amount()?;
+        let type_name = u32::from(type_id)
+            .checked_sub(1)
+            .ok_or_else(|| format_err!("invalid type id {}", type_id))
+            .and_then(|index| package.get_spec_string(index))?;
+
+        writeln!(
+            out,
+            "{INDENT}type {type_name} id={type_id:02x} entryCount={amount}"
+        )?;
+
+        for index in 0..amount {
+            let id = ResourceId::new(package_id, type_id, index as u16);
+            let entries = package.get_config_entries(id);
+            let name = match resources.name_of(id) {
+                Some(name) => name.get_entry().to_string(),
+                None if entries.is_empty() => continue,
+                None => format!("{index}"),
+            };
+            let visibility = if package.get_spec_flags(id)?.is_public() {
+                " PUBLIC"
+            } else {
+                ""
+            };
+
+            writeln!(
+                out,
+                "{INDENT}{INDENT}resource {id} {type_name}/{name}{visibility}"
+            )?;
+
+            for (config, entry) in entries {
+                let qualifiers = config.to_qualifier_string()?;
+                let indent = INDENT.repeat(3);
+                write!(out, "{indent}({qualifiers}) ")?;
+                write_entry(&mut out, package, &type_name, entry, &reference)
+                    .context(format_err!("could not dump {} on ({})", id, qualifiers))?;
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Dumps the tree of a binary XML as `aapt2 dump xmltree` does: namespaces (`N:`), elements
+/// (`E:`), attributes (`A:`) with the id of the attribute resource, their typed value and their
+/// raw string, and texts (`T:`). References are printed as ids.
+///
+/// # Errors
+///
+/// It fails if the buffer is not a binary XML or one of its chunks can not be decoded.
+pub fn dump_xml_tree(buffer: &[u8]) -> Result<String, Error> {
+    let mut cursor = Cursor::new(buffer);
+    skip_document_header(&mut cursor, TOKEN_XML)?;
+
+    let mut string_table: Option<StringTableCache<StringTableWrapper>> = None;
+    let mut xml_resources = Vec::new();
+    let mut depth = 0_usize;
+    let mut out = String::new();
+
+    for chunk in ChunkLoaderStream::new(cursor) {
+        match chunk? {
+            Chunk::StringTable(stw) if string_table.is_none() => {
+                string_table = Some(StringTableCache::new(stw));
+            }
+            Chunk::Resource(rw) => xml_resources = rw.get_resources()?,
+            Chunk::XmlNamespaceStart(xnsw) => {
+                let strings = get_strings(string_table.as_ref())?;
+                writeln!(
+                    out,
+                    "{}N: {}={} (line={})",
+                    INDENT.repeat(depth),
+                    xnsw.get_prefix(strings)?,
+                    xnsw.get_namespace(strings)?,
+                    xnsw.get_line()?
+                )?;
+                depth += 1;
+            }
+            Chunk::XmlNamespaceEnd(_) => depth = depth.saturating_sub(1),
+            Chunk::XmlTagStart(xtsw) => {
+                let strings = get_strings(string_table.as_ref())?;
+                write_element(&mut out, &xtsw, strings, &xml_resources, depth)?;
+                depth += 2;
+            }
+            Chunk::XmlTagEnd(_) => depth = depth.saturating_sub(2),
+            Chunk::XmlText(xtw) => {
+                let strings = get_strings(string_table.as_ref())?;
+                writeln!(
+                    out,
+                    "{}T: '{}'",
+                    INDENT.repeat(depth),
+                    strings.get_string(xtw.get_text_index()?)?
+                )?;
+            }
+            _ => (),
+        }
+    }
+
+    Ok(out)
+}
+
+fn get_strings<'a, 'b>(
+    string_table: Option<&'b StringTableCache<StringTableWrapper<'a>>>,
+) -> Result<&'b StringTableCache<StringTableWrapper<'a>>, Error> {
+    string_table.ok_or_else(|| format_err!("no main string table found"))
+}
+
+fn write_element<S: StringTable>(
+    out: &mut String,
+    tag_start: &XmlTagStartWrapper,
+    strings: &S,
+    xml_resources: &[u32],
+    depth: usize,
+) -> Result<(), Error> {
+    write!(out, "{}E: ", INDENT.repeat(depth))?;
+
+    let namespace = tag_start.get_namespace_index()?;
+    if namespace != NO_NAMESPACE {
+        write!(out, "{}:", strings.get_string(namespace)?)?;
+    }
+
+    writeln!(
+        out,
+        "{} (line={})",
+        strings.get_string(tag_start.get_element_name_index()?)?,
+        tag_start.get_line()?
+    )?;
+
+    for index in 0..tag_start.get_attributes_amount()? {
+        let attribute = tag_start.get_attribute(index)?;
+        write!(out, "{}A: ", INDENT.repeat(depth + 1))?;
+
+        let namespace = attribute.get_namespace()?;
+        if namespace != NO_NAMESPACE {
+            write!(out, "{}:", strings.get_string(namespace)?)?;
+        }
+
+        let name = attribute.get_name()?;
+        write!(out, "{}", strings.get_string(name)?)?;
+
+        match xml_resources.get(name as usize) {
+            Some(id) if *id != 0 => write!(out, "({})", ResourceId::from(*id))?,
+            _ => (),
+        }
+
+        let data_type = ((attribute.get_resource_value()? >> 24) & 0xFF) as u8;
+        let value = match Value::create(data_type, attribute.get_data()?)? {
+            Value::StringReference(index) => Value::String((*strings.get_string(index)?).clone()),
+            value => value,
+        };
+        write!(
+            out,
+            "={}",
+            format_value(&value, &|id: ResourceId| id.to_string())
+        )?;
+
+        let raw = attribute.get_class()?;
+        if raw != NO_NAMESPACE {
+            write!(out, " (Raw: \"{}\")", strings.get_string(raw)?)?;
+        }
+
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+fn write_entry<F: Fn(ResourceId) -> String>(
+    out: &mut String,
+    package: &Package,
+    type_name: &str,
+    entry: &Entry,
+    reference: &F,
+) -> Result<(), Error> {
+    let complex = match entry {
+        Entry::Empty(_, _) => return writeln!(out, "@null").map_err(Error::from),
+        Entry::Simple(simple) => {
+            let value = match Value::create(simple.get_type(), simple.get_value())? {
+                Value::StringReference(index) => {
+                    Value::String((*package.get_global_string(index)?).clone())
+                }
+                value => value,
+            };
+
+            return match value {
+                Value::String(path) if type_name != "string" && path.starts_with("res/") => {
+                    writeln!(out, "(file) {} type={}", path, file_type(&path))
+                }
+                value => writeln!(out, "{}", format_value(&value, reference)),
+            }
+            .map_err(Error::from);
+        }
+        Entry::Complex(complex) => complex,
+    };
+
+    write_bag(out, package, type_name, complex, reference)
+}
+
+fn write_bag<F: Fn(ResourceId) -> String>(
+    out: &mut String,
+    package: &Package,
+    type_name: &str,
+    complex: &ComplexEntry,
+    reference: &F,
+) -> Result<(), Error> {
+    let body_indent = INDENT.repeat(4);
+    let strings = package.get_global_strings()?;
+
+    match BagValue::new(type_name, complex, strings) {
+        Ok(BagValue::Style(style)) => {
+            write!(out, "(style) size={}", style.get_items().len())?;
+            if !style.get_parent().is_null() {
+                write!(out, " parent=@{}", reference(style.get_parent()))?;
+            }
+            writeln!(out)?;
+
+            for (attribute, value) in style.get_items() {
+                writeln!(
+                    out,
+                    "{}{}={}",
+                    body_indent,
+                    reference(*attribute),
+                    format_value(value, reference)
+                )?;
+            }
+        }
+        Ok(BagValue::Array(array)) => {
+            writeln!(out, "(array) size={}", array.get_items().len())?;
+
+            for (index, value) in array.get_items().iter().enumerate() {
+                writeln!(
+                    out,
+                    "{}[{}]={}",
+                    body_indent,
+                    index,
+                    format_value(value, reference)
+                )?;
+            }
+        }
+        Ok(BagValue::Plurals(plurals)) => {
+            writeln!(out, "(plurals) size={}", plurals.get_quantities().len())?;
+
+            for (quantity, value) in plurals.get_quantities() {
+                writeln!(
+                    out,
+                    "{}{}={}",
+                    body_indent,
+                    quantity,
+                    format_value(value, reference)
+                )?;
+            }
+            for key in plurals.get_skipped() {
+                warn!("skipped the unknown plurals quantity {}", reference(*key));
+            }
+        }
+        Ok(BagValue::Attr(attr)) => {
+            let format = attr.get_format();
+            if format == AttributeFormat::ANY {
+                write!(out, "(attr) type=any")?;
+            } else {
+                write!(out, "(attr) type={format}")?;
+            }
+            if let Some(min) = attr.get_min() {
+                write!(out, " min={min}")?;
+            }
+            if let Some(max) = attr.get_max() {
+                write!(out, " max={max}")?;
+            }
+            writeln!(out)?;
+
+            for symbol in attr.get_symbols() {
+                writeln!(
+                    out,
+                    "{}{}=0x{:08x}",
+                    body_indent,
+                    reference(symbol.get_name()),
+                    symbol.get_value()
+                )?;
+            }
+        }
+        Ok(BagValue::Styleable(styleable)) => {
+            writeln!(out, "(styleable) size={}", styleable.get_attributes().len())?;
+
+            for attribute in styleable.get_attributes() {
+                writeln!(out, "{}{}", body_indent, reference(*attribute))?;
+            }
+        }
+        Err(_) => {
+            writeln!(out, "(bag) size={}", complex.get_entries().len())?;
+
+            for item in complex.get_entries() {
+                let value = Value::create(item.get_type(), item.get_value())?;
+                writeln!(
+                    out,
+                    "{}{}={}",
+                    body_indent,
+                    reference(item.get_id()),
+                    format_value(&value, reference)
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats a value as aapt2 prints it. `reference` formats the ids of the referenced resources.
+fn format_value<F: Fn(ResourceId) -> String + ?Sized>(value: &Value, reference: &F) -> String {
+    match value {
+        Value::Null => "@null".to_string(),
+        Value::Empty => "@empty".to_string(),
+        Value::String(text) => format!(
+            "\"{}\"",
+            text.replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        ),
+        Value::StringReference(index) => format!("(string) 0x{index:08x}"),
+        Value::Dimension(dimension) => {
+            let unit = match dimension.get_unit().as_str() {
+                "dip" => "dp",
+                unit => unit,
+            };
+
+            format!("{}{}", dimension.get_value(), unit)
+        }
+        Value::Fraction(fraction) => format!(
+            "{}{}",
+            fraction.get_value() * 100.0,
+            fraction.get_unit().as_str()
+        ),
+        Value::Float(float) => float.to_string(),
+        Value::Integer(integer) => integer.to_string(),
+        Value::IntegerHex(integer) => format!("0x{integer:08x}"),
+        Value::Boolean(boolean) => boolean.to_string(),
+        Value::ColorARGB8(color)
+        | Value::ColorRGB8(color)
+        | Value::ColorARGB4(color)
+        | Value::ColorRGB4(color) => format!("#{color:08x}"),
+        Value::ReferenceId(id) | Value::DynamicReferenceId(id) if id.is_null() => {
+            "@null".to_string()
+        }
+        Value::ReferenceId(id) | Value::DynamicReferenceId(id) => format!("@{}", reference(*id)),
+        Value::AttributeReferenceId(id) | Value::DynamicAttributeReferenceId(id) => {
+            format!("?{}", reference(*id))
+        }
+        Value::Unknown(data_type, data) => format!("(unknown 0x{data_type:02x}) 0x{data:08x}"),
+    }
+}
+
+/// Type of a file resource, as aapt2 names it
+fn file_type(path: &str) -> &'static str {
+    match Path::new(path).extension().and_then(OsStr::to_str) {
+        Some(extension) if extension.eq_ignore_ascii_case("xml") => "XML",
+        Some(extension) if extension.eq_ignore_ascii_case("png") => "PNG",
+        _ => "UNKNOWN",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dump_resources, dump_xml_tree};
+    use crate::{
+        model::{
+            builder::Xml,
+            owned::{
+                AttributeBuf, ConfigurationBuf, ResourcesBuf, StringTableBuf, XmlNamespaceEndBuf,
+                XmlNamespaceStartBuf, XmlTagEndBuf, XmlTagStartBuf,
+            },
+            AttributeValue, ResourceId, ResourceTable, TableEntry,
+        },
+        visitor::{Executor, ModelVisitor},
+    };
+
+    #[test]
+    fn it_dumps_the_resources_of_a_package() {
+        let default = ConfigurationBuf::default();
+        let catalan = ConfigurationBuf::from_qualifiers("ca").unwrap();
+
+        let mut android = ResourceTable::default();
+        android.add_package(0x01, "android").unwrap();
+        let text_color = android.add_resource(0x01, "attr", "textColor").unwrap();
+        let empty_bag = TableEntry::Bag {
+            parent: ResourceId::default(),
+            items: Vec::new(),
+        };
+        android.set_entry(text_color, &default, empty_bag).unwrap();
+
+        let mut app = ResourceTable::default();
+        app.add_package(0x7F, "com.example").unwrap();
+        let title = app.add_resource(0x7F, "string", "title").unwrap();
+        let icon = app.add_resource(0x7F, "drawable", "icon").unwrap();
+        let theme = app.add_resource(0x7F, "style", "AppTheme").unwrap();
+        let value = |text: &str| TableEntry::Value(AttributeValue::String(text.to_string()));
+        app.set_entry(title, &default, value("Title")).unwrap();
+        app.set_entry(title, &catalan, value("Títol")).unwrap();
+        app.set_entry(icon, &default, value("res/drawable/icon.png"))
+            .unwrap();
+        let style = TableEntry::Bag {
+            parent: ResourceId::default(),
+            items: vec![(text_color, AttributeValue::Reference(title))],
+        };
+        app.set_entry(theme, &default, style).unwrap();
+        app.set_public(title, true).unwrap();
+
+        let (android, app) = (android.to_vec().unwrap(), app.to_vec().unwrap());
+        let mut visitor = ModelVisitor::default();
+        Executor::arsc(&android, &mut visitor).unwrap();
+        Executor::arsc(&app, &mut visitor).unwrap();
+
+        let dump = dump_resources(visitor.get_resources(), 0x7F).unwrap();
+
+        assert_eq!(
+            "Package name=com.example id=7f\n  \
+             type string id=01 entryCount=1\n    \
+             resource 0x7f010000 string/title PUBLIC\n      \
+             () \"Title\"\n      \
+             (ca) \"Títol\"\n  \
+             type drawable id=02 entryCount=1\n    \
+             resource 0x7f020000 drawable/icon\n      \
+             () (file) res/drawable/icon.png type=PNG\n  \
+             type style id=03 entryCount=1\n    \
+             resource 0x7f030000 style/AppTheme\n      \
+             () (style) size=1\n        \
+             android:attr/textColor=@string/title\n",
+            dump
+        );
+    }
+
+    #[test]
+    fn it_dumps_the_tree_of_a_binary_xml() {
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("versionCode".to_string());
+        st.add_string("android".to_string());
+        st.add_string("http://schemas.android.com/apk/res/android".to_string());
+        st.add_string("manifest".to_string());
+        st.add_string("package".to_string());
+        st.add_string("com.example".to_string());
+
+        let mut tag_start = XmlTagStartBuf::new(2, 0xFFFF_FFFF, 0xFFFF_FFFF, 3, 0x0014_0014, 0);
+        tag_start.add_attribute(AttributeBuf::new(2, 0, 0xFFFF_FFFF, 0x10 << 24, 12));
+        tag_start.add_attribute(AttributeBuf::new(0xFFFF_FFFF, 4, 5, 3 << 24, 5));
+
+        let mut resources = ResourcesBuf::default();
+        resources.push_resource(0x0101_021B);
+
+        xml.push_owned(Box::new(st));
+        xml.push_owned(Box::new(resources));
+        xml.push_owned(Box::new(XmlNamespaceStartBuf::new(1, 1, 2)));
+        xml.push_owned(Box::new(tag_start));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(3)));
+        xml.push_owned(Box::new(XmlNamespaceEndBuf::new(1, 1, 2)));
+
+        let dump = dump_xml_tree(&xml.into_vec().unwrap()).unwrap();
+
+        assert_eq!(
+            "N: android=http://schemas.android.com/apk/res/android (line=1)\n  \
+             E: manifest (line=2)\n    \
+             A: http://schemas.android.com/apk/res/android:versionCode(0x0101021b)=12\n    \
+             A: package=\"com.example\" (Raw: \"com.example\")\n",
+            dump
+        );
+    }
+
+    #[test]
+    fn it_does_not_dump_non_xml_documents() {
+        assert!(dump_xml_tree(&[2, 0, 12, 0, 0, 0, 0, 0]).is_err());
+    }
+}
@@ -0,0 +1,332 @@
+//! Typed errors with the location of the bytes that could not be decoded
+
+use std::fmt;
+
+use failure::{Error, Fail};
+use log::warn;
+
+use crate::{chunks::token_name, model::ResourceId};
+
+/// Chunk that contains the location of an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkContext {
+    token: u16,
+    offset: u64,
+}
+
+impl ChunkContext {
+    #[must_use]
+    pub fn new(token: u16, offset: u64) -> Self {
+        Self { token, offset }
+    }
+
+    #[must_use]
+    pub fn get_token(self) -> u16 {
+        self.token
+    }
+
+    /// Absolute offset of the chunk on the file
+    #[must_use]
+    pub fn get_offset(self) -> u64 {
+        self.offset
+    }
+
+    /// Name of the type of the chunk, as it appears on the Android sources
+    #[must_use]
+    pub fn get_name(self) -> &'static str {
+        token_name(self.token)
+    }
+}
+
+/// Offset of the bytes that caused an error and the chunks that contain them, from the outermost
+/// to the innermost
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Location {
+    offset: u64,
+    path: Vec<ChunkContext>,
+}
+
+impl Location {
+    #[must_use]
+    pub fn new(offset: u64, path: Vec<ChunkContext>) -> Self {
+        Self { offset, path }
+    }
+
+    /// Location relative to the start of the chunk being decoded. It becomes absolute once the
+    /// chunk path is known.
+    #[must_use]
+    pub fn relative(offset: u64) -> Self {
+        Self::new(offset, Vec::new())
+    }
+
+    #[must_use]
+    pub fn get_offset(&self) -> u64 {
+        self.offset
+    }
+
+    #[must_use]
+    pub fn get_path(&self) -> &[ChunkContext] {
+        &self.path
+    }
+}
+
+/// Formats the offset and the chunk path (`0x1c (RES_XML_TYPE@0x0 > RES_XML_START_ELEMENT_TYPE@0x14)`)
+impl fmt::Display for Location {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "0x{:x}", self.offset)?;
+
+        if !self.path.is_empty() {
+            let path: Vec<String> = self
+                .path
+                .iter()
+                .map(|chunk| format!("{}@0x{:x}", chunk.get_name(), chunk.offset))
+                .collect();
+            write!(formatter, " ({})", path.join(" > "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Kinds of failures found while decoding binary files. The decoding functions return
+/// `failure::Error`, which can be downcast to this type to know what failed and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbxmlError {
+    /// The file ends before the end of a chunk
+    TruncatedChunk {
+        location: Location,
+        needed: u64,
+        available: u64,
+    },
+    /// The file does not start with the expected chunk
+    BadToken {
+        location: Location,
+        expected: u16,
+        found: u16,
+    },
+    /// The sizes on the header of a chunk are not consistent
+    InvalidChunk { location: Location, reason: String },
+    /// A string was requested with an index that is not on the string table
+    StringIndexOutOfRange {
+        location: Location,
+        index: u32,
+        len: u32,
+    },
+    /// A reference points to a resource that is not on the loaded packages
+    UnresolvedReference { location: Location, id: ResourceId },
+    /// The chunk has a type that is not known or not expected on the document
+    UnknownChunk { location: Location, token: u16 },
+    /// One of the `DecodeLimits` was exceeded
+    LimitExceeded {
+        location: Location,
+        limit: &'static str,
+        max: u64,
+        found: u64,
+    },
+    /// Any other problem found while decoding a chunk
+    Malformed { location: Location, reason: String },
+}
+
+impl fmt::Display for AbxmlError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::TruncatedChunk {
+                location,
+                needed,
+                available,
+            } => write!(
+                formatter,
+                "truncated chunk at {location}: it needs {needed} bytes but only {available} \
+                are available"
+            ),
+            Self::BadToken {
+                location,
+                expected,
+                found,
+            } => write!(
+                formatter,
+                "unexpected token 0x{found:04x} at {location}, expected 0x{expected:04x}"
+            ),
+            Self::InvalidChunk { location, reason } => {
+                write!(formatter, "invalid chunk header at {location}: {reason}")
+            }
+            Self::StringIndexOutOfRange {
+                location,
+                index,
+                len,
+            } => write!(
+                formatter,
+                "string index {index} at {location} is out of range, the string table has {len} \
+                strings"
+            ),
+            Self::UnresolvedReference { location, id } => write!(
+                formatter,
+                "reference to {id} at {location} could not be resolved"
+            ),
+            Self::UnknownChunk { location, token } => write!(
+                formatter,
+                "unexpected chunk {} (0x{:04x}) at {}",
+                token_name(*token),
+                token,
+                location
+            ),
+            Self::LimitExceeded {
+                location,
+                limit,
+                max,
+                found,
+            } => write!(
+                formatter,
+                "{limit} at {location} exceeds the limit: {found} is above {max}"
+            ),
+            Self::Malformed { location, reason } => {
+                write!(formatter, "malformed chunk at {location}: {reason}")
+            }
+        }
+    }
+}
+
+impl Fail for AbxmlError {}
+
+impl AbxmlError {
+    #[must_use]
+    pub fn get_location(&self) -> &Location {
+        match self {
+            Self::TruncatedChunk { location, .. }
+            | Self::BadToken { location, .. }
+            | Self::InvalidChunk { location, .. }
+            | Self::StringIndexOutOfRange { location, .. }
+            | Self::UnresolvedReference { location, .. }
+            | Self::UnknownChunk { location, .. }
+            | Self::LimitExceeded { location, .. }
+            | Self::Malformed { location, .. } => location,
+        }
+    }
+
+    fn get_mut_location(&mut self) -> &mut Location {
+        match self {
+            Self::TruncatedChunk { location, .. }
+            | Self::BadToken { location, .. }
+            | Self::InvalidChunk { location, .. }
+            | Self::StringIndexOutOfRange { location, .. }
+            | Self::UnresolvedReference { location, .. }
+            | Self::UnknownChunk { location, .. }
+            | Self::LimitExceeded { location, .. }
+            | Self::Malformed { location, .. } => location,
+        }
+    }
+
+    /// Completes the location of errors raised without knowing the chunks that contain them:
+    /// their offset is made absolute by adding `base` and their path is set to `path`. Errors
+    /// that already have a path are returned as they are.
+    pub(crate) fn located(mut self, base: u64, path: &[ChunkContext]) -> Self {
+        let location = self.get_mut_location();
+
+        if location.path.is_empty() {
+            location.offset += base;
+            location.path = path.to_vec();
+        }
+
+        self
+    }
+}
+
+/// Problem found and skipped while decoding a document on lenient mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    error: AbxmlError,
+}
+
+impl Diagnostic {
+    #[must_use]
+    pub fn new(error: AbxmlError) -> Self {
+        Self { error }
+    }
+
+    #[must_use]
+    pub fn get_error(&self) -> &AbxmlError {
+        &self.error
+    }
+
+    #[must_use]
+    pub fn get_location(&self) -> &Location {
+        self.error.get_location()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.error.fmt(formatter)
+    }
+}
+
+/// Logs the problems skipped by the entry points that do not return them
+pub(crate) fn warn_skipped(document: &str, diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        warn!("skipped a problem on {document}: {diagnostic}");
+    }
+}
+
+/// Converts the error into an `AbxmlError` located on the given chunks. If there is an
+/// `AbxmlError` on the chain of causes, it keeps its kind. Otherwise, it becomes `Malformed` with
+/// the messages of the chain.
+pub(crate) fn locate(error: &Error, base: u64, path: &[ChunkContext]) -> AbxmlError {
+    let found = error
+        .iter_chain()
+        .find_map(|cause| cause.downcast_ref::<AbxmlError>())
+        .cloned();
+
+    if let Some(abxml_error) = found {
+        abxml_error.located(base, path)
+    } else {
+        let messages: Vec<String> = error.iter_chain().map(ToString::to_string).collect();
+
+        AbxmlError::Malformed {
+            location: Location::new(base, path.to_vec()),
+            reason: messages.join(": "),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use failure::{format_err, Error};
+
+    use super::{locate, AbxmlError, ChunkContext, Location};
+
+    #[test]
+    fn it_completes_relative_locations() {
+        let error: Error = AbxmlError::StringIndexOutOfRange {
+            location: Location::relative(4),
+            index: 9,
+            len: 2,
+        }
+        .into();
+        let error: Error = error.context("could not read the name").into();
+        let path = [ChunkContext::new(0x3, 0), ChunkContext::new(0x102, 0x40)];
+
+        let located = locate(&error, 0x40, &path);
+
+        assert_eq!(0x44, located.get_location().get_offset());
+        assert_eq!(&path, located.get_location().get_path());
+        assert_eq!(
+            "string index 9 at 0x44 (RES_XML_TYPE@0x0 > RES_XML_START_ELEMENT_TYPE@0x40) is out \
+             of range, the string table has 2 strings",
+            located.to_string()
+        );
+    }
+
+    #[test]
+    fn it_wraps_other_errors_as_malformed() {
+        let error = format_err!("could not get data");
+        let located = locate(&error, 0x10, &[ChunkContext::new(0x2, 0)]);
+
+        match located {
+            AbxmlError::Malformed { location, reason } => {
+                assert_eq!(0x10, location.get_offset());
+                assert_eq!("could not get data", reason);
+            }
+            _ => panic!("error should be malformed"),
+        }
+    }
+}
@@ -0,0 +1,668 @@
+//! Inspector that walks the chunks of a binary file without decoding their contents, to find out
+//! by hand why a file can not be parsed
+
+use std::{cmp, fmt};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::chunks::{
+    token_name, ChunkHeader, TOKEN_LIBRARY, TOKEN_PACKAGE, TOKEN_RESOURCE, TOKEN_STRING_TABLE,
+    TOKEN_TABLE, TOKEN_TABLE_SPEC, TOKEN_TABLE_TYPE, TOKEN_XML, TOKEN_XML_END_NAMESPACE,
+    TOKEN_XML_START_NAMESPACE, TOKEN_XML_TAG_END, TOKEN_XML_TAG_START, TOKEN_XML_TEXT,
+};
+
+const CHUNK_HEADER_SIZE: u64 = 8;
+const PACKAGE_NAME_OFFSET: usize = 12;
+const PACKAGE_NAME_LENGTH: usize = 256;
+const NO_INDEX: u32 = 0xFFFF_FFFF;
+/// Amount of bytes shown on the hex view of a region
+const HEX_LIMIT: usize = 256;
+/// Maximum nesting of container chunks that is walked. Valid files nest up to three levels
+/// (table, package and type), so anything deeper is reported as invalid instead of exhausting
+/// the stack.
+const MAX_DEPTH: usize = 32;
+
+/// Kind of a region of bytes that is not covered by a well formed chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// Bytes between chunks that do not belong to any of them, like padding or trailing data
+    Gap,
+    /// Part of a chunk that extends beyond the end of its parent
+    Overlap,
+    /// Part of a chunk that extends beyond the end of the file
+    Truncated,
+    /// Bytes that can not be a chunk: they are too few to hold a chunk header, their header
+    /// sizes are inconsistent or they are nested too deep. The rest of the parent can not be
+    /// walked after them.
+    Invalid,
+    /// Body of a chunk whose type is not known
+    Unparsed,
+}
+
+impl fmt::Display for RegionKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Gap => "gap",
+            Self::Overlap => "overlap",
+            Self::Truncated => "truncated",
+            Self::Invalid => "invalid",
+            Self::Unparsed => "unparsed",
+        };
+
+        formatter.write_str(name)
+    }
+}
+
+/// Range of bytes, as absolute offsets on the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    kind: RegionKind,
+    start: u64,
+    end: u64,
+}
+
+impl Region {
+    #[must_use]
+    pub fn get_kind(self) -> RegionKind {
+        self.kind
+    }
+
+    #[must_use]
+    pub fn get_start(self) -> u64 {
+        self.start
+    }
+
+    #[must_use]
+    pub fn get_end(self) -> u64 {
+        self.end
+    }
+}
+
+/// Chunk found on the file, with its decoded header fields and the chunks it contains
+#[derive(Debug, Clone)]
+pub struct InspectedChunk {
+    header: ChunkHeader,
+    fields: Vec<(&'static str, String)>,
+    children: Vec<InspectedChunk>,
+    regions: Vec<Region>,
+}
+
+impl InspectedChunk {
+    #[must_use]
+    pub fn get_header(&self) -> &ChunkHeader {
+        &self.header
+    }
+
+    /// Name of the type of the chunk, as it appears on the Android sources (`RES_TABLE_TYPE`)
+    #[must_use]
+    pub fn get_name(&self) -> &'static str {
+        token_name(self.header.get_token())
+    }
+
+    /// Fields of the header of the chunk that are specific to its type
+    #[must_use]
+    pub fn get_fields(&self) -> &[(&'static str, String)] {
+        &self.fields
+    }
+
+    #[must_use]
+    pub fn get_field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[must_use]
+    pub fn get_children(&self) -> &[Self] {
+        &self.children
+    }
+
+    /// Regions of the chunk that are not covered by its header or by its children
+    #[must_use]
+    pub fn get_regions(&self) -> &[Region] {
+        &self.regions
+    }
+}
+
+/// Tree of all the chunks of a binary file (a `resources.arsc` or a binary XML). Its `Display`
+/// implementation prints the tree with the byte range of every chunk and a hex view of the
+/// regions that are not covered by any chunk.
+#[derive(Debug)]
+pub struct Inspection<'a> {
+    buffer: &'a [u8],
+    chunks: Vec<InspectedChunk>,
+    regions: Vec<Region>,
+}
+
+impl<'a> Inspection<'a> {
+    /// Walks the chunks of the buffer. It never fails: anything that can not be walked is
+    /// reported as a `Region`.
+    #[must_use]
+    pub fn new(buffer: &'a [u8]) -> Self {
+        let (chunks, regions) = inspect_range(buffer, 0, buffer.len() as u64, 0);
+
+        Self {
+            buffer,
+            chunks,
+            regions,
+        }
+    }
+
+    /// Top level chunks. Well formed files have only one.
+    #[must_use]
+    pub fn get_chunks(&self) -> &[InspectedChunk] {
+        &self.chunks
+    }
+
+    /// Top level regions that are not covered by any chunk, like trailing data
+    #[must_use]
+    pub fn get_regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    /// Returns all the regions of the file, including the ones found inside the chunks
+    #[must_use]
+    pub fn get_all_regions(&self) -> Vec<Region> {
+        let mut regions = self.regions.clone();
+        let mut pending: Vec<&InspectedChunk> = self.chunks.iter().collect();
+
+        while let Some(chunk) = pending.pop() {
+            regions.extend_from_slice(&chunk.regions);
+            pending.extend(chunk.children.iter());
+        }
+
+        regions.sort_by_key(|region| region.start);
+        regions
+    }
+
+    fn write_level(
+        &self,
+        formatter: &mut fmt::Formatter,
+        chunks: &[InspectedChunk],
+        regions: &[Region],
+        depth: usize,
+    ) -> fmt::Result {
+        let mut chunks = chunks.iter().peekable();
+        let mut regions = regions.iter().peekable();
+
+        loop {
+            let next_is_chunk = match (chunks.peek(), regions.peek()) {
+                (Some(chunk), Some(region)) => chunk.header.get_offset() <= region.start,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => return Ok(()),
+            };
+
+            if next_is_chunk {
+                if let Some(chunk) = chunks.next() {
+                    self.write_chunk(formatter, chunk, depth)?;
+                }
+            } else if let Some(region) = regions.next() {
+                self.write_region(formatter, *region, depth)?;
+            }
+        }
+    }
+
+    fn write_chunk(
+        &self,
+        formatter: &mut fmt::Formatter,
+        chunk: &InspectedChunk,
+        depth: usize,
+    ) -> fmt::Result {
+        let header = &chunk.header;
+        write!(
+            formatter,
+            "{}{} [0x{:08x}..0x{:08x}] token=0x{:04x} header={} size={}",
+            "  ".repeat(depth),
+            chunk.get_name(),
+            header.get_offset(),
+            header.get_chunk_end(),
+            header.get_token(),
+            header.get_header_size(),
+            header.get_chunk_end() - header.get_offset()
+        )?;
+
+        for (name, value) in &chunk.fields {
+            write!(formatter, " {name}={value}")?;
+        }
+
+        writeln!(formatter)?;
+
+        self.write_level(formatter, &chunk.children, &chunk.regions, depth + 1)
+    }
+
+    fn write_region(
+        &self,
+        formatter: &mut fmt::Formatter,
+        region: Region,
+        depth: usize,
+    ) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        writeln!(
+            formatter,
+            "{}{} [0x{:08x}..0x{:08x}] ({} bytes)",
+            indent,
+            region.kind,
+            region.start,
+            region.end,
+            region.end - region.start
+        )?;
+
+        let start = cmp::min(region.start, self.buffer.len() as u64) as usize;
+        let end = cmp::min(region.end, self.buffer.len() as u64) as usize;
+        let shown = cmp::min(end, start + HEX_LIMIT);
+
+        for (line, bytes) in self.buffer[start..shown].chunks(16).enumerate() {
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+            let ascii: String = bytes
+                .iter()
+                .map(|byte| {
+                    if byte.is_ascii_graphic() || *byte == b' ' {
+                        char::from(*byte)
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+
+            writeln!(
+                formatter,
+                "{}  {:08x}  {:<47}  |{}|",
+                indent,
+                start + line * 16,
+                hex.join(" "),
+                ascii
+            )?;
+        }
+
+        if end > shown {
+            writeln!(formatter, "{}  ... {} more bytes", indent, end - shown)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Inspection<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.write_level(formatter, &self.chunks, &self.regions, 0)
+    }
+}
+
+/// Walks the chunks found between `start` and `end`, which must be inside the buffer, as the
+/// children of a chunk nested `depth` levels deep
+fn inspect_range(
+    buffer: &[u8],
+    start: u64,
+    end: u64,
+    depth: usize,
+) -> (Vec<InspectedChunk>, Vec<Region>) {
+    let mut chunks = Vec::new();
+    let mut regions = Vec::new();
+    let mut position = start;
+
+    if depth > MAX_DEPTH && start < end {
+        regions.push(Region {
+            kind: RegionKind::Invalid,
+            start,
+            end,
+        });
+        return (chunks, regions);
+    }
+
+    while position < end {
+        if end - position < CHUNK_HEADER_SIZE {
+            regions.push(Region {
+                kind: RegionKind::Invalid,
+                start: position,
+                end,
+            });
+            break;
+        }
+
+        let bytes = &buffer[position as usize..];
+        let header = ChunkHeader::new(
+            position,
+            LittleEndian::read_u16(&bytes[2..4]),
+            LittleEndian::read_u32(&bytes[4..8]),
+            LittleEndian::read_u16(&bytes[0..2]),
+        );
+        let chunk_end = header.get_chunk_end();
+
+        if u64::from(header.get_header_size()) < CHUNK_HEADER_SIZE
+            || header.get_data_offset() > chunk_end
+        {
+            regions.push(Region {
+                kind: RegionKind::Invalid,
+                start: position,
+                end,
+            });
+            break;
+        }
+
+        chunks.push(inspect_chunk(buffer, header, end, depth));
+        position = chunk_end;
+    }
+
+    (chunks, regions)
+}
+
+fn inspect_chunk(
+    buffer: &[u8],
+    header: ChunkHeader,
+    parent_end: u64,
+    depth: usize,
+) -> InspectedChunk {
+    let chunk_end = header.get_chunk_end();
+    let available_end = cmp::min(chunk_end, parent_end);
+    let mut chunk = InspectedChunk {
+        header,
+        fields: header_fields(
+            &buffer[header.get_offset() as usize..available_end as usize],
+            &header,
+        ),
+        children: Vec::new(),
+        regions: Vec::new(),
+    };
+
+    let data_offset = cmp::min(header.get_data_offset(), available_end);
+
+    match header.get_token() {
+        TOKEN_TABLE | TOKEN_XML | TOKEN_PACKAGE => {
+            let (children, regions) = inspect_range(buffer, data_offset, available_end, depth + 1);
+            let mut covered = data_offset;
+
+            for child in &children {
+                let child_start = child.header.get_offset();
+                if child_start > covered {
+                    chunk.regions.push(Region {
+                        kind: RegionKind::Gap,
+                        start: covered,
+                        end: child_start,
+                    });
+                }
+                covered = cmp::min(child.header.get_chunk_end(), available_end);
+            }
+
+            chunk.children = children;
+            chunk.regions.extend(regions);
+        }
+        TOKEN_STRING_TABLE
+        | TOKEN_RESOURCE
+        | TOKEN_TABLE_SPEC
+        | TOKEN_TABLE_TYPE
+        | TOKEN_LIBRARY
+        | TOKEN_XML_START_NAMESPACE
+        | TOKEN_XML_END_NAMESPACE
+        | TOKEN_XML_TAG_START
+        | TOKEN_XML_TAG_END
+        | TOKEN_XML_TEXT => (),
+        _ => {
+            if available_end > data_offset {
+                chunk.regions.push(Region {
+                    kind: RegionKind::Unparsed,
+                    start: data_offset,
+                    end: available_end,
+                });
+            }
+        }
+    }
+
+    if chunk_end > parent_end {
+        let kind = if parent_end == buffer.len() as u64 {
+            RegionKind::Truncated
+        } else {
+            RegionKind::Overlap
+        };
+
+        chunk.regions.push(Region {
+            kind,
+            start: parent_end,
+            end: chunk_end,
+        });
+    }
+
+    chunk
+}
+
+/// Name, offset from the start of the chunk and size in bytes of the fields of each chunk type
+fn field_layout(token: u16) -> &'static [(&'static str, usize, usize)] {
+    match token {
+        TOKEN_STRING_TABLE => &[
+            ("stringCount", 8, 4),
+            ("styleCount", 12, 4),
+            ("flags", 16, 4),
+            ("stringsStart", 20, 4),
+            ("stylesStart", 24, 4),
+        ],
+        TOKEN_TABLE => &[("packageCount", 8, 4)],
+        TOKEN_XML_START_NAMESPACE | TOKEN_XML_END_NAMESPACE => &[
+            ("lineNumber", 8, 4),
+            ("comment", 12, 4),
+            ("prefix", 16, 4),
+            ("uri", 20, 4),
+        ],
+        TOKEN_XML_TAG_START => &[
+            ("lineNumber", 8, 4),
+            ("comment", 12, 4),
+            ("ns", 16, 4),
+            ("name", 20, 4),
+            ("attributeStart", 24, 2),
+            ("attributeSize", 26, 2),
+            ("attributeCount", 28, 2),
+            ("idIndex", 30, 2),
+            ("classIndex", 32, 2),
+            ("styleIndex", 34, 2),
+        ],
+        TOKEN_XML_TAG_END => &[
+            ("lineNumber", 8, 4),
+            ("comment", 12, 4),
+            ("ns", 16, 4),
+            ("name", 20, 4),
+        ],
+        TOKEN_XML_TEXT => &[("lineNumber", 8, 4), ("comment", 12, 4), ("data", 16, 4)],
+        TOKEN_PACKAGE => &[
+            ("id", 8, 4),
+            ("typeStrings", 268, 4),
+            ("lastPublicType", 272, 4),
+            ("keyStrings", 276, 4),
+            ("lastPublicKey", 280, 4),
+            ("typeIdOffset", 284, 4),
+        ],
+        TOKEN_TABLE_SPEC => &[
+            ("id", 8, 1),
+            ("res0", 9, 1),
+            ("types", 10, 2),
+            ("entryCount", 12, 4),
+        ],
+        TOKEN_TABLE_TYPE => &[
+            ("id", 8, 1),
+            ("flags", 9, 1),
+            ("reserved", 10, 2),
+            ("entryCount", 12, 4),
+            ("entriesStart", 16, 4),
+            ("configSize", 20, 4),
+        ],
+        TOKEN_LIBRARY => &[("count", 8, 4)],
+        _ => &[],
+    }
+}
+
+/// Decodes the fields of the chunk that fit on the given bytes
+fn header_fields(bytes: &[u8], header: &ChunkHeader) -> Vec<(&'static str, String)> {
+    let mut fields = Vec::new();
+
+    for (name, offset, size) in field_layout(header.get_token()) {
+        let value = match bytes.get(*offset..offset + size) {
+            Some(field) if *size == 1 => u32::from(field[0]),
+            Some(field) if *size == 2 => u32::from(LittleEndian::read_u16(field)),
+            Some(field) => LittleEndian::read_u32(field),
+            None => continue,
+        };
+        let formatted = if *name == "flags" {
+            format!("0x{value:x}")
+        } else if value == NO_INDEX {
+            "none".to_string()
+        } else {
+            value.to_string()
+        };
+
+        fields.push((*name, formatted));
+    }
+
+    match header.get_token() {
+        TOKEN_PACKAGE => {
+            if let Some(name) =
+                bytes.get(PACKAGE_NAME_OFFSET..PACKAGE_NAME_OFFSET + PACKAGE_NAME_LENGTH)
+            {
+                let units: Vec<u16> = name
+                    .chunks(2)
+                    .map(LittleEndian::read_u16)
+                    .take_while(|unit| *unit != 0)
+                    .collect();
+
+                fields.insert(1, ("name", String::from_utf16_lossy(&units)));
+            }
+        }
+        TOKEN_RESOURCE => {
+            let body = header
+                .get_chunk_end()
+                .saturating_sub(header.get_data_offset());
+            fields.push(("count", (body / 4).to_string()));
+        }
+        _ => (),
+    }
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Inspection, RegionKind};
+    use crate::model::{
+        builder::Xml,
+        owned::{StringTableBuf, XmlTagEndBuf, XmlTagStartBuf},
+        ResourceTable,
+    };
+
+    fn example_xml() -> Vec<u8> {
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("manifest".to_string());
+
+        xml.push_owned(Box::new(st));
+        xml.push_owned(Box::new(XmlTagStartBuf::new(
+            2,
+            0xFFFF_FFFF,
+            0xFFFF_FFFF,
+            0,
+            0x0014_0014,
+            0,
+        )));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+
+        xml.into_vec().unwrap()
+    }
+
+    #[test]
+    fn it_walks_the_chunks_of_a_binary_xml() {
+        let content = example_xml();
+        let inspection = Inspection::new(&content);
+
+        let root = &inspection.get_chunks()[0];
+        let children = root.get_children();
+
+        assert_eq!("RES_XML_TYPE", root.get_name());
+        assert_eq!(3, children.len());
+        assert_eq!("RES_STRING_POOL_TYPE", children[0].get_name());
+        assert_eq!(Some("1"), children[0].get_field("stringCount"));
+        assert_eq!(Some("2"), children[1].get_field("lineNumber"));
+        assert_eq!(Some("none"), children[1].get_field("ns"));
+        assert!(inspection.get_all_regions().is_empty());
+    }
+
+    #[test]
+    fn it_nests_the_chunks_of_the_packages() {
+        let mut table = ResourceTable::default();
+        table.add_package(0x7F, "com.example").unwrap();
+        table.add_resource(0x7F, "string", "title").unwrap();
+        let content = table.to_vec().unwrap();
+
+        let inspection = Inspection::new(&content);
+        let package = &inspection.get_chunks()[0].get_children()[1];
+
+        assert_eq!("RES_TABLE_PACKAGE_TYPE", package.get_name());
+        assert_eq!(Some("com.example"), package.get_field("name"));
+        assert_eq!(Some("127"), package.get_field("id"));
+        assert!(package
+            .get_children()
+            .iter()
+            .any(|chunk| chunk.get_name() == "RES_TABLE_TYPE_SPEC_TYPE"));
+    }
+
+    #[test]
+    fn it_reports_trailing_data_and_truncated_chunks() {
+        let mut content = example_xml();
+        content.extend_from_slice(&[1, 2, 3]);
+
+        let inspection = Inspection::new(&content);
+        let regions = inspection.get_regions();
+
+        assert_eq!(1, regions.len());
+        assert_eq!(RegionKind::Invalid, regions[0].get_kind());
+        assert_eq!(3, regions[0].get_end() - regions[0].get_start());
+
+        let output = inspection.to_string();
+        assert!(output.contains("invalid ["));
+        assert!(output.contains("01 02 03"));
+
+        let truncated = &content[..content.len() - 10];
+        let inspection = Inspection::new(truncated);
+        let kinds: Vec<RegionKind> = inspection
+            .get_all_regions()
+            .iter()
+            .map(|region| region.get_kind())
+            .collect();
+
+        assert!(kinds.contains(&RegionKind::Truncated));
+    }
+
+    #[test]
+    fn it_reports_chunks_that_overlap_their_parent() {
+        let mut content = example_xml();
+        // The document claims to end 4 bytes before its last chunk does
+        let document_size = content.len() as u32 - 4;
+        content[4..8].copy_from_slice(&document_size.to_le_bytes());
+
+        let inspection = Inspection::new(&content);
+        let tag_end = inspection.get_chunks()[0].get_children()[2].get_regions();
+
+        assert_eq!(RegionKind::Overlap, tag_end[0].get_kind());
+        assert_eq!(u64::from(document_size), tag_end[0].get_start());
+        assert_eq!(RegionKind::Invalid, inspection.get_regions()[0].get_kind());
+    }
+
+    #[test]
+    fn it_stops_walking_deeply_nested_chunks() {
+        let levels = 20_000;
+        let mut content = Vec::with_capacity(levels * 8);
+        for level in 0..levels {
+            let size = ((levels - level) * 8) as u32;
+            content.extend_from_slice(&[3, 0, 8, 0]);
+            content.extend_from_slice(&size.to_le_bytes());
+        }
+
+        let inspection = Inspection::new(&content);
+        let mut chunk = &inspection.get_chunks()[0];
+        let mut depth = 0;
+        while let Some(child) = chunk.get_children().first() {
+            chunk = child;
+            depth += 1;
+        }
+
+        assert!(depth < 64);
+        assert_eq!(RegionKind::Invalid, chunk.get_regions()[0].get_kind());
+        assert!(!inspection.to_string().is_empty());
+    }
+}
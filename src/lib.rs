@@ -33,14 +33,21 @@
     clippy::shadow_unrelated
 )]
 
+pub mod anomalies;
 #[cfg(feature = "zip_decode")]
 pub mod apk;
 pub mod chunks;
 pub mod decoder;
+pub mod dump;
 pub mod encoder;
+pub mod errors;
+pub mod inspector;
 pub mod model;
+pub mod options;
 #[cfg(test)]
 pub mod raw_chunks;
+pub mod reader;
+pub mod symbols;
 #[cfg(test)]
 pub mod test;
 pub mod visitor;
@@ -0,0 +1,627 @@
+use std::fmt;
+
+use failure::{bail, Error, ResultExt};
+
+use crate::model::{
+    owned::{ComplexEntry, SimpleEntry},
+    ResourceId, StringTable, Value,
+};
+
+/// Key of the item that holds the allowed formats of an attribute
+const ATTR_TYPE: u32 = 0x0100_0000;
+/// Key of the item that holds the minimum integer value of an attribute
+const ATTR_MIN: u32 = 0x0100_0001;
+/// Key of the item that holds the maximum integer value of an attribute
+const ATTR_MAX: u32 = 0x0100_0002;
+/// Key of the item that holds the localization requirements of an attribute
+const ATTR_L10N: u32 = 0x0100_0003;
+const ATTR_OTHER: u32 = 0x0100_0004;
+const ATTR_ZERO: u32 = 0x0100_0005;
+const ATTR_ONE: u32 = 0x0100_0006;
+const ATTR_TWO: u32 = 0x0100_0007;
+const ATTR_FEW: u32 = 0x0100_0008;
+const ATTR_MANY: u32 = 0x0100_0009;
+/// Keys of the array items are the index of the item added to this value
+const ARRAY_INDEX: u32 = 0x0200_0000;
+
+/// Typed view of a complex entry, which depends on the type of the resource
+#[derive(Debug, Clone, PartialEq)]
+pub enum BagValue {
+    Style(StyleValue),
+    Array(ArrayValue),
+    Plurals(PluralsValue),
+    Attr(AttrValue),
+    Styleable(StyleableValue),
+}
+
+impl BagValue {
+    /// Creates the view that corresponds to the given type name (`style`, `array`...). Strings
+    /// are resolved with the given string table. If the name is not one of the known bag types,
+    /// as on tables with renamed or obfuscated types, the view is chosen from the keys of the
+    /// entry.
+    ///
+    /// # Errors
+    ///
+    /// It fails if a string of the entry is not on the string table, a value has an unknown type or
+    /// the view of an unknown type name can not be guessed.
+    pub fn new<S: StringTable>(
+        type_name: &str,
+        entry: &ComplexEntry,
+        strings: &S,
+    ) -> Result<Self, Error> {
+        let value = match type_name {
+            "style" => Self::Style(StyleValue::from_entry(entry, strings)?),
+            "array" | "string-array" | "integer-array" => {
+                Self::Array(ArrayValue::from_entry(entry, strings)?)
+            }
+            "plurals" => Self::Plurals(PluralsValue::from_entry(entry, strings)?),
+            "attr" | "^attr-private" => Self::Attr(AttrValue::from_entry(entry)),
+            "styleable" => Self::Styleable(StyleableValue::from_entry(entry)),
+            _ => Self::from_keys(entry, strings)
+                .with_context(|_| format!("could not decode a bag of type {type_name}"))?,
+        };
+
+        Ok(value)
+    }
+
+    /// Creates the view that fits the keys of the entry: attrs have a format, plurals only have
+    /// quantities, arrays only have indexes and anything else is taken as a style
+    fn from_keys<S: StringTable>(entry: &ComplexEntry, strings: &S) -> Result<Self, Error> {
+        let keys: Vec<u32> = entry
+            .get_entries()
+            .iter()
+            .map(|item| item.get_id().as_u32())
+            .collect();
+
+        if keys.is_empty() {
+            bail!("the bag does not have items to tell its type");
+        }
+
+        let value = if keys.contains(&ATTR_TYPE) {
+            Self::Attr(AttrValue::from_entry(entry))
+        } else if keys.iter().all(|&key| Quantity::from_key(key).is_some()) {
+            Self::Plurals(PluralsValue::from_entry(entry, strings)?)
+        } else if keys.iter().all(|&key| key & 0xFFFF_0000 == ARRAY_INDEX) {
+            Self::Array(ArrayValue::from_entry(entry, strings)?)
+        } else {
+            Self::Style(StyleValue::from_entry(entry, strings)?)
+        };
+
+        Ok(value)
+    }
+}
+
+/// Style or theme: a parent and the values of the attributes it sets
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleValue {
+    parent: ResourceId,
+    items: Vec<(ResourceId, Value)>,
+}
+
+impl StyleValue {
+    /// Decodes the style with the strings resolved with the given string table
+    ///
+    /// # Errors
+    ///
+    /// It fails if a string is not on the string table or a value has an unknown type.
+    pub fn from_entry<S: StringTable>(entry: &ComplexEntry, strings: &S) -> Result<Self, Error> {
+        let items = entry
+            .get_entries()
+            .iter()
+            .map(|item| Ok((item.get_id(), item_value(item, strings)?)))
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Self {
+            parent: entry.get_parent(),
+            items,
+        })
+    }
+
+    /// Parent style. It is a null id if the style does not have an explicit parent
+    #[must_use]
+    pub fn get_parent(&self) -> ResourceId {
+        self.parent
+    }
+
+    #[must_use]
+    pub fn get_items(&self) -> &[(ResourceId, Value)] {
+        &self.items
+    }
+
+    #[must_use]
+    pub fn get_item(&self, attribute: ResourceId) -> Option<&Value> {
+        self.items
+            .iter()
+            .find(|(id, _)| *id == attribute)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Ordered list of values
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayValue {
+    items: Vec<Value>,
+}
+
+impl ArrayValue {
+    /// Decodes the array items, sorted by their index
+    ///
+    /// # Errors
+    ///
+    /// It fails if a string is not on the string table or a value has an unknown type.
+    pub fn from_entry<S: StringTable>(entry: &ComplexEntry, strings: &S) -> Result<Self, Error> {
+        let mut indexed = entry
+            .get_entries()
+            .iter()
+            .map(|item| {
+                let index = item.get_id().as_u32().wrapping_sub(ARRAY_INDEX);
+
+                Ok((index, item_value(item, strings)?))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        indexed.sort_by_key(|(index, _)| *index);
+
+        Ok(Self {
+            items: indexed.into_iter().map(|(_, value)| value).collect(),
+        })
+    }
+
+    #[must_use]
+    pub fn get_items(&self) -> &[Value] {
+        &self.items
+    }
+}
+
+/// Quantity keywords used by the plurals
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quantity {
+    Other,
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+}
+
+impl Quantity {
+    fn from_key(key: u32) -> Option<Self> {
+        let quantity = match key {
+            ATTR_OTHER => Self::Other,
+            ATTR_ZERO => Self::Zero,
+            ATTR_ONE => Self::One,
+            ATTR_TWO => Self::Two,
+            ATTR_FEW => Self::Few,
+            ATTR_MANY => Self::Many,
+            _ => return None,
+        };
+
+        Some(quantity)
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let keyword = match self {
+            Self::Other => "other",
+            Self::Zero => "zero",
+            Self::One => "one",
+            Self::Two => "two",
+            Self::Few => "few",
+            Self::Many => "many",
+        };
+
+        formatter.write_str(keyword)
+    }
+}
+
+/// Values of a string for each of the quantities
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluralsValue {
+    quantities: Vec<(Quantity, Value)>,
+    skipped: Vec<ResourceId>,
+}
+
+impl PluralsValue {
+    /// Decodes the value of each quantity. Keys that are not a quantity are skipped and kept on
+    /// `get_skipped`.
+    ///
+    /// # Errors
+    ///
+    /// It fails if a string is not on the string table or a value has an unknown type.
+    pub fn from_entry<S: StringTable>(entry: &ComplexEntry, strings: &S) -> Result<Self, Error> {
+        let mut quantities = Vec::new();
+        let mut skipped = Vec::new();
+
+        for item in entry.get_entries() {
+            match Quantity::from_key(item.get_id().as_u32()) {
+                Some(quantity) => quantities.push((quantity, item_value(item, strings)?)),
+                None => skipped.push(item.get_id()),
+            }
+        }
+
+        Ok(Self {
+            quantities,
+            skipped,
+        })
+    }
+
+    #[must_use]
+    pub fn get_quantities(&self) -> &[(Quantity, Value)] {
+        &self.quantities
+    }
+
+    #[must_use]
+    pub fn get_quantity(&self, quantity: Quantity) -> Option<&Value> {
+        self.quantities
+            .iter()
+            .find(|(current, _)| *current == quantity)
+            .map(|(_, value)| value)
+    }
+
+    /// Keys of the items that are not a known quantity. They are skipped instead of failing to
+    /// decode the rest of the plurals.
+    #[must_use]
+    pub fn get_skipped(&self) -> &[ResourceId] {
+        &self.skipped
+    }
+}
+
+/// Formats accepted by an attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttributeFormat(u32);
+
+impl AttributeFormat {
+    pub const ANY: Self = Self(0xFFFF);
+    pub const REFERENCE: Self = Self(1);
+    pub const STRING: Self = Self(1 << 1);
+    pub const INTEGER: Self = Self(1 << 2);
+    pub const BOOLEAN: Self = Self(1 << 3);
+    pub const COLOR: Self = Self(1 << 4);
+    pub const FLOAT: Self = Self(1 << 5);
+    pub const DIMENSION: Self = Self(1 << 6);
+    pub const FRACTION: Self = Self(1 << 7);
+    pub const ENUM: Self = Self(1 << 16);
+    pub const FLAGS: Self = Self(1 << 17);
+
+    #[must_use]
+    pub fn contains(self, format: Self) -> bool {
+        self.0 & format.0 == format.0
+    }
+
+    #[must_use]
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for AttributeFormat {
+    fn from(format: u32) -> Self {
+        Self(format)
+    }
+}
+
+/// Formats the names as they appear on the `format` of an `attr` (`reference|color`)
+impl fmt::Display for AttributeFormat {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let names = [
+            (Self::REFERENCE, "reference"),
+            (Self::STRING, "string"),
+            (Self::INTEGER, "integer"),
+            (Self::BOOLEAN, "boolean"),
+            (Self::COLOR, "color"),
+            (Self::FLOAT, "float"),
+            (Self::DIMENSION, "dimension"),
+            (Self::FRACTION, "fraction"),
+            (Self::ENUM, "enum"),
+            (Self::FLAGS, "flags"),
+        ];
+        let set: Vec<&str> = names
+            .iter()
+            .filter(|(format, _)| self.contains(*format))
+            .map(|(_, name)| *name)
+            .collect();
+
+        formatter.write_str(&set.join("|"))
+    }
+}
+
+/// Symbol of an enum or a flags attribute. The name is the id of an `id` resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttrSymbol {
+    name: ResourceId,
+    value: u32,
+}
+
+impl AttrSymbol {
+    #[must_use]
+    pub fn get_name(self) -> ResourceId {
+        self.name
+    }
+
+    #[must_use]
+    pub fn get_value(self) -> u32 {
+        self.value
+    }
+}
+
+/// Definition of an attribute
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttrValue {
+    format: AttributeFormat,
+    min: Option<i32>,
+    max: Option<i32>,
+    l10n: Option<u32>,
+    symbols: Vec<AttrSymbol>,
+}
+
+impl AttrValue {
+    #[allow(clippy::cast_possible_wrap)]
+    #[must_use]
+    pub fn from_entry(entry: &ComplexEntry) -> Self {
+        let mut attr = Self {
+            format: AttributeFormat::ANY,
+            min: None,
+            max: None,
+            l10n: None,
+            symbols: Vec::new(),
+        };
+
+        for item in entry.get_entries() {
+            match item.get_id().as_u32() {
+                ATTR_TYPE => attr.format = AttributeFormat::from(item.get_value()),
+                ATTR_MIN => attr.min = Some(item.get_value() as i32),
+                ATTR_MAX => attr.max = Some(item.get_value() as i32),
+                ATTR_L10N => attr.l10n = Some(item.get_value()),
+                id => attr.symbols.push(AttrSymbol {
+                    name: ResourceId::from(id),
+                    value: item.get_value(),
+                }),
+            }
+        }
+
+        // Other keys are only symbols on the attributes that take enum or flags values. Attributes
+        // without a format item can not tell, so their keys are kept.
+        let declared = entry
+            .get_entries()
+            .iter()
+            .any(|item| item.get_id().as_u32() == ATTR_TYPE);
+        if declared
+            && !attr.format.contains(AttributeFormat::ENUM)
+            && !attr.format.contains(AttributeFormat::FLAGS)
+        {
+            attr.symbols.clear();
+        }
+
+        attr
+    }
+
+    #[must_use]
+    pub fn get_format(&self) -> AttributeFormat {
+        self.format
+    }
+
+    #[must_use]
+    pub fn get_min(&self) -> Option<i32> {
+        self.min
+    }
+
+    #[must_use]
+    pub fn get_max(&self) -> Option<i32> {
+        self.max
+    }
+
+    #[must_use]
+    pub fn get_l10n(&self) -> Option<u32> {
+        self.l10n
+    }
+
+    /// Symbols of the enum or flags values the attribute accepts
+    #[must_use]
+    pub fn get_symbols(&self) -> &[AttrSymbol] {
+        &self.symbols
+    }
+}
+
+/// Attributes grouped by a `declare-styleable`. aapt does not compile them into the resource
+/// table, so they only appear on tables generated by other tools.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleableValue {
+    attributes: Vec<ResourceId>,
+}
+
+impl StyleableValue {
+    pub fn from_entry(entry: &ComplexEntry) -> Self {
+        Self {
+            attributes: entry
+                .get_entries()
+                .iter()
+                .map(SimpleEntry::get_id)
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    pub fn get_attributes(&self) -> &[ResourceId] {
+        &self.attributes
+    }
+}
+
+fn item_value<S: StringTable>(item: &SimpleEntry, strings: &S) -> Result<Value, Error> {
+    match Value::create(item.get_type(), item.get_value())? {
+        Value::StringReference(index) => Ok(Value::String((*strings.get_string(index)?).clone())),
+        value => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AttrValue, AttributeFormat, BagValue, Quantity, ATTR_MAX, ATTR_MIN, ATTR_ONE, ATTR_OTHER,
+        ATTR_TYPE, ATTR_ZERO,
+    };
+    use crate::{
+        model::{
+            owned::{ComplexEntry, SimpleEntry},
+            ResourceId, Value,
+        },
+        test::FakeStringTable,
+    };
+
+    const TYPE_STRING: u8 = 0x03;
+    const TYPE_INTEGER: u8 = 0x10;
+
+    #[test]
+    fn it_can_decode_a_style() {
+        let attribute = 0x0101_0000;
+        let items = vec![SimpleEntry::new(attribute, 0, TYPE_INTEGER, 7)];
+        let entry = ComplexEntry::new(0, 0, 0x7F02_0000, items);
+
+        let style = match BagValue::new("style", &entry, &FakeStringTable).unwrap() {
+            BagValue::Style(style) => style,
+            other => panic!("unexpected bag: {:?}", other),
+        };
+
+        assert_eq!(ResourceId::from(0x7F02_0000), style.get_parent());
+        assert_eq!(
+            Some(&Value::Integer(7)),
+            style.get_item(ResourceId::from(attribute))
+        );
+    }
+
+    #[test]
+    fn it_can_decode_an_array_in_order() {
+        let items = vec![
+            SimpleEntry::new(0x0200_0001, 0, TYPE_STRING, 22),
+            SimpleEntry::new(0x0200_0000, 0, TYPE_STRING, 11),
+        ];
+        let entry = ComplexEntry::new(0, 0, 0, items);
+
+        match BagValue::new("array", &entry, &FakeStringTable).unwrap() {
+            BagValue::Array(array) => assert_eq!(
+                &[
+                    Value::String("Ones".to_string()),
+                    Value::String("Twos".to_string())
+                ],
+                array.get_items()
+            ),
+            other => panic!("unexpected bag: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_can_decode_plurals() {
+        let items = vec![
+            SimpleEntry::new(ATTR_ONE, 0, TYPE_STRING, 11),
+            SimpleEntry::new(ATTR_OTHER, 0, TYPE_STRING, 33),
+        ];
+        let entry = ComplexEntry::new(0, 0, 0, items);
+
+        let plurals = match BagValue::new("plurals", &entry, &FakeStringTable).unwrap() {
+            BagValue::Plurals(plurals) => plurals,
+            other => panic!("unexpected bag: {:?}", other),
+        };
+
+        assert_eq!(
+            Some(&Value::String("Threes".to_string())),
+            plurals.get_quantity(Quantity::Other)
+        );
+        assert_eq!("one", plurals.get_quantities()[0].0.to_string());
+        assert!(plurals.get_quantity(Quantity::Many).is_none());
+    }
+
+    #[test]
+    fn it_skips_the_unknown_quantities_of_plurals() {
+        let items = vec![
+            SimpleEntry::new(ATTR_OTHER, 0, TYPE_STRING, 33),
+            SimpleEntry::new(0x0100_0010, 0, TYPE_STRING, 11),
+        ];
+        let entry = ComplexEntry::new(0, 0, 0, items);
+
+        let plurals = match BagValue::new("plurals", &entry, &FakeStringTable).unwrap() {
+            BagValue::Plurals(plurals) => plurals,
+            other => panic!("unexpected bag: {:?}", other),
+        };
+
+        assert_eq!(1, plurals.get_quantities().len());
+        assert_eq!(&[ResourceId::from(0x0100_0010)], plurals.get_skipped());
+    }
+
+    #[test]
+    fn it_chooses_the_view_of_renamed_types_from_their_keys() {
+        let plurals = ComplexEntry::new(
+            0,
+            0,
+            0,
+            vec![
+                SimpleEntry::new(ATTR_ZERO, 0, TYPE_STRING, 11),
+                SimpleEntry::new(ATTR_OTHER, 0, TYPE_STRING, 33),
+            ],
+        );
+        let array = ComplexEntry::new(0, 0, 0, vec![SimpleEntry::new(0x0200_0000, 0, 0x10, 1)]);
+        let attr = ComplexEntry::new(0, 0, 0, vec![SimpleEntry::new(ATTR_TYPE, 0, 0x10, 4)]);
+        let style = ComplexEntry::new(0, 0, 0, vec![SimpleEntry::new(0x0101_0000, 0, 0x10, 7)]);
+
+        assert!(matches!(
+            BagValue::new("a", &plurals, &FakeStringTable),
+            Ok(BagValue::Plurals(_))
+        ));
+        assert!(matches!(
+            BagValue::new("b", &array, &FakeStringTable),
+            Ok(BagValue::Array(_))
+        ));
+        assert!(matches!(
+            BagValue::new("c", &attr, &FakeStringTable),
+            Ok(BagValue::Attr(_))
+        ));
+        assert!(matches!(
+            BagValue::new("d", &style, &FakeStringTable),
+            Ok(BagValue::Style(_))
+        ));
+    }
+
+    #[test]
+    fn it_can_decode_an_attr() {
+        let format = AttributeFormat::INTEGER.as_u32() | AttributeFormat::ENUM.as_u32();
+        let items = vec![
+            SimpleEntry::new(ATTR_TYPE, 0, TYPE_INTEGER, format),
+            SimpleEntry::new(ATTR_MIN, 0, TYPE_INTEGER, 0xFFFF_FFFF),
+            SimpleEntry::new(ATTR_MAX, 0, TYPE_INTEGER, 10),
+            SimpleEntry::new(0x7F03_0000, 0, TYPE_INTEGER, 1),
+        ];
+        let attr = AttrValue::from_entry(&ComplexEntry::new(0, 0, 0, items));
+
+        assert_eq!("integer|enum", attr.get_format().to_string());
+        assert!(attr.get_format().contains(AttributeFormat::ENUM));
+        assert_eq!(Some(-1), attr.get_min());
+        assert_eq!(Some(10), attr.get_max());
+        assert_eq!(1, attr.get_symbols().len());
+        assert_eq!(
+            ResourceId::from(0x7F03_0000),
+            attr.get_symbols()[0].get_name()
+        );
+    }
+
+    #[test]
+    fn it_only_collects_symbols_of_enum_and_flags_attrs() {
+        let items = vec![
+            SimpleEntry::new(
+                ATTR_TYPE,
+                0,
+                TYPE_INTEGER,
+                AttributeFormat::INTEGER.as_u32(),
+            ),
+            SimpleEntry::new(0x7F03_0000, 0, TYPE_INTEGER, 1),
+        ];
+        let attr = AttrValue::from_entry(&ComplexEntry::new(0, 0, 0, items));
+
+        assert!(attr.get_symbols().is_empty());
+    }
+
+    #[test]
+    fn it_does_not_decode_unknown_types() {
+        let entry = ComplexEntry::new(0, 0, 0, Vec::new());
+
+        assert!(BagValue::new("string", &entry, &FakeStringTable).is_err());
+    }
+}
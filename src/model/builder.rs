@@ -1,7 +1,7 @@
 use byteorder::{LittleEndian, WriteBytesExt};
 use failure::{Error, ResultExt};
 
-use crate::model::owned::OwnedBuf;
+use crate::{chunks::TOKEN_PACKAGE, model::owned::OwnedBuf};
 
 #[derive(Default, Debug)]
 pub struct Arsc {
@@ -17,11 +17,16 @@ impl Arsc {
         let mut out = Vec::new();
         let mut inner = Vec::new();
         let mut file_size = 0;
+        let mut package_amount = 0;
 
         for c in &self.chunks {
             let encoded_chunk = c.to_vec().context("could not encode a chunk")?;
             file_size += encoded_chunk.len();
 
+            if c.get_token() == TOKEN_PACKAGE {
+                package_amount += 1;
+            }
+
             inner.extend(encoded_chunk);
         }
 
@@ -31,12 +36,11 @@ impl Arsc {
         // Header_size
         out.write_u16::<LittleEndian>(3 * 4)?;
 
-        // Chunk size
-        out.write_u32::<LittleEndian>(file_size as u32)?;
+        // Chunk size, including this header
+        out.write_u32::<LittleEndian>((file_size + 3 * 4) as u32)?;
 
-        // TODO: Review this value
         // Package amount
-        out.write_u32::<LittleEndian>(0)?;
+        out.write_u32::<LittleEndian>(package_amount)?;
 
         out.extend(inner);
 
@@ -72,8 +76,8 @@ impl Xml {
         // Header_size
         out.write_u16::<LittleEndian>(2 * 4)?;
 
-        // Chunk size
-        out.write_u32::<LittleEndian>(file_size as u32)?;
+        // Chunk size, including this header
+        out.write_u32::<LittleEndian>((file_size + 2 * 4) as u32)?;
 
         out.extend(inner);
 
@@ -98,7 +102,7 @@ mod tests {
         let content = arsc.to_vec().unwrap();
         let mut visitor = CounterChunkVisitor::default();
 
-        assert_eq!(vec![2, 0, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0], content);
+        assert_eq!(vec![2, 0, 12, 0, 12, 0, 0, 0, 0, 0, 0, 0], content);
 
         Executor::arsc(&content, &mut visitor).unwrap();
 
@@ -128,7 +132,7 @@ mod tests {
         let content = xml.into_vec().unwrap();
         let mut visitor = CounterChunkVisitor::default();
 
-        assert_eq!(vec![3, 0, 8, 0, 0, 0, 0, 0], content);
+        assert_eq!(vec![3, 0, 8, 0, 8, 0, 0, 0], content);
 
         Executor::xml(Cursor::new(&content), &mut visitor).unwrap();
 
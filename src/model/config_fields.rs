@@ -0,0 +1,186 @@
+//! Typed values of the fields of a configuration
+
+/// Defines an enum for a field of the configuration. Zero always means that the field is not set,
+/// so it matches any device, and values not known by this library are kept on `Unknown`.
+macro_rules! config_field {
+    (
+        $(#[$meta:meta])*
+        $name:ident: $repr:ty {
+            $($(#[$variant_meta:meta])* $variant:ident = $value:literal => $qualifier:expr,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum $name {
+            /// Not set, it matches any device
+            Any,
+            $($(#[$variant_meta])* $variant,)*
+            Unknown($repr),
+        }
+
+        impl $name {
+            /// Returns the qualifier used on the resource directory names, if it has any
+            pub fn to_qualifier(self) -> Option<&'static str> {
+                match self {
+                    $(Self::$variant => $qualifier,)*
+                    Self::Any | Self::Unknown(_) => None,
+                }
+            }
+
+            pub fn from_qualifier(qualifier: &str) -> Option<Self> {
+                [$(Self::$variant),*]
+                    .iter()
+                    .find(|value| value.to_qualifier() == Some(qualifier))
+                    .cloned()
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::Any
+            }
+        }
+
+        impl From<$repr> for $name {
+            fn from(value: $repr) -> Self {
+                match value {
+                    0 => Self::Any,
+                    $($value => Self::$variant,)*
+                    value => Self::Unknown(value),
+                }
+            }
+        }
+
+        impl From<$name> for $repr {
+            fn from(value: $name) -> Self {
+                match value {
+                    $name::Any => 0,
+                    $($name::$variant => $value,)*
+                    $name::Unknown(value) => value,
+                }
+            }
+        }
+    };
+}
+
+pub(crate) const MASK_UI_MODE_TYPE: u8 = 0x0F;
+pub(crate) const MASK_UI_MODE_NIGHT: u8 = 0x30;
+pub(crate) const MASK_GRAMMATICAL_GENDER: u8 = 0x03;
+pub(crate) const MASK_SCREEN_SIZE: u8 = 0x0F;
+pub(crate) const MASK_SCREEN_LONG: u8 = 0x30;
+pub(crate) const MASK_LAYOUT_DIR: u8 = 0xC0;
+pub(crate) const MASK_SCREEN_ROUND: u8 = 0x03;
+pub(crate) const MASK_WIDE_COLOR_GAMUT: u8 = 0x03;
+pub(crate) const MASK_HDR: u8 = 0x0C;
+pub(crate) const MASK_KEYS_HIDDEN: u8 = 0x03;
+pub(crate) const MASK_NAV_HIDDEN: u8 = 0x0C;
+
+config_field! {
+    /// Orientation of the screen
+    Orientation: u8 {
+        Portrait = 1 => Some("port"),
+        Landscape = 2 => Some("land"),
+        Square = 3 => Some("square"),
+    }
+}
+
+config_field! {
+    /// Kind of touchscreen of the device
+    Touchscreen: u8 {
+        NoTouch = 1 => Some("notouch"),
+        Stylus = 2 => Some("stylus"),
+        Finger = 3 => Some("finger"),
+    }
+}
+
+config_field! {
+    /// Kind of keyboard of the device
+    Keyboard: u8 {
+        NoKeys = 1 => Some("nokeys"),
+        Qwerty = 2 => Some("qwerty"),
+        TwelveKey = 3 => Some("12key"),
+    }
+}
+
+config_field! {
+    /// Kind of non touch navigation of the device
+    Navigation: u8 {
+        NoNav = 1 => Some("nonav"),
+        Dpad = 2 => Some("dpad"),
+        Trackball = 3 => Some("trackball"),
+        Wheel = 4 => Some("wheel"),
+    }
+}
+
+config_field! {
+    /// Night mode, stored on the bits 4 and 5 of the ui mode
+    NightMode: u8 {
+        No = 0x10 => Some("notnight"),
+        Yes = 0x20 => Some("night"),
+    }
+}
+
+config_field! {
+    /// Type of device, stored on the lowest 4 bits of the ui mode
+    UiModeType: u8 {
+        /// Regular device. It has no qualifier.
+        Normal = 1 => None,
+        Desk = 2 => Some("desk"),
+        Car = 3 => Some("car"),
+        Television = 4 => Some("television"),
+        Appliance = 5 => Some("appliance"),
+        Watch = 6 => Some("watch"),
+        VrHeadset = 7 => Some("vrheadset"),
+    }
+}
+
+config_field! {
+    /// Screen density, in dots per inch. Densities without a name are kept on `Unknown` and
+    /// written as `420dpi`.
+    Density: u16 {
+        Low = 120 => Some("ldpi"),
+        Medium = 160 => Some("mdpi"),
+        Tv = 213 => Some("tvdpi"),
+        High = 240 => Some("hdpi"),
+        XHigh = 320 => Some("xhdpi"),
+        XxHigh = 480 => Some("xxhdpi"),
+        XxxHigh = 640 => Some("xxxhdpi"),
+        /// Resources that can be scaled to any density, like vector drawables
+        AnyDpi = 0xFFFE => Some("anydpi"),
+        /// Resources that must not be scaled
+        NoDpi = 0xFFFF => Some("nodpi"),
+    }
+}
+
+config_field! {
+    /// Grammatical gender used to address the user
+    GrammaticalGender: u8 {
+        Neuter = 1 => Some("neuter"),
+        Feminine = 2 => Some("feminine"),
+        Masculine = 3 => Some("masculine"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Density, NightMode, Orientation, UiModeType};
+
+    #[test]
+    fn it_converts_from_and_to_raw_values() {
+        assert_eq!(Orientation::Landscape, Orientation::from(2));
+        assert_eq!(Orientation::Any, Orientation::from(0));
+        assert_eq!(Orientation::Unknown(9), Orientation::from(9));
+        assert_eq!(9_u8, Orientation::Unknown(9).into());
+        assert_eq!(0xFFFE_u16, Density::AnyDpi.into());
+        assert_eq!(Density::Unknown(420), Density::from(420));
+    }
+
+    #[test]
+    fn it_converts_from_and_to_qualifiers() {
+        assert_eq!(Some(NightMode::Yes), NightMode::from_qualifier("night"));
+        assert_eq!(Some("nodpi"), Density::NoDpi.to_qualifier());
+        assert_eq!(None, UiModeType::Normal.to_qualifier());
+        assert_eq!(None, UiModeType::from_qualifier("normal"));
+        assert_eq!(None, Density::from_qualifier("420dpi"));
+    }
+}
@@ -0,0 +1,281 @@
+use std::fmt;
+
+use failure::{bail, Error};
+
+const COMPLEX_UNIT_MASK: u32 = 0xF;
+const COMPLEX_RADIX_SHIFT: u32 = 4;
+const COMPLEX_RADIX_MASK: u32 = 0x3;
+const COMPLEX_MANTISSA_SHIFT: u32 = 8;
+const COMPLEX_MANTISSA_MASK: u32 = 0x00FF_FFFF;
+
+const RADIX_23P0: u32 = 0;
+const RADIX_16P7: u32 = 1;
+const RADIX_8P15: u32 = 2;
+const RADIX_0P23: u32 = 3;
+
+/// Units of a `Dimension`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DimensionUnit {
+    Px,
+    Dip,
+    Sp,
+    Pt,
+    In,
+    Mm,
+}
+
+impl DimensionUnit {
+    fn from_complex(data: u32) -> Result<Self, Error> {
+        let unit = match data & COMPLEX_UNIT_MASK {
+            0 => Self::Px,
+            1 => Self::Dip,
+            2 => Self::Sp,
+            3 => Self::Pt,
+            4 => Self::In,
+            5 => Self::Mm,
+            unit => bail!("expected a valid unit index, got: {}", unit),
+        };
+
+        Ok(unit)
+    }
+
+    fn to_complex(self) -> u32 {
+        match self {
+            Self::Px => 0,
+            Self::Dip => 1,
+            Self::Sp => 2,
+            Self::Pt => 3,
+            Self::In => 4,
+            Self::Mm => 5,
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Px => "px",
+            Self::Dip => "dip",
+            Self::Sp => "sp",
+            Self::Pt => "pt",
+            Self::In => "in",
+            Self::Mm => "mm",
+        }
+    }
+}
+
+/// Units of a `Fraction`: relative to the element itself (`%`) or to its parent (`%p`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FractionUnit {
+    Percent,
+    PercentParent,
+}
+
+impl FractionUnit {
+    fn from_complex(data: u32) -> Result<Self, Error> {
+        let unit = match data & COMPLEX_UNIT_MASK {
+            0 => Self::Percent,
+            1 => Self::PercentParent,
+            unit => bail!("expected a valid unit index, got: {}", unit),
+        };
+
+        Ok(unit)
+    }
+
+    fn to_complex(self) -> u32 {
+        match self {
+            Self::Percent => 0,
+            Self::PercentParent => 1,
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Percent => "%",
+            Self::PercentParent => "%p",
+        }
+    }
+}
+
+/// Dimension, like `16.0dip`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dimension {
+    value: f32,
+    unit: DimensionUnit,
+}
+
+impl Dimension {
+    #[must_use]
+    pub fn new(value: f32, unit: DimensionUnit) -> Self {
+        Self { value, unit }
+    }
+
+    /// Decodes the complex representation used on the binary files
+    ///
+    /// # Errors
+    ///
+    /// It fails if the unit is not one of the dimension units.
+    pub fn from_complex(data: u32) -> Result<Self, Error> {
+        Ok(Self::new(
+            complex_to_float(data),
+            DimensionUnit::from_complex(data)?,
+        ))
+    }
+
+    /// Encodes the dimension with the complex representation used on the binary files
+    #[must_use]
+    pub fn to_complex(self) -> u32 {
+        float_to_complex(self.value) | self.unit.to_complex()
+    }
+
+    #[must_use]
+    pub fn get_value(self) -> f32 {
+        self.value
+    }
+
+    #[must_use]
+    pub fn get_unit(self) -> DimensionUnit {
+        self.unit
+    }
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{:?}{}", self.value, self.unit.as_str())
+    }
+}
+
+/// Fraction, like `50%`. The value is stored as a ratio, so `50%` has a value of `0.5`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fraction {
+    value: f32,
+    unit: FractionUnit,
+}
+
+impl Fraction {
+    #[must_use]
+    pub fn new(value: f32, unit: FractionUnit) -> Self {
+        Self { value, unit }
+    }
+
+    /// Decodes the complex representation used on the binary files
+    ///
+    /// # Errors
+    ///
+    /// It fails if the unit is not one of the fraction units.
+    pub fn from_complex(data: u32) -> Result<Self, Error> {
+        Ok(Self::new(
+            complex_to_float(data),
+            FractionUnit::from_complex(data)?,
+        ))
+    }
+
+    /// Encodes the fraction with the complex representation used on the binary files
+    #[must_use]
+    pub fn to_complex(self) -> u32 {
+        float_to_complex(self.value) | self.unit.to_complex()
+    }
+
+    #[must_use]
+    pub fn get_value(self) -> f32 {
+        self.value
+    }
+
+    #[must_use]
+    pub fn get_unit(self) -> FractionUnit {
+        self.unit
+    }
+}
+
+impl fmt::Display for Fraction {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{:?}{}", self.value * 100.0, self.unit.as_str())
+    }
+}
+
+/// Decodes a complex value. Bits [31..8] are a signed mantissa and bits [5..4] select where the
+/// radix point is placed.
+#[allow(clippy::cast_possible_wrap, clippy::cast_precision_loss)]
+fn complex_to_float(data: u32) -> f32 {
+    let mantissa = (data & (COMPLEX_MANTISSA_MASK << COMPLEX_MANTISSA_SHIFT)) as i32;
+    let radix = (data >> COMPLEX_RADIX_SHIFT) & COMPLEX_RADIX_MASK;
+    let shift = match radix {
+        RADIX_23P0 => 0,
+        RADIX_16P7 => 7,
+        RADIX_8P15 => 15,
+        _ => 23,
+    };
+
+    mantissa as f32 / (1_u64 << (COMPLEX_MANTISSA_SHIFT + shift)) as f32
+}
+
+/// Encodes a float as a complex value, selecting the radix that keeps the most precision, as
+/// aapt does
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn float_to_complex(value: f32) -> u32 {
+    let negative = value < 0.0;
+    let bits = (f64::from(value.abs()) * (1_u64 << 23) as f64 + 0.5) as u64;
+
+    let (radix, shift) = if bits.trailing_zeros() >= 23 {
+        (RADIX_23P0, 23)
+    } else if bits & 0xFFFF_FFFF_FF80_0000 == 0 {
+        (RADIX_0P23, 0)
+    } else if bits & 0xFFFF_FFFF_8000_0000 == 0 {
+        (RADIX_8P15, 8)
+    } else if bits & 0xFFFF_FF80_0000_0000 == 0 {
+        (RADIX_16P7, 16)
+    } else {
+        (RADIX_23P0, 23)
+    };
+
+    let mut mantissa = ((bits >> shift) as u32) & COMPLEX_MANTISSA_MASK;
+    if negative {
+        mantissa = mantissa.wrapping_neg() & COMPLEX_MANTISSA_MASK;
+    }
+
+    (radix << COMPLEX_RADIX_SHIFT) | (mantissa << COMPLEX_MANTISSA_SHIFT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dimension, DimensionUnit, Fraction, FractionUnit};
+
+    #[test]
+    fn it_can_decode_and_encode_dimensions() {
+        let dimension = Dimension::from_complex(0x0000_1001).unwrap();
+
+        assert_eq!(Dimension::new(16.0, DimensionUnit::Dip), dimension);
+        assert_eq!("16.0dip", dimension.to_string());
+        assert_eq!(0x0000_1001, dimension.to_complex());
+    }
+
+    #[test]
+    fn it_encodes_dimensions_with_fractional_part() {
+        for value in &[0.5, 1.25, -3.75, 100.125, -0.001] {
+            let dimension = Dimension::new(*value, DimensionUnit::Sp);
+            let decoded = Dimension::from_complex(dimension.to_complex()).unwrap();
+
+            assert!((decoded.get_value() - value).abs() < 0.0001);
+            assert_eq!(DimensionUnit::Sp, decoded.get_unit());
+        }
+    }
+
+    #[test]
+    fn it_can_decode_and_encode_fractions() {
+        let fraction = Fraction::new(0.5, FractionUnit::PercentParent);
+        let decoded = Fraction::from_complex(fraction.to_complex()).unwrap();
+
+        assert_eq!(fraction, decoded);
+        assert_eq!("50.0%p", decoded.to_string());
+    }
+
+    #[test]
+    fn it_does_not_decode_unknown_units() {
+        assert!(Dimension::from_complex(0x6).is_err());
+        assert!(Fraction::from_complex(0x2).is_err());
+    }
+}
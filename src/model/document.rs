@@ -0,0 +1,1091 @@
+//! Mutable representation of a binary XML document that can be encoded back to binary form
+
+use std::{collections::HashMap, io::Cursor};
+
+use failure::{format_err, Error, ResultExt};
+
+use crate::{
+    chunks::{
+        skip_document_header, Chunk, ChunkLoaderStream, StringTableWrapper, XmlTagStartWrapper,
+        TOKEN_XML,
+    },
+    errors::{locate, AbxmlError, ChunkContext, Diagnostic, Location},
+    model::{
+        builder::Xml,
+        owned::{
+            AttributeBuf, Encoding, ResourcesBuf, StringTableBuf, XmlNamespaceEndBuf,
+            XmlNamespaceStartBuf, XmlTagEndBuf, XmlTagStartBuf, XmlTextBuf,
+        },
+        AttributeTrait, NamespaceStart, ResourceId, StringTable, TagStart,
+    },
+    options::{check_limit, ParseOptions},
+};
+
+/// URI of the `android` namespace
+pub const ANDROID_NAMESPACE: &str = "http://schemas.android.com/apk/res/android";
+
+const TYPE_REFERENCE: u8 = 0x01;
+const TYPE_ATTRIBUTE: u8 = 0x02;
+const TYPE_STRING: u8 = 0x03;
+const TYPE_INT_DEC: u8 = 0x10;
+const TYPE_INT_BOOLEAN: u8 = 0x12;
+
+const NO_INDEX: u32 = 0xFFFF_FFFF;
+
+/// Typed value of an attribute or of a resource table entry
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue {
+    /// Literal string
+    String(String),
+    /// String with style spans, as the `<b>` or `<i>` tags of a string resource
+    StyledString(String, Vec<StringSpan>),
+    /// Boolean value
+    Boolean(bool),
+    /// Signed decimal integer
+    Integer(i32),
+    /// Reference to a resource (`@type/name`)
+    Reference(ResourceId),
+    /// Reference to a theme attribute (`?attr/name`)
+    AttributeReference(ResourceId),
+    /// Any other `Res_value`, kept as its type and its data
+    Raw(u8, u32),
+}
+
+impl AttributeValue {
+    /// Typed value of a `Res_value`. `string` is the value of the `TYPE_STRING` values; the raw
+    /// string that other types may carry is not part of their value, so it is ignored.
+    #[allow(clippy::cast_possible_wrap)]
+    pub(super) fn from_raw(data_type: u8, data: u32, string: Option<String>) -> Self {
+        match (data_type, string) {
+            (TYPE_STRING, Some(string)) => Self::String(string),
+            (TYPE_INT_BOOLEAN, _) => Self::Boolean(data != 0),
+            (TYPE_INT_DEC, _) => Self::Integer(data as i32),
+            (TYPE_REFERENCE, _) => Self::Reference(ResourceId::from(data)),
+            (TYPE_ATTRIBUTE, _) => Self::AttributeReference(ResourceId::from(data)),
+            (data_type, _) => Self::Raw(data_type, data),
+        }
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    pub(super) fn to_raw(&self, string_index: Option<u32>) -> (u8, u32) {
+        match self {
+            Self::String(_) | Self::StyledString(..) => {
+                (TYPE_STRING, string_index.unwrap_or(NO_INDEX))
+            }
+            Self::Boolean(value) => (TYPE_INT_BOOLEAN, if *value { NO_INDEX } else { 0 }),
+            Self::Integer(value) => (TYPE_INT_DEC, *value as u32),
+            Self::Reference(id) => (TYPE_REFERENCE, id.as_u32()),
+            Self::AttributeReference(id) => (TYPE_ATTRIBUTE, id.as_u32()),
+            Self::Raw(data_type, data) => (*data_type, *data),
+        }
+    }
+}
+
+/// Style applied to a range of a `StyledString`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StringSpan {
+    name: String,
+    first_char: u32,
+    last_char: u32,
+}
+
+impl StringSpan {
+    /// Creates a span of the style `name` (`b`, `font;color=red`...) from `first_char` to
+    /// `last_char`, both included and counted in UTF-16 code units
+    #[must_use]
+    pub fn new(name: &str, first_char: u32, last_char: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            first_char,
+            last_char,
+        }
+    }
+
+    #[must_use]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn get_first_char(&self) -> u32 {
+        self.first_char
+    }
+
+    #[must_use]
+    pub fn get_last_char(&self) -> u32 {
+        self.last_char
+    }
+}
+
+/// Attribute of a `XmlElement`
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlAttribute {
+    namespace: Option<String>,
+    name: String,
+    resource_id: Option<ResourceId>,
+    value: AttributeValue,
+}
+
+impl XmlAttribute {
+    /// Creates an attribute without namespace nor resource id
+    #[must_use]
+    pub fn new(name: &str, value: AttributeValue) -> Self {
+        Self {
+            namespace: None,
+            name: name.to_string(),
+            resource_id: None,
+            value,
+        }
+    }
+
+    /// Creates an attribute on the `android` namespace. `resource_id` is the identifier of the
+    /// attribute on the framework (for example, `0x0101000f` for `android:debuggable`).
+    #[must_use]
+    pub fn android(name: &str, resource_id: ResourceId, value: AttributeValue) -> Self {
+        Self {
+            namespace: Some(ANDROID_NAMESPACE.to_string()),
+            name: name.to_string(),
+            resource_id: Some(resource_id),
+            value,
+        }
+    }
+
+    /// Creates an attribute on the given namespace URI
+    #[must_use]
+    pub fn with_namespace(
+        namespace: &str,
+        name: &str,
+        resource_id: Option<ResourceId>,
+        value: AttributeValue,
+    ) -> Self {
+        Self {
+            namespace: Some(namespace.to_string()),
+            name: name.to_string(),
+            resource_id,
+            value,
+        }
+    }
+
+    #[must_use]
+    pub fn get_namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    #[must_use]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn get_resource_id(&self) -> Option<ResourceId> {
+        self.resource_id
+    }
+
+    #[must_use]
+    pub fn get_value(&self) -> &AttributeValue {
+        &self.value
+    }
+
+    pub fn set_value(&mut self, value: AttributeValue) {
+        self.value = value;
+    }
+
+    fn matches(&self, namespace: Option<&str>, name: &str) -> bool {
+        self.get_namespace() == namespace && self.name == name
+    }
+}
+
+/// Child of a `XmlElement`: elements and text keep the order they have on the document
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlNode {
+    Element(XmlElement),
+    Text(String),
+}
+
+impl XmlNode {
+    #[must_use]
+    pub fn as_element(&self) -> Option<&XmlElement> {
+        match self {
+            Self::Element(element) => Some(element),
+            Self::Text(_) => None,
+        }
+    }
+
+    pub fn as_mut_element(&mut self) -> Option<&mut XmlElement> {
+        match self {
+            Self::Element(element) => Some(element),
+            Self::Text(_) => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Element(_) => None,
+            Self::Text(text) => Some(text),
+        }
+    }
+}
+
+/// Element of a `BinaryXmlDocument`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct XmlElement {
+    /// Namespaces declared on this element as (prefix, URI). They are in scope for the element
+    /// and its descendants.
+    namespaces: Vec<(String, String)>,
+    namespace: Option<String>,
+    name: String,
+    attributes: Vec<XmlAttribute>,
+    children: Vec<XmlNode>,
+    line: u32,
+}
+
+impl XmlElement {
+    /// Creates an element without namespace
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Creates an element on the given namespace URI
+    #[must_use]
+    pub fn with_namespace(namespace: &str, name: &str) -> Self {
+        Self {
+            namespace: Some(namespace.to_string()),
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    pub fn get_namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    #[must_use]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn get_line(&self) -> u32 {
+        self.line
+    }
+
+    /// Namespace declarations of the element as (prefix, URI)
+    #[must_use]
+    pub fn get_namespaces(&self) -> &[(String, String)] {
+        &self.namespaces
+    }
+
+    /// Declares a namespace on the element. If the URI is already declared on it, its prefix is
+    /// replaced.
+    pub fn add_namespace(&mut self, prefix: &str, uri: &str) {
+        match self.namespaces.iter_mut().find(|(_, u)| u == uri) {
+            Some(namespace) => namespace.0 = prefix.to_string(),
+            None => self.namespaces.push((prefix.to_string(), uri.to_string())),
+        }
+    }
+
+    /// Text of the element, joining all its text nodes, or `None` if it has no text
+    pub fn get_text(&self) -> Option<String> {
+        let texts: Vec<&str> = self.children.iter().filter_map(XmlNode::as_text).collect();
+
+        if texts.is_empty() {
+            None
+        } else {
+            Some(texts.concat())
+        }
+    }
+
+    #[must_use]
+    pub fn get_attributes(&self) -> &[XmlAttribute] {
+        &self.attributes
+    }
+
+    /// Returns the attribute with the given namespace URI and name
+    #[must_use]
+    pub fn get_attribute(&self, namespace: Option<&str>, name: &str) -> Option<&XmlAttribute> {
+        self.attributes.iter().find(|a| a.matches(namespace, name))
+    }
+
+    pub fn get_mut_attribute(
+        &mut self,
+        namespace: Option<&str>,
+        name: &str,
+    ) -> Option<&mut XmlAttribute> {
+        self.attributes
+            .iter_mut()
+            .find(|a| a.matches(namespace, name))
+    }
+
+    /// Adds the attribute to the element. If an attribute with the same namespace and name
+    /// already exists, it is replaced.
+    pub fn set_attribute(&mut self, attribute: XmlAttribute) {
+        let namespace = attribute.namespace.clone();
+
+        match self.get_mut_attribute(namespace.as_deref(), &attribute.name) {
+            Some(existing) => *existing = attribute,
+            None => self.attributes.push(attribute),
+        }
+    }
+
+    /// Removes and returns the attribute with the given namespace URI and name
+    pub fn remove_attribute(
+        &mut self,
+        namespace: Option<&str>,
+        name: &str,
+    ) -> Option<XmlAttribute> {
+        let position = self
+            .attributes
+            .iter()
+            .position(|a| a.matches(namespace, name))?;
+
+        Some(self.attributes.remove(position))
+    }
+
+    /// Child elements and text, on document order
+    #[must_use]
+    pub fn get_children(&self) -> &[XmlNode] {
+        &self.children
+    }
+
+    pub fn get_mut_children(&mut self) -> &mut Vec<XmlNode> {
+        &mut self.children
+    }
+
+    /// Appends a child element
+    pub fn append(&mut self, element: Self) {
+        self.children.push(XmlNode::Element(element));
+    }
+
+    /// Appends a text node
+    pub fn append_text(&mut self, text: &str) {
+        self.children.push(XmlNode::Text(text.to_string()));
+    }
+
+    /// Returns the child elements
+    pub fn elements(&self) -> impl Iterator<Item = &Self> {
+        self.children.iter().filter_map(XmlNode::as_element)
+    }
+
+    /// Returns the child elements, to modify them
+    pub fn elements_mut(&mut self) -> impl Iterator<Item = &mut Self> {
+        self.children.iter_mut().filter_map(XmlNode::as_mut_element)
+    }
+
+    /// Returns the child elements with the given name
+    pub fn find_children<'b>(&'b self, name: &'b str) -> impl Iterator<Item = &'b Self> {
+        self.elements().filter(move |c| c.name == name)
+    }
+
+    /// Removes all the child elements that do not satisfy the predicate. Text is kept.
+    pub fn retain_children<F: FnMut(&Self) -> bool>(&mut self, mut predicate: F) {
+        self.children.retain(|node| match node {
+            XmlNode::Element(element) => predicate(element),
+            XmlNode::Text(_) => true,
+        });
+    }
+}
+
+/// Mutable binary XML document. String pool and resource map are not stored: they are
+/// regenerated when the document is encoded. A `StyledString` is encoded as a plain string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryXmlDocument {
+    root: XmlElement,
+    encoding: Encoding,
+}
+
+impl BinaryXmlDocument {
+    /// Creates a new document with the given root element
+    #[must_use]
+    pub fn new(root: XmlElement) -> Self {
+        Self {
+            root,
+            encoding: Encoding::Utf16,
+        }
+    }
+
+    /// Decodes a binary XML document. Decoding is strict: the document is encoded again as a
+    /// whole, so any problem on it fails instead of losing the broken parts.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the buffer is not a binary XML or it has any problem.
+    pub fn from_bytes(buffer: &[u8]) -> Result<Self, Error> {
+        Self::from_bytes_with_options(buffer, ParseOptions::strict()).map(|(document, _)| document)
+    }
+
+    /// Decodes a binary XML document, handling the problems as `options` says. It returns the
+    /// problems that were skipped on lenient mode.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the buffer is not a binary XML or it has problems that can not be skipped.
+    pub fn from_bytes_with_options(
+        buffer: &[u8],
+        options: ParseOptions,
+    ) -> Result<(Self, Vec<Diagnostic>), Error> {
+        let mut cursor = Cursor::new(buffer);
+        skip_document_header(&mut cursor, TOKEN_XML)?;
+
+        let mut loader = DocumentLoader::with_options(options);
+        let mut stream = ChunkLoaderStream::with_limits(cursor, options.get_limits());
+        let mut diagnostics = Vec::new();
+
+        while let Some(chunk) = stream.next() {
+            let chunk = chunk.context("error reading next chunk")?;
+            let (offset, path) = match stream.get_header() {
+                Some(header) => (
+                    header.get_offset(),
+                    vec![
+                        ChunkContext::new(TOKEN_XML, 0),
+                        ChunkContext::new(header.get_token(), header.get_offset()),
+                    ],
+                ),
+                None => (0, vec![ChunkContext::new(TOKEN_XML, 0)]),
+            };
+
+            loader
+                .load(chunk)
+                .map_err(|e| locate(&e, offset, &path))
+                .context("could not load document")?;
+
+            for error in loader.diagnostics.drain(..) {
+                diagnostics.push(Diagnostic::new(error.located(offset, &path)));
+            }
+        }
+
+        Ok((loader.into_document()?, diagnostics))
+    }
+
+    #[must_use]
+    pub fn get_root(&self) -> &XmlElement {
+        &self.root
+    }
+
+    pub fn get_mut_root(&mut self) -> &mut XmlElement {
+        &mut self.root
+    }
+
+    /// Namespace declarations of the root element as (prefix, URI)
+    #[must_use]
+    pub fn get_namespaces(&self) -> &[(String, String)] {
+        self.root.get_namespaces()
+    }
+
+    /// Declares a namespace on the root element. If the URI is already declared, its prefix is
+    /// replaced.
+    pub fn add_namespace(&mut self, prefix: &str, uri: &str) {
+        self.root.add_namespace(prefix, uri);
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    /// Encodes the document as a binary XML
+    ///
+    /// # Errors
+    ///
+    /// It fails if the strings or the attributes can not be encoded.
+    pub fn to_vec(&self) -> Result<Vec<u8>, Error> {
+        let pool = StringPool::build(self);
+        let mut xml = Xml::default();
+
+        let mut string_table = StringTableBuf::default();
+        string_table.set_encoding(self.encoding);
+        for string in &pool.strings {
+            string_table.add_string(string.clone());
+        }
+        xml.push_owned(Box::new(string_table));
+
+        let mut resources = ResourcesBuf::default();
+        for id in &pool.resource_ids {
+            resources.push_resource(id.as_u32());
+        }
+        xml.push_owned(Box::new(resources));
+
+        Self::encode_element(&mut xml, &pool, &self.root)?;
+
+        xml.into_vec()
+    }
+
+    fn encode_element(xml: &mut Xml, pool: &StringPool, element: &XmlElement) -> Result<(), Error> {
+        for (prefix, uri) in &element.namespaces {
+            xml.push_owned(Box::new(XmlNamespaceStartBuf::new(
+                element.line,
+                pool.index(prefix)?,
+                pool.index(uri)?,
+            )));
+        }
+
+        let namespace = pool.optional_index(element.get_namespace())?;
+        let name = pool.index(&element.name)?;
+        // Attributes start at offset 20 and each one is 20 bytes long
+        let mut tag_start =
+            XmlTagStartBuf::new(element.line, NO_INDEX, namespace, name, 0x0014_0014, 0);
+
+        // Android performs binary searches on the attribute resource ids, so they are sorted
+        let mut attributes: Vec<&XmlAttribute> = element.attributes.iter().collect();
+        attributes.sort_by_key(|a| a.resource_id.map_or(NO_INDEX, ResourceId::as_u32));
+
+        for attribute in attributes {
+            let raw_string = match &attribute.value {
+                AttributeValue::String(value) | AttributeValue::StyledString(value, _) => {
+                    Some(pool.index(value)?)
+                }
+                _ => None,
+            };
+            let (data_type, data) = attribute.value.to_raw(raw_string);
+
+            tag_start.add_attribute(AttributeBuf::new(
+                pool.optional_index(attribute.get_namespace())?,
+                pool.attribute_index(attribute)?,
+                raw_string.unwrap_or(NO_INDEX),
+                8 | (u32::from(data_type) << 24),
+                data,
+            ));
+        }
+
+        xml.push_owned(Box::new(tag_start));
+
+        for child in &element.children {
+            match child {
+                XmlNode::Element(child) => Self::encode_element(xml, pool, child)?,
+                XmlNode::Text(text) => {
+                    xml.push_owned(Box::new(XmlTextBuf::new(element.line, pool.index(text)?)));
+                }
+            }
+        }
+
+        xml.push_owned(Box::new(XmlTagEndBuf::with_namespace(
+            element.line,
+            namespace,
+            name,
+        )));
+
+        for (prefix, uri) in element.namespaces.iter().rev() {
+            xml.push_owned(Box::new(XmlNamespaceEndBuf::new(
+                element.line,
+                pool.index(prefix)?,
+                pool.index(uri)?,
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// String pool of a document being encoded. Names of attributes with a resource id must be
+/// placed first and in the same order than the resource map.
+struct StringPool {
+    strings: Vec<String>,
+    resource_ids: Vec<ResourceId>,
+    indexes: HashMap<String, u32>,
+    attribute_indexes: HashMap<(String, ResourceId), u32>,
+}
+
+impl StringPool {
+    fn build(document: &BinaryXmlDocument) -> Self {
+        let mut pool = Self {
+            strings: Vec::new(),
+            resource_ids: Vec::new(),
+            indexes: HashMap::new(),
+            attribute_indexes: HashMap::new(),
+        };
+
+        pool.add_attribute_names(&document.root);
+        pool.add_element_strings(&document.root);
+
+        pool
+    }
+
+    fn add_attribute_names(&mut self, element: &XmlElement) {
+        for attribute in &element.attributes {
+            if let Some(id) = attribute.resource_id {
+                let key = (attribute.name.clone(), id);
+
+                if !self.attribute_indexes.contains_key(&key) {
+                    self.attribute_indexes
+                        .insert(key, self.strings.len() as u32);
+                    self.strings.push(attribute.name.clone());
+                    self.resource_ids.push(id);
+                }
+            }
+        }
+
+        for child in element.elements() {
+            self.add_attribute_names(child);
+        }
+    }
+
+    fn add_element_strings(&mut self, element: &XmlElement) {
+        for (prefix, uri) in &element.namespaces {
+            self.add(prefix);
+            self.add(uri);
+        }
+
+        if let Some(namespace) = &element.namespace {
+            self.add(namespace);
+        }
+
+        self.add(&element.name);
+
+        for attribute in &element.attributes {
+            if let Some(namespace) = &attribute.namespace {
+                self.add(namespace);
+            }
+
+            if attribute.resource_id.is_none() {
+                self.add(&attribute.name);
+            }
+
+            match &attribute.value {
+                AttributeValue::String(value) | AttributeValue::StyledString(value, _) => {
+                    self.add(value);
+                }
+                _ => (),
+            }
+        }
+
+        for child in &element.children {
+            match child {
+                XmlNode::Element(child) => self.add_element_strings(child),
+                XmlNode::Text(text) => self.add(text),
+            }
+        }
+    }
+
+    fn add(&mut self, string: &str) {
+        if !self.indexes.contains_key(string) {
+            self.indexes
+                .insert(string.to_string(), self.strings.len() as u32);
+            self.strings.push(string.to_string());
+        }
+    }
+
+    fn index(&self, string: &str) -> Result<u32, Error> {
+        self.indexes
+            .get(string)
+            .copied()
+            .ok_or_else(|| format_err!("string not found on the pool: {}", string))
+    }
+
+    fn optional_index(&self, string: Option<&str>) -> Result<u32, Error> {
+        string.map_or(Ok(NO_INDEX), |s| self.index(s))
+    }
+
+    fn attribute_index(&self, attribute: &XmlAttribute) -> Result<u32, Error> {
+        match attribute.resource_id {
+            Some(id) => self
+                .attribute_indexes
+                .get(&(attribute.name.clone(), id))
+                .copied()
+                .ok_or_else(|| format_err!("attribute not found on the pool: {}", attribute.name)),
+            None => self.index(&attribute.name),
+        }
+    }
+}
+
+/// Builds a `BinaryXmlDocument` from the chunks of a binary XML
+#[derive(Default)]
+struct DocumentLoader<'a> {
+    string_table: Option<StringTableWrapper<'a>>,
+    resources: Vec<u32>,
+    /// Namespaces declared since the last element started, which belong to the next one
+    namespaces: Vec<(String, String)>,
+    stack: Vec<XmlElement>,
+    root: Option<XmlElement>,
+    encoding: Option<Encoding>,
+    options: ParseOptions,
+    /// Problems recovered from while loading the last chunk
+    diagnostics: Vec<AbxmlError>,
+}
+
+impl<'a> DocumentLoader<'a> {
+    fn with_options(options: ParseOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    fn load(&mut self, chunk: Chunk<'a>) -> Result<(), Error> {
+        match chunk {
+            Chunk::StringTable(stw) => {
+                if self.string_table.is_some() {
+                    self.options.recover(
+                        AbxmlError::Malformed {
+                            location: Location::default(),
+                            reason: "secondary string tables are not supported".to_string(),
+                        },
+                        &mut self.diagnostics,
+                    )?;
+                } else {
+                    self.encoding = Some(stw.to_buffer()?.get_encoding());
+                    self.string_table = Some(stw);
+                }
+            }
+            Chunk::Resource(rw) => self.resources = rw.get_resources()?,
+            Chunk::XmlNamespaceStart(xnsw) => {
+                let string_table = self.get_string_table()?;
+                let prefix = (*xnsw.get_prefix(string_table)?).clone();
+                let uri = (*xnsw.get_namespace(string_table)?).clone();
+
+                self.namespaces.push((prefix, uri));
+            }
+            Chunk::XmlTagStart(xtsw) => {
+                check_limit(
+                    "XML depth",
+                    self.stack.len() as u64 + 1,
+                    self.options.get_limits().get_max_xml_depth() as u64,
+                    Location::default(),
+                )?;
+                let mut element = self.build_element(&xtsw)?;
+                element.namespaces = self.namespaces.drain(..).collect();
+                self.stack.push(element);
+            }
+            Chunk::XmlTagEnd(_) => {
+                let element = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| format_err!("tag end without a matching tag start"))?;
+
+                match self.stack.last_mut() {
+                    Some(parent) => parent.append(element),
+                    None => self.root = Some(element),
+                }
+            }
+            Chunk::XmlText(xtw) => {
+                let text = (*self.get_string_table()?.get_string(xtw.get_text_index()?)?).clone();
+
+                if let Some(current) = self.stack.last_mut() {
+                    current.children.push(XmlNode::Text(text));
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn get_string_table(&self) -> Result<&StringTableWrapper<'a>, Error> {
+        self.string_table
+            .as_ref()
+            .ok_or_else(|| format_err!("no main string table found"))
+    }
+
+    fn get_optional_string(&self, index: u32) -> Result<Option<String>, Error> {
+        if index == NO_INDEX {
+            Ok(None)
+        } else {
+            Ok(Some((*self.get_string_table()?.get_string(index)?).clone()))
+        }
+    }
+
+    fn build_element(&self, tag_start: &XmlTagStartWrapper) -> Result<XmlElement, Error> {
+        let string_table = self.get_string_table()?;
+        let mut element = XmlElement {
+            namespace: self.get_optional_string(tag_start.get_namespace_index()?)?,
+            name: (*string_table.get_string(tag_start.get_element_name_index()?)?).clone(),
+            line: tag_start.get_line()?,
+            ..XmlElement::default()
+        };
+
+        for i in 0..tag_start.get_attributes_amount()? {
+            let attribute = tag_start
+                .get_attribute(i)
+                .context(format_err!("could not read attribute {} ", i))?;
+            let name_index = attribute.get_name()?;
+            let data_type = ((attribute.get_resource_value()? >> 24) & 0xFF) as u8;
+            let data = attribute.get_data()?;
+            let string = match (data_type, attribute.get_class()?) {
+                (TYPE_STRING, NO_INDEX) => self.get_optional_string(data)?,
+                (TYPE_STRING, raw_index) => self.get_optional_string(raw_index)?,
+                _ => None,
+            };
+
+            element.attributes.push(XmlAttribute {
+                namespace: self.get_optional_string(attribute.get_namespace()?)?,
+                name: (*string_table.get_string(name_index)?).clone(),
+                resource_id: self
+                    .resources
+                    .get(name_index as usize)
+                    .copied()
+                    .map(ResourceId::from),
+                value: AttributeValue::from_raw(data_type, data, string),
+            });
+        }
+
+        Ok(element)
+    }
+
+    fn into_document(self) -> Result<BinaryXmlDocument, Error> {
+        let root = self
+            .root
+            .ok_or_else(|| format_err!("document without root element"))?;
+
+        Ok(BinaryXmlDocument {
+            root,
+            encoding: self.encoding.unwrap_or(Encoding::Utf16),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AttributeValue, BinaryXmlDocument, XmlAttribute, XmlElement, XmlNode, ANDROID_NAMESPACE,
+        NO_INDEX, TYPE_INT_BOOLEAN,
+    };
+    use crate::{
+        decoder::Decoder,
+        errors::AbxmlError,
+        model::{
+            builder::Xml,
+            owned::{
+                AttributeBuf, Encoding, OwnedBuf, StringTableBuf, XmlTagEndBuf, XmlTagStartBuf,
+            },
+            ResourceId,
+        },
+        options::{DecodeLimits, ParseOptions},
+        reader::BinaryXmlReader,
+        visitor::Resources,
+    };
+
+    const ARSC: [u8; 12] = [2, 0, 12, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    fn example_document() -> BinaryXmlDocument {
+        let mut root = XmlElement::new("manifest");
+        root.set_attribute(XmlAttribute::android(
+            "versionCode",
+            ResourceId::from(0x0101_021b),
+            AttributeValue::Integer(3),
+        ));
+        root.set_attribute(XmlAttribute::new(
+            "package",
+            AttributeValue::String("com.example".to_string()),
+        ));
+
+        let mut application = XmlElement::new("application");
+        application.set_attribute(XmlAttribute::android(
+            "debuggable",
+            ResourceId::from(0x0101_000f),
+            AttributeValue::Boolean(false),
+        ));
+        root.append(application);
+
+        let mut document = BinaryXmlDocument::new(root);
+        document.add_namespace("android", ANDROID_NAMESPACE);
+
+        document
+    }
+
+    fn to_string(document: &BinaryXmlDocument) -> String {
+        let content = document.to_vec().unwrap();
+        let decoder = Decoder::from_buffer(&ARSC).unwrap();
+        let visitor = decoder.xml_visitor(&content).unwrap();
+
+        visitor.into_string().unwrap()
+    }
+
+    #[test]
+    fn it_encodes_a_document_that_can_be_decoded_again() {
+        let document = example_document();
+        let content = document.to_vec().unwrap();
+
+        let decoded = BinaryXmlDocument::from_bytes(&content).unwrap();
+
+        assert_eq!(document, decoded);
+        assert_eq!(
+            Some(ResourceId::from(0x0101_000f)),
+            decoded
+                .get_root()
+                .elements()
+                .next()
+                .unwrap()
+                .get_attribute(Some(ANDROID_NAMESPACE), "debuggable")
+                .unwrap()
+                .get_resource_id()
+        );
+    }
+
+    #[test]
+    fn it_can_modify_attributes_and_elements() {
+        let content = example_document().to_vec().unwrap();
+        let mut document = BinaryXmlDocument::from_bytes(&content).unwrap();
+
+        {
+            let root = document.get_mut_root();
+            root.set_attribute(XmlAttribute::new(
+                "package",
+                AttributeValue::String("com.patched".to_string()),
+            ));
+            root.elements_mut()
+                .next()
+                .unwrap()
+                .get_mut_attribute(Some(ANDROID_NAMESPACE), "debuggable")
+                .unwrap()
+                .set_value(AttributeValue::Boolean(true));
+
+            let mut permission = XmlElement::new("uses-permission");
+            permission.set_attribute(XmlAttribute::android(
+                "name",
+                ResourceId::from(0x0101_0003),
+                AttributeValue::String("android.permission.INTERNET".to_string()),
+            ));
+            root.get_mut_children()
+                .insert(0, XmlNode::Element(permission));
+            assert!(root
+                .remove_attribute(Some(ANDROID_NAMESPACE), "versionCode")
+                .is_some());
+        }
+
+        let out = to_string(&document);
+
+        assert!(out.contains("package=\"com.patched\""));
+        assert!(out.contains("<uses-permission android:name=\"android.permission.INTERNET\" />"));
+        assert!(out.contains("android:debuggable=\"true\""));
+        assert!(!out.contains("versionCode"));
+    }
+
+    #[test]
+    fn it_keeps_the_original_encoding() {
+        let mut document = example_document();
+        document.set_encoding(Encoding::Utf8);
+        let content = document.to_vec().unwrap();
+
+        let decoded = BinaryXmlDocument::from_bytes(&content).unwrap();
+
+        assert_eq!(document, decoded);
+    }
+
+    #[test]
+    fn it_keeps_text_and_namespaces_where_they_are() {
+        let mut item = XmlElement::new("item");
+        item.add_namespace("tools", "http://schemas.android.com/tools");
+        item.set_attribute(XmlAttribute::with_namespace(
+            "http://schemas.android.com/tools",
+            "ignore",
+            None,
+            AttributeValue::String("all".to_string()),
+        ));
+        let mut root = XmlElement::new("resources");
+        root.append_text("before");
+        root.append(item);
+        root.append_text("after");
+        let mut document = BinaryXmlDocument::new(root);
+        document.add_namespace("android", ANDROID_NAMESPACE);
+
+        let decoded = BinaryXmlDocument::from_bytes(&document.to_vec().unwrap()).unwrap();
+
+        assert_eq!(document, decoded);
+        let children = decoded.get_root().get_children();
+        assert_eq!(Some("before"), children[0].as_text());
+        assert_eq!("item", children[1].as_element().unwrap().get_name());
+        assert_eq!(Some("after"), children[2].as_text());
+        assert_eq!(
+            Some("beforeafter".to_string()),
+            decoded.get_root().get_text()
+        );
+        assert_eq!(
+            &[("android".to_string(), ANDROID_NAMESPACE.to_string())],
+            decoded.get_namespaces()
+        );
+        assert_eq!(
+            &[(
+                "tools".to_string(),
+                "http://schemas.android.com/tools".to_string()
+            )],
+            children[1].as_element().unwrap().get_namespaces()
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_type_of_values_with_a_raw_string() {
+        let mut string_table = StringTableBuf::default();
+        string_table.add_string("manifest".to_string());
+        string_table.add_string("debuggable".to_string());
+        string_table.add_string("true".to_string());
+        let mut tag_start = XmlTagStartBuf::new(1, NO_INDEX, NO_INDEX, 0, 0x0014_0014, 0);
+        tag_start.add_attribute(AttributeBuf::new(
+            NO_INDEX,
+            1,
+            2,
+            8 | (u32::from(TYPE_INT_BOOLEAN) << 24),
+            NO_INDEX,
+        ));
+        let mut xml = Xml::default();
+        xml.push_owned(Box::new(string_table));
+        xml.push_owned(Box::new(tag_start));
+        xml.push_owned(Box::new(XmlTagEndBuf::with_namespace(1, NO_INDEX, 0)));
+        let content = xml.into_vec().unwrap();
+
+        let document = BinaryXmlDocument::from_bytes(&content).unwrap();
+        let decoded = BinaryXmlDocument::from_bytes(&document.to_vec().unwrap()).unwrap();
+
+        for document in &[document, decoded] {
+            assert_eq!(
+                &AttributeValue::Boolean(true),
+                document
+                    .get_root()
+                    .get_attribute(None, "debuggable")
+                    .unwrap()
+                    .get_value()
+            );
+        }
+    }
+
+    #[test]
+    fn it_only_skips_problems_when_asked_to() {
+        let mut content = example_document().to_vec().unwrap();
+        // A second string table is a problem, as the strings are taken from the first one
+        content.extend(StringTableBuf::default().to_vec().unwrap());
+        let size = content.len() as u32;
+        content[4..8].copy_from_slice(&size.to_le_bytes());
+
+        assert!(BinaryXmlDocument::from_bytes(&content).is_err());
+
+        let (document, diagnostics) =
+            BinaryXmlDocument::from_bytes_with_options(&content, ParseOptions::lenient()).unwrap();
+
+        assert_eq!(example_document(), document);
+        assert_eq!(1, diagnostics.len());
+    }
+
+    #[test]
+    fn it_enforces_the_xml_depth_limit() {
+        let mut element = XmlElement::new("leaf");
+        for _ in 0..9 {
+            let mut parent = XmlElement::new("node");
+            parent.append(element);
+            element = parent;
+        }
+        let content = BinaryXmlDocument::new(element).to_vec().unwrap();
+        let options =
+            ParseOptions::default().with_limits(DecodeLimits::default().with_max_xml_depth(5));
+
+        assert!(BinaryXmlDocument::from_bytes(&content).is_ok());
+        let error = BinaryXmlDocument::from_bytes_with_options(&content, options)
+            .err()
+            .unwrap();
+        let limit = error
+            .iter_chain()
+            .find_map(|cause| cause.downcast_ref::<AbxmlError>())
+            .cloned();
+        match limit {
+            Some(AbxmlError::LimitExceeded { max, found, .. }) => assert_eq!((5, 6), (max, found)),
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let resources = Resources::default();
+        let reader = BinaryXmlReader::with_options(&content, &resources, options).unwrap();
+        let events: Vec<_> = reader.collect();
+        let first_error = events.iter().position(Result::is_err).unwrap();
+        assert_eq!(5, first_error);
+    }
+}
@@ -5,7 +5,12 @@ use std::{
     rc::Rc,
 };
 
-use log::error;
+use failure::{format_err, Error};
+
+use crate::{
+    errors::Location,
+    options::{check_limit, DecodeLimits},
+};
 
 #[derive(Default, Debug, PartialEq, Eq, Hash)]
 pub struct Tag {
@@ -81,38 +86,75 @@ impl Display for Element {
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Debug)]
 pub struct ElementContainer {
     stack: Vec<Element>,
     root: Option<Element>,
+    max_depth: usize,
+}
+
+impl Default for ElementContainer {
+    fn default() -> Self {
+        Self::with_limits(DecodeLimits::default())
+    }
 }
 
 impl ElementContainer {
-    pub fn start_element(&mut self, mut element: Element) {
+    /// Container that rejects the elements nested deeper than the limit
+    #[must_use]
+    pub fn with_limits(limits: DecodeLimits) -> Self {
+        Self {
+            stack: Vec::new(),
+            root: None,
+            max_depth: limits.get_max_xml_depth(),
+        }
+    }
+
+    /// Opens the given element as a child of the current one
+    ///
+    /// # Errors
+    ///
+    /// It fails if the element is nested deeper than the XML depth limit.
+    pub fn start_element(&mut self, mut element: Element) -> Result<(), Error> {
+        check_limit(
+            "XML depth",
+            self.stack.len() as u64 + 1,
+            self.max_depth as u64,
+            Location::default(),
+        )?;
+
         element.set_level(self.stack.len() as u32);
         self.stack.push(element);
+
+        Ok(())
     }
 
-    pub fn end_element(&mut self) {
-        self.stack
+    /// Closes the current element and appends it to its parent
+    ///
+    /// # Errors
+    ///
+    /// It fails if there is no open element.
+    pub fn end_element(&mut self) -> Result<(), Error> {
+        let element = self
+            .stack
             .pop()
-            .and_then(|element| {
-                if self.stack.is_empty() {
-                    self.root = Some(element);
-                } else {
-                    // Append child to current element
-                    let last_element = self.stack.len();
-                    self.stack[last_element - 1].append(element);
-                }
-
-                Some(())
-            })
-            .unwrap_or_else(|| {
-                error!("Received an end element event with an empty stack");
-            });
+            .ok_or_else(|| format_err!("received an end element event with an empty stack"))?;
+
+        match self.stack.last_mut() {
+            // Append child to current element
+            Some(parent) => parent.append(element),
+            None => self.root = Some(element),
+        }
+
+        Ok(())
     }
 
     pub fn get_root(&self) -> &Option<Element> {
         &self.root
     }
+
+    #[must_use]
+    pub fn into_root(self) -> Option<Element> {
+        self.root
+    }
 }
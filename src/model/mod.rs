@@ -6,49 +6,54 @@ use std::{
 };
 
 use failure::Error;
-use log::info;
 
+mod bag;
 pub mod builder;
+mod config_fields;
+mod dimension;
+mod document;
 mod element;
+mod name;
 pub mod owned;
+mod qualifiers;
+mod resource_id;
+mod spec_flags;
+mod table;
+mod theme;
 mod value;
 
+pub(crate) use self::config_fields::{
+    MASK_GRAMMATICAL_GENDER, MASK_HDR, MASK_KEYS_HIDDEN, MASK_LAYOUT_DIR, MASK_NAV_HIDDEN,
+    MASK_SCREEN_LONG, MASK_SCREEN_ROUND, MASK_SCREEN_SIZE, MASK_UI_MODE_NIGHT, MASK_UI_MODE_TYPE,
+    MASK_WIDE_COLOR_GAMUT,
+};
 use self::owned::Entry;
 pub use self::{
+    bag::{
+        ArrayValue, AttrSymbol, AttrValue, AttributeFormat, BagValue, PluralsValue, Quantity,
+        StyleValue, StyleableValue,
+    },
+    config_fields::{
+        Density, GrammaticalGender, Keyboard, Navigation, NightMode, Orientation, Touchscreen,
+        UiModeType,
+    },
+    dimension::{Dimension, DimensionUnit, Fraction, FractionUnit},
+    document::{
+        AttributeValue, BinaryXmlDocument, StringSpan, XmlAttribute, XmlElement, XmlNode,
+        ANDROID_NAMESPACE,
+    },
     element::{Element, ElementContainer, Tag},
+    name::ResourceName,
+    resource_id::ResourceId,
+    spec_flags::{ConfigChanges, SpecFlags},
+    table::{ResourceTable, TableConfig, TableEntry, TablePackage, TableSpec},
+    theme::Theme,
     value::Value,
 };
 use crate::visitor::Origin;
 
 pub type Namespaces = BTreeMap<String, String>;
-pub type Entries = HashMap<u32, Entry>;
-
-pub trait Identifier {
-    fn get_package(&self) -> u8;
-    fn get_spec(&self) -> u8;
-    fn get_id(&self) -> u16;
-}
-
-impl Identifier for u32 {
-    fn get_package(&self) -> u8 {
-        let mut package_id = (self >> 24) as u8;
-
-        if package_id == 0 {
-            package_id = 1;
-            info!("Resource with package id 0 found. Recreate id with current package id");
-        }
-
-        package_id
-    }
-
-    fn get_spec(&self) -> u8 {
-        ((self & 0x00FF0000) >> 16) as u8
-    }
-
-    fn get_id(&self) -> u16 {
-        (self & 0xFFFF) as u16
-    }
-}
+pub type Entries = HashMap<ResourceId, Entry>;
 
 // Traits
 pub trait StringTable {
@@ -65,15 +70,25 @@ pub trait Package {
 
 pub trait Library {
     fn get_name(&self) -> Option<String>;
+    /// Formats the reference to the resource with the given id as it appears on a text XML
+    ///
+    /// # Errors
+    ///
+    /// It fails if the resource or its type are not on the library.
     fn format_reference(
         &self,
-        id: u32,
+        id: ResourceId,
         key: u32,
         namespace: Option<String>,
         prefix: &str,
     ) -> Result<String, Error>;
     // fn get_entries(&self) -> &Entries;
-    fn get_entry(&self, id: u32) -> Result<&Entry, Error>;
+    /// Returns the entry of the resource with the given id
+    ///
+    /// # Errors
+    ///
+    /// It fails if the resource is not on the library.
+    fn get_entry(&self, id: ResourceId) -> Result<&Entry, Error>;
     fn get_entries_string(&self, str_id: u32) -> Result<Rc<String>, Error>;
     fn get_spec_string(&self, str_id: u32) -> Result<Rc<String>, Error>;
 }
@@ -168,6 +183,15 @@ pub trait TypeSpec {
     fn get_id(&self) -> Result<u16, Error>;
     fn get_amount(&self) -> Result<u32, Error>;
     fn get_flag(&self, index: u32) -> Result<u32, Error>;
+
+    /// Returns the flags of the entry with the given index
+    ///
+    /// # Errors
+    ///
+    /// It fails if the index is not on the type spec.
+    fn get_spec_flags(&self, index: u32) -> Result<SpecFlags, Error> {
+        Ok(SpecFlags::from(self.get_flag(index)?))
+    }
 }
 
 pub trait TableType {
@@ -202,30 +226,53 @@ pub trait Configuration {
     fn get_screen_height(&self) -> Result<u16, Error>;
     fn get_locale_script(&self) -> Result<Option<String>, Error>;
     fn get_locale_variant(&self) -> Result<Option<String>, Error>;
+    /// Returns the second screen layout byte, which holds if the screen is round
     fn get_secondary_layout(&self) -> Result<Option<u8>, Error>;
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::model::Identifier;
-
-    #[test]
-    fn it_extracts_package_id() {
-        assert_eq!(2130837685.get_package(), 127)
-    }
-
-    #[test]
-    fn it_converts_package_id_if_is_0() {
-        assert_eq!(131253.get_package(), 1)
+    /// Returns the color mode: wide color gamut on the lowest 2 bits and HDR on the next 2
+    ///
+    /// # Errors
+    ///
+    /// It fails if the configuration is too short to hold the color mode.
+    fn get_color_mode(&self) -> Result<u8, Error>;
+    /// Returns the grammatical gender the configuration targets
+    ///
+    /// # Errors
+    ///
+    /// It fails if the configuration is too short to hold the grammatical gender.
+    fn get_grammatical_gender(&self) -> Result<u8, Error>;
+    /// Returns the numbering system of the locale (`latn`, `arab`...), if it has one
+    ///
+    /// # Errors
+    ///
+    /// It fails if the configuration is too short to hold the numbering system.
+    fn get_numbering_system(&self) -> Result<Option<String>, Error>;
+
+    /// Returns the night mode bits of the UI mode
+    ///
+    /// # Errors
+    ///
+    /// It fails if the configuration is too short to hold the UI mode.
+    fn get_night_mode(&self) -> Result<NightMode, Error> {
+        Ok(NightMode::from(self.get_ui_mode()? & MASK_UI_MODE_NIGHT))
     }
 
-    #[test]
-    fn it_extracts_spec_id() {
-        assert_eq!(2130837685.get_spec(), 2)
+    /// Returns the type bits of the UI mode
+    ///
+    /// # Errors
+    ///
+    /// It fails if the configuration is too short to hold the UI mode.
+    fn get_ui_mode_type(&self) -> Result<UiModeType, Error> {
+        Ok(UiModeType::from(self.get_ui_mode()? & MASK_UI_MODE_TYPE))
     }
 
-    #[test]
-    fn it_extracts_id() {
-        assert_eq!(2130837685.get_id(), 181)
+    /// Returns the qualifiers of the configuration as they appear on the name of the resource
+    /// directories (`es-rES-land-v21`), in canonical order. The default configuration has no
+    /// qualifiers, so it returns an empty string.
+    ///
+    /// # Errors
+    ///
+    /// It fails if one of the fields of the configuration can not be read.
+    fn to_qualifier_string(&self) -> Result<String, Error> {
+        qualifiers::to_qualifier_string(self)
     }
 }
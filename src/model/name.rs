@@ -0,0 +1,110 @@
+use std::{fmt, str::FromStr};
+
+use failure::{ensure, format_err, Error};
+
+/// Fully qualified name of a resource, like `android:attr/layout_width`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResourceName {
+    package: Option<String>,
+    type_name: String,
+    entry: String,
+}
+
+impl ResourceName {
+    pub fn new(package: Option<&str>, type_name: &str, entry: &str) -> Self {
+        Self {
+            package: package.map(ToString::to_string),
+            type_name: type_name.to_string(),
+            entry: entry.to_string(),
+        }
+    }
+
+    /// Name of the package. It may be missing when the name was parsed from a string without
+    /// package, in which case it refers to the main package.
+    #[must_use]
+    pub fn get_package(&self) -> Option<&str> {
+        self.package.as_deref()
+    }
+
+    #[must_use]
+    pub fn get_type(&self) -> &str {
+        &self.type_name
+    }
+
+    #[must_use]
+    pub fn get_entry(&self) -> &str {
+        &self.entry
+    }
+
+    pub(crate) fn with_package(&self, package: &str) -> Self {
+        Self {
+            package: Some(package.to_string()),
+            ..self.clone()
+        }
+    }
+}
+
+/// Parses names with the form `[@][package:]type/entry`
+impl FromStr for ResourceName {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Error> {
+        let name = name.trim_start_matches('@');
+        let (package, rest) = match name.find(':') {
+            Some(index) => (Some(&name[..index]), &name[index + 1..]),
+            None => (None, name),
+        };
+        let separator = rest
+            .find('/')
+            .ok_or_else(|| format_err!("resource name without type: {}", name))?;
+        let (type_name, entry) = (&rest[..separator], &rest[separator + 1..]);
+
+        ensure!(
+            !type_name.is_empty() && !entry.is_empty() && package != Some(""),
+            "invalid resource name: {}",
+            name
+        );
+
+        Ok(Self::new(package, type_name, entry))
+    }
+}
+
+impl fmt::Display for ResourceName {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(package) = &self.package {
+            write!(formatter, "{package}:")?;
+        }
+
+        write!(formatter, "{}/{}", self.type_name, self.entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ResourceName;
+
+    #[test]
+    fn it_can_parse_a_name_with_package() {
+        let name: ResourceName = "@android:attr/layout_width".parse().unwrap();
+
+        assert_eq!(Some("android"), name.get_package());
+        assert_eq!("attr", name.get_type());
+        assert_eq!("layout_width", name.get_entry());
+        assert_eq!("android:attr/layout_width", name.to_string());
+    }
+
+    #[test]
+    fn it_can_parse_a_name_without_package() {
+        let name: ResourceName = "string/app_name".parse().unwrap();
+
+        assert_eq!(None, name.get_package());
+        assert_eq!("string/app_name", name.to_string());
+    }
+
+    #[test]
+    fn it_does_not_parse_invalid_names() {
+        assert!("app_name".parse::<ResourceName>().is_err());
+        assert!("string/".parse::<ResourceName>().is_err());
+        assert!(":string/app_name".parse::<ResourceName>().is_err());
+    }
+}
@@ -6,10 +6,14 @@ use failure::{Error, ResultExt};
 pub use self::{
     package::PackageBuf,
     resources::ResourcesBuf,
-    string_table::{Encoding, StringTableBuf},
+    string_table::{Encoding, SpanBuf, StringTableBuf},
     table_type::{ComplexEntry, ConfigurationBuf, Entry, EntryHeader, SimpleEntry, TableTypeBuf},
     table_type_spec::TableTypeSpecBuf,
-    xml::{AttributeBuf, XmlNamespaceEndBuf, XmlNamespaceStartBuf, XmlTagEndBuf, XmlTagStartBuf},
+    unknown::UnknownBuf,
+    xml::{
+        AttributeBuf, XmlNamespaceEndBuf, XmlNamespaceStartBuf, XmlTagEndBuf, XmlTagStartBuf,
+        XmlTextBuf,
+    },
 };
 
 mod package;
@@ -17,6 +21,7 @@ mod resources;
 mod string_table;
 mod table_type;
 mod table_type_spec;
+mod unknown;
 mod xml;
 
 /// Implementors are able to be converted to well formed chunks as expected on `ChunkLoaderStream`
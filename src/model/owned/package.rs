@@ -51,20 +51,40 @@ impl OwnedBuf for PackageBuf {
         let mut buffer = Vec::new();
         let mut encoder = utf_16::UTF_16LE_ENCODING.raw_encoder();
         let mut encoded_string = Vec::new();
-        let (size, error) = encoder.raw_feed(&self.package_name, &mut encoded_string);
+        let (_, error) = encoder.raw_feed(&self.package_name, &mut encoded_string);
 
         ensure!(error.is_none(), "error encoding package name as UTF-16");
+        ensure!(
+            encoded_string.len() < 256,
+            "package name does not fit on 128 UTF-16 characters"
+        );
 
         buffer.write_u32::<LittleEndian>(self.id)?;
-        buffer.extend(encoded_string);
+        buffer.extend(&encoded_string);
+
+        // Padding package name up to 256 bytes (128 UTF-16 characters)
+        buffer.resize(4 + 256, 0);
+
+        // Type and key string tables are expected to be the first inner chunks. Their offsets
+        // are relative to the start of the package chunk.
+        let header_size = 8 + buffer.len() as u32 + 5 * 4;
+        let mut offsets = [0; 2];
+        let mut current_offset = header_size;
 
-        // Padding package name up to 256 characters
-        for _ in 0..(256 - size) {
-            buffer.push(0);
+        for (offset, chunk) in offsets.iter_mut().zip(&self.inner_chunks) {
+            *offset = current_offset;
+            current_offset += chunk.to_vec()?.len() as u32;
         }
 
-        // Padding (non-used data)
+        // Type strings offset and last public type
+        buffer.write_u32::<LittleEndian>(offsets[0])?;
         buffer.write_u32::<LittleEndian>(0)?;
+
+        // Key strings offset and last public key
+        buffer.write_u32::<LittleEndian>(offsets[1])?;
+        buffer.write_u32::<LittleEndian>(0)?;
+
+        // Type id offset
         buffer.write_u32::<LittleEndian>(0)?;
 
         Ok(buffer)
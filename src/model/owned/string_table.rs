@@ -18,10 +18,50 @@ pub enum Encoding {
     Utf16,
 }
 
+/// Marks the end of the spans of a style
+const SPAN_END: u32 = 0xFFFF_FFFF;
+
+/// Span of a styled string: the style named by the string at `name` (`b`, `font;color=red`)
+/// applies from `first_char` to `last_char`, both included and counted in UTF-16 code units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpanBuf {
+    name: u32,
+    first_char: u32,
+    last_char: u32,
+}
+
+impl SpanBuf {
+    #[must_use]
+    pub fn new(name: u32, first_char: u32, last_char: u32) -> Self {
+        Self {
+            name,
+            first_char,
+            last_char,
+        }
+    }
+
+    #[must_use]
+    pub fn get_name(&self) -> u32 {
+        self.name
+    }
+
+    #[must_use]
+    pub fn get_first_char(&self) -> u32 {
+        self.first_char
+    }
+
+    #[must_use]
+    pub fn get_last_char(&self) -> u32 {
+        self.last_char
+    }
+}
+
 #[derive(Debug)]
 pub struct StringTableBuf {
     strings: Vec<Rc<String>>,
-    styles: Vec<Rc<String>>,
+    /// Spans of the first strings of the table: the style at each index belongs to the string
+    /// with the same index
+    styles: Vec<Vec<SpanBuf>>,
     encoding: Encoding,
 }
 
@@ -47,6 +87,79 @@ impl StringTableBuf {
     pub fn add_string(&mut self, new_string: String) {
         self.strings.push(Rc::new(new_string));
     }
+
+    /// Adds the spans of the next string without style. Styled strings must be the first ones of
+    /// the table, as the style at each index belongs to the string with the same index.
+    pub fn add_style(&mut self, spans: Vec<SpanBuf>) {
+        self.styles.push(spans);
+    }
+
+    /// Returns the spans of the string with the given index, if it has style
+    pub fn get_style(&self, idx: u32) -> Option<&[SpanBuf]> {
+        self.styles.get(idx as usize).map(Vec::as_slice)
+    }
+
+    fn write_utf8_length(buffer: &mut Vec<u8>, length: usize) -> Result<(), Error> {
+        ensure!(length <= 0x7FFF, "string too long to be encoded");
+
+        if length > 0x7F {
+            buffer.push(0x80 | (length >> 8) as u8);
+        }
+        buffer.push((length & 0xFF) as u8);
+
+        Ok(())
+    }
+
+    /// Encodes the strings, returning the offset of each one and the string data, aligned to 4
+    /// bytes
+    fn encode_strings(&self) -> Result<(Vec<u32>, Vec<u8>), Error> {
+        let mut string_offsets: Vec<u32> = Vec::new();
+        let mut string_buffer: Vec<u8> = Vec::new();
+        let mut current_offset = 0;
+        let mut encoder = if self.encoding == Encoding::Utf8 {
+            utf_8::UTF8Encoder::new()
+        } else {
+            utf_16::UTF_16LE_ENCODING.raw_encoder()
+        };
+
+        // Encode strings and save offsets
+        for string in &self.strings {
+            string_offsets.push(current_offset);
+            let mut encoded_string = Vec::new();
+            let (_, error) = encoder.raw_feed(string, &mut encoded_string);
+
+            ensure!(error.is_none(), "error encoding string");
+
+            if self.encoding == Encoding::Utf8 {
+                // UTF-8 strings are prefixed by the amount of UTF-16 code units and the amount of
+                // bytes. Android rejects the string if the first one does not match.
+                Self::write_utf8_length(&mut string_buffer, string.encode_utf16().count())?;
+                Self::write_utf8_length(&mut string_buffer, encoded_string.len())?;
+                string_buffer.extend(&encoded_string);
+                string_buffer.push(0x00);
+            } else {
+                // UTF-16 strings are prefixed by the amount of code units
+                let units = encoded_string.len() / 2;
+                ensure!(units <= 0x7FFF_FFFF, "string too long to be encoded");
+
+                if units > 0x7FFF {
+                    string_buffer.write_u16::<LittleEndian>(0x8000 | (units >> 16) as u16)?;
+                }
+                string_buffer.write_u16::<LittleEndian>((units & 0xFFFF) as u16)?;
+                string_buffer.extend(&encoded_string);
+                string_buffer.push(0x00);
+                string_buffer.push(0x00);
+            }
+
+            current_offset = string_buffer.len() as u32;
+        }
+
+        // String data is aligned to 4 bytes
+        let padding = (4 - string_buffer.len() % 4) % 4;
+        string_buffer.extend(vec![0; padding]);
+
+        Ok((string_offsets, string_buffer))
+    }
 }
 
 impl OwnedBuf for StringTableBuf {
@@ -65,13 +178,16 @@ impl OwnedBuf for StringTableBuf {
 
         let header_size = 7 * 4;
         let string_offset = header_size + self.get_strings_len() * 4 + self.get_styles_len() * 4;
+        // Style data goes after the string data
+        let style_offset = if self.styles.is_empty() {
+            0
+        } else {
+            string_offset + self.encode_strings()?.1.len() as u32
+        };
 
         out.write_u32::<LittleEndian>(self.strings.len() as u32)?;
         out.write_u32::<LittleEndian>(self.styles.len() as u32)?;
         out.write_u32::<LittleEndian>(flags)?;
-
-        // TODO: Calculate properly the offset as we are calculating on the decoding part
-        let style_offset = 0;
         out.write_u32::<LittleEndian>(string_offset)?;
         out.write_u32::<LittleEndian>(style_offset)?;
 
@@ -80,44 +196,26 @@ impl OwnedBuf for StringTableBuf {
 
     fn get_body_data(&self) -> Result<Vec<u8>, Error> {
         let mut out = Vec::new();
+        let (string_offsets, string_buffer) = self.encode_strings()?;
 
-        let mut string_offsets: Vec<u32> = Vec::new();
         let mut style_offsets: Vec<u32> = Vec::new();
-        let mut string_buffer: Vec<u8> = Vec::new();
-        let style_buffer: Vec<u8> = Vec::new();
-
-        let mut current_offset = 0;
-        let mut encoder = if self.encoding == Encoding::Utf8 {
-            utf_8::UTF8Encoder::new()
-        } else {
-            utf_16::UTF_16LE_ENCODING.raw_encoder()
-        };
-
-        // Encode strings and save offsets
-        for string in &self.strings {
-            string_offsets.push(current_offset);
-            let mut encoded_string = Vec::new();
-            let (size, error) = encoder.raw_feed(string, &mut encoded_string);
-
-            ensure!(error.is_none(), "error encoding string");
+        let mut style_buffer: Vec<u8> = Vec::new();
 
-            // Write size
-            let low = (size & 0xFF) as u8;
-            let high = ((size & 0xFF00) >> 8) as u8;
-            string_buffer.push(low);
-            string_buffer.push(high);
-            string_buffer.extend(&encoded_string);
-            string_buffer.push(0x00);
-            string_buffer.push(0x00);
+        for spans in &self.styles {
+            style_offsets.push(style_buffer.len() as u32);
 
-            current_offset = string_buffer.len() as u32;
-            // println!("Current offset: {}", current_offset);
+            for span in spans {
+                style_buffer.write_u32::<LittleEndian>(span.name)?;
+                style_buffer.write_u32::<LittleEndian>(span.first_char)?;
+                style_buffer.write_u32::<LittleEndian>(span.last_char)?;
+            }
+            style_buffer.write_u32::<LittleEndian>(SPAN_END)?;
         }
 
-        // Encode styles and save offsets
-        for _ in &self.styles {
-            style_offsets.push(current_offset);
-            // Encode with utf8/utf16 depending on the flag
+        if !self.styles.is_empty() {
+            // Android expects a whole span of `SPAN_END` at the end of the style data
+            style_buffer.write_u32::<LittleEndian>(SPAN_END)?;
+            style_buffer.write_u32::<LittleEndian>(SPAN_END)?;
         }
 
         for offset in string_offsets {
@@ -156,8 +254,11 @@ impl StringTable for StringTableBuf {
 #[cfg(test)]
 #[allow(clippy::non_ascii_literal)]
 mod tests {
-    use super::{OwnedBuf, StringTable, StringTableBuf};
-    use crate::{chunks::StringTableWrapper, raw_chunks, test::compare_chunks};
+    use super::{Encoding, OwnedBuf, SpanBuf, StringTable, StringTableBuf};
+    use crate::{
+        chunks::StringTableWrapper, errors::AbxmlError, options::DecodeLimits, raw_chunks,
+        test::compare_chunks,
+    };
 
     #[test]
     fn it_can_generate_an_empty_chunk() {
@@ -194,6 +295,116 @@ mod tests {
 
     #[test]
     fn identity_utf8() {
-        // TODO: Test with UTF8 encoding
+        let mut string_table = StringTableBuf::default();
+        string_table.add_string("some string".to_string());
+        string_table.add_string("忠犬ハチ公".to_string());
+        string_table.add_string("a".repeat(200));
+        let raw = string_table.to_vec().unwrap();
+
+        assert_eq!(0, raw.len() % 4);
+
+        let wrapper = StringTableWrapper::new(&raw);
+
+        assert_eq!("some string", *wrapper.get_string(0).unwrap());
+        assert_eq!("忠犬ハチ公", *wrapper.get_string(1).unwrap());
+        assert_eq!("a".repeat(200), *wrapper.get_string(2).unwrap());
+        compare_chunks(&raw, &wrapper.to_buffer().unwrap().to_vec().unwrap());
+    }
+
+    #[test]
+    fn it_writes_the_utf16_length_of_utf8_strings() {
+        let mut string_table = StringTableBuf::default();
+        string_table.add_string("😀".to_string());
+        let raw = string_table.to_vec().unwrap();
+
+        let header_size = usize::from(u16::from_le_bytes([raw[2], raw[3]]));
+        let strings_start = u32::from_le_bytes([raw[20], raw[21], raw[22], raw[23]]) as usize;
+        // One string offset, then the UTF-16 length and the UTF-8 length of the string
+        assert_eq!(header_size + 4, strings_start);
+        assert_eq!(&[2, 4], &raw[strings_start..strings_start + 2]);
+
+        let wrapper = StringTableWrapper::new(&raw);
+        assert_eq!("😀", *wrapper.get_string(0).unwrap());
+        compare_chunks(&raw, &wrapper.to_buffer().unwrap().to_vec().unwrap());
+    }
+
+    #[test]
+    fn identity_utf16() {
+        let mut string_table = StringTableBuf::default();
+        string_table.set_encoding(Encoding::Utf16);
+        string_table.add_string("some string".to_string());
+        string_table.add_string("忠犬ハチ公".to_string());
+        let raw = string_table.to_vec().unwrap();
+
+        let wrapper = StringTableWrapper::new(&raw);
+
+        assert_eq!("some string", *wrapper.get_string(0).unwrap());
+        assert_eq!("忠犬ハチ公", *wrapper.get_string(1).unwrap());
+    }
+
+    #[test]
+    fn it_round_trips_utf16_strings_longer_than_one_length_word() {
+        let long = "a".repeat(40_000);
+        let mut string_table = StringTableBuf::default();
+        string_table.set_encoding(Encoding::Utf16);
+        string_table.add_string(long.clone());
+        string_table.add_string("short".to_string());
+        let raw = string_table.to_vec().unwrap();
+
+        let wrapper = StringTableWrapper::new(&raw);
+
+        assert_eq!(long, *wrapper.get_string(0).unwrap());
+        assert_eq!("short", *wrapper.get_string(1).unwrap());
+        compare_chunks(&raw, &wrapper.to_buffer().unwrap().to_vec().unwrap());
+    }
+
+    #[test]
+    fn it_round_trips_style_spans() {
+        let mut string_table = StringTableBuf::default();
+        string_table.add_string("Hello world".to_string());
+        string_table.add_string("plain".to_string());
+        string_table.add_string("b".to_string());
+        string_table.add_string("i".to_string());
+        string_table.add_style(vec![SpanBuf::new(2, 0, 4), SpanBuf::new(3, 6, 10)]);
+        string_table.add_style(Vec::new());
+        let raw = string_table.to_vec().unwrap();
+
+        let wrapper = StringTableWrapper::new(&raw);
+
+        assert_eq!(2, wrapper.get_styles_len());
+        assert_eq!("Hello world", *wrapper.get_string(0).unwrap());
+        assert_eq!("i", *wrapper.get_string(3).unwrap());
+        assert_eq!(
+            vec![SpanBuf::new(2, 0, 4), SpanBuf::new(3, 6, 10)],
+            wrapper.get_style(0).unwrap()
+        );
+        assert!(wrapper.get_style(1).unwrap().is_empty());
+        assert!(wrapper.get_style(2).unwrap().is_empty());
+        compare_chunks(&raw, &wrapper.to_buffer().unwrap().to_vec().unwrap());
+    }
+
+    #[test]
+    fn it_reads_utf8_strings_by_their_declared_length() {
+        let mut string_table = StringTableBuf::default();
+        string_table.add_string("a".repeat(200));
+        string_table.add_string("abc".to_string());
+        let mut raw = string_table.to_vec().unwrap();
+
+        let limited = StringTableWrapper::new(&raw)
+            .with_limits(DecodeLimits::default().with_max_string_length(4));
+        let error = limited.get_string(0).unwrap_err();
+        match error.downcast::<AbxmlError>().unwrap() {
+            AbxmlError::LimitExceeded { max, found, .. } => assert_eq!((4, 200), (max, found)),
+            error => panic!("unexpected error: {}", error),
+        }
+        assert_eq!("abc", *limited.get_string(1).unwrap());
+
+        // The byte length of "abc" says 2, so the string does not end where it should
+        let strings_start = u32::from_le_bytes([raw[20], raw[21], raw[22], raw[23]]) as usize;
+        let second = strings_start + 2 + 2 + 200 + 1;
+        assert_eq!(&[3, 3], &raw[second..second + 2]);
+        raw[second + 1] = 2;
+
+        assert!(StringTableWrapper::new(&raw).get_string(1).is_err());
     }
 }
@@ -1,11 +1,23 @@
-use std::io::Cursor;
-
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use failure::Error;
-
-use crate::{chunks::table_type::Region, model::Configuration};
-
-#[derive(Clone, Default, Debug)]
+use byteorder::{LittleEndian, WriteBytesExt};
+use failure::{ensure, Error};
+
+use crate::{
+    chunks::{
+        table_type::{Region, LANGUAGE_BASE, REGION_BASE},
+        ConfigurationWrapper,
+    },
+    model::{
+        qualifiers, Configuration, GrammaticalGender, Keyboard, Navigation, NightMode, Orientation,
+        Touchscreen, UiModeType, MASK_HDR, MASK_KEYS_HIDDEN, MASK_LAYOUT_DIR, MASK_NAV_HIDDEN,
+        MASK_SCREEN_LONG, MASK_SCREEN_ROUND, MASK_SCREEN_SIZE, MASK_UI_MODE_NIGHT,
+        MASK_UI_MODE_TYPE, MASK_WIDE_COLOR_GAMUT,
+    },
+};
+
+const KEYS_HIDDEN_NO: u8 = 1;
+const KEYS_HIDDEN_SOFT: u8 = 3;
+
+#[derive(Clone, Debug)]
 pub struct ConfigurationBuf {
     size: u32,
     original_size: u32,
@@ -19,6 +31,7 @@ pub struct ConfigurationBuf {
     keyboard: u8,
     navigation: u8,
     input_flags: u8,
+    grammatical_gender: u8,
     width: u16,
     height: u16,
     sdk_version: u16,
@@ -29,11 +42,324 @@ pub struct ConfigurationBuf {
     screen_width_dp: u16,
     screen_height_dp: u16,
     locale_script: Option<String>,
+    locale_script_was_computed: bool,
     locale_variant: Option<String>,
-    secondary_screen_layout: Option<u8>,
+    secondary_screen_layout: u8,
+    color_mode: u8,
+    numbering_system: Option<String>,
+}
+
+impl Default for ConfigurationBuf {
+    /// Creates the default configuration, which matches any device
+    fn default() -> Self {
+        Self {
+            size: 64,
+            original_size: 64,
+            mcc: 0,
+            mnc: 0,
+            language: String::new(),
+            region: String::new(),
+            orientation: 0,
+            touchscreen: 0,
+            density: 0,
+            keyboard: 0,
+            navigation: 0,
+            input_flags: 0,
+            grammatical_gender: 0,
+            width: 0,
+            height: 0,
+            sdk_version: 0,
+            min_sdk_version: 0,
+            screen_layout: 0,
+            ui_mode: 0,
+            smallest_screen: 0,
+            screen_width_dp: 0,
+            screen_height_dp: 0,
+            locale_script: None,
+            locale_script_was_computed: false,
+            locale_variant: None,
+            secondary_screen_layout: 0,
+            color_mode: 0,
+            numbering_system: None,
+        }
+    }
+}
+
+/// Two configurations are equal if they target the same devices, even if they were encoded with
+/// different sizes
+impl PartialEq for ConfigurationBuf {
+    fn eq(&self, other: &Self) -> bool {
+        self.mcc == other.mcc
+            && self.mnc == other.mnc
+            && self.language_bytes() == other.language_bytes()
+            && self.region_bytes() == other.region_bytes()
+            && self.orientation == other.orientation
+            && self.touchscreen == other.touchscreen
+            && self.density == other.density
+            && self.keyboard == other.keyboard
+            && self.navigation == other.navigation
+            && self.input_flags == other.input_flags
+            && self.width == other.width
+            && self.height == other.height
+            && self.sdk_version == other.sdk_version
+            && self.min_sdk_version == other.min_sdk_version
+            && self.screen_layout == other.screen_layout
+            && self.ui_mode == other.ui_mode
+            && self.smallest_screen == other.smallest_screen
+            && self.screen_width_dp == other.screen_width_dp
+            && self.screen_height_dp == other.screen_height_dp
+            && self.grammatical_gender == other.grammatical_gender
+            && self.locale_script == other.locale_script
+            && self.locale_variant == other.locale_variant
+            && self.numbering_system == other.numbering_system
+            && self.secondary_screen_layout == other.secondary_screen_layout
+            && self.color_mode == other.color_mode
+    }
 }
 
 impl ConfigurationBuf {
+    /// Sets the two or three letters language code (`en`, `ca`, `fil`...)
+    pub fn set_language(&mut self, language: &str) {
+        self.language = language.to_string();
+    }
+
+    /// Sets the two letters or three digits region code (`US`, `ES`, `419`...)
+    pub fn set_region(&mut self, region: &str) {
+        self.region = region.to_string();
+    }
+
+    /// Parses the qualifiers used on the name of the resource directories
+    /// (`es-rES-land-night-xxhdpi-v21`, `b+sr+Latn`...). They must appear in canonical order.
+    ///
+    /// # Errors
+    ///
+    /// It fails if a qualifier is not known or it is not in canonical order.
+    pub fn from_qualifiers(qualifiers: &str) -> Result<Self, Error> {
+        qualifiers::parse_qualifiers(qualifiers)
+    }
+
+    pub fn set_mcc(&mut self, mcc: u16) {
+        self.mcc = mcc;
+    }
+
+    pub fn set_mnc(&mut self, mnc: u16) {
+        self.mnc = mnc;
+    }
+
+    /// Sets the four letters script code (`Latn`, `Cyrl`...)
+    pub fn set_locale_script(&mut self, script: Option<&str>) {
+        self.locale_script = script.map(ToString::to_string);
+        self.locale_script_was_computed = false;
+    }
+
+    pub fn set_locale_variant(&mut self, variant: Option<&str>) {
+        self.locale_variant = variant.map(ToString::to_string);
+    }
+
+    /// Sets the numbering system of the locale (`latn`, `arab`...)
+    pub fn set_numbering_system(&mut self, numbering_system: Option<&str>) {
+        self.numbering_system = numbering_system.map(ToString::to_string);
+    }
+
+    pub fn set_grammatical_gender(&mut self, grammatical_gender: GrammaticalGender) {
+        self.grammatical_gender = grammatical_gender.into();
+    }
+
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation.into();
+    }
+
+    pub fn set_touchscreen(&mut self, touchscreen: Touchscreen) {
+        self.touchscreen = touchscreen.into();
+    }
+
+    pub fn set_density(&mut self, density: u16) {
+        self.density = density;
+    }
+
+    pub fn set_keyboard(&mut self, keyboard: Keyboard) {
+        self.keyboard = keyboard.into();
+    }
+
+    pub fn set_navigation(&mut self, navigation: Navigation) {
+        self.navigation = navigation.into();
+    }
+
+    pub fn set_input_flags(&mut self, input_flags: u8) {
+        self.input_flags = input_flags;
+    }
+
+    pub fn set_screen_size(&mut self, width: u16, height: u16) {
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn set_sdk_version(&mut self, sdk_version: u16) {
+        self.sdk_version = sdk_version;
+    }
+
+    pub fn set_screen_layout(&mut self, screen_layout: u8) {
+        self.screen_layout = screen_layout;
+    }
+
+    pub fn set_ui_mode(&mut self, ui_mode: u8) {
+        self.ui_mode = ui_mode;
+    }
+
+    pub fn set_ui_mode_type(&mut self, ui_mode_type: UiModeType) {
+        self.ui_mode = (self.ui_mode & !MASK_UI_MODE_TYPE) | u8::from(ui_mode_type);
+    }
+
+    pub fn set_night_mode(&mut self, night_mode: NightMode) {
+        self.ui_mode = (self.ui_mode & !MASK_UI_MODE_NIGHT) | u8::from(night_mode);
+    }
+
+    pub fn set_smallest_screen(&mut self, smallest_screen: u16) {
+        self.smallest_screen = smallest_screen;
+    }
+
+    pub fn set_screen_width(&mut self, screen_width_dp: u16) {
+        self.screen_width_dp = screen_width_dp;
+    }
+
+    pub fn set_screen_height(&mut self, screen_height_dp: u16) {
+        self.screen_height_dp = screen_height_dp;
+    }
+
+    /// Sets the second screen layout byte, which holds if the screen is round
+    pub fn set_secondary_layout(&mut self, secondary_layout: u8) {
+        self.secondary_screen_layout = secondary_layout;
+    }
+
+    /// Sets the color mode: wide color gamut on the lowest 2 bits and HDR on the next 2
+    pub fn set_color_mode(&mut self, color_mode: u8) {
+        self.color_mode = color_mode;
+    }
+
+    /// Returns true if the resources of this configuration can be used on a device with the given
+    /// configuration. Unset fields match any device, the density is only used to rank the
+    /// matching configurations and the sdk version matches the devices with the same or a newer
+    /// version. Fields that pack several qualifiers, like the screen layout, are compared one
+    /// qualifier at a time, as Android does.
+    #[must_use]
+    pub fn matches(&self, device: &Self) -> bool {
+        let any_or_equal = |value: u16, target: u16| value == 0 || value == target;
+        let any_or_below = |value: u16, target: u16| value == 0 || value <= target;
+        let masked =
+            |value: u8, target: u8, mask: u8| value & mask == 0 || value & mask == target & mask;
+        let language = self.language_bytes();
+        let region = self.region_bytes();
+        let keys_hidden = self.input_flags & MASK_KEYS_HIDDEN;
+        let device_keys_hidden = device.input_flags & MASK_KEYS_HIDDEN;
+
+        any_or_equal(self.mcc, device.mcc)
+            && any_or_equal(self.mnc, device.mnc)
+            && (language == (0, 0) || language == device.language_bytes())
+            && (region == (0, 0) || region == device.region_bytes())
+            && any_or_equal(self.orientation.into(), device.orientation.into())
+            && any_or_equal(self.touchscreen.into(), device.touchscreen.into())
+            && any_or_equal(self.keyboard.into(), device.keyboard.into())
+            && any_or_equal(self.navigation.into(), device.navigation.into())
+            // Resources for exposed keys are also used when the keyboard is a soft one
+            && (masked(self.input_flags, device.input_flags, MASK_KEYS_HIDDEN)
+                || (keys_hidden == KEYS_HIDDEN_NO && device_keys_hidden == KEYS_HIDDEN_SOFT))
+            && masked(self.input_flags, device.input_flags, MASK_NAV_HIDDEN)
+            && any_or_below(self.width, device.width)
+            && any_or_below(self.height, device.height)
+            && any_or_below(self.sdk_version, device.sdk_version)
+            && masked(self.screen_layout, device.screen_layout, MASK_LAYOUT_DIR)
+            // Resources for smaller screens are also used on larger ones
+            && any_or_below(
+                (self.screen_layout & MASK_SCREEN_SIZE).into(),
+                (device.screen_layout & MASK_SCREEN_SIZE).into(),
+            )
+            && masked(self.screen_layout, device.screen_layout, MASK_SCREEN_LONG)
+            && masked(self.ui_mode, device.ui_mode, MASK_UI_MODE_TYPE)
+            && masked(self.ui_mode, device.ui_mode, MASK_UI_MODE_NIGHT)
+            && any_or_below(self.smallest_screen, device.smallest_screen)
+            && any_or_below(self.screen_width_dp, device.screen_width_dp)
+            && any_or_below(self.screen_height_dp, device.screen_height_dp)
+            && any_or_equal(
+                self.grammatical_gender.into(),
+                device.grammatical_gender.into(),
+            )
+            && masked(
+                self.secondary_screen_layout,
+                device.secondary_screen_layout,
+                MASK_SCREEN_ROUND,
+            )
+            && masked(self.color_mode, device.color_mode, MASK_WIDE_COLOR_GAMUT)
+            && masked(self.color_mode, device.color_mode, MASK_HDR)
+    }
+
+    /// Returns true if this configuration is a better match than `other` for the given device.
+    /// Both configurations are expected to match the device. The fields are compared following
+    /// the same precedence Android uses, although ranges are simplified to the closest value.
+    #[must_use]
+    pub fn is_better_than(&self, other: &Self, device: &Self) -> bool {
+        self.precedence(device) > other.precedence(device)
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn precedence(
+        &self,
+        device: &Self,
+    ) -> (
+        (bool, bool, bool, bool, bool),
+        bool,
+        (u16, u16, u16),
+        (u8, bool, bool, bool, bool, bool, bool),
+        (bool, bool),
+        (bool, bool, bool, bool, bool),
+        (u16, u16, u16),
+    ) {
+        let density = (self.density == device.density, self.density != 0);
+        let is_set = |value: u8, mask: u8| value & mask != 0;
+
+        (
+            (
+                self.mcc != 0,
+                self.mnc != 0,
+                self.language_bytes() != (0, 0),
+                self.region_bytes() != (0, 0),
+                self.grammatical_gender != 0,
+            ),
+            is_set(self.screen_layout, MASK_LAYOUT_DIR),
+            (
+                self.smallest_screen,
+                self.screen_width_dp,
+                self.screen_height_dp,
+            ),
+            (
+                // The largest screen size that fits the device is the best one
+                self.screen_layout & MASK_SCREEN_SIZE,
+                is_set(self.screen_layout, MASK_SCREEN_LONG),
+                is_set(self.secondary_screen_layout, MASK_SCREEN_ROUND),
+                is_set(self.color_mode, MASK_WIDE_COLOR_GAMUT | MASK_HDR),
+                self.orientation != 0,
+                is_set(self.ui_mode, MASK_UI_MODE_TYPE),
+                is_set(self.ui_mode, MASK_UI_MODE_NIGHT),
+            ),
+            density,
+            (
+                self.touchscreen != 0,
+                is_set(self.input_flags, MASK_KEYS_HIDDEN),
+                self.keyboard != 0,
+                is_set(self.input_flags, MASK_NAV_HIDDEN),
+                self.navigation != 0,
+            ),
+            (self.width, self.height, self.sdk_version),
+        )
+    }
+
+    fn language_bytes(&self) -> (u8, u8) {
+        Region::pack(&self.language, LANGUAGE_BASE).into()
+    }
+
+    fn region_bytes(&self) -> (u8, u8) {
+        Region::pack(&self.region, REGION_BASE).into()
+    }
+
     pub fn to_vec(&self) -> Result<Vec<u8>, Error> {
         let mut buffer = Vec::new();
 
@@ -41,14 +367,12 @@ impl ConfigurationBuf {
         buffer.write_u16::<LittleEndian>(self.mcc)?;
         buffer.write_u16::<LittleEndian>(self.mnc)?;
 
-        let lang = Region::from(self.language.as_ref());
-        let (low, high) = lang.into();
+        let (low, high) = self.language_bytes();
 
         buffer.write_u8(low)?;
         buffer.write_u8(high)?;
 
-        let region = Region::from(self.region.as_ref());
-        let (low, high) = region.into();
+        let (low, high) = self.region_bytes();
 
         buffer.write_u8(low)?;
         buffer.write_u8(high)?;
@@ -59,13 +383,40 @@ impl ConfigurationBuf {
         buffer.write_u8(self.keyboard)?;
         buffer.write_u8(self.navigation)?;
         buffer.write_u8(self.input_flags)?;
-        buffer.write_u8(0)?;
+        buffer.write_u8(self.grammatical_gender)?;
 
         buffer.write_u16::<LittleEndian>(self.width)?;
         buffer.write_u16::<LittleEndian>(self.height)?;
         buffer.write_u16::<LittleEndian>(self.sdk_version)?;
         buffer.write_u16::<LittleEndian>(self.min_sdk_version)?;
 
+        if self.size >= 32 {
+            buffer.write_u8(self.screen_layout)?;
+            buffer.write_u8(self.ui_mode)?;
+            buffer.write_u16::<LittleEndian>(self.smallest_screen)?;
+        }
+
+        if self.size >= 36 {
+            buffer.write_u16::<LittleEndian>(self.screen_width_dp)?;
+            buffer.write_u16::<LittleEndian>(self.screen_height_dp)?;
+        }
+
+        if self.size >= 48 {
+            write_padded(&mut buffer, self.locale_script.as_ref(), 4)?;
+            write_padded(&mut buffer, self.locale_variant.as_ref(), 8)?;
+        }
+
+        if self.size >= 52 {
+            buffer.write_u8(self.secondary_screen_layout)?;
+            buffer.write_u8(self.color_mode)?;
+            buffer.write_u16::<LittleEndian>(0)?;
+        }
+
+        if self.size >= 61 {
+            buffer.write_u8(self.locale_script_was_computed.into())?;
+            write_padded(&mut buffer, self.numbering_system.as_ref(), 8)?;
+        }
+
         let current = buffer.len();
 
         // Fill with 0 up to target size
@@ -76,98 +427,63 @@ impl ConfigurationBuf {
         Ok(buffer)
     }
 
+    #[allow(clippy::needless_pass_by_value)]
     pub fn from_cursor(buffer: Vec<u8>) -> Result<Self, Error> {
         let original_size = buffer.len() as u32;
-        let mut cursor = Cursor::new(buffer);
-        let size = cursor.read_u32::<LittleEndian>()?;
-        let mcc = cursor.read_u16::<LittleEndian>()?;
-        let mnc = cursor.read_u16::<LittleEndian>()?;
-
-        let lang1 = cursor.read_u8()?;
-        let lang2 = cursor.read_u8()?;
-
-        let lang = Region::from((lang1, lang2));
-        let str_lang = lang.to_string();
-
-        let reg1 = cursor.read_u8()?;
-        let reg2 = cursor.read_u8()?;
-
-        let reg = Region::from((reg1, reg2));
-        let str_reg = reg.to_string();
-
-        let orientation = cursor.read_u8()?;
-        let touchscreen = cursor.read_u8()?;
-
-        let density = cursor.read_u16::<LittleEndian>()?;
-
-        let keyboard = cursor.read_u8()?;
-        let navigation = cursor.read_u8()?;
-        let input_flags = cursor.read_u8()?;
-
-        cursor.read_u8()?; // Padding
-
-        let width = cursor.read_u16::<LittleEndian>()?;
-        let height = cursor.read_u16::<LittleEndian>()?;
-        let sdk_version = cursor.read_u16::<LittleEndian>()?;
-        let min_sdk_version = cursor.read_u16::<LittleEndian>()?;
-
-        let mut screen_layout = 0;
-        let mut ui_mode = 0;
-        let mut smallest_screen = 0;
-        let mut screen_width_dp = 0;
-        let mut screen_height_dp = 0;
-
-        if size >= 32 {
-            screen_layout = cursor.read_u8()?;
-            ui_mode = cursor.read_u8()?;
-            smallest_screen = cursor.read_u16::<LittleEndian>()?;
-        }
-
-        if size >= 36 {
-            screen_width_dp = cursor.read_u16::<LittleEndian>()?;
-            screen_height_dp = cursor.read_u16::<LittleEndian>()?;
-        }
-
-        if size >= 48 {
-            // TODO: Read following bytes
-            cursor.read_u32::<LittleEndian>()?;
-            cursor.read_u32::<LittleEndian>()?;
-            cursor.read_u32::<LittleEndian>()?;
-        }
-
-        if size >= 52 {
-            // TODO: Read bytes
-        }
-
+        let wrapper = ConfigurationWrapper::new(&buffer);
+        let size = wrapper.get_size()?;
         Ok(Self {
             size,
             original_size,
-            mcc,
-            mnc,
-            language: str_lang,
-            region: str_reg,
-            orientation,
-            touchscreen,
-            density,
-            keyboard,
-            navigation,
-            input_flags,
-            width,
-            height,
-            sdk_version,
-            min_sdk_version,
-            screen_layout,
-            ui_mode,
-            smallest_screen,
-            screen_width_dp,
-            screen_height_dp,
-            locale_script: None,
-            locale_variant: None,
-            secondary_screen_layout: None,
+            mcc: wrapper.get_mcc()?,
+            mnc: wrapper.get_mnc()?,
+            language: wrapper.get_language()?,
+            region: wrapper.get_region()?,
+            orientation: wrapper.get_orientation()?,
+            touchscreen: wrapper.get_touchscreen()?,
+            density: wrapper.get_density()?,
+            keyboard: wrapper.get_keyboard()?,
+            navigation: wrapper.get_navigation()?,
+            input_flags: wrapper.get_input_flags()?,
+            grammatical_gender: wrapper.get_grammatical_gender()?,
+            width: wrapper.get_width()?,
+            height: wrapper.get_height()?,
+            sdk_version: wrapper.get_sdk_version()?,
+            min_sdk_version: wrapper.get_min_sdk_version()?,
+            screen_layout: wrapper.get_screen_layout().unwrap_or_default(),
+            ui_mode: wrapper.get_ui_mode().unwrap_or_default(),
+            smallest_screen: wrapper.get_smallest_screen().unwrap_or_default(),
+            screen_width_dp: wrapper.get_screen_width().unwrap_or_default(),
+            screen_height_dp: wrapper.get_screen_height().unwrap_or_default(),
+            locale_script: wrapper.get_raw_locale_script()?,
+            locale_script_was_computed: wrapper.is_locale_script_computed()?,
+            locale_variant: wrapper.get_locale_variant()?,
+            secondary_screen_layout: wrapper.get_secondary_layout()?.unwrap_or_default(),
+            color_mode: wrapper.get_color_mode()?,
+            numbering_system: wrapper.get_numbering_system()?,
         })
     }
 }
 
+/// Writes the text on a fixed length field padded with zeros
+fn write_padded(buffer: &mut Vec<u8>, text: Option<&String>, len: usize) -> Result<(), Error> {
+    let mut field = vec![0; len];
+
+    if let Some(text) = text {
+        ensure!(
+            text.len() <= len,
+            "'{}' does not fit on a field of {} bytes",
+            text,
+            len
+        );
+        field[..text.len()].copy_from_slice(text.as_bytes());
+    }
+
+    buffer.extend_from_slice(&field);
+
+    Ok(())
+}
+
 impl Configuration for ConfigurationBuf {
     fn get_size(&self) -> Result<u32, Error> {
         Ok(self.size)
@@ -182,12 +498,11 @@ impl Configuration for ConfigurationBuf {
     }
 
     fn get_language(&self) -> Result<String, Error> {
-        Ok(Region::from(self.language.as_ref()).to_string())
+        Ok(Region::pack(&self.language, LANGUAGE_BASE).unpack(LANGUAGE_BASE))
     }
 
     fn get_region(&self) -> Result<String, Error> {
-        let region = Region::from(self.region.as_ref());
-        Ok(region.to_string())
+        Ok(Region::pack(&self.region, REGION_BASE).unpack(REGION_BASE))
     }
 
     fn get_orientation(&self) -> Result<u8, Error> {
@@ -251,6 +566,10 @@ impl Configuration for ConfigurationBuf {
     }
 
     fn get_locale_script(&self) -> Result<Option<String>, Error> {
+        if self.locale_script_was_computed {
+            return Ok(None);
+        }
+
         Ok(self.locale_script.clone())
     }
 
@@ -259,14 +578,30 @@ impl Configuration for ConfigurationBuf {
     }
 
     fn get_secondary_layout(&self) -> Result<Option<u8>, Error> {
-        Ok(self.secondary_screen_layout)
+        Ok(Some(self.secondary_screen_layout).filter(|_| self.size >= 49))
+    }
+
+    fn get_color_mode(&self) -> Result<u8, Error> {
+        Ok(self.color_mode)
+    }
+
+    fn get_grammatical_gender(&self) -> Result<u8, Error> {
+        Ok(self.grammatical_gender)
+    }
+
+    fn get_numbering_system(&self) -> Result<Option<String>, Error> {
+        Ok(self.numbering_system.clone())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::ConfigurationBuf;
     use crate::{
-        chunks::ConfigurationWrapper, raw_chunks::EXAMPLE_CONFIGURATION, test::compare_chunks,
+        chunks::ConfigurationWrapper,
+        model::{Configuration, Density, GrammaticalGender, NightMode, UiModeType},
+        raw_chunks::EXAMPLE_CONFIGURATION,
+        test::compare_chunks,
     };
 
     #[test]
@@ -278,4 +613,74 @@ mod tests {
 
         compare_chunks(EXAMPLE_CONFIGURATION, &new_raw);
     }
+
+    #[test]
+    fn it_encodes_the_fields_of_modern_configurations() {
+        let mut config = ConfigurationBuf::from_qualifiers(
+            "b+sr+Latn+RS+u+nu+latn-feminine-round-widecg-highdr-watch-night-anydpi",
+        )
+        .unwrap();
+        config.set_locale_variant(Some("ekavsk"));
+        let raw = config.to_vec().unwrap();
+
+        assert_eq!(64, raw.len());
+        assert_eq!(b"Latn", &raw[36..40]);
+        assert_eq!(b"ekavsk\0\0", &raw[40..48]);
+        assert_eq!(&[2, 0x0A], &raw[48..50]);
+
+        let decoded = ConfigurationWrapper::new(&raw).to_buffer().unwrap();
+
+        assert_eq!(config, decoded);
+        assert_eq!(
+            Density::AnyDpi,
+            Density::from(decoded.get_density().unwrap())
+        );
+        assert_eq!(NightMode::Yes, decoded.get_night_mode().unwrap());
+        assert_eq!(UiModeType::Watch, decoded.get_ui_mode_type().unwrap());
+        assert_eq!(
+            GrammaticalGender::Feminine,
+            GrammaticalGender::from(decoded.get_grammatical_gender().unwrap())
+        );
+        assert_eq!(raw, decoded.to_vec().unwrap());
+    }
+
+    fn qualifiers(qualifiers: &str) -> ConfigurationBuf {
+        ConfigurationBuf::from_qualifiers(qualifiers).unwrap()
+    }
+
+    #[test]
+    fn it_matches_each_qualifier_of_the_packed_fields() {
+        let desk_night = qualifiers("desk-night");
+        assert!(qualifiers("night").matches(&desk_night));
+        assert!(qualifiers("desk").matches(&desk_night));
+        assert!(!qualifiers("car-night").matches(&desk_night));
+        assert!(!qualifiers("notnight").matches(&desk_night));
+
+        let large = qualifiers("ldrtl-large-long");
+        assert!(qualifiers("large").matches(&large));
+        assert!(qualifiers("normal").matches(&large));
+        assert!(qualifiers("ldrtl").matches(&large));
+        assert!(qualifiers("long").matches(&large));
+        assert!(!qualifiers("xlarge").matches(&large));
+        assert!(!qualifiers("ldltr-large").matches(&large));
+        assert!(!qualifiers("notlong").matches(&large));
+
+        let round_widecg = qualifiers("round-widecg-highdr-keyssoft-navhidden");
+        assert!(qualifiers("round").matches(&round_widecg));
+        assert!(qualifiers("highdr").matches(&round_widecg));
+        assert!(qualifiers("keysexposed").matches(&round_widecg));
+        assert!(qualifiers("navhidden").matches(&round_widecg));
+        assert!(!qualifiers("keyshidden").matches(&round_widecg));
+        assert!(!qualifiers("lowdr").matches(&round_widecg));
+    }
+
+    #[test]
+    fn it_prefers_the_configuration_closest_to_the_device() {
+        let device = qualifiers("ldrtl-large-long-desk-night");
+
+        assert!(qualifiers("large").is_better_than(&qualifiers("normal"), &device));
+        assert!(qualifiers("ldrtl").is_better_than(&qualifiers("large-long"), &device));
+        assert!(qualifiers("desk-night").is_better_than(&qualifiers("night"), &device));
+        assert!(qualifiers("desk").is_better_than(&qualifiers("night"), &device));
+    }
 }
@@ -1,6 +1,8 @@
 use byteorder::{LittleEndian, WriteBytesExt};
 use failure::{format_err, Error};
 
+use crate::model::ResourceId;
+
 const MASK_COMPLEX: u16 = 0x0001;
 
 #[allow(dead_code)]
@@ -47,8 +49,9 @@ impl SimpleEntry {
         }
     }
 
-    pub fn get_id(&self) -> u32 {
-        self.id
+    #[must_use]
+    pub fn get_id(&self) -> ResourceId {
+        ResourceId::from(self.id)
     }
 
     pub fn get_key(&self) -> u32 {
@@ -105,15 +108,22 @@ impl ComplexEntry {
         }
     }
 
-    pub fn get_id(&self) -> u32 {
-        self.id
+    #[must_use]
+    pub fn get_id(&self) -> ResourceId {
+        ResourceId::from(self.id)
     }
 
     pub fn get_key(&self) -> u32 {
         self.key_index
     }
 
-    pub fn get_referent_id(&self, value: u32) -> Option<u32> {
+    #[must_use]
+    pub fn get_parent(&self) -> ResourceId {
+        ResourceId::from(self.parent_entry_id)
+    }
+
+    #[must_use]
+    pub fn get_referent_id(&self, value: u32) -> Option<ResourceId> {
         for e in &self.entries {
             if e.get_value() == value {
                 return Some(e.get_id());
@@ -143,17 +153,12 @@ impl ComplexEntry {
         out.write_u32::<LittleEndian>(self.parent_entry_id)?;
 
         // Children entry amount
-        let children_amount = self.entries.len() as u32;
-        if children_amount == 0 {
-            out.write_u32::<LittleEndian>(0xFFFF_FFFF)?;
-        } else {
-            out.write_u32::<LittleEndian>(self.entries.len() as u32)?;
-        }
+        out.write_u32::<LittleEndian>(self.entries.len() as u32)?;
 
         for e in &self.entries {
             // TODO: Unify this with simple entry without header
             // Key index
-            out.write_u32::<LittleEndian>(e.get_id())?;
+            out.write_u32::<LittleEndian>(e.id)?;
 
             // Value type
             out.write_u16::<LittleEndian>(8)?;
@@ -200,11 +205,14 @@ impl Entry {
         }
     }
 
-    pub fn get_id(&self) -> u32 {
+    /// Id of the entry. Entries decoded from a table type only know their position on it, so
+    /// only the entry part of their ids is set.
+    #[must_use]
+    pub fn get_id(&self) -> ResourceId {
         match self {
             Self::Complex(complex) => complex.get_id(),
             Self::Simple(simple) => simple.get_id(),
-            Self::Empty(id, _) => *id,
+            Self::Empty(id, _) => ResourceId::from(*id),
         }
     }
 
@@ -103,7 +103,7 @@ mod tests {
     use super::{ComplexEntry, ConfigurationBuf, Entry, SimpleEntry, TableTypeBuf};
     use crate::{
         chunks::TableTypeWrapper,
-        model::{owned::OwnedBuf, TableType},
+        model::{owned::OwnedBuf, ResourceId, TableType},
         raw_chunks,
         test::compare_chunks,
     };
@@ -126,11 +126,26 @@ mod tests {
         assert_eq!(5, table_type.get_id().unwrap());
         assert_eq!(3, table_type.get_amount().unwrap());
         assert_eq!(
-            10,
+            ResourceId::from(10),
             table_type.get_entry(1).unwrap().complex().unwrap().get_id()
         )
     }
 
+    #[test]
+    fn it_encodes_complex_entries_without_children() {
+        let mut table_type = TableTypeBuf::new(5, ConfigurationBuf::default());
+        table_type.add_entry(Entry::Complex(ComplexEntry::new(0, 1, 0, Vec::new())));
+
+        let raw = table_type.to_vec().unwrap();
+        let header_size = u64::from(u16::from_le_bytes([raw[2], raw[3]]));
+        let wrapper = TableTypeWrapper::new(&raw, header_size);
+        let entries = wrapper.get_entries().unwrap();
+
+        let complex = entries[0].complex().unwrap();
+        assert_eq!(1, complex.get_key());
+        assert!(complex.get_entries().is_empty());
+    }
+
     #[test]
     fn identity() {
         let wrapper = TableTypeWrapper::new(raw_chunks::EXAMPLE_TABLE_TYPE, 68);
@@ -0,0 +1,85 @@
+use std::io::Cursor;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use failure::{ensure, Error};
+
+use crate::model::owned::OwnedBuf;
+
+/// Chunk that is not decoded, kept with all its bytes so it can be encoded again as it was
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownBuf {
+    raw_data: Vec<u8>,
+}
+
+impl UnknownBuf {
+    /// Creates a chunk from all its bytes, header included
+    ///
+    /// # Errors
+    ///
+    /// It fails if the data is shorter than a chunk header or its header size is not valid.
+    pub fn new(raw_data: Vec<u8>) -> Result<Self, Error> {
+        ensure!(raw_data.len() >= 8, "chunk shorter than its header");
+
+        let header_size = Self::read_header_size(&raw_data)?;
+        ensure!(
+            header_size >= 8 && header_size <= raw_data.len(),
+            "invalid header size: {}",
+            header_size
+        );
+
+        Ok(Self { raw_data })
+    }
+
+    #[must_use]
+    pub fn get_raw_data(&self) -> &[u8] {
+        &self.raw_data
+    }
+
+    fn read_header_size(raw_data: &[u8]) -> Result<usize, Error> {
+        let mut cursor = Cursor::new(raw_data);
+        cursor.set_position(2);
+
+        Ok(usize::from(cursor.read_u16::<LittleEndian>()?))
+    }
+}
+
+impl OwnedBuf for UnknownBuf {
+    fn get_token(&self) -> u16 {
+        u16::from_le_bytes([self.raw_data[0], self.raw_data[1]])
+    }
+
+    fn get_body_data(&self) -> Result<Vec<u8>, Error> {
+        let header_size = Self::read_header_size(&self.raw_data)?;
+
+        Ok(self.raw_data[header_size..].to_vec())
+    }
+
+    fn get_header(&self) -> Result<Vec<u8>, Error> {
+        let header_size = Self::read_header_size(&self.raw_data)?;
+
+        Ok(self.raw_data[8..header_size].to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OwnedBuf, UnknownBuf};
+
+    #[test]
+    fn it_encodes_the_chunk_as_it_was() {
+        let raw = vec![
+            0x03, 0x02, 0x0C, 0x00, 0x14, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x7F, 0x00,
+            0x00, 0x00, 0x61, 0x00, 0x00, 0x00,
+        ];
+        let chunk = UnknownBuf::new(raw.clone()).unwrap();
+
+        assert_eq!(0x0203, chunk.get_token());
+        assert_eq!(raw, chunk.to_vec().unwrap());
+    }
+
+    #[test]
+    fn it_rejects_chunks_without_a_whole_header() {
+        assert!(UnknownBuf::new(vec![0x03, 0x02, 0x08, 0x00]).is_err());
+        assert!(UnknownBuf::new(vec![0x03, 0x02, 0x10, 0x00, 0x08, 0x00, 0x00, 0x00]).is_err());
+    }
+}
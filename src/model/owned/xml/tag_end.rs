@@ -8,12 +8,28 @@ use crate::{
 
 #[derive(Debug, Copy, Clone)]
 pub struct XmlTagEndBuf {
+    line: u32,
+    namespace: u32,
     id: u32,
 }
 
 impl XmlTagEndBuf {
     pub fn new(id: u32) -> Self {
-        Self { id }
+        Self {
+            line: 3,
+            namespace: 0xFFFF_FFFF,
+            id,
+        }
+    }
+
+    /// Creates a tag end for an element that belongs to the namespace with the given index
+    #[must_use]
+    pub fn with_namespace(line: u32, namespace: u32, id: u32) -> Self {
+        Self {
+            line,
+            namespace,
+            id,
+        }
     }
 }
 
@@ -25,8 +41,8 @@ impl OwnedBuf for XmlTagEndBuf {
     fn get_body_data(&self) -> Result<Vec<u8>, Error> {
         let mut out = Vec::new();
 
-        // ??
-        out.write_u32::<LittleEndian>(0xFFFF_FFFF)?;
+        // Namespace
+        out.write_u32::<LittleEndian>(self.namespace)?;
         // Id
         out.write_u32::<LittleEndian>(self.id)?;
 
@@ -36,8 +52,8 @@ impl OwnedBuf for XmlTagEndBuf {
     fn get_header(&self) -> Result<Vec<u8>, Error> {
         let mut out = Vec::new();
 
-        // Amount of writes
-        out.write_u32::<LittleEndian>(3)?;
+        // Line
+        out.write_u32::<LittleEndian>(self.line)?;
         // ??
         out.write_u32::<LittleEndian>(0xFFFF_FFFF)?;
 
@@ -0,0 +1,78 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+use failure::Error;
+
+use crate::{chunks::TOKEN_XML_TEXT, model::owned::OwnedBuf};
+
+/// Representation of a XML text (CDATA) chunk
+#[derive(Debug, Copy, Clone)]
+pub struct XmlTextBuf {
+    line: u32,
+    text: u32,
+}
+
+impl XmlTextBuf {
+    /// Creates a new text chunk which content is the string at index `text` on the string table
+    #[must_use]
+    pub fn new(line: u32, text: u32) -> Self {
+        Self { line, text }
+    }
+
+    #[must_use]
+    pub fn get_line(&self) -> u32 {
+        self.line
+    }
+
+    #[must_use]
+    pub fn get_text_index(&self) -> u32 {
+        self.text
+    }
+}
+
+impl OwnedBuf for XmlTextBuf {
+    fn get_token(&self) -> u16 {
+        TOKEN_XML_TEXT
+    }
+
+    fn get_body_data(&self) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+
+        // Text index
+        out.write_u32::<LittleEndian>(self.text)?;
+        // Typed value: size, padding, undefined type and empty data
+        out.write_u16::<LittleEndian>(8)?;
+        out.write_u8(0)?;
+        out.write_u8(0)?;
+        out.write_u32::<LittleEndian>(0)?;
+
+        Ok(out)
+    }
+
+    fn get_header(&self) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+
+        // Line
+        out.write_u32::<LittleEndian>(self.line)?;
+        // Comment
+        out.write_u32::<LittleEndian>(0xFFFF_FFFF)?;
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OwnedBuf, XmlTextBuf};
+    use crate::chunks::XmlTextWrapper;
+
+    #[test]
+    fn identity() {
+        let text = XmlTextBuf::new(12, 34);
+        let raw = text.to_vec().unwrap();
+
+        let wrapper = XmlTextWrapper::new(&raw);
+
+        assert_eq!(12, wrapper.get_line().unwrap());
+        assert_eq!(34, wrapper.get_text_index().unwrap());
+        assert_eq!(raw, wrapper.to_buffer().unwrap().to_vec().unwrap());
+    }
+}
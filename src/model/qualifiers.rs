@@ -0,0 +1,571 @@
+//! Conversions between configurations and the qualifiers used on the resource directory names,
+//! like `es-rES-land-night-xxhdpi-v21`
+
+use failure::{bail, Error};
+
+use crate::model::{
+    owned::ConfigurationBuf, Configuration, Density, GrammaticalGender, Keyboard, Navigation,
+    NightMode, Orientation, Touchscreen, UiModeType, MASK_HDR, MASK_KEYS_HIDDEN, MASK_LAYOUT_DIR,
+    MASK_NAV_HIDDEN, MASK_SCREEN_LONG, MASK_SCREEN_ROUND, MASK_SCREEN_SIZE, MASK_WIDE_COLOR_GAMUT,
+};
+
+const MNC_ZERO: u16 = 0xFFFF;
+
+const LAYOUT_DIR: &[(u8, &str)] = &[(0x40, "ldltr"), (0x80, "ldrtl")];
+const SCREEN_SIZE: &[(u8, &str)] = &[(1, "small"), (2, "normal"), (3, "large"), (4, "xlarge")];
+const SCREEN_LONG: &[(u8, &str)] = &[(0x10, "notlong"), (0x20, "long")];
+const SCREEN_ROUND: &[(u8, &str)] = &[(1, "notround"), (2, "round")];
+const WIDE_COLOR_GAMUT: &[(u8, &str)] = &[(1, "nowidecg"), (2, "widecg")];
+const HDR: &[(u8, &str)] = &[(4, "lowdr"), (8, "highdr")];
+const KEYS_HIDDEN: &[(u8, &str)] = &[(1, "keysexposed"), (2, "keyshidden"), (3, "keyssoft")];
+const NAV_HIDDEN: &[(u8, &str)] = &[(4, "navexposed"), (8, "navhidden")];
+
+/// Parses one qualifier. It returns `false` if the qualifier is not of the expected kind.
+type Parser = fn(&str, &mut ConfigurationBuf) -> bool;
+
+/// Parsers of each of the qualifiers, in the order they must appear
+const PARSERS: &[Parser] = &[
+    parse_mcc,
+    parse_mnc,
+    parse_language,
+    parse_region,
+    parse_grammatical_gender,
+    parse_layout_direction,
+    parse_smallest_width,
+    parse_screen_width,
+    parse_screen_height,
+    parse_screen_size,
+    parse_screen_long,
+    parse_screen_round,
+    parse_wide_color_gamut,
+    parse_hdr,
+    parse_orientation,
+    parse_ui_mode_type,
+    parse_ui_mode_night,
+    parse_density,
+    parse_touchscreen,
+    parse_keys_hidden,
+    parse_keyboard,
+    parse_nav_hidden,
+    parse_navigation,
+    parse_screen_dimensions,
+    parse_version,
+];
+
+/// Builds the qualifier string of the given configuration, in canonical order
+pub(crate) fn to_qualifier_string<C: Configuration + ?Sized>(config: &C) -> Result<String, Error> {
+    let mut parts = Vec::new();
+
+    match config.get_mcc()? {
+        0 => {}
+        mcc => parts.push(format!("mcc{mcc}")),
+    }
+
+    match config.get_mnc()? {
+        0 => {}
+        MNC_ZERO => parts.push("mnc00".to_string()),
+        mnc => parts.push(format!("mnc{mnc}")),
+    }
+
+    if let Some(locale) = format_locale(config)? {
+        parts.push(locale);
+    }
+
+    let screen_layout = config.get_screen_layout().unwrap_or_default();
+    let secondary_layout = config
+        .get_secondary_layout()
+        .unwrap_or_default()
+        .unwrap_or_default();
+    let color_mode = config.get_color_mode()?;
+    let input_flags = config.get_input_flags()?;
+
+    push_qualifier(
+        &mut parts,
+        GrammaticalGender::from(config.get_grammatical_gender()?).to_qualifier(),
+    );
+    push_name(&mut parts, LAYOUT_DIR, screen_layout & MASK_LAYOUT_DIR);
+
+    for (prefix, dp) in &[
+        ("sw", config.get_smallest_screen().unwrap_or_default()),
+        ("w", config.get_screen_width().unwrap_or_default()),
+        ("h", config.get_screen_height().unwrap_or_default()),
+    ] {
+        if *dp != 0 {
+            parts.push(format!("{prefix}{dp}dp"));
+        }
+    }
+
+    push_name(&mut parts, SCREEN_SIZE, screen_layout & MASK_SCREEN_SIZE);
+    push_name(&mut parts, SCREEN_LONG, screen_layout & MASK_SCREEN_LONG);
+    push_name(
+        &mut parts,
+        SCREEN_ROUND,
+        secondary_layout & MASK_SCREEN_ROUND,
+    );
+    push_name(
+        &mut parts,
+        WIDE_COLOR_GAMUT,
+        color_mode & MASK_WIDE_COLOR_GAMUT,
+    );
+    push_name(&mut parts, HDR, color_mode & MASK_HDR);
+    push_qualifier(
+        &mut parts,
+        Orientation::from(config.get_orientation()?).to_qualifier(),
+    );
+
+    if config.get_ui_mode().is_ok() {
+        push_qualifier(&mut parts, config.get_ui_mode_type()?.to_qualifier());
+        push_qualifier(&mut parts, config.get_night_mode()?.to_qualifier());
+    }
+
+    match Density::from(config.get_density()?) {
+        Density::Any => {}
+        Density::Unknown(density) => parts.push(format!("{density}dpi")),
+        density => push_qualifier(&mut parts, density.to_qualifier()),
+    }
+
+    push_qualifier(
+        &mut parts,
+        Touchscreen::from(config.get_touchscreen()?).to_qualifier(),
+    );
+    push_name(&mut parts, KEYS_HIDDEN, input_flags & MASK_KEYS_HIDDEN);
+    push_qualifier(
+        &mut parts,
+        Keyboard::from(config.get_keyboard()?).to_qualifier(),
+    );
+    push_name(&mut parts, NAV_HIDDEN, input_flags & MASK_NAV_HIDDEN);
+    push_qualifier(
+        &mut parts,
+        Navigation::from(config.get_navigation()?).to_qualifier(),
+    );
+
+    let (width, height) = (config.get_width()?, config.get_height()?);
+    if width != 0 && height != 0 {
+        parts.push(format!("{width}x{height}"));
+    }
+
+    match config.get_sdk_version()? {
+        0 => {}
+        version => parts.push(format!("v{version}")),
+    }
+
+    Ok(parts.join("-"))
+}
+
+/// Parses a qualifier string. The qualifiers must appear in canonical order, as aapt requires.
+pub(crate) fn parse_qualifiers(qualifiers: &str) -> Result<ConfigurationBuf, Error> {
+    let mut config = ConfigurationBuf::default();
+
+    if qualifiers.is_empty() {
+        return Ok(config);
+    }
+
+    let mut parsers = PARSERS.iter();
+
+    'parts: for part in qualifiers.split('-') {
+        for parser in &mut parsers {
+            if parser(part, &mut config) {
+                continue 'parts;
+            }
+        }
+
+        bail!(
+            "invalid or out of order qualifier '{}' on {}",
+            part,
+            qualifiers
+        );
+    }
+
+    Ok(config)
+}
+
+fn format_locale<C: Configuration + ?Sized>(config: &C) -> Result<Option<String>, Error> {
+    let is_set = |value: &str| !value.is_empty() && value != "any";
+    let language = config.get_language()?;
+    let region = config.get_region()?;
+    let script = config.get_locale_script().unwrap_or_default();
+    let variant = config.get_locale_variant().unwrap_or_default();
+    let numbering_system = config.get_numbering_system()?;
+
+    if !is_set(&language) {
+        return Ok(None);
+    }
+
+    let is_bcp47 = script.is_some()
+        || variant.is_some()
+        || numbering_system.is_some()
+        || language.len() != 2
+        || (is_set(&region) && region.len() != 2);
+
+    let locale = if is_bcp47 {
+        let mut locale = format!("b+{language}");
+
+        for subtag in [
+            script,
+            Some(region).filter(|region| is_set(region)),
+            variant,
+        ]
+        .iter()
+        .flatten()
+        {
+            locale.push('+');
+            locale.push_str(subtag);
+        }
+
+        if let Some(numbering_system) = numbering_system {
+            locale.push_str("+u+nu+");
+            locale.push_str(&numbering_system);
+        }
+
+        locale
+    } else if is_set(&region) {
+        format!("{language}-r{region}")
+    } else {
+        language
+    };
+
+    Ok(Some(locale))
+}
+
+fn push_qualifier(parts: &mut Vec<String>, qualifier: Option<&str>) {
+    parts.extend(qualifier.map(ToString::to_string));
+}
+
+fn push_name<T: PartialEq + Copy>(parts: &mut Vec<String>, names: &[(T, &str)], value: T) {
+    if let Some((_, name)) = names.iter().find(|(current, _)| *current == value) {
+        parts.push((*name).to_string());
+    }
+}
+
+fn find_value<T: Copy>(names: &[(T, &str)], part: &str) -> Option<T> {
+    names
+        .iter()
+        .find(|(_, name)| *name == part)
+        .map(|(value, _)| *value)
+}
+
+fn parse_number<T: std::str::FromStr>(part: &str, prefix: &str, suffix: &str) -> Option<T> {
+    let digits = part.strip_prefix(prefix)?.strip_suffix(suffix)?;
+
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    digits.parse().ok()
+}
+
+fn parse_mcc(part: &str, config: &mut ConfigurationBuf) -> bool {
+    match parse_number(part, "mcc", "") {
+        Some(mcc) if mcc != 0 => {
+            config.set_mcc(mcc);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn parse_mnc(part: &str, config: &mut ConfigurationBuf) -> bool {
+    match parse_number::<u16>(part, "mnc", "") {
+        Some(0) => {
+            config.set_mnc(MNC_ZERO);
+            true
+        }
+        Some(mnc) => {
+            config.set_mnc(mnc);
+            true
+        }
+        None => false,
+    }
+}
+
+fn parse_language(part: &str, config: &mut ConfigurationBuf) -> bool {
+    if let Some(tag) = part.strip_prefix("b+") {
+        return parse_bcp47(tag, config);
+    }
+
+    // `car` is the only qualifier that could be confused with a language
+    if (part.len() == 2 || part.len() == 3)
+        && part.bytes().all(|byte| byte.is_ascii_lowercase())
+        && part != "car"
+    {
+        config.set_language(part);
+        return true;
+    }
+
+    false
+}
+
+/// Parses locales with the form `b+language[+Script][+REGION][+variant][+u+nu+numbering]`
+fn parse_bcp47(tag: &str, config: &mut ConfigurationBuf) -> bool {
+    let (tag, numbering_system) = match tag.find("+u+nu+") {
+        Some(position) => (&tag[..position], Some(&tag[position + 6..])),
+        None => (tag, None),
+    };
+
+    if let Some(numbering_system) = numbering_system {
+        if numbering_system.len() < 3
+            || numbering_system.len() > 8
+            || !numbering_system
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric())
+        {
+            return false;
+        }
+
+        config.set_numbering_system(Some(&numbering_system.to_ascii_lowercase()));
+    }
+
+    let mut subtags = tag.split('+');
+
+    match subtags.next() {
+        Some(language)
+            if (language.len() == 2 || language.len() == 3)
+                && language.bytes().all(|byte| byte.is_ascii_alphabetic()) =>
+        {
+            config.set_language(&language.to_ascii_lowercase());
+        }
+        _ => return false,
+    }
+
+    for subtag in subtags {
+        let is_alphabetic = subtag.bytes().all(|byte| byte.is_ascii_alphabetic());
+        let is_numeric = subtag.bytes().all(|byte| byte.is_ascii_digit());
+
+        match subtag.len() {
+            4 if is_alphabetic => {
+                let mut script = subtag.to_ascii_lowercase();
+                script[..1].make_ascii_uppercase();
+                config.set_locale_script(Some(&script));
+            }
+            2 if is_alphabetic => config.set_region(&subtag.to_ascii_uppercase()),
+            3 if is_numeric => config.set_region(subtag),
+            5..=8 => config.set_locale_variant(Some(&subtag.to_ascii_lowercase())),
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+fn parse_region(part: &str, config: &mut ConfigurationBuf) -> bool {
+    match part.strip_prefix('r') {
+        Some(region)
+            if region.len() == 2 && region.bytes().all(|byte| byte.is_ascii_uppercase()) =>
+        {
+            config.set_region(region);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn parse_grammatical_gender(part: &str, config: &mut ConfigurationBuf) -> bool {
+    GrammaticalGender::from_qualifier(part)
+        .map(|value| config.set_grammatical_gender(value))
+        .is_some()
+}
+
+fn parse_layout_direction(part: &str, config: &mut ConfigurationBuf) -> bool {
+    parse_screen_layout(part, config, LAYOUT_DIR, MASK_LAYOUT_DIR)
+}
+
+fn parse_smallest_width(part: &str, config: &mut ConfigurationBuf) -> bool {
+    parse_number(part, "sw", "dp")
+        .map(|dp| config.set_smallest_screen(dp))
+        .is_some()
+}
+
+fn parse_screen_width(part: &str, config: &mut ConfigurationBuf) -> bool {
+    parse_number(part, "w", "dp")
+        .map(|dp| config.set_screen_width(dp))
+        .is_some()
+}
+
+fn parse_screen_height(part: &str, config: &mut ConfigurationBuf) -> bool {
+    parse_number(part, "h", "dp")
+        .map(|dp| config.set_screen_height(dp))
+        .is_some()
+}
+
+fn parse_screen_size(part: &str, config: &mut ConfigurationBuf) -> bool {
+    parse_screen_layout(part, config, SCREEN_SIZE, MASK_SCREEN_SIZE)
+}
+
+fn parse_screen_long(part: &str, config: &mut ConfigurationBuf) -> bool {
+    parse_screen_layout(part, config, SCREEN_LONG, MASK_SCREEN_LONG)
+}
+
+fn parse_screen_layout(
+    part: &str,
+    config: &mut ConfigurationBuf,
+    names: &[(u8, &str)],
+    mask: u8,
+) -> bool {
+    find_value(names, part)
+        .map(|value| {
+            let screen_layout = config.get_screen_layout().unwrap_or_default();
+            config.set_screen_layout((screen_layout & !mask) | value);
+        })
+        .is_some()
+}
+
+fn parse_screen_round(part: &str, config: &mut ConfigurationBuf) -> bool {
+    find_value(SCREEN_ROUND, part)
+        .map(|value| config.set_secondary_layout(value))
+        .is_some()
+}
+
+fn parse_wide_color_gamut(part: &str, config: &mut ConfigurationBuf) -> bool {
+    parse_color_mode(part, config, WIDE_COLOR_GAMUT, MASK_WIDE_COLOR_GAMUT)
+}
+
+fn parse_hdr(part: &str, config: &mut ConfigurationBuf) -> bool {
+    parse_color_mode(part, config, HDR, MASK_HDR)
+}
+
+fn parse_color_mode(
+    part: &str,
+    config: &mut ConfigurationBuf,
+    names: &[(u8, &str)],
+    mask: u8,
+) -> bool {
+    find_value(names, part)
+        .map(|value| {
+            let color_mode = config.get_color_mode().unwrap_or_default();
+            config.set_color_mode((color_mode & !mask) | value);
+        })
+        .is_some()
+}
+
+fn parse_orientation(part: &str, config: &mut ConfigurationBuf) -> bool {
+    Orientation::from_qualifier(part)
+        .map(|value| config.set_orientation(value))
+        .is_some()
+}
+
+fn parse_ui_mode_type(part: &str, config: &mut ConfigurationBuf) -> bool {
+    UiModeType::from_qualifier(part)
+        .map(|value| config.set_ui_mode_type(value))
+        .is_some()
+}
+
+fn parse_ui_mode_night(part: &str, config: &mut ConfigurationBuf) -> bool {
+    NightMode::from_qualifier(part)
+        .map(|value| config.set_night_mode(value))
+        .is_some()
+}
+
+fn parse_density(part: &str, config: &mut ConfigurationBuf) -> bool {
+    Density::from_qualifier(part)
+        .map(u16::from)
+        .or_else(|| parse_number(part, "", "dpi").filter(|density| *density != 0))
+        .map(|density| config.set_density(density))
+        .is_some()
+}
+
+fn parse_touchscreen(part: &str, config: &mut ConfigurationBuf) -> bool {
+    Touchscreen::from_qualifier(part)
+        .map(|value| config.set_touchscreen(value))
+        .is_some()
+}
+
+fn parse_keys_hidden(part: &str, config: &mut ConfigurationBuf) -> bool {
+    parse_input_flags(part, config, KEYS_HIDDEN, MASK_KEYS_HIDDEN)
+}
+
+fn parse_keyboard(part: &str, config: &mut ConfigurationBuf) -> bool {
+    Keyboard::from_qualifier(part)
+        .map(|value| config.set_keyboard(value))
+        .is_some()
+}
+
+fn parse_nav_hidden(part: &str, config: &mut ConfigurationBuf) -> bool {
+    parse_input_flags(part, config, NAV_HIDDEN, MASK_NAV_HIDDEN)
+}
+
+fn parse_input_flags(
+    part: &str,
+    config: &mut ConfigurationBuf,
+    names: &[(u8, &str)],
+    mask: u8,
+) -> bool {
+    find_value(names, part)
+        .map(|value| {
+            let input_flags = config.get_input_flags().unwrap_or_default();
+            config.set_input_flags((input_flags & !mask) | value);
+        })
+        .is_some()
+}
+
+fn parse_navigation(part: &str, config: &mut ConfigurationBuf) -> bool {
+    Navigation::from_qualifier(part)
+        .map(|value| config.set_navigation(value))
+        .is_some()
+}
+
+fn parse_screen_dimensions(part: &str, config: &mut ConfigurationBuf) -> bool {
+    let mut dimensions = part.splitn(2, 'x');
+    let width = dimensions
+        .next()
+        .and_then(|width| parse_number::<u16>(width, "", ""));
+    let height = dimensions
+        .next()
+        .and_then(|height| parse_number::<u16>(height, "", ""));
+
+    match (width, height) {
+        (Some(width), Some(height)) if width != 0 && height != 0 => {
+            // The biggest dimension always goes first
+            config.set_screen_size(width.max(height), width.min(height));
+            true
+        }
+        _ => false,
+    }
+}
+
+fn parse_version(part: &str, config: &mut ConfigurationBuf) -> bool {
+    parse_number(part, "v", "")
+        .map(|version| config.set_sdk_version(version))
+        .is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_qualifiers, to_qualifier_string};
+
+    fn round_trip(qualifiers: &str) -> String {
+        to_qualifier_string(&parse_qualifiers(qualifiers).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn it_can_parse_and_print_qualifiers() {
+        for qualifiers in &[
+            "",
+            "es-rES-land-night-xxhdpi-v21",
+            "mcc310-mnc00-en-rUS",
+            "ldrtl-sw600dp-w720dp-h1024dp-xlarge-long-round",
+            "port-car-notnight-420dpi-finger-keyshidden-qwerty-navhidden-dpad-640x480-v26",
+            "b+sr+Latn",
+            "b+es+419",
+            "television-anydpi",
+            "b+fil+PH-masculine-notround-nowidecg-lowdr-nodpi",
+            "b+ar+EG+u+nu+latn-desk",
+        ] {
+            assert_eq!(*qualifiers, round_trip(qualifiers));
+        }
+    }
+
+    #[test]
+    fn it_normalizes_the_qualifiers() {
+        assert_eq!("b+sr+Latn+RS", round_trip("b+SR+latn+rs"));
+        assert_eq!("640x480", round_trip("480x640"));
+        assert_eq!("mnc00", round_trip("mnc0"));
+    }
+
+    #[test]
+    fn it_does_not_parse_invalid_or_unordered_qualifiers() {
+        assert!(parse_qualifiers("land-es").is_err());
+        assert!(parse_qualifiers("v21-hdpi").is_err());
+        assert!(parse_qualifiers("hello").is_err());
+        assert!(parse_qualifiers("es--land").is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use std::{fmt, str::FromStr};
+
+use failure::{format_err, Error};
+
+/// Identifier of a resource. It has the form `0xPPTTEEEE`, where `PP` is the package id, `TT` is
+/// the type id and `EEEE` is the index of the entry inside the type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct ResourceId(u32);
+
+impl ResourceId {
+    #[must_use]
+    pub fn new(package: u8, type_id: u8, entry: u16) -> Self {
+        Self(u32::from(package) << 24 | u32::from(type_id) << 16 | u32::from(entry))
+    }
+
+    #[must_use]
+    pub fn get_package(self) -> u8 {
+        (self.0 >> 24) as u8
+    }
+
+    #[must_use]
+    pub fn get_type(self) -> u8 {
+        ((self.0 >> 16) & 0xFF) as u8
+    }
+
+    #[must_use]
+    pub fn get_entry(self) -> u16 {
+        (self.0 & 0xFFFF) as u16
+    }
+
+    #[must_use]
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+
+    /// `0` is used to represent a null reference
+    #[must_use]
+    pub fn is_null(self) -> bool {
+        self.0 == 0
+    }
+
+    /// An id is valid if it has a type. Package id may be `0` for references to shared
+    /// libraries, which are assigned a package id at runtime.
+    #[must_use]
+    pub fn is_valid(self) -> bool {
+        self.get_type() != 0
+    }
+}
+
+impl From<u32> for ResourceId {
+    fn from(id: u32) -> Self {
+        Self(id)
+    }
+}
+
+impl From<ResourceId> for u32 {
+    fn from(id: ResourceId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for ResourceId {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "0x{:08x}", self.0)
+    }
+}
+
+impl fmt::LowerHex for ResourceId {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, formatter)
+    }
+}
+
+impl fmt::UpperHex for ResourceId {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        fmt::UpperHex::fmt(&self.0, formatter)
+    }
+}
+
+/// Parses hexadecimal ids, with or without `0x` prefix
+impl FromStr for ResourceId {
+    type Err = Error;
+
+    fn from_str(id: &str) -> Result<Self, Error> {
+        let digits = id
+            .trim_start_matches('@')
+            .trim_start_matches("0x")
+            .trim_start_matches("0X");
+
+        u32::from_str_radix(digits, 16)
+            .map(Self)
+            .map_err(|e| format_err!("invalid resource id {}: {}", id, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ResourceId;
+
+    #[test]
+    fn it_extracts_package_id() {
+        assert_eq!(ResourceId::from(2130837685).get_package(), 127)
+    }
+
+    #[test]
+    fn it_keeps_package_id_if_is_0() {
+        let id = ResourceId::from(131253);
+
+        assert_eq!(id.get_package(), 0);
+    }
+
+    #[test]
+    fn it_extracts_spec_id() {
+        assert_eq!(ResourceId::from(2130837685).get_type(), 2)
+    }
+
+    #[test]
+    fn it_extracts_id() {
+        assert_eq!(ResourceId::from(2130837685).get_entry(), 181)
+    }
+
+    #[test]
+    fn it_can_be_formatted_and_parsed() {
+        let id = ResourceId::new(0x7F, 2, 1);
+
+        assert_eq!("0x7f020001", id.to_string());
+        assert_eq!(id, "0x7f020001".parse().unwrap());
+        assert_eq!(id, "7F020001".parse().unwrap());
+        assert_eq!(ResourceId::from(1), "0x00000001".parse().unwrap());
+        assert!("0xnothex".parse::<ResourceId>().is_err());
+    }
+
+    #[test]
+    fn it_checks_validity() {
+        assert!(ResourceId::new(0x7F, 2, 1).is_valid());
+        assert!(!ResourceId::from(0x7F00_0001).is_valid());
+        assert!(ResourceId::from(0).is_null());
+    }
+}
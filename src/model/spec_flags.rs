@@ -0,0 +1,258 @@
+use std::{fmt, ops::BitOr};
+
+use failure::Error;
+
+use crate::model::{Configuration, MASK_LAYOUT_DIR, MASK_UI_MODE_NIGHT, MASK_UI_MODE_TYPE};
+
+const SPEC_PUBLIC: u32 = 0x4000_0000;
+const SPEC_STAGED_API: u32 = 0x2000_0000;
+
+/// Configuration axes the values of a resource vary over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ConfigChanges(u32);
+
+impl ConfigChanges {
+    pub const MCC: Self = Self(0x0001);
+    pub const MNC: Self = Self(0x0002);
+    pub const LOCALE: Self = Self(0x0004);
+    pub const TOUCHSCREEN: Self = Self(0x0008);
+    pub const KEYBOARD: Self = Self(0x0010);
+    pub const KEYBOARD_HIDDEN: Self = Self(0x0020);
+    pub const NAVIGATION: Self = Self(0x0040);
+    pub const ORIENTATION: Self = Self(0x0080);
+    pub const DENSITY: Self = Self(0x0100);
+    pub const SCREEN_SIZE: Self = Self(0x0200);
+    pub const VERSION: Self = Self(0x0400);
+    pub const SCREEN_LAYOUT: Self = Self(0x0800);
+    pub const UI_MODE: Self = Self(0x1000);
+    pub const SMALLEST_SCREEN_SIZE: Self = Self(0x2000);
+    pub const LAYOUT_DIR: Self = Self(0x4000);
+    pub const SCREEN_ROUND: Self = Self(0x8000);
+    pub const COLOR_MODE: Self = Self(0x0001_0000);
+    pub const GRAMMATICAL_GENDER: Self = Self(0x0002_0000);
+
+    /// Returns the axes on which the given configuration differs from the default one, which are
+    /// the changes a resource needs when it has a value for that configuration
+    ///
+    /// # Errors
+    ///
+    /// It fails if one of the fields of the configuration can not be read.
+    pub fn from_configuration<C: Configuration + ?Sized>(config: &C) -> Result<Self, Error> {
+        let screen_layout = config.get_screen_layout().unwrap_or_default();
+        let ui_mode = config.get_ui_mode().unwrap_or_default();
+        let is_set = |value: &str| !value.is_empty() && value != "any";
+
+        let fields = [
+            (config.get_mcc()? != 0, Self::MCC),
+            (config.get_mnc()? != 0, Self::MNC),
+            (
+                is_set(&config.get_language()?)
+                    || is_set(&config.get_region()?)
+                    || config.get_locale_script()?.is_some()
+                    || config.get_locale_variant()?.is_some()
+                    || config.get_numbering_system()?.is_some(),
+                Self::LOCALE,
+            ),
+            (config.get_touchscreen()? != 0, Self::TOUCHSCREEN),
+            (config.get_keyboard()? != 0, Self::KEYBOARD),
+            (config.get_input_flags()? != 0, Self::KEYBOARD_HIDDEN),
+            (config.get_navigation()? != 0, Self::NAVIGATION),
+            (config.get_orientation()? != 0, Self::ORIENTATION),
+            (config.get_density()? != 0, Self::DENSITY),
+            (
+                config.get_width()? != 0
+                    || config.get_height()? != 0
+                    || config.get_screen_width().unwrap_or_default() != 0
+                    || config.get_screen_height().unwrap_or_default() != 0,
+                Self::SCREEN_SIZE,
+            ),
+            (
+                config.get_sdk_version()? != 0 || config.get_min_sdk_version()? != 0,
+                Self::VERSION,
+            ),
+            (screen_layout & !MASK_LAYOUT_DIR != 0, Self::SCREEN_LAYOUT),
+            (
+                ui_mode & (MASK_UI_MODE_TYPE | MASK_UI_MODE_NIGHT) != 0,
+                Self::UI_MODE,
+            ),
+            (
+                config.get_smallest_screen().unwrap_or_default() != 0,
+                Self::SMALLEST_SCREEN_SIZE,
+            ),
+            (screen_layout & MASK_LAYOUT_DIR != 0, Self::LAYOUT_DIR),
+            (
+                config.get_secondary_layout()?.unwrap_or_default() != 0,
+                Self::SCREEN_ROUND,
+            ),
+            (config.get_color_mode()? != 0, Self::COLOR_MODE),
+            (
+                config.get_grammatical_gender()? != 0,
+                Self::GRAMMATICAL_GENDER,
+            ),
+        ];
+
+        Ok(fields
+            .iter()
+            .filter(|(changed, _)| *changed)
+            .fold(Self::default(), |changes, (_, flag)| changes | *flag))
+    }
+
+    #[must_use]
+    pub fn contains(self, changes: Self) -> bool {
+        self.0 & changes.0 == changes.0
+    }
+
+    #[must_use]
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[must_use]
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for ConfigChanges {
+    fn from(changes: u32) -> Self {
+        Self(changes)
+    }
+}
+
+impl BitOr for ConfigChanges {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Formats the names of the axes (`locale|density`)
+impl fmt::Display for ConfigChanges {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let names = [
+            (Self::MCC, "mcc"),
+            (Self::MNC, "mnc"),
+            (Self::LOCALE, "locale"),
+            (Self::TOUCHSCREEN, "touchscreen"),
+            (Self::KEYBOARD, "keyboard"),
+            (Self::KEYBOARD_HIDDEN, "keyboardHidden"),
+            (Self::NAVIGATION, "navigation"),
+            (Self::ORIENTATION, "orientation"),
+            (Self::DENSITY, "density"),
+            (Self::SCREEN_SIZE, "screenSize"),
+            (Self::VERSION, "version"),
+            (Self::SCREEN_LAYOUT, "screenLayout"),
+            (Self::UI_MODE, "uiMode"),
+            (Self::SMALLEST_SCREEN_SIZE, "smallestScreenSize"),
+            (Self::LAYOUT_DIR, "layoutDirection"),
+            (Self::SCREEN_ROUND, "screenRound"),
+            (Self::COLOR_MODE, "colorMode"),
+            (Self::GRAMMATICAL_GENDER, "grammaticalGender"),
+        ];
+        let set: Vec<&str> = names
+            .iter()
+            .filter(|(changes, _)| self.contains(*changes))
+            .map(|(_, name)| *name)
+            .collect();
+
+        formatter.write_str(&set.join("|"))
+    }
+}
+
+/// Flags of an entry of a type spec: the configuration changes its values vary over and if it
+/// is part of the public API of the package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SpecFlags(u32);
+
+impl SpecFlags {
+    /// Returns true if the resource was declared on `public.xml`
+    #[must_use]
+    pub fn is_public(self) -> bool {
+        self.0 & SPEC_PUBLIC != 0
+    }
+
+    /// Returns true if the resource is part of an API that is not finalized yet
+    #[must_use]
+    pub fn is_staged_api(self) -> bool {
+        self.0 & SPEC_STAGED_API != 0
+    }
+
+    #[must_use]
+    pub fn get_config_changes(self) -> ConfigChanges {
+        ConfigChanges(self.0 & !(SPEC_PUBLIC | SPEC_STAGED_API))
+    }
+
+    pub fn set_public(&mut self, public: bool) {
+        if public {
+            self.0 |= SPEC_PUBLIC;
+        } else {
+            self.0 &= !SPEC_PUBLIC;
+        }
+    }
+
+    pub fn add_config_changes(&mut self, changes: ConfigChanges) {
+        self.0 |= changes.0;
+    }
+
+    #[must_use]
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for SpecFlags {
+    fn from(flags: u32) -> Self {
+        Self(flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigChanges, SpecFlags};
+    use crate::model::owned::ConfigurationBuf;
+
+    #[test]
+    fn it_splits_the_public_flag_from_the_config_changes() {
+        let flags = SpecFlags::from(0x4000_0104);
+
+        assert!(flags.is_public());
+        assert!(!flags.is_staged_api());
+        assert_eq!(
+            ConfigChanges::LOCALE | ConfigChanges::DENSITY,
+            flags.get_config_changes()
+        );
+        assert_eq!("locale|density", flags.get_config_changes().to_string());
+    }
+
+    #[test]
+    fn it_can_change_the_flags() {
+        let mut flags = SpecFlags::default();
+        flags.add_config_changes(ConfigChanges::VERSION);
+        flags.set_public(true);
+
+        assert_eq!(0x4000_0400, flags.as_u32());
+
+        flags.set_public(false);
+
+        assert_eq!(0x0400, flags.as_u32());
+    }
+
+    #[test]
+    fn it_computes_the_changes_of_a_configuration() {
+        let config = ConfigurationBuf::from_qualifiers("b+sr+Latn-ldrtl-night-v21").unwrap();
+
+        assert_eq!(
+            ConfigChanges::LOCALE
+                | ConfigChanges::LAYOUT_DIR
+                | ConfigChanges::UI_MODE
+                | ConfigChanges::VERSION,
+            ConfigChanges::from_configuration(&config).unwrap()
+        );
+        assert!(
+            ConfigChanges::from_configuration(&ConfigurationBuf::default())
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,1080 @@
+//! Mutable representation of a `resources.arsc` file that can be encoded back to binary form
+
+use std::collections::HashMap;
+
+use failure::{bail, ensure, format_err, Error};
+
+use crate::{
+    chunks::{ChunkHeader, PackageWrapper, StringTableWrapper, TableTypeWrapper, TypeSpecWrapper},
+    model::{
+        builder::Arsc,
+        document::{AttributeValue, StringSpan},
+        owned::{
+            ComplexEntry, ConfigurationBuf, Encoding, Entry, PackageBuf, SimpleEntry, SpanBuf,
+            StringTableBuf, TableTypeBuf, TableTypeSpecBuf, UnknownBuf,
+        },
+        ConfigChanges, ResourceId, SpecFlags, StringTable, TableType, TypeSpec,
+    },
+    options::ParseOptions,
+    visitor::{ChunkVisitor, Executor, Origin},
+};
+
+const TYPE_STRING: u8 = 0x03;
+
+/// Value of a resource on a given configuration
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableEntry {
+    /// Single value, like a string or a color
+    Value(AttributeValue),
+    /// Map of attribute ids to values, like styles, arrays or plurals
+    Bag {
+        parent: ResourceId,
+        items: Vec<(ResourceId, AttributeValue)>,
+    },
+}
+
+/// Values of all the entries of a type on a single configuration. Index of each entry matches
+/// the entry index of the resource id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableConfig {
+    config: ConfigurationBuf,
+    entries: Vec<Option<TableEntry>>,
+}
+
+impl TableConfig {
+    #[must_use]
+    pub fn get_configuration(&self) -> &ConfigurationBuf {
+        &self.config
+    }
+
+    #[must_use]
+    pub fn get_entries(&self) -> &[Option<TableEntry>] {
+        &self.entries
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.iter().all(Option::is_none)
+    }
+}
+
+/// Resource type (`string`, `drawable`...) with the names of its entries and their values on
+/// each configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSpec {
+    id: u8,
+    name: String,
+    flags: Vec<SpecFlags>,
+    entry_names: Vec<String>,
+    configs: Vec<TableConfig>,
+}
+
+impl TableSpec {
+    fn new(id: u8, name: &str) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            flags: Vec::new(),
+            entry_names: Vec::new(),
+            configs: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn get_id(&self) -> u8 {
+        self.id
+    }
+
+    #[must_use]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Flags of each entry: the configuration changes its values vary over and if it is public
+    #[must_use]
+    pub fn get_flags(&self) -> &[SpecFlags] {
+        &self.flags
+    }
+
+    #[must_use]
+    pub fn get_entry_names(&self) -> &[String] {
+        &self.entry_names
+    }
+
+    #[must_use]
+    pub fn get_configs(&self) -> &[TableConfig] {
+        &self.configs
+    }
+
+    fn resize(&mut self, amount: usize) {
+        if self.entry_names.len() < amount {
+            self.entry_names.resize(amount, String::new());
+            self.flags.resize(amount, SpecFlags::default());
+        }
+
+        for config in &mut self.configs {
+            config.entries.resize(self.entry_names.len(), None);
+        }
+    }
+
+    fn get_mut_config(&mut self, config: &ConfigurationBuf) -> &mut TableConfig {
+        let entries = self.entry_names.len();
+        let configs = &mut self.configs;
+        let position = configs
+            .iter()
+            .position(|c| c.config == *config)
+            .unwrap_or_else(|| {
+                configs.push(TableConfig {
+                    config: config.clone(),
+                    entries: vec![None; entries],
+                });
+
+                configs.len() - 1
+            });
+
+        &mut self.configs[position]
+    }
+
+    fn is_defined(&self, index: usize) -> bool {
+        self.configs
+            .iter()
+            .any(|c| c.entries.get(index).and_then(Option::as_ref).is_some())
+    }
+}
+
+/// Package of a `ResourceTable`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TablePackage {
+    id: u8,
+    name: String,
+    specs: Vec<TableSpec>,
+    chunks: Vec<UnknownBuf>,
+}
+
+impl TablePackage {
+    #[must_use]
+    pub fn get_id(&self) -> u8 {
+        self.id
+    }
+
+    #[must_use]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn get_specs(&self) -> &[TableSpec] {
+        &self.specs
+    }
+
+    /// Chunks of the package that are not decoded, like the shared library ones
+    #[must_use]
+    pub fn get_chunks(&self) -> &[UnknownBuf] {
+        &self.chunks
+    }
+
+    /// Returns the type with the given name (`string`, `drawable`...)
+    #[must_use]
+    pub fn get_spec(&self, name: &str) -> Option<&TableSpec> {
+        self.specs.iter().find(|s| s.name == name)
+    }
+
+    fn get_spec_by_id(&self, id: u8) -> Option<&TableSpec> {
+        self.specs.iter().find(|s| s.id == id)
+    }
+
+    fn get_mut_spec_by_id(&mut self, id: u8) -> Option<&mut TableSpec> {
+        self.specs.iter_mut().find(|s| s.id == id)
+    }
+}
+
+/// Mutable resource table. Resource ids of the existing entries are kept when the table is
+/// encoded again, while all the string pools and offsets are regenerated. Chunks that are not
+/// decoded, like the shared library ones, are kept as they are after the string pools of the
+/// table or of their package.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceTable {
+    packages: Vec<TablePackage>,
+    chunks: Vec<UnknownBuf>,
+    encoding: Encoding,
+}
+
+impl Default for ResourceTable {
+    fn default() -> Self {
+        Self {
+            packages: Vec::new(),
+            chunks: Vec::new(),
+            encoding: Encoding::Utf8,
+        }
+    }
+}
+
+impl ResourceTable {
+    /// Decodes the contents of a `resources.arsc` file. Decoding is strict: the table is encoded
+    /// again as a whole, so any problem on it fails instead of losing the broken parts.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the buffer is not a resource table or it has any problem.
+    pub fn from_bytes(buffer: &[u8]) -> Result<Self, Error> {
+        let mut loader = TableLoader::default();
+        Executor::arsc_with_options(buffer, &mut loader, ParseOptions::strict())?;
+
+        Ok(Self {
+            packages: loader.packages,
+            chunks: loader.chunks,
+            encoding: loader.encoding.unwrap_or(Encoding::Utf8),
+        })
+    }
+
+    #[must_use]
+    pub fn get_packages(&self) -> &[TablePackage] {
+        &self.packages
+    }
+
+    #[must_use]
+    pub fn get_package(&self, id: u8) -> Option<&TablePackage> {
+        self.packages.iter().find(|p| p.id == id)
+    }
+
+    /// Chunks outside of the packages that are not decoded
+    #[must_use]
+    pub fn get_chunks(&self) -> &[UnknownBuf] {
+        &self.chunks
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    /// Adds a new empty package
+    ///
+    /// # Errors
+    ///
+    /// It fails if there is already a package with the given id.
+    pub fn add_package(&mut self, id: u8, name: &str) -> Result<(), Error> {
+        ensure!(
+            self.get_package(id).is_none(),
+            "package {:X} already exists",
+            id
+        );
+
+        self.packages.push(TablePackage {
+            id,
+            name: name.to_string(),
+            specs: Vec::new(),
+            chunks: Vec::new(),
+        });
+
+        Ok(())
+    }
+
+    /// Adds a new resource with the given type and name and returns its id. The id is given after
+    /// the last entry of the type, so the ids of the already existing resources do not change. If
+    /// the type does not exist, it is created.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the package does not exist, the resource already exists or the package or the
+    /// type have no ids left.
+    pub fn add_resource(
+        &mut self,
+        package_id: u8,
+        type_name: &str,
+        name: &str,
+    ) -> Result<ResourceId, Error> {
+        let package = self
+            .packages
+            .iter_mut()
+            .find(|p| p.id == package_id)
+            .ok_or_else(|| format_err!("package {:X} not found", package_id))?;
+
+        let position =
+            if let Some(position) = package.specs.iter().position(|s| s.name == type_name) {
+                position
+            } else {
+                let id = package.specs.iter().map(|s| s.id).max().unwrap_or(0);
+                ensure!(id < 0xFF, "too many types on package {:X}", package_id);
+
+                package.specs.push(TableSpec::new(id + 1, type_name));
+                package.specs.len() - 1
+            };
+
+        let spec = &mut package.specs[position];
+        ensure!(
+            !spec.entry_names.iter().any(|n| n == name),
+            "resource {}/{} already exists",
+            type_name,
+            name
+        );
+        ensure!(
+            spec.entry_names.len() < 0xFFFF,
+            "too many entries on type {}",
+            type_name
+        );
+
+        let index = spec.entry_names.len();
+        spec.resize(index + 1);
+        spec.entry_names[index] = name.to_string();
+
+        Ok(ResourceId::new(package_id, spec.id, index as u16))
+    }
+
+    /// Returns the value of the resource on exactly the given configuration
+    #[must_use]
+    pub fn get_entry(&self, id: ResourceId, config: &ConfigurationBuf) -> Option<&TableEntry> {
+        let spec = self
+            .get_package(id.get_package())?
+            .get_spec_by_id(id.get_type())?;
+
+        spec.configs
+            .iter()
+            .find(|c| c.config == *config)?
+            .entries
+            .get(usize::from(id.get_entry()))?
+            .as_ref()
+    }
+
+    /// Sets the value of the resource on the given configuration. If there are no values for
+    /// that configuration yet, it is created.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the resource does not exist.
+    pub fn set_entry(
+        &mut self,
+        id: ResourceId,
+        config: &ConfigurationBuf,
+        entry: TableEntry,
+    ) -> Result<(), Error> {
+        let spec = self.get_mut_spec(id)?;
+        let index = usize::from(id.get_entry());
+
+        let changes = ConfigChanges::from_configuration(config)?;
+        spec.flags[index].add_config_changes(changes);
+        spec.get_mut_config(config).entries[index] = Some(entry);
+
+        Ok(())
+    }
+
+    /// Removes the value of the resource on the given configuration
+    pub fn remove_entry(
+        &mut self,
+        id: ResourceId,
+        config: &ConfigurationBuf,
+    ) -> Option<TableEntry> {
+        let spec = self.get_mut_spec(id).ok()?;
+
+        spec.configs
+            .iter_mut()
+            .find(|c| c.config == *config)?
+            .entries
+            .get_mut(usize::from(id.get_entry()))?
+            .take()
+    }
+
+    /// Marks the resource as part of the public API of the package, as `public.xml` does
+    ///
+    /// # Errors
+    ///
+    /// It fails if the resource does not exist.
+    pub fn set_public(&mut self, id: ResourceId, public: bool) -> Result<(), Error> {
+        let spec = self.get_mut_spec(id)?;
+        spec.flags[usize::from(id.get_entry())].set_public(public);
+
+        Ok(())
+    }
+
+    /// Removes the values of the resource on all the configurations. The id is not reused, so
+    /// the rest of resources keep their ids.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the resource does not exist.
+    pub fn remove_resource(&mut self, id: ResourceId) -> Result<(), Error> {
+        let spec = self.get_mut_spec(id)?;
+        let index = usize::from(id.get_entry());
+
+        for config in &mut spec.configs {
+            config.entries[index] = None;
+        }
+
+        spec.flags[index] = SpecFlags::default();
+
+        Ok(())
+    }
+
+    /// Removes all the values on the given configuration. It returns the amount of types that had
+    /// values for that configuration.
+    pub fn remove_configuration(&mut self, config: &ConfigurationBuf) -> usize {
+        let mut removed = 0;
+
+        for spec in self.packages.iter_mut().flat_map(|p| p.specs.iter_mut()) {
+            let previous = spec.configs.len();
+            spec.configs.retain(|c| c.config != *config);
+            removed += previous - spec.configs.len();
+        }
+
+        removed
+    }
+
+    /// Encodes the table as a `resources.arsc` file
+    ///
+    /// # Errors
+    ///
+    /// It fails if a string pool or an entry can not be encoded.
+    pub fn to_vec(&self) -> Result<Vec<u8>, Error> {
+        let mut values = Pool::default();
+
+        // Styled strings go first, as the style at each index belongs to the string with the same
+        // index
+        for value in self.values() {
+            if let AttributeValue::StyledString(value, spans) = value {
+                values.add_styled(value, spans);
+            }
+        }
+
+        for value in self.values() {
+            match value {
+                AttributeValue::String(value) => values.add(value),
+                AttributeValue::StyledString(_, spans) => {
+                    for span in spans {
+                        values.add(span.get_name());
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        let mut arsc = Arsc::default();
+        arsc.push_owned(Box::new(values.to_buffer(self.encoding)?));
+
+        for chunk in &self.chunks {
+            arsc.push_owned(Box::new(chunk.clone()));
+        }
+
+        for package in &self.packages {
+            arsc.push_owned(Box::new(self.encode_package(package, &values)?));
+        }
+
+        arsc.to_vec()
+    }
+
+    fn encode_package(&self, package: &TablePackage, values: &Pool) -> Result<PackageBuf, Error> {
+        let mut package_buf = PackageBuf::create(u32::from(package.id), package.name.clone())?;
+        let mut specs: Vec<&TableSpec> = package.specs.iter().collect();
+        specs.sort_by_key(|s| s.id);
+
+        let mut types = Pool::default();
+        let last_type = specs.last().map_or(0, |s| s.id);
+
+        for id in 1..=last_type {
+            types.push(package.get_spec_by_id(id).map_or("", |s| s.name.as_str()));
+        }
+
+        let mut keys = Pool::default();
+
+        for spec in &specs {
+            for (index, name) in spec.entry_names.iter().enumerate() {
+                if spec.is_defined(index) {
+                    keys.add(name);
+                }
+            }
+        }
+
+        package_buf.add_chunk(Box::new(types.to_buffer(self.encoding)?));
+        package_buf.add_chunk(Box::new(keys.to_buffer(self.encoding)?));
+
+        for chunk in &package.chunks {
+            package_buf.add_chunk(Box::new(chunk.clone()));
+        }
+
+        for spec in specs {
+            let mut spec_buf = TableTypeSpecBuf::new(u16::from(spec.id));
+
+            for flag in &spec.flags {
+                spec_buf.push_flag(flag.as_u32());
+            }
+
+            package_buf.add_chunk(Box::new(spec_buf));
+
+            for config in spec.configs.iter().filter(|c| !c.is_empty()) {
+                let mut type_buf = TableTypeBuf::new(spec.id, config.config.clone());
+
+                for (index, entry) in config.entries.iter().enumerate() {
+                    let index = index as u32;
+                    let encoded = match entry {
+                        Some(entry) => {
+                            let key = keys.index(&spec.entry_names[index as usize])?;
+
+                            Self::encode_entry(index, key, entry, values)?
+                        }
+                        None => Entry::Empty(index, 0),
+                    };
+
+                    type_buf.add_entry(encoded);
+                }
+
+                package_buf.add_chunk(Box::new(type_buf));
+            }
+        }
+
+        Ok(package_buf)
+    }
+
+    fn encode_entry(
+        index: u32,
+        key: u32,
+        entry: &TableEntry,
+        values: &Pool,
+    ) -> Result<Entry, Error> {
+        let encode_value = |id: u32, value: &AttributeValue| -> Result<SimpleEntry, Error> {
+            let string_index = match value {
+                AttributeValue::String(value) => Some(values.index(value)?),
+                AttributeValue::StyledString(value, spans) => {
+                    Some(values.styled_index(value, spans)?)
+                }
+                _ => None,
+            };
+            let (data_type, data) = value.to_raw(string_index);
+
+            Ok(SimpleEntry::new(id, key, data_type, data))
+        };
+
+        let encoded = match entry {
+            TableEntry::Value(value) => Entry::Simple(encode_value(index, value)?),
+            TableEntry::Bag { parent, items } => {
+                let children = items
+                    .iter()
+                    .map(|(id, value)| encode_value(id.as_u32(), value))
+                    .collect::<Result<Vec<_>, Error>>()?;
+
+                Entry::Complex(ComplexEntry::new(index, key, parent.as_u32(), children))
+            }
+        };
+
+        Ok(encoded)
+    }
+
+    /// Values of all the entries, including the items of the bags
+    fn values(&self) -> impl Iterator<Item = &AttributeValue> {
+        self.packages
+            .iter()
+            .flat_map(|p| p.specs.iter())
+            .flat_map(|s| s.configs.iter())
+            .flat_map(|c| c.entries.iter())
+            .filter_map(Option::as_ref)
+            .flat_map(|entry| match entry {
+                TableEntry::Value(value) => vec![value],
+                TableEntry::Bag { items, .. } => items.iter().map(|(_, value)| value).collect(),
+            })
+    }
+
+    fn get_mut_spec(&mut self, id: ResourceId) -> Result<&mut TableSpec, Error> {
+        let spec = self
+            .packages
+            .iter_mut()
+            .find(|p| p.id == id.get_package())
+            .and_then(|p| p.get_mut_spec_by_id(id.get_type()))
+            .ok_or_else(|| format_err!("resource {} not found", id))?;
+
+        ensure!(
+            usize::from(id.get_entry()) < spec.entry_names.len(),
+            "resource {} not found",
+            id
+        );
+
+        Ok(spec)
+    }
+}
+
+/// String pool being generated
+#[derive(Default)]
+struct Pool {
+    strings: Vec<String>,
+    indexes: HashMap<String, u32>,
+    styles: Vec<Vec<StringSpan>>,
+    styled: HashMap<(String, Vec<StringSpan>), u32>,
+}
+
+impl Pool {
+    /// Adds the styled string if it is not already on the pool. Styled strings have to be added
+    /// before any other string.
+    fn add_styled(&mut self, string: &str, spans: &[StringSpan]) {
+        let key = (string.to_string(), spans.to_vec());
+
+        if !self.styled.contains_key(&key) {
+            self.styled.insert(key, self.strings.len() as u32);
+            self.strings.push(string.to_string());
+            self.styles.push(spans.to_vec());
+        }
+    }
+
+    fn styled_index(&self, string: &str, spans: &[StringSpan]) -> Result<u32, Error> {
+        self.styled
+            .get(&(string.to_string(), spans.to_vec()))
+            .copied()
+            .ok_or_else(|| format_err!("styled string not found on the pool: {}", string))
+    }
+
+    /// Adds the string if it is not already on the pool
+    fn add(&mut self, string: &str) {
+        if !self.indexes.contains_key(string) {
+            self.push(string);
+        }
+    }
+
+    /// Adds the string at the end of the pool, even if it was already present
+    fn push(&mut self, string: &str) {
+        self.indexes
+            .entry(string.to_string())
+            .or_insert(self.strings.len() as u32);
+        self.strings.push(string.to_string());
+    }
+
+    fn index(&self, string: &str) -> Result<u32, Error> {
+        self.indexes
+            .get(string)
+            .copied()
+            .ok_or_else(|| format_err!("string not found on the pool: {}", string))
+    }
+
+    fn to_buffer(&self, encoding: Encoding) -> Result<StringTableBuf, Error> {
+        let mut buffer = StringTableBuf::default();
+        buffer.set_encoding(encoding);
+
+        for string in &self.strings {
+            buffer.add_string(string.clone());
+        }
+
+        for spans in &self.styles {
+            let spans = spans
+                .iter()
+                .map(|span| {
+                    let name = self.index(span.get_name())?;
+
+                    Ok(SpanBuf::new(
+                        name,
+                        span.get_first_char(),
+                        span.get_last_char(),
+                    ))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            buffer.add_style(spans);
+        }
+
+        Ok(buffer)
+    }
+}
+
+/// Builds the packages of a `ResourceTable` from the chunks of a `resources.arsc`
+#[derive(Default)]
+struct TableLoader {
+    values: Vec<String>,
+    styles: Vec<Vec<StringSpan>>,
+    chunks: Vec<UnknownBuf>,
+    types: Vec<String>,
+    keys: Vec<String>,
+    pools_on_package: usize,
+    packages: Vec<TablePackage>,
+    encoding: Option<Encoding>,
+}
+
+impl TableLoader {
+    fn read_strings(string_table: &StringTableWrapper) -> Result<Vec<String>, Error> {
+        (0..string_table.get_strings_len())
+            .map(|i| string_table.get_string(i).map(|s| (*s).clone()))
+            .collect()
+    }
+
+    fn get_current_package(&mut self) -> Result<&mut TablePackage, Error> {
+        self.packages
+            .last_mut()
+            .ok_or_else(|| format_err!("found a type outside of a package"))
+    }
+
+    fn load_string_table(&mut self, string_table: &StringTableWrapper) -> Result<(), Error> {
+        let strings = Self::read_strings(string_table)?;
+
+        if self.packages.is_empty() {
+            self.encoding = Some(string_table.to_buffer()?.get_encoding());
+            self.styles = (0..string_table.get_styles_len())
+                .map(|i| {
+                    string_table
+                        .get_style(i)?
+                        .iter()
+                        .map(|span| {
+                            let name = strings.get(span.get_name() as usize).ok_or_else(|| {
+                                format_err!("could not find style name {}", span.get_name())
+                            })?;
+
+                            Ok(StringSpan::new(
+                                name,
+                                span.get_first_char(),
+                                span.get_last_char(),
+                            ))
+                        })
+                        .collect::<Result<Vec<_>, Error>>()
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            self.values = strings;
+        } else {
+            match self.pools_on_package {
+                0 => self.types = strings,
+                1 => self.keys = strings,
+                _ => bail!("unexpected string table inside a package"),
+            }
+
+            self.pools_on_package += 1;
+        }
+
+        Ok(())
+    }
+
+    fn load_package(&mut self, package: &PackageWrapper) -> Result<(), Error> {
+        let id = package.get_id()?;
+        ensure!(id <= 0xFF, "invalid package id: {:X}", id);
+
+        self.packages.push(TablePackage {
+            id: id as u8,
+            name: package.get_name()?,
+            specs: Vec::new(),
+            chunks: Vec::new(),
+        });
+        self.pools_on_package = 0;
+
+        Ok(())
+    }
+
+    fn load_type_spec(&mut self, type_spec: &TypeSpecWrapper) -> Result<(), Error> {
+        let id = type_spec.get_id()? as u8;
+        let name = self
+            .types
+            .get(usize::from(id).saturating_sub(1))
+            .cloned()
+            .ok_or_else(|| format_err!("could not find the name of type {}", id))?;
+
+        let mut spec = TableSpec::new(id, &name);
+
+        for i in 0..type_spec.get_amount()? {
+            spec.flags.push(type_spec.get_spec_flags(i)?);
+        }
+
+        spec.entry_names = vec![String::new(); spec.flags.len()];
+        self.get_current_package()?.specs.push(spec);
+
+        Ok(())
+    }
+
+    fn load_table_type(&mut self, table_type: &TableTypeWrapper) -> Result<(), Error> {
+        let id = table_type.get_id()?;
+        let config = table_type.get_configuration()?.to_buffer()?;
+        let mut values = Vec::new();
+
+        for entry in table_type.get_entries()? {
+            let index = usize::from(entry.get_id().get_entry());
+            let decoded = match &entry {
+                Entry::Empty(_, _) => continue,
+                Entry::Simple(simple) => {
+                    TableEntry::Value(self.decode_value(simple.get_type(), simple.get_value())?)
+                }
+                Entry::Complex(complex) => TableEntry::Bag {
+                    parent: complex.get_parent(),
+                    items: complex
+                        .get_entries()
+                        .iter()
+                        .map(|e| {
+                            let value = self.decode_value(e.get_type(), e.get_value())?;
+
+                            Ok((e.get_id(), value))
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?,
+                },
+            };
+            let name = self
+                .keys
+                .get(entry.get_key() as usize)
+                .cloned()
+                .ok_or_else(|| format_err!("could not find key {}", entry.get_key()))?;
+
+            values.push((index, name, decoded));
+        }
+
+        let spec = self
+            .get_current_package()?
+            .get_mut_spec_by_id(id)
+            .ok_or_else(|| format_err!("type {} without type spec", id))?;
+        spec.resize(table_type.get_amount()? as usize);
+
+        for (index, name, value) in values {
+            ensure!(
+                index < spec.entry_names.len(),
+                "entry {} out of bounds on type {}",
+                index,
+                id
+            );
+
+            spec.entry_names[index] = name;
+            spec.get_mut_config(&config).entries[index] = Some(value);
+        }
+
+        Ok(())
+    }
+
+    fn decode_value(&self, data_type: u8, data: u32) -> Result<AttributeValue, Error> {
+        if data_type == TYPE_STRING {
+            let string = self
+                .values
+                .get(data as usize)
+                .cloned()
+                .ok_or_else(|| format_err!("could not find string {}", data))?;
+
+            match self.styles.get(data as usize) {
+                Some(spans) if !spans.is_empty() => {
+                    Ok(AttributeValue::StyledString(string, spans.clone()))
+                }
+                _ => Ok(AttributeValue::String(string)),
+            }
+        } else {
+            Ok(AttributeValue::from_raw(data_type, data, None))
+        }
+    }
+}
+
+impl<'a> ChunkVisitor<'a> for TableLoader {
+    fn visit_string_table(
+        &mut self,
+        string_table: StringTableWrapper<'a>,
+        _origin: Origin,
+    ) -> Result<(), Error> {
+        self.load_string_table(&string_table)
+    }
+
+    fn visit_package(&mut self, package: PackageWrapper<'a>) -> Result<(), Error> {
+        self.load_package(&package)
+    }
+
+    fn visit_table_type(&mut self, table_type: TableTypeWrapper<'a>) -> Result<(), Error> {
+        self.load_table_type(&table_type)
+    }
+
+    fn visit_type_spec(&mut self, type_spec: TypeSpecWrapper<'a>) -> Result<(), Error> {
+        self.load_type_spec(&type_spec)
+    }
+
+    fn visit_unknown(&mut self, _header: &ChunkHeader, raw: &'a [u8]) -> Result<(), Error> {
+        let chunk = UnknownBuf::new(raw.to_vec())?;
+
+        match self.packages.last_mut() {
+            Some(package) => package.chunks.push(chunk),
+            None => self.chunks.push(chunk),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigChanges, ResourceTable, TableEntry};
+    use crate::{
+        decoder::Decoder,
+        model::{
+            owned::{ConfigurationBuf, UnknownBuf},
+            AttributeValue, Library, ResourceId, Resources, StringSpan,
+        },
+    };
+
+    fn string(value: &str) -> TableEntry {
+        TableEntry::Value(AttributeValue::String(value.to_string()))
+    }
+
+    fn catalan() -> ConfigurationBuf {
+        let mut config = ConfigurationBuf::default();
+        config.set_language("ca");
+
+        config
+    }
+
+    fn example_table() -> (ResourceTable, ResourceId, ResourceId, ResourceId) {
+        let mut table = ResourceTable::default();
+        table.add_package(0x7F, "com.example").unwrap();
+
+        let app_name = table.add_resource(0x7F, "string", "app_name").unwrap();
+        let title = table.add_resource(0x7F, "string", "title").unwrap();
+        let style = table.add_resource(0x7F, "style", "AppTheme").unwrap();
+
+        let any = ConfigurationBuf::default();
+        table.set_entry(app_name, &any, string("Example")).unwrap();
+        table.set_entry(title, &any, string("Title")).unwrap();
+        table
+            .set_entry(
+                style,
+                &any,
+                TableEntry::Bag {
+                    parent: ResourceId::from(0x0103_0005),
+                    items: vec![(
+                        ResourceId::from(0x0101_0098),
+                        AttributeValue::Reference(app_name),
+                    )],
+                },
+            )
+            .unwrap();
+
+        (table, app_name, title, style)
+    }
+
+    #[test]
+    fn it_encodes_a_table_that_can_be_decoded_again() {
+        let (table, app_name, _, style) = example_table();
+        let content = table.to_vec().unwrap();
+
+        let decoded = ResourceTable::from_bytes(&content).unwrap();
+
+        assert_eq!(table, decoded);
+        assert_eq!(ResourceId::new(0x7F, 1, 0), app_name);
+        assert_eq!(ResourceId::new(0x7F, 2, 0), style);
+    }
+
+    #[test]
+    fn it_encodes_a_table_that_can_be_read_by_the_decoder() {
+        let (table, _, title, _) = example_table();
+        let content = table.to_vec().unwrap();
+
+        let decoder = Decoder::from_buffer(&content).unwrap();
+        let package = decoder.get_resources().get_package(0x7F).unwrap();
+        let entry = package.get_entry(title).unwrap();
+
+        assert_eq!(Some("com.example".to_string()), package.get_name());
+        assert_eq!(
+            "title",
+            package
+                .get_entries_string(entry.get_key())
+                .unwrap()
+                .as_str()
+        );
+    }
+
+    #[test]
+    fn it_can_add_a_new_locale_and_keep_the_ids() {
+        let (table, app_name, title, style) = example_table();
+        let mut table = ResourceTable::from_bytes(&table.to_vec().unwrap()).unwrap();
+
+        table.set_entry(title, &catalan(), string("Títol")).unwrap();
+        let subtitle = table.add_resource(0x7F, "string", "subtitle").unwrap();
+        table.remove_resource(app_name).unwrap();
+
+        let decoded = ResourceTable::from_bytes(&table.to_vec().unwrap()).unwrap();
+        let spec = decoded
+            .get_package(0x7F)
+            .unwrap()
+            .get_spec("string")
+            .unwrap();
+
+        assert_eq!(ResourceId::new(0x7F, 1, 2), subtitle);
+        assert_eq!(Some(&string("Títol")), decoded.get_entry(title, &catalan()));
+        assert_eq!(
+            Some(&string("Title")),
+            decoded.get_entry(title, &ConfigurationBuf::default())
+        );
+        assert_eq!(
+            None,
+            decoded.get_entry(app_name, &ConfigurationBuf::default())
+        );
+        assert!(decoded
+            .get_entry(style, &ConfigurationBuf::default())
+            .is_some());
+        assert_eq!(
+            ConfigChanges::LOCALE,
+            spec.get_flags()[1].get_config_changes()
+        );
+        assert_eq!(2, spec.get_configs().len());
+    }
+
+    #[test]
+    fn it_can_remove_a_whole_configuration() {
+        let (mut table, _, title, _) = example_table();
+        table.set_entry(title, &catalan(), string("Títol")).unwrap();
+
+        assert_eq!(1, table.remove_configuration(&catalan()));
+        assert_eq!(None, table.remove_entry(title, &catalan()));
+
+        let decoded = ResourceTable::from_bytes(&table.to_vec().unwrap()).unwrap();
+
+        assert_eq!(None, decoded.get_entry(title, &catalan()));
+    }
+
+    #[test]
+    fn it_does_not_set_values_of_unknown_resources() {
+        let (mut table, ..) = example_table();
+
+        assert!(table
+            .set_entry(
+                ResourceId::new(0x7F, 1, 0x10),
+                &ConfigurationBuf::default(),
+                string("x"),
+            )
+            .is_err());
+        assert!(table.add_resource(0x7F, "string", "title").is_err());
+    }
+
+    #[test]
+    fn it_does_not_decode_tables_with_problems() {
+        let (mut table, _, title, _) = example_table();
+        // String value that points out of the string pool
+        let broken = TableEntry::Value(AttributeValue::Raw(0x03, 99));
+        table
+            .set_entry(title, &ConfigurationBuf::default(), broken)
+            .unwrap();
+
+        assert!(ResourceTable::from_bytes(&table.to_vec().unwrap()).is_err());
+    }
+
+    #[test]
+    fn it_keeps_the_chunks_that_are_not_decoded() {
+        // Shared library chunk with a single entry: package id 0x7F and name "a"
+        let mut library = vec![0x03, 0x02, 0x0C, 0x00, 0x10, 0x01, 0x00, 0x00];
+        library.extend(&[0x01, 0x00, 0x00, 0x00, 0x7F, 0x00, 0x00, 0x00, 0x61]);
+        library.resize(0x110, 0);
+        let library = UnknownBuf::new(library).unwrap();
+
+        let (mut table, _, title, _) = example_table();
+        table.chunks.push(library.clone());
+        table.packages[0].chunks.push(library.clone());
+        let content = table.to_vec().unwrap();
+
+        let decoded = ResourceTable::from_bytes(&content).unwrap();
+
+        assert_eq!(table, decoded);
+        assert_eq!(&[library.clone()], decoded.get_chunks());
+        assert_eq!(&[library], decoded.get_package(0x7F).unwrap().get_chunks());
+
+        let decoder = Decoder::from_buffer(&content).unwrap();
+        assert!(decoder
+            .get_resources()
+            .get_package(0x7F)
+            .unwrap()
+            .get_entry(title)
+            .is_ok());
+    }
+
+    #[test]
+    fn it_keeps_the_style_spans_of_the_strings() {
+        let (mut table, app_name, title, _) = example_table();
+        let spans = vec![StringSpan::new("b", 0, 4), StringSpan::new("i", 6, 10)];
+        let styled = TableEntry::Value(AttributeValue::StyledString(
+            "Hello world".to_string(),
+            spans,
+        ));
+        let any = ConfigurationBuf::default();
+        table.set_entry(app_name, &any, styled.clone()).unwrap();
+        table.set_entry(title, &any, string("Hello world")).unwrap();
+        table.set_entry(title, &catalan(), string("b")).unwrap();
+
+        let decoded = ResourceTable::from_bytes(&table.to_vec().unwrap()).unwrap();
+
+        assert_eq!(table, decoded);
+        assert_eq!(Some(&styled), decoded.get_entry(app_name, &any));
+        assert_eq!(Some(&string("Hello world")), decoded.get_entry(title, &any));
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use crate::model::{ResourceId, Value};
+
+/// Attributes of a theme, including the ones inherited from its parents
+#[derive(Debug, Default)]
+pub struct Theme {
+    id: ResourceId,
+    parents: Vec<ResourceId>,
+    attributes: HashMap<ResourceId, Value>,
+}
+
+impl Theme {
+    #[must_use]
+    pub fn new(id: ResourceId) -> Self {
+        Self {
+            id,
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    pub fn get_id(&self) -> ResourceId {
+        self.id
+    }
+
+    /// Styles the theme inherits from, from the closest to the farthest one
+    #[must_use]
+    pub fn get_parents(&self) -> &[ResourceId] {
+        &self.parents
+    }
+
+    #[must_use]
+    pub fn get_attributes(&self) -> &HashMap<ResourceId, Value> {
+        &self.attributes
+    }
+
+    #[must_use]
+    pub fn get_attribute(&self, attribute: ResourceId) -> Option<&Value> {
+        self.attributes.get(&attribute)
+    }
+
+    pub(crate) fn push_parent(&mut self, parent: ResourceId) {
+        self.parents.push(parent);
+    }
+
+    /// Adds the value unless the attribute was already set by a closer style
+    pub(crate) fn inherit(&mut self, attribute: ResourceId, value: Value) {
+        self.attributes.entry(attribute).or_insert(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Theme;
+    use crate::model::{ResourceId, Value};
+
+    #[test]
+    fn it_keeps_the_values_of_the_closest_style() {
+        let attribute = ResourceId::new(0x01, 1, 0);
+        let mut theme = Theme::new(ResourceId::new(0x7F, 2, 0));
+
+        theme.inherit(attribute, Value::Boolean(true));
+        theme.inherit(attribute, Value::Boolean(false));
+
+        assert_eq!(Some(&Value::Boolean(true)), theme.get_attribute(attribute));
+        assert_eq!(1, theme.get_attributes().len());
+    }
+}
@@ -1,7 +1,10 @@
-use std::{mem, string::ToString};
+use std::string::ToString;
 
-use failure::{format_err, Error};
+use failure::Error;
 
+use crate::model::{Dimension, Fraction, ResourceId};
+
+const TOKEN_TYPE_NULL: u8 = 0x00;
 const TOKEN_TYPE_REFERENCE_ID: u8 = 0x01;
 const TOKEN_TYPE_ATTRIBUTE_REFERENCE_ID: u8 = 0x02;
 const TOKEN_TYPE_STRING: u8 = 0x03;
@@ -11,165 +14,189 @@ const TOKEN_TYPE_FRACTION: u8 = 0x06;
 const TOKEN_TYPE_DYN_REFERENCE: u8 = 0x07;
 const TOKEN_TYPE_DYN_ATTRIBUTE: u8 = 0x08;
 const TOKEN_TYPE_INTEGER: u8 = 0x10;
-const TOKEN_TYPE_FLAGS: u8 = 0x11;
+const TOKEN_TYPE_INT_HEX: u8 = 0x11;
 const TOKEN_TYPE_BOOLEAN: u8 = 0x12;
 const TOKEN_TYPE_ARGB8: u8 = 0x1C;
 const TOKEN_TYPE_RGB8: u8 = 0x1D;
 const TOKEN_TYPE_ARGB4: u8 = 0x1E;
 const TOKEN_TYPE_RGB4: u8 = 0x1F;
 
-#[derive(Debug)]
+/// Payload of a `TYPE_NULL` value that represents an explicitly empty value
+const DATA_NULL_EMPTY: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
 /// Represents a value on the binary documents. It is formed by a type and a 32 bits payload. The
 /// payloads are interpreted depending on the type.
 pub enum Value {
+    /// Undefined value (`@null`)
+    Null,
+    /// Explicitly empty value (`@empty`)
+    Empty,
     /// Represents an index on a `StringTable`
     StringReference(u32),
-    /// Represents a dimension. Bits [31..8] represents the numeric value. Bits [7..4] is an
-    /// index on a lookup table that modified the numeric value. Bits [3..0] is an index on a
-    /// dimensions lookup table
-    Dimension(String),
-    /// Represents a fraction. Bits [31..8] represents the numeric value. Bits [7..4] seems to be
-    /// unused. Bits [3..0] is an index on a units lookup table
-    Fraction(String),
+    /// Represents a string, once its index has been resolved against its `StringTable`
+    String(String),
+    /// Represents a dimension, like `16.0dip`
+    Dimension(Dimension),
+    /// Represents a fraction, like `50.0%`
+    Fraction(Fraction),
     /// Represents a float value
     Float(f32),
     /// Represents an integer value
-    Integer(u32),
-    /// Integer value that should be interpreted as a bit flag array
-    Flags(u32),
+    Integer(i32),
+    /// Integer value that was defined in hexadecimal. It is usually a bit flag array
+    IntegerHex(u32),
     /// Represents a boolean value
     Boolean(bool),
     /// Represents a ARGB8 color
-    ColorARGB8(String),
+    ColorARGB8(u32),
     /// Represents a RGB8 color
-    ColorRGB8(String),
-    /// Represents a ARGB4 color
-    ColorARGB4(String),
-    /// Represents a RGB4 color
-    ColorRGB4(String),
+    ColorRGB8(u32),
+    /// Represents a ARGB4 color. It is stored expanded to 8 bits per channel
+    ColorARGB4(u32),
+    /// Represents a RGB4 color. It is stored expanded to 8 bits per channel
+    ColorRGB4(u32),
     /// Represents a reference to an `Entry`
-    ReferenceId(u32),
+    ReferenceId(ResourceId),
     /// Represents a reference to an `Entry` on attribute context
-    AttributeReferenceId(u32),
+    AttributeReferenceId(ResourceId),
+    /// Reference to an `Entry` of a shared library, whose package id is assigned at runtime
+    DynamicReferenceId(ResourceId),
+    /// Reference to an attribute of a shared library, whose package id is assigned at runtime
+    DynamicAttributeReferenceId(ResourceId),
     /// Unknown value. It saves the type and the payload in case that needs to be checked
     Unknown(u8, u32),
 }
 
+/// Formats the values as Apktool does
 impl ToString for Value {
     fn to_string(&self) -> String {
         match self {
+            Self::Null => "@null".to_string(),
+            Self::Empty => "@empty".to_string(),
             Self::StringReference(i) => format!("@string/{}", i),
-            Self::Dimension(s)
-            | Self::Fraction(s)
-            | Self::ColorARGB8(s)
-            | Self::ColorRGB8(s)
-            | Self::ColorARGB4(s)
-            | Self::ColorRGB4(s) => s.clone(),
-            Self::Float(f) => format!("{:.*}", 1, f),
-            Self::Integer(i) | Self::Flags(i) => i.to_string(),
+            Self::String(s) => s.clone(),
+            Self::Dimension(dimension) => dimension.to_string(),
+            Self::Fraction(fraction) => fraction.to_string(),
+            Self::Float(f) => java_float(*f),
+            Self::Integer(i) => i.to_string(),
+            Self::IntegerHex(i) => format!("0x{i:x}"),
             Self::Boolean(b) => b.to_string(),
-            Self::ReferenceId(s) | Self::AttributeReferenceId(s) => format!("@id/0x{:x}", s),
-            _ => "Unknown".to_string(),
+            Self::ColorARGB8(c) => format!("#{c:08x}"),
+            Self::ColorRGB8(c) => format!("#{:06x}", c & 0x00FF_FFFF),
+            Self::ColorARGB4(c) => format!(
+                "#{:x}{:x}{:x}{:x}",
+                (c >> 28) & 0xF,
+                (c >> 20) & 0xF,
+                (c >> 12) & 0xF,
+                (c >> 4) & 0xF
+            ),
+            Self::ColorRGB4(c) => {
+                format!(
+                    "#{:x}{:x}{:x}",
+                    (c >> 20) & 0xF,
+                    (c >> 12) & 0xF,
+                    (c >> 4) & 0xF
+                )
+            }
+            Self::ReferenceId(s)
+            | Self::AttributeReferenceId(s)
+            | Self::DynamicReferenceId(s)
+            | Self::DynamicAttributeReferenceId(s) => format!("@id/0x{s:x}"),
+            Self::Unknown(_, _) => "Unknown".to_string(),
+        }
+    }
+}
+
+/// Formats the float as Java's `Float.toString`, which Apktool uses: decimal notation between
+/// `10^-3` and `10^7` and computerized scientific notation (`1.0E20`) otherwise
+fn java_float(float: f32) -> String {
+    if float.is_nan() {
+        return "NaN".to_string();
+    }
+    if float.is_infinite() {
+        return if float > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+
+    let magnitude = float.abs();
+    if magnitude == 0.0 || (1.0e-3..1.0e7).contains(&magnitude) {
+        let decimal = float.to_string();
+        return if decimal.contains('.') {
+            decimal
+        } else {
+            format!("{decimal}.0")
+        };
+    }
+
+    let scientific = format!("{float:e}");
+    match scientific.split_once('e') {
+        Some((mantissa, exponent)) if mantissa.contains('.') => {
+            format!("{mantissa}E{exponent}")
         }
+        Some((mantissa, exponent)) => format!("{mantissa}.0E{exponent}"),
+        None => scientific,
     }
 }
 
 impl Value {
     /// Creates a new `Value`. If the payload can not be interpreted by the given `value_type`, it
     /// will return an error. If the type is not know, it will return `Value::Unknown`
+    #[allow(clippy::cast_possible_wrap)]
     pub fn create(value_type: u8, data: u32) -> Result<Self, Error> {
         let value = match value_type {
-            TOKEN_TYPE_REFERENCE_ID | TOKEN_TYPE_DYN_REFERENCE => Self::ReferenceId(data),
-            TOKEN_TYPE_ATTRIBUTE_REFERENCE_ID | TOKEN_TYPE_DYN_ATTRIBUTE => {
-                Self::AttributeReferenceId(data)
-            }
+            TOKEN_TYPE_NULL if data == DATA_NULL_EMPTY => Self::Empty,
+            TOKEN_TYPE_NULL => Self::Null,
+            TOKEN_TYPE_REFERENCE_ID => Self::ReferenceId(ResourceId::from(data)),
+            TOKEN_TYPE_DYN_REFERENCE => Self::DynamicReferenceId(ResourceId::from(data)),
+            TOKEN_TYPE_ATTRIBUTE_REFERENCE_ID => Self::AttributeReferenceId(ResourceId::from(data)),
+            TOKEN_TYPE_DYN_ATTRIBUTE => Self::DynamicAttributeReferenceId(ResourceId::from(data)),
             TOKEN_TYPE_STRING => Self::StringReference(data),
-            TOKEN_TYPE_DIMENSION => {
-                let units: [&str; 6] = ["px", "dip", "sp", "pt", "in", "mm"];
-                let value = Self::complex(data);
-                let unit_idx = data & 0xF;
-
-                if let Some(unit) = units.get(unit_idx as usize) {
-                    let formatted = format!("{:.*}{}", 1, value, unit);
-                    Self::Dimension(formatted)
-                } else {
-                    return Err(format_err!(
-                        "expected a valid unit index, got: {}",
-                        unit_idx
-                    ));
-                }
-            }
-            TOKEN_TYPE_FRACTION => {
-                let units: [&str; 2] = ["%", "%p"];
-                let unit_idx = (data & 0xF) as usize;
-                let final_value = Self::complex(data) * 100.0;
-
-                if let Some(unit) = units.get(unit_idx) {
-                    let integer = final_value.round();
-                    let diff = final_value - integer;
-                    let formatted_fraction = if diff > 0.0000001 {
-                        format!("{:.*}{}", 6, final_value, unit)
-                    } else {
-                        format!("{:.*}{}", 1, final_value, unit)
-                    };
-
-                    Self::Fraction(formatted_fraction)
-                } else {
-                    return Err(format_err!(
-                        "expected a valid unit index, got: {}",
-                        unit_idx
-                    ));
-                }
-            }
-            TOKEN_TYPE_INTEGER => {
-                // TODO: Should we transmute to signed integer?
-                Self::Integer(data)
-            }
-            TOKEN_TYPE_FLAGS => Self::Flags(data),
+            TOKEN_TYPE_DIMENSION => Self::Dimension(Dimension::from_complex(data)?),
+            TOKEN_TYPE_FRACTION => Self::Fraction(Fraction::from_complex(data)?),
+            TOKEN_TYPE_INTEGER => Self::Integer(data as i32),
+            TOKEN_TYPE_INT_HEX => Self::IntegerHex(data),
             TOKEN_TYPE_FLOAT => Self::Float(f32::from_bits(data)),
             TOKEN_TYPE_BOOLEAN => Self::Boolean(data > 0),
-            TOKEN_TYPE_ARGB8 => {
-                let formatted_color = format!("#{:08x}", data);
-                Self::ColorARGB8(formatted_color)
-            }
-            TOKEN_TYPE_RGB8 => {
-                let formatted_color = format!("#{:08x}", data);
-                Self::ColorRGB8(formatted_color)
-            }
-            TOKEN_TYPE_ARGB4 => {
-                let formatted_color = format!("#{:08x}", data);
-                Self::ColorARGB4(formatted_color)
-            }
-            TOKEN_TYPE_RGB4 => {
-                let formatted_color = format!("#{:08x}", data);
-                Self::ColorRGB4(formatted_color)
-            }
+            TOKEN_TYPE_ARGB8 => Self::ColorARGB8(data),
+            TOKEN_TYPE_RGB8 => Self::ColorRGB8(data),
+            TOKEN_TYPE_ARGB4 => Self::ColorARGB4(data),
+            TOKEN_TYPE_RGB4 => Self::ColorRGB4(data),
             _ => Self::Unknown(value_type, data),
         };
 
         Ok(value)
     }
 
-    // TODO: maybe remove the unsafe code.
-    #[allow(unsafe_code)]
-    fn complex(data: u32) -> f32 {
-        // TODO: Clean this mess
-        let mantissa = 0xffffff << 8;
-        let u_value = data & mantissa;
-        let i_value: i32 = unsafe { mem::transmute(u_value) };
-        let m = i_value as f32;
-        let mm = 1.0 / ((1 << 8) as f32);
-
-        let radix = [
-            1.0 * mm,
-            1.0 / ((1 << 7) as f32) * mm,
-            1.0 / ((1 << 15) as f32) * mm,
-            1.0 / ((1 << 23) as f32) * mm,
-        ];
-
-        let idx = (data >> 4) & 0x3;
+    /// Returns the type and the payload that represent this value on the binary files. Resolved
+    /// strings can not be converted back to their index, so they return `None`. `true` is
+    /// always encoded as `0xFFFFFFFF` and dimensions and fractions are encoded with the radix
+    /// that keeps the most precision, as aapt does.
+    #[allow(clippy::cast_sign_loss)]
+    #[must_use]
+    pub fn to_raw(&self) -> Option<(u8, u32)> {
+        let raw = match self {
+            Self::Null => (TOKEN_TYPE_NULL, 0),
+            Self::Empty => (TOKEN_TYPE_NULL, DATA_NULL_EMPTY),
+            Self::StringReference(index) => (TOKEN_TYPE_STRING, *index),
+            Self::String(_) => return None,
+            Self::Dimension(dimension) => (TOKEN_TYPE_DIMENSION, dimension.to_complex()),
+            Self::Fraction(fraction) => (TOKEN_TYPE_FRACTION, fraction.to_complex()),
+            Self::Float(float) => (TOKEN_TYPE_FLOAT, float.to_bits()),
+            Self::Integer(integer) => (TOKEN_TYPE_INTEGER, *integer as u32),
+            Self::IntegerHex(integer) => (TOKEN_TYPE_INT_HEX, *integer),
+            Self::Boolean(boolean) => (TOKEN_TYPE_BOOLEAN, if *boolean { 0xFFFF_FFFF } else { 0 }),
+            Self::ColorARGB8(color) => (TOKEN_TYPE_ARGB8, *color),
+            Self::ColorRGB8(color) => (TOKEN_TYPE_RGB8, *color),
+            Self::ColorARGB4(color) => (TOKEN_TYPE_ARGB4, *color),
+            Self::ColorRGB4(color) => (TOKEN_TYPE_RGB4, *color),
+            Self::ReferenceId(id) => (TOKEN_TYPE_REFERENCE_ID, id.as_u32()),
+            Self::AttributeReferenceId(id) => (TOKEN_TYPE_ATTRIBUTE_REFERENCE_ID, id.as_u32()),
+            Self::DynamicReferenceId(id) => (TOKEN_TYPE_DYN_REFERENCE, id.as_u32()),
+            Self::DynamicAttributeReferenceId(id) => (TOKEN_TYPE_DYN_ATTRIBUTE, id.as_u32()),
+            Self::Unknown(value_type, data) => (*value_type, *data),
+        };
 
-        m * radix[idx as usize]
+        Some(raw)
     }
 }
 
@@ -178,9 +205,9 @@ mod tests {
     use super::{
         ToString, Value, TOKEN_TYPE_ARGB4, TOKEN_TYPE_ARGB8, TOKEN_TYPE_ATTRIBUTE_REFERENCE_ID,
         TOKEN_TYPE_BOOLEAN, TOKEN_TYPE_DIMENSION, TOKEN_TYPE_DYN_ATTRIBUTE,
-        TOKEN_TYPE_DYN_REFERENCE, TOKEN_TYPE_FLAGS, TOKEN_TYPE_FLOAT, TOKEN_TYPE_FRACTION,
-        TOKEN_TYPE_INTEGER, TOKEN_TYPE_REFERENCE_ID, TOKEN_TYPE_RGB4, TOKEN_TYPE_RGB8,
-        TOKEN_TYPE_STRING,
+        TOKEN_TYPE_DYN_REFERENCE, TOKEN_TYPE_FLOAT, TOKEN_TYPE_FRACTION, TOKEN_TYPE_INTEGER,
+        TOKEN_TYPE_INT_HEX, TOKEN_TYPE_NULL, TOKEN_TYPE_REFERENCE_ID, TOKEN_TYPE_RGB4,
+        TOKEN_TYPE_RGB8, TOKEN_TYPE_STRING,
     };
 
     #[test]
@@ -260,7 +287,7 @@ mod tests {
         let value = Value::create(TOKEN_TYPE_FRACTION, dim | units);
         let str_value = value.unwrap().to_string();
 
-        assert_eq!("-25599.988281%", str_value);
+        assert_eq!("-25599.988%", str_value);
     }
 
     #[test]
@@ -284,12 +311,19 @@ mod tests {
     }
 
     #[test]
-    fn it_can_generate_flag_values() {
+    fn it_can_generate_negative_integer_values() {
+        let value = Value::create(TOKEN_TYPE_INTEGER, 0xFFFF_FFFE);
+
+        assert_eq!("-2", value.unwrap().to_string());
+    }
+
+    #[test]
+    fn it_can_generate_hex_integer_values() {
         let int = 12345;
 
-        let value = Value::create(TOKEN_TYPE_FLAGS, int);
+        let value = Value::create(TOKEN_TYPE_INT_HEX, int);
 
-        assert_eq!("12345", value.unwrap().to_string());
+        assert_eq!("0x3039", value.unwrap().to_string());
     }
 
     #[test]
@@ -300,6 +334,40 @@ mod tests {
         let value = Value::create(TOKEN_TYPE_FLOAT, float);
 
         assert_eq!("0.0", value.unwrap().to_string());
+
+        let value = Value::create(TOKEN_TYPE_FLOAT, 0.1_f32.to_bits());
+
+        assert_eq!("0.1", value.unwrap().to_string());
+    }
+
+    #[test]
+    fn it_formats_floats_as_java_does() {
+        let cases = [
+            (1.0, "1.0"),
+            (-2.5, "-2.5"),
+            (0.001, "0.001"),
+            (1_234_567.0, "1234567.0"),
+            (1.0e7, "1.0E7"),
+            (1.0e20, "1.0E20"),
+            (1.5e-5, "1.5E-5"),
+            (-0.0, "-0.0"),
+            (f32::INFINITY, "Infinity"),
+            (f32::NEG_INFINITY, "-Infinity"),
+            (f32::NAN, "NaN"),
+        ];
+
+        for (float, expected) in &cases {
+            assert_eq!(*expected, Value::Float(*float).to_string());
+        }
+    }
+
+    #[test]
+    fn it_can_generate_null_and_empty_values() {
+        let null = Value::create(TOKEN_TYPE_NULL, 0).unwrap();
+        let empty = Value::create(TOKEN_TYPE_NULL, 1).unwrap();
+
+        assert_eq!("@null", null.to_string());
+        assert_eq!("@empty", empty.to_string());
     }
 
     #[test]
@@ -335,7 +403,7 @@ mod tests {
 
         let value = Value::create(TOKEN_TYPE_RGB8, data);
 
-        assert_eq!("#01ab23fe", value.unwrap().to_string());
+        assert_eq!("#ab23fe", value.unwrap().to_string());
     }
 
     #[test]
@@ -344,7 +412,7 @@ mod tests {
 
         let value = Value::create(TOKEN_TYPE_ARGB4, data);
 
-        assert_eq!("#01ab23fe", value.unwrap().to_string());
+        assert_eq!("#0a2f", value.unwrap().to_string());
     }
 
     #[test]
@@ -353,7 +421,7 @@ mod tests {
 
         let value = Value::create(TOKEN_TYPE_RGB4, data);
 
-        assert_eq!("#01ab23fe", value.unwrap().to_string());
+        assert_eq!("#a2f", value.unwrap().to_string());
     }
 
     #[test]
@@ -364,4 +432,24 @@ mod tests {
 
         assert_eq!("Unknown", value.unwrap().to_string());
     }
+
+    #[test]
+    fn it_keeps_the_raw_representation() {
+        let raw = [
+            (TOKEN_TYPE_NULL, 1),
+            (TOKEN_TYPE_DYN_REFERENCE, 0x0002_0001),
+            (TOKEN_TYPE_DIMENSION, 0x0000_1001),
+            (TOKEN_TYPE_INTEGER, 0xFFFF_FFFE),
+            (TOKEN_TYPE_INT_HEX, 0x10),
+            (TOKEN_TYPE_BOOLEAN, 0xFFFF_FFFF),
+            (TOKEN_TYPE_RGB4, 0xFFAA_BBCC),
+            (0x20, 0x1234),
+        ];
+
+        for (value_type, data) in raw.iter() {
+            let value = Value::create(*value_type, *data).unwrap();
+
+            assert_eq!(Some((*value_type, *data)), value.to_raw());
+        }
+    }
 }
@@ -0,0 +1,208 @@
+//! Options that change how documents are decoded
+
+use failure::Error;
+
+use crate::errors::{AbxmlError, Location};
+
+/// What to do when a problem is found while decoding a document. Exceeding one of the
+/// `DecodeLimits` always aborts the decoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Any problem aborts the decoding and it is returned as an error. Chunks with an unknown
+    /// type, or that do not belong to the document, are skipped, as Android does.
+    Strict,
+    /// Problems are collected as diagnostics and the decoding goes on: chunks and attributes that
+    /// can not be decoded are skipped and the stream stops on the first chunk that can not be read.
+    /// It is the default: the entry points without options skip what they can not decode and log
+    /// it as a warning, except the ones that decode documents to edit them, which are strict.
+    #[default]
+    Lenient,
+}
+
+/// Bounds to the work done while decoding untrusted files. When a limit is exceeded, the decoding
+/// fails with `AbxmlError::LimitExceeded`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    chunks: usize,
+    entries_per_type: u32,
+    xml_depth: usize,
+    string_length: usize,
+    entry_size: u64,
+    total_output: u64,
+}
+
+/// Limits that are far above what valid APKs need, even the framework ones
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            chunks: 1_000_000,
+            entries_per_type: 0x1_0000,
+            xml_depth: 256,
+            string_length: 1 << 20,
+            entry_size: 512 << 20,
+            total_output: 4 << 30,
+        }
+    }
+}
+
+impl DecodeLimits {
+    /// Maximum amount of chunks on a document, including the ones inside packages
+    #[must_use]
+    pub fn with_max_chunks(mut self, max_chunks: usize) -> Self {
+        self.chunks = max_chunks;
+        self
+    }
+
+    /// Maximum amount of entries on each type of a `resources.arsc`
+    #[must_use]
+    pub fn with_max_entries_per_type(mut self, max_entries_per_type: u32) -> Self {
+        self.entries_per_type = max_entries_per_type;
+        self
+    }
+
+    /// Maximum nesting of the elements of a binary XML
+    #[must_use]
+    pub fn with_max_xml_depth(mut self, max_xml_depth: usize) -> Self {
+        self.xml_depth = max_xml_depth;
+        self
+    }
+
+    /// Maximum length, in bytes, of the strings of a string table
+    #[must_use]
+    pub fn with_max_string_length(mut self, max_string_length: usize) -> Self {
+        self.string_length = max_string_length;
+        self
+    }
+
+    /// Maximum decompressed size of each file of an APK
+    #[must_use]
+    pub fn with_max_entry_size(mut self, max_entry_size: u64) -> Self {
+        self.entry_size = max_entry_size;
+        self
+    }
+
+    /// Maximum amount of bytes written when an APK is exported
+    #[must_use]
+    pub fn with_max_total_output(mut self, max_total_output: u64) -> Self {
+        self.total_output = max_total_output;
+        self
+    }
+
+    #[must_use]
+    pub fn get_max_chunks(self) -> usize {
+        self.chunks
+    }
+
+    #[must_use]
+    pub fn get_max_entries_per_type(self) -> u32 {
+        self.entries_per_type
+    }
+
+    #[must_use]
+    pub fn get_max_xml_depth(self) -> usize {
+        self.xml_depth
+    }
+
+    #[must_use]
+    pub fn get_max_string_length(self) -> usize {
+        self.string_length
+    }
+
+    #[must_use]
+    pub fn get_max_entry_size(self) -> u64 {
+        self.entry_size
+    }
+
+    #[must_use]
+    pub fn get_max_total_output(self) -> u64 {
+        self.total_output
+    }
+}
+
+/// Fails if `value` is above `max`
+pub(crate) fn check_limit(
+    limit: &'static str,
+    value: u64,
+    max: u64,
+    location: Location,
+) -> Result<(), AbxmlError> {
+    if value > max {
+        return Err(AbxmlError::LimitExceeded {
+            location,
+            limit,
+            max,
+            found: value,
+        });
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    mode: ParseMode,
+    limits: DecodeLimits,
+}
+
+impl ParseOptions {
+    #[must_use]
+    pub fn new(mode: ParseMode) -> Self {
+        Self {
+            mode,
+            limits: DecodeLimits::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    #[must_use]
+    pub fn strict() -> Self {
+        Self::new(ParseMode::Strict)
+    }
+
+    #[must_use]
+    pub fn lenient() -> Self {
+        Self::new(ParseMode::Lenient)
+    }
+
+    #[must_use]
+    pub fn get_mode(self) -> ParseMode {
+        self.mode
+    }
+
+    #[must_use]
+    pub fn is_lenient(self) -> bool {
+        self.mode == ParseMode::Lenient
+    }
+
+    #[must_use]
+    pub fn get_limits(self) -> DecodeLimits {
+        self.limits
+    }
+
+    /// If the decoding can go on after the given error: only on lenient mode, and never after
+    /// exceeding a limit
+    pub(crate) fn is_recoverable(self, error: &AbxmlError) -> bool {
+        self.is_lenient() && !matches!(error, AbxmlError::LimitExceeded { .. })
+    }
+
+    /// Returns the error if it is not recoverable. Otherwise, it is stored on `diagnostics` so the
+    /// caller can recover from it.
+    pub(crate) fn recover(
+        self,
+        error: AbxmlError,
+        diagnostics: &mut Vec<AbxmlError>,
+    ) -> Result<(), Error> {
+        if self.is_recoverable(&error) {
+            diagnostics.push(error);
+
+            Ok(())
+        } else {
+            Err(error.into())
+        }
+    }
+}
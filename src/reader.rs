@@ -0,0 +1,453 @@
+//! Pull parser that iterates over the contents of a binary XML as a stream of events
+
+use std::{io::Cursor, iter::FusedIterator};
+
+use failure::{format_err, Error, ResultExt};
+use xml::{attribute::OwnedAttribute, name::OwnedName};
+
+use crate::{
+    chunks::{
+        skip_document_header, Chunk, ChunkLoaderStream, StringTableCache, StringTableWrapper,
+        XmlNamespaceEndWrapper, XmlNamespaceStartWrapper, XmlTagEndWrapper, XmlTagStartWrapper,
+        XmlTextWrapper, TOKEN_XML,
+    },
+    errors::{locate, AbxmlError, ChunkContext, Diagnostic, Location},
+    model::{
+        AttributeTrait, NamespaceEnd, NamespaceStart, Namespaces, StringTable, TagEnd, TagStart,
+    },
+    options::{check_limit, ParseOptions},
+    visitor::{AttributeHelper, Resources},
+};
+
+/// Event emitted by `BinaryXmlReader`. It mimics the events exposed by `xml::reader::XmlEvent`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlEvent {
+    /// A namespace declaration starts. It remains in scope until the matching `EndNamespace`
+    StartNamespace {
+        prefix: String,
+        uri: String,
+        line: u32,
+    },
+    /// A namespace declaration goes out of scope
+    EndNamespace {
+        prefix: String,
+        uri: String,
+        line: u32,
+    },
+    /// Opening of a XML element
+    StartElement {
+        name: OwnedName,
+        /// Namespaces in scope for this element (URI to prefix)
+        namespace: Namespaces,
+        /// Attributes with their values already formatted as they would appear on a text XML
+        attributes: Vec<OwnedAttribute>,
+        line: u32,
+    },
+    /// Closing of a XML element
+    EndElement { name: OwnedName, line: u32 },
+    /// Text content
+    Characters { text: String, line: u32 },
+}
+
+/// Iterator over the events of a binary XML document. Contrary to `XmlVisitor`, it does not
+/// build any tree: each chunk is decoded when the next event is requested.
+#[derive(Debug)]
+pub struct BinaryXmlReader<'a> {
+    stream: ChunkLoaderStream<'a>,
+    resources: &'a Resources<'a>,
+    string_table: Option<StringTableCache<StringTableWrapper<'a>>>,
+    namespaces: Namespaces,
+    res: Vec<u32>,
+    /// Amount of elements that are open
+    depth: usize,
+    options: ParseOptions,
+    diagnostics: Vec<Diagnostic>,
+    /// Whether the end of the document or an error was reached
+    finished: bool,
+}
+
+impl<'a> BinaryXmlReader<'a> {
+    /// Creates a new reader over the given binary XML contents. References found on attributes
+    /// will be resolved against `resources`.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the buffer does not start with a binary XML header.
+    pub fn new(buffer: &'a [u8], resources: &'a Resources<'a>) -> Result<Self, Error> {
+        Self::with_options(buffer, resources, ParseOptions::default())
+    }
+
+    /// Creates a reader that handles the problems found on the document as `options` says. On
+    /// lenient mode, attributes that can not be resolved keep their raw value and the problems
+    /// are available on `get_diagnostics`.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the buffer does not start with a binary XML header.
+    pub fn with_options(
+        buffer: &'a [u8],
+        resources: &'a Resources<'a>,
+        options: ParseOptions,
+    ) -> Result<Self, Error> {
+        let mut cursor = Cursor::new(buffer);
+        skip_document_header(&mut cursor, TOKEN_XML)?;
+
+        Ok(Self {
+            stream: ChunkLoaderStream::with_limits(cursor, options.get_limits()),
+            resources,
+            string_table: None,
+            namespaces: Namespaces::default(),
+            res: Vec::new(),
+            depth: 0,
+            options,
+            diagnostics: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Namespaces currently in scope (URI to prefix)
+    pub fn get_namespaces(&self) -> &Namespaces {
+        &self.namespaces
+    }
+
+    /// Problems skipped on lenient mode on the events read so far
+    pub fn get_diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    fn get_string_table(&self) -> Result<&StringTableCache<StringTableWrapper<'a>>, Error> {
+        self.string_table
+            .as_ref()
+            .ok_or_else(|| format_err!("no main string table found"))
+    }
+
+    fn process(
+        &mut self,
+        chunk: Chunk<'a>,
+        diagnostics: &mut Vec<AbxmlError>,
+    ) -> Result<Option<XmlEvent>, Error> {
+        let event = match chunk {
+            Chunk::StringTable(stw) => {
+                if self.string_table.is_some() {
+                    self.options.recover(
+                        AbxmlError::Malformed {
+                            location: Location::default(),
+                            reason: "secondary string tables are not supported".to_string(),
+                        },
+                        diagnostics,
+                    )?;
+                } else {
+                    self.string_table = Some(StringTableCache::new(stw));
+                }
+
+                None
+            }
+            Chunk::Resource(rw) => {
+                self.res = rw.get_resources()?;
+
+                None
+            }
+            Chunk::XmlNamespaceStart(xnsw) => Some(self.start_namespace(&xnsw)?),
+            Chunk::XmlNamespaceEnd(xnew) => Some(self.end_namespace(&xnew)?),
+            Chunk::XmlTagStart(xtsw) => {
+                check_limit(
+                    "XML depth",
+                    self.depth as u64 + 1,
+                    self.options.get_limits().get_max_xml_depth() as u64,
+                    Location::default(),
+                )?;
+                self.depth += 1;
+
+                Some(self.start_element(&xtsw, diagnostics)?)
+            }
+            Chunk::XmlTagEnd(xtew) => {
+                self.depth = self.depth.saturating_sub(1);
+
+                Some(self.end_element(&xtew)?)
+            }
+            Chunk::XmlText(xtw) => Some(self.characters(&xtw)?),
+            _ => None,
+        };
+
+        Ok(event)
+    }
+
+    fn start_namespace(
+        &mut self,
+        namespace_start: &XmlNamespaceStartWrapper,
+    ) -> Result<XmlEvent, Error> {
+        let string_table = self.get_string_table()?;
+        let uri = (*namespace_start.get_namespace(string_table)?).clone();
+        let prefix = (*namespace_start.get_prefix(string_table)?).clone();
+        let line = namespace_start.get_line()?;
+
+        self.namespaces.insert(uri.clone(), prefix.clone());
+
+        Ok(XmlEvent::StartNamespace { prefix, uri, line })
+    }
+
+    fn end_namespace(&mut self, namespace_end: &XmlNamespaceEndWrapper) -> Result<XmlEvent, Error> {
+        let string_table = self.get_string_table()?;
+        let uri = (*namespace_end.get_namespace(string_table)?).clone();
+        let prefix = (*namespace_end.get_prefix(string_table)?).clone();
+        let line = namespace_end.get_line()?;
+
+        self.namespaces.remove(&uri);
+
+        Ok(XmlEvent::EndNamespace { prefix, uri, line })
+    }
+
+    fn start_element(
+        &self,
+        tag_start: &XmlTagStartWrapper,
+        diagnostics: &mut Vec<AbxmlError>,
+    ) -> Result<XmlEvent, Error> {
+        let string_table = self.get_string_table()?;
+        let name = self
+            .build_name(
+                tag_start.get_namespace_index()?,
+                tag_start.get_element_name_index()?,
+            )
+            .context("could not read element name")?;
+
+        let num_attributes = tag_start
+            .get_attributes_amount()
+            .context("could not get the amount of attributes")?;
+        let mut attributes = Vec::new();
+
+        for i in 0..num_attributes {
+            let attribute = tag_start
+                .get_attribute(i)
+                .context(format_err!("could not read attribute {} ", i))?;
+            let name = self.build_name(attribute.get_namespace()?, attribute.get_name()?)?;
+            let value = match AttributeHelper::format_value(
+                &attribute,
+                string_table,
+                &self.res,
+                self.resources,
+            ) {
+                Ok(value) => value,
+                Err(e) => {
+                    let raw_value = attribute.get_value()?;
+
+                    if let Some(reference) = self.resources.format_missing_reference(&raw_value) {
+                        reference
+                    } else {
+                        let offset = 36 + u64::from(i) * 20;
+                        self.options.recover(locate(&e, offset, &[]), diagnostics)?;
+
+                        raw_value.to_string()
+                    }
+                }
+            };
+
+            attributes.push(OwnedAttribute::new(name, value));
+        }
+
+        Ok(XmlEvent::StartElement {
+            name,
+            namespace: self.namespaces.clone(),
+            attributes,
+            line: tag_start.get_line()?,
+        })
+    }
+
+    fn end_element(&self, tag_end: &XmlTagEndWrapper) -> Result<XmlEvent, Error> {
+        let name = self
+            .build_name(tag_end.get_namespace_index()?, tag_end.get_id()?)
+            .context("could not read element name")?;
+
+        Ok(XmlEvent::EndElement {
+            name,
+            line: tag_end.get_line()?,
+        })
+    }
+
+    fn characters(&self, text: &XmlTextWrapper) -> Result<XmlEvent, Error> {
+        let string_table = self.get_string_table()?;
+        let text_content = (*string_table.get_string(text.get_text_index()?)?).clone();
+
+        Ok(XmlEvent::Characters {
+            text: text_content,
+            line: text.get_line()?,
+        })
+    }
+
+    fn build_name(&self, namespace_index: u32, name_index: u32) -> Result<OwnedName, Error> {
+        let string_table = self.get_string_table()?;
+        let local_name = (*string_table.get_string(name_index)?).clone();
+
+        if namespace_index == 0xFFFF_FFFF {
+            return Ok(OwnedName::local(local_name));
+        }
+
+        let namespace = (*string_table.get_string(namespace_index)?).clone();
+        let prefix = self
+            .namespaces
+            .get(&namespace)
+            .cloned()
+            .ok_or_else(|| format_err!("namespace not found"))?;
+
+        Ok(OwnedName::qualified(local_name, namespace, Some(prefix)))
+    }
+}
+
+impl Iterator for BinaryXmlReader<'_> {
+    type Item = Result<XmlEvent, Error>;
+
+    /// Returns `None` after the first error: the state of the reader is not consistent anymore.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            let chunk = match self.stream.next() {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(e)) => {
+                    self.finished = true;
+                    return Some(Err(e.context("error reading next chunk").into()));
+                }
+                None => {
+                    self.finished = true;
+                    return None;
+                }
+            };
+
+            let mut diagnostics = Vec::new();
+            let result = self.process(chunk, &mut diagnostics);
+
+            if let Some(header) = self.stream.get_header() {
+                let path = [
+                    ChunkContext::new(TOKEN_XML, 0),
+                    ChunkContext::new(header.get_token(), header.get_offset()),
+                ];
+                for error in diagnostics {
+                    self.diagnostics
+                        .push(Diagnostic::new(error.located(header.get_offset(), &path)));
+                }
+            }
+
+            match result {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => (),
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+impl FusedIterator for BinaryXmlReader<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use xml::name::OwnedName;
+
+    use super::{BinaryXmlReader, XmlEvent};
+    use crate::{
+        model::{
+            builder::Xml,
+            owned::{
+                AttributeBuf, StringTableBuf, XmlNamespaceEndBuf, XmlNamespaceStartBuf,
+                XmlTagEndBuf, XmlTagStartBuf,
+            },
+        },
+        options::ParseOptions,
+        visitor::Resources,
+    };
+
+    fn example_document() -> Vec<u8> {
+        let mut xml = Xml::default();
+        let mut st = StringTableBuf::default();
+        st.add_string("android".to_string());
+        st.add_string("http://schemas.android.com/apk/res/android".to_string());
+        st.add_string("manifest".to_string());
+        st.add_string("package".to_string());
+        st.add_string("com.example".to_string());
+        st.add_string("label".to_string());
+
+        let mut tag_start = XmlTagStartBuf::new(2, 0xFFFF_FFFF, 0xFFFF_FFFF, 2, 0x0014_0014, 0);
+        tag_start.add_attribute(AttributeBuf::new(0xFFFF_FFFF, 3, 4, 3 << 24, 4));
+        tag_start.add_attribute(AttributeBuf::new(1, 5, 0xFFFF_FFFF, 0x12 << 24, 1));
+
+        xml.push_owned(Box::new(st));
+        xml.push_owned(Box::new(XmlNamespaceStartBuf::new(1, 0, 1)));
+        xml.push_owned(Box::new(tag_start));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(2)));
+        xml.push_owned(Box::new(XmlNamespaceEndBuf::new(1, 0, 1)));
+
+        xml.into_vec().unwrap()
+    }
+
+    #[test]
+    fn it_streams_the_events_of_a_document() {
+        let content = example_document();
+        let resources = Resources::default();
+        let reader = BinaryXmlReader::new(&content, &resources).unwrap();
+
+        let events: Vec<XmlEvent> = reader.map(Result::unwrap).collect();
+
+        assert_eq!(4, events.len());
+        assert_eq!(
+            XmlEvent::StartNamespace {
+                prefix: "android".to_string(),
+                uri: "http://schemas.android.com/apk/res/android".to_string(),
+                line: 1,
+            },
+            events[0]
+        );
+
+        match &events[1] {
+            XmlEvent::StartElement {
+                name,
+                namespace,
+                attributes,
+                line,
+            } => {
+                assert_eq!(&OwnedName::local("manifest"), name);
+                assert_eq!(2, *line);
+                assert_eq!(1, namespace.len());
+                assert_eq!(2, attributes.len());
+                assert_eq!("package", attributes[0].name.local_name);
+                assert_eq!("com.example", attributes[0].value);
+                assert_eq!(Some("android".to_string()), attributes[1].name.prefix);
+                assert_eq!("label", attributes[1].name.local_name);
+                assert_eq!("true", attributes[1].value);
+            }
+            _ => panic!("Second event should be a start element"),
+        }
+
+        match &events[2] {
+            XmlEvent::EndElement { name, .. } => assert_eq!("manifest", name.local_name),
+            _ => panic!("Third event should be an end element"),
+        }
+    }
+
+    #[test]
+    fn it_stops_after_the_first_error() {
+        let mut content = example_document();
+        let end_element = content.len() - 24 - 24;
+        // Name of the end element out of the string table
+        content[end_element + 20..end_element + 24].copy_from_slice(&[0xFF, 0, 0, 0]);
+        let resources = Resources::default();
+        let mut reader =
+            BinaryXmlReader::with_options(&content, &resources, ParseOptions::strict()).unwrap();
+
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn it_does_not_create_a_reader_for_non_xml_documents() {
+        let resources = Resources::default();
+        let result = BinaryXmlReader::new(&[2, 0, 12, 0, 0, 0, 0, 0], &resources);
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,495 @@
+//! Generators of the resource id constants of a package: `R.java`, `R.kt`, `R.txt` and
+//! `public.xml`
+
+use std::fmt::Write;
+
+use failure::{bail, format_err, Error, ResultExt};
+use xml::writer::{EmitterConfig, XmlEvent};
+
+use crate::{
+    model::{owned::ConfigurationBuf, BagValue, Library, ResourceId, Resources as ResourcesTrait},
+    visitor::model::Resources,
+};
+
+const STYLEABLE: &str = "styleable";
+
+const HEADER: &str = "/* AUTO-GENERATED FILE. DO NOT MODIFY.
+ *
+ * This class was generated from the resource data of the package. It should not be modified by
+ * hand.
+ */";
+
+/// Resource with the name it has on the `R` classes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    name: String,
+    entry: String,
+    id: ResourceId,
+}
+
+impl Symbol {
+    fn new(entry: &str, id: ResourceId) -> Self {
+        Self {
+            name: identifier(entry),
+            entry: entry.to_string(),
+            id,
+        }
+    }
+
+    /// Name of the constant (`Theme_App`)
+    #[must_use]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Name of the resource (`Theme.App`)
+    #[must_use]
+    pub fn get_entry(&self) -> &str {
+        &self.entry
+    }
+
+    #[must_use]
+    pub fn get_id(&self) -> ResourceId {
+        self.id
+    }
+}
+
+/// Styleable, which is an array of attribute ids. Each of its attributes also has a constant with
+/// its index on the array, named `Styleable_attribute`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Styleable {
+    name: String,
+    attributes: Vec<Symbol>,
+}
+
+impl Styleable {
+    #[must_use]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Attributes sorted by id, as aapt does
+    #[must_use]
+    pub fn get_attributes(&self) -> &[Symbol] {
+        &self.attributes
+    }
+}
+
+/// Resource ids of a package grouped by type, sorted by id
+#[derive(Debug, Clone, Default)]
+pub struct Symbols {
+    package: String,
+    types: Vec<(String, Vec<Symbol>)>,
+    styleables: Vec<Styleable>,
+}
+
+impl Symbols {
+    /// Collects the resources of the package with the given id. Names are converted to valid
+    /// identifiers (`Theme.App` becomes `Theme_App`).
+    ///
+    /// # Errors
+    ///
+    /// It fails if there is no package with the given id or the names of its resources can not be
+    /// read.
+    pub fn from_resources(resources: &Resources, package_id: u8) -> Result<Self, Error> {
+        let package = resources
+            .get_package(package_id)
+            .and_then(Library::get_name)
+            .ok_or_else(|| format_err!("could not find package {}", package_id))?;
+        let mut names: Vec<_> = resources
+            .get_names()
+            .filter(|(id, _)| id.get_package() == package_id)
+            .collect();
+        names.sort_by_key(|(id, _)| *id);
+
+        let mut symbols = Self {
+            package,
+            ..Self::default()
+        };
+
+        for (id, name) in names {
+            if name.get_type() == STYLEABLE {
+                let styleable = symbols
+                    .get_styleable(resources, id, name.get_entry())
+                    .context(format_err!("could not read the styleable {}", name))?;
+                symbols.styleables.push(styleable);
+                continue;
+            }
+
+            let symbol = Symbol::new(name.get_entry(), id);
+
+            match symbols
+                .types
+                .iter_mut()
+                .find(|(type_name, _)| type_name == name.get_type())
+            {
+                Some((_, type_symbols)) => type_symbols.push(symbol),
+                None => symbols
+                    .types
+                    .push((name.get_type().to_string(), vec![symbol])),
+            }
+        }
+
+        Ok(symbols)
+    }
+
+    /// Name of the package, used as the package of the `R` classes
+    #[must_use]
+    pub fn get_package(&self) -> &str {
+        &self.package
+    }
+
+    /// Types and their resources, sorted by id. Styleables are not included.
+    #[must_use]
+    pub fn get_types(&self) -> &[(String, Vec<Symbol>)] {
+        &self.types
+    }
+
+    #[must_use]
+    pub fn get_styleables(&self) -> &[Styleable] {
+        &self.styleables
+    }
+
+    /// Generates the `R.java` class with the ids of the resources
+    ///
+    /// # Errors
+    ///
+    /// It fails if a styleable refers to attributes whose names are not known.
+    pub fn to_r_java(&self) -> Result<String, Error> {
+        let mut out = String::new();
+        writeln!(out, "{HEADER}\n")?;
+        writeln!(out, "package {};\n", self.package)?;
+        writeln!(out, "public final class R {{")?;
+
+        for (type_name, symbols) in &self.types {
+            writeln!(out, "    public static final class {type_name} {{")?;
+
+            for symbol in symbols {
+                writeln!(
+                    out,
+                    "        public static final int {}=0x{:08x};",
+                    symbol.name,
+                    symbol.id.as_u32()
+                )?;
+            }
+
+            writeln!(out, "    }}")?;
+        }
+
+        if !self.styleables.is_empty() {
+            writeln!(out, "    public static final class {STYLEABLE} {{")?;
+
+            for styleable in &self.styleables {
+                writeln!(
+                    out,
+                    "        public static final int[] {}={{\n            {}\n        }};",
+                    styleable.name,
+                    styleable.format_ids()
+                )?;
+
+                for (index, attribute) in styleable.attributes.iter().enumerate() {
+                    writeln!(
+                        out,
+                        "        public static final int {}_{}={};",
+                        styleable.name, attribute.name, index
+                    )?;
+                }
+            }
+
+            writeln!(out, "    }}")?;
+        }
+
+        writeln!(out, "}}")?;
+
+        Ok(out)
+    }
+
+    /// Generates the `R.kt` object with the ids of the resources
+    ///
+    /// # Errors
+    ///
+    /// It fails if a styleable refers to attributes whose names are not known.
+    pub fn to_r_kotlin(&self) -> Result<String, Error> {
+        let mut out = String::new();
+        writeln!(out, "{HEADER}\n")?;
+        writeln!(out, "package {}\n", self.package)?;
+        writeln!(out, "object R {{")?;
+
+        for (type_name, symbols) in &self.types {
+            writeln!(out, "    object {type_name} {{")?;
+
+            for symbol in symbols {
+                writeln!(
+                    out,
+                    "        const val {}: Int = 0x{:08x}",
+                    symbol.name,
+                    symbol.id.as_u32()
+                )?;
+            }
+
+            writeln!(out, "    }}")?;
+        }
+
+        if !self.styleables.is_empty() {
+            writeln!(out, "    object {STYLEABLE} {{")?;
+
+            for styleable in &self.styleables {
+                writeln!(
+                    out,
+                    "        val {}: IntArray = intArrayOf({})",
+                    styleable.name,
+                    styleable.format_ids()
+                )?;
+
+                for (index, attribute) in styleable.attributes.iter().enumerate() {
+                    writeln!(
+                        out,
+                        "        const val {}_{}: Int = {}",
+                        styleable.name, attribute.name, index
+                    )?;
+                }
+            }
+
+            writeln!(out, "    }}")?;
+        }
+
+        writeln!(out, "}}")?;
+
+        Ok(out)
+    }
+
+    /// Generates the symbol file (`R.txt`) that aapt emits for libraries
+    ///
+    /// # Errors
+    ///
+    /// It fails if a styleable refers to attributes whose names are not known.
+    pub fn to_r_txt(&self) -> Result<String, Error> {
+        let mut out = String::new();
+
+        for (type_name, symbols) in &self.types {
+            for symbol in symbols {
+                writeln!(
+                    out,
+                    "int {} {} 0x{:08x}",
+                    type_name,
+                    symbol.name,
+                    symbol.id.as_u32()
+                )?;
+            }
+        }
+
+        for styleable in &self.styleables {
+            writeln!(
+                out,
+                "int[] {} {} {{ {} }}",
+                STYLEABLE,
+                styleable.name,
+                styleable.format_ids()
+            )?;
+
+            for (index, attribute) in styleable.attributes.iter().enumerate() {
+                writeln!(
+                    out,
+                    "int {} {}_{} {}",
+                    STYLEABLE, styleable.name, attribute.name, index
+                )?;
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Generates a `public.xml` that keeps the ids of all the resources when the package is
+    /// built again
+    ///
+    /// # Errors
+    ///
+    /// It fails if the XML can not be written.
+    pub fn to_public_xml(&self) -> Result<String, Error> {
+        let mut writer = EmitterConfig::new()
+            .perform_indent(true)
+            .create_writer(Vec::new());
+
+        writer.write(XmlEvent::start_element("resources"))?;
+
+        for (type_name, symbols) in &self.types {
+            for symbol in symbols {
+                let id = format!("0x{:08x}", symbol.id.as_u32());
+                writer.write(
+                    XmlEvent::start_element("public")
+                        .attr("type", type_name)
+                        .attr("name", &symbol.entry)
+                        .attr("id", &id),
+                )?;
+                writer.write(XmlEvent::end_element())?;
+            }
+        }
+
+        writer.write(XmlEvent::end_element())?;
+
+        Ok(String::from_utf8(writer.into_inner()).context("could not export public.xml")?)
+    }
+
+    fn get_styleable(
+        &self,
+        resources: &Resources,
+        id: ResourceId,
+        name: &str,
+    ) -> Result<Styleable, Error> {
+        let mut attribute_ids = match resources.get_bag(id, &ConfigurationBuf::default())? {
+            BagValue::Styleable(styleable) => styleable.get_attributes().to_vec(),
+            _ => bail!("{} is not a styleable", id),
+        };
+        attribute_ids.sort();
+
+        let mut attributes = Vec::new();
+        for attribute in attribute_ids {
+            let attribute_name = resources
+                .name_of(attribute)
+                .ok_or_else(|| format_err!("could not find the name of {}", attribute))?;
+
+            // Attributes of other packages are prefixed with the package name
+            let name = match attribute_name.get_package() {
+                Some(package) if package != self.package => {
+                    format!("{}_{}", package, attribute_name.get_entry())
+                }
+                _ => attribute_name.get_entry().to_string(),
+            };
+
+            attributes.push(Symbol::new(&name, attribute));
+        }
+
+        Ok(Styleable {
+            name: identifier(name),
+            attributes,
+        })
+    }
+}
+
+impl Styleable {
+    fn format_ids(&self) -> String {
+        self.attributes
+            .iter()
+            .map(|attribute| format!("0x{:08x}", attribute.id.as_u32()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Replaces the characters that are not valid on Java and Kotlin identifiers
+fn identifier(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Symbols;
+    use crate::{
+        model::{owned::ConfigurationBuf, AttributeValue, ResourceId, ResourceTable, TableEntry},
+        visitor::{Executor, ModelVisitor},
+    };
+
+    fn symbols() -> Symbols {
+        let default = ConfigurationBuf::default();
+        let empty_bag = || TableEntry::Bag {
+            parent: ResourceId::default(),
+            items: Vec::new(),
+        };
+
+        let mut android = ResourceTable::default();
+        android.add_package(0x01, "android").unwrap();
+        let text_color = android.add_resource(0x01, "attr", "textColor").unwrap();
+        android
+            .set_entry(text_color, &default, empty_bag())
+            .unwrap();
+
+        let mut app = ResourceTable::default();
+        app.add_package(0x7F, "com.example").unwrap();
+        let title = app.add_resource(0x7F, "string", "title").unwrap();
+        let theme = app.add_resource(0x7F, "style", "AppTheme.Dark").unwrap();
+        let accent = app.add_resource(0x7F, "attr", "accent").unwrap();
+        let view = app.add_resource(0x7F, "styleable", "MyView").unwrap();
+        let title_value = AttributeValue::String("Title".to_string());
+        app.set_entry(title, &default, TableEntry::Value(title_value))
+            .unwrap();
+        app.set_entry(theme, &default, empty_bag()).unwrap();
+        app.set_entry(accent, &default, empty_bag()).unwrap();
+        let items = vec![
+            (accent, AttributeValue::Integer(0)),
+            (text_color, AttributeValue::Integer(0)),
+        ];
+        let styleable = TableEntry::Bag {
+            parent: ResourceId::default(),
+            items,
+        };
+        app.set_entry(view, &default, styleable).unwrap();
+
+        let (android, app) = (android.to_vec().unwrap(), app.to_vec().unwrap());
+        let mut visitor = ModelVisitor::default();
+        Executor::arsc(&android, &mut visitor).unwrap();
+        Executor::arsc(&app, &mut visitor).unwrap();
+
+        Symbols::from_resources(visitor.get_resources(), 0x7F).unwrap()
+    }
+
+    #[test]
+    fn it_collects_the_symbols_of_a_package() {
+        let symbols = symbols();
+        let styleable = &symbols.get_styleables()[0];
+
+        assert_eq!("com.example", symbols.get_package());
+        assert_eq!(3, symbols.get_types().len());
+        assert_eq!("AppTheme_Dark", symbols.get_types()[1].1[0].get_name());
+        assert_eq!("MyView", styleable.get_name());
+        assert_eq!(
+            "android_textColor",
+            styleable.get_attributes()[0].get_name()
+        );
+        assert_eq!("accent", styleable.get_attributes()[1].get_name());
+    }
+
+    #[test]
+    fn it_generates_r_classes() {
+        let symbols = symbols();
+        let java = symbols.to_r_java().unwrap();
+        let kotlin = symbols.to_r_kotlin().unwrap();
+
+        assert!(java.contains("package com.example;"));
+        assert!(java.contains("        public static final int title=0x7f010000;"));
+        assert!(java.contains(
+            "        public static final int[] MyView={\n            0x01010000, 0x7f030000\n"
+        ));
+        assert!(java.contains("        public static final int MyView_accent=1;"));
+        assert!(kotlin.contains("        const val AppTheme_Dark: Int = 0x7f020000"));
+        assert!(
+            kotlin.contains("        val MyView: IntArray = intArrayOf(0x01010000, 0x7f030000)")
+        );
+    }
+
+    #[test]
+    fn it_generates_symbol_files_and_public_xml() {
+        let symbols = symbols();
+        let r_txt = symbols.to_r_txt().unwrap();
+        let public = symbols.to_public_xml().unwrap();
+
+        assert_eq!(
+            "int string title 0x7f010000\n\
+             int style AppTheme_Dark 0x7f020000\n\
+             int attr accent 0x7f030000\n\
+             int[] styleable MyView { 0x01010000, 0x7f030000 }\n\
+             int styleable MyView_android_textColor 0\n\
+             int styleable MyView_accent 1\n",
+            r_txt
+        );
+        assert!(public.contains(r#"<public type="style" name="AppTheme.Dark" id="0x7f020000" />"#));
+        assert!(!public.contains("MyView"));
+    }
+}
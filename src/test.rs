@@ -24,35 +24,65 @@ impl CounterChunkVisitor {
 }
 
 impl<'a> ChunkVisitor<'a> for CounterChunkVisitor {
-    fn visit_string_table(&mut self, _string_table: StringTableWrapper<'a>, _origin: Origin) {
-        self.count += 1
+    fn visit_string_table(
+        &mut self,
+        _string_table: StringTableWrapper<'a>,
+        _origin: Origin,
+    ) -> Result<(), Error> {
+        self.count += 1;
+
+        Ok(())
     }
-    fn visit_package(&mut self, _package: PackageWrapper<'a>) {
-        self.count += 1
+    fn visit_package(&mut self, _package: PackageWrapper<'a>) -> Result<(), Error> {
+        self.count += 1;
+
+        Ok(())
     }
-    fn visit_table_type(&mut self, _table_type: TableTypeWrapper<'a>) {
-        self.count += 1
+    fn visit_table_type(&mut self, _table_type: TableTypeWrapper<'a>) -> Result<(), Error> {
+        self.count += 1;
+
+        Ok(())
     }
-    fn visit_type_spec(&mut self, _type_spec: TypeSpecWrapper<'a>) {
-        self.count += 1
+    fn visit_type_spec(&mut self, _type_spec: TypeSpecWrapper<'a>) -> Result<(), Error> {
+        self.count += 1;
+
+        Ok(())
     }
-    fn visit_xml_namespace_start(&mut self, _namespace_start: XmlNamespaceStartWrapper<'a>) {
-        self.count += 1
+    fn visit_xml_namespace_start(
+        &mut self,
+        _namespace_start: XmlNamespaceStartWrapper<'a>,
+    ) -> Result<(), Error> {
+        self.count += 1;
+
+        Ok(())
     }
-    fn visit_xml_namespace_end(&mut self, _namespace_end: XmlNamespaceEndWrapper<'a>) {
-        self.count += 1
+    fn visit_xml_namespace_end(
+        &mut self,
+        _namespace_end: XmlNamespaceEndWrapper<'a>,
+    ) -> Result<(), Error> {
+        self.count += 1;
+
+        Ok(())
     }
-    fn visit_xml_tag_start(&mut self, _tag_start: XmlTagStartWrapper<'a>) {
-        self.count += 1
+    fn visit_xml_tag_start(&mut self, _tag_start: XmlTagStartWrapper<'a>) -> Result<(), Error> {
+        self.count += 1;
+
+        Ok(())
     }
-    fn visit_xml_tag_end(&mut self, _tag_end: XmlTagEndWrapper<'a>) {
-        self.count += 1
+    fn visit_xml_tag_end(&mut self, _tag_end: XmlTagEndWrapper<'a>) -> Result<(), Error> {
+        self.count += 1;
+
+        Ok(())
     }
-    fn visit_xml_text(&mut self, _text: XmlTextWrapper<'a>) {
-        self.count += 1
+    fn visit_xml_text(&mut self, _text: XmlTextWrapper<'a>) -> Result<(), Error> {
+        self.count += 1;
+
+        Ok(())
     }
-    fn visit_resource(&mut self, _resource: ResourceWrapper<'a>) {
-        self.count += 1
+    fn visit_resource(&mut self, _resource: ResourceWrapper<'a>) -> Result<(), Error> {
+        self.count += 1;
+
+        Ok(())
     }
 }
 
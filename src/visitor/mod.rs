@@ -1,142 +1,362 @@
 //! Collection of visitors that are fed from chunk iterator
 use std::io::Cursor;
 
-use byteorder::{LittleEndian, ReadBytesExt};
-use failure::{bail, Error, ResultExt};
-use log::warn;
-
-use crate::chunks::{
-    Chunk, ChunkLoaderStream, PackageWrapper, ResourceWrapper, StringTableWrapper,
-    TableTypeWrapper, TypeSpecWrapper, XmlNamespaceEndWrapper, XmlNamespaceStartWrapper,
-    XmlTagEndWrapper, XmlTagStartWrapper, XmlTextWrapper,
+use failure::Error;
+
+use crate::{
+    chunks::{
+        skip_document_header, Chunk, ChunkHeader, ChunkLoaderStream, PackageWrapper,
+        ResourceWrapper, StringTableWrapper, TableTypeWrapper, TypeSpecWrapper,
+        XmlNamespaceEndWrapper, XmlNamespaceStartWrapper, XmlTagEndWrapper, XmlTagStartWrapper,
+        XmlTextWrapper, TOKEN_TABLE, TOKEN_XML,
+    },
+    errors::{locate, warn_skipped, AbxmlError, ChunkContext, Diagnostic, Location},
+    options::ParseOptions,
 };
 
 pub mod model;
 mod print;
 mod xml;
 
+pub(crate) use self::xml::AttributeHelper;
 pub use self::{
     model::{ModelVisitor, RefPackage, Resources},
     xml::XmlVisitor,
 };
 
+/// Receives the chunks of a document, in order. When a method returns an error, the decoding of
+/// the document stops and the error is returned located on the chunk that was visited. On lenient
+/// mode, the error is collected as a diagnostic and the next chunk is visited.
 pub trait ChunkVisitor<'a> {
-    fn visit_string_table(&mut self, _string_table: StringTableWrapper<'a>, _origin: Origin) {}
-    fn visit_package(&mut self, _package: PackageWrapper<'a>) {}
-    fn visit_table_type(&mut self, _table_type: TableTypeWrapper<'a>) {}
-    fn visit_type_spec(&mut self, _type_spec: TypeSpecWrapper<'a>) {}
-    fn visit_xml_namespace_start(&mut self, _namespace_start: XmlNamespaceStartWrapper<'a>) {}
-    fn visit_xml_namespace_end(&mut self, _namespace_end: XmlNamespaceEndWrapper<'a>) {}
-    fn visit_xml_tag_start(&mut self, _tag_start: XmlTagStartWrapper<'a>) {}
-    fn visit_xml_tag_end(&mut self, _tag_end: XmlTagEndWrapper<'a>) {}
-    fn visit_xml_text(&mut self, _text: XmlTextWrapper<'a>) {}
-    fn visit_resource(&mut self, _resource: ResourceWrapper<'a>) {}
+    /// Called for each string pool. `origin` tells if it holds values, type names or entry names.
+    ///
+    /// # Errors
+    ///
+    /// On strict mode, an error stops the decoding of the document.
+    fn visit_string_table(
+        &mut self,
+        _string_table: StringTableWrapper<'a>,
+        _origin: Origin,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called when a package starts, before its types
+    ///
+    /// # Errors
+    ///
+    /// On strict mode, an error stops the decoding of the document.
+    fn visit_package(&mut self, _package: PackageWrapper<'a>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called for the values of a type on one configuration
+    ///
+    /// # Errors
+    ///
+    /// On strict mode, an error stops the decoding of the document.
+    fn visit_table_type(&mut self, _table_type: TableTypeWrapper<'a>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called for the flags of the entries of a type
+    ///
+    /// # Errors
+    ///
+    /// On strict mode, an error stops the decoding of the document.
+    fn visit_type_spec(&mut self, _type_spec: TypeSpecWrapper<'a>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called when a namespace prefix is declared
+    ///
+    /// # Errors
+    ///
+    /// On strict mode, an error stops the decoding of the document.
+    fn visit_xml_namespace_start(
+        &mut self,
+        _namespace_start: XmlNamespaceStartWrapper<'a>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called when a namespace prefix goes out of scope
+    ///
+    /// # Errors
+    ///
+    /// On strict mode, an error stops the decoding of the document.
+    fn visit_xml_namespace_end(
+        &mut self,
+        _namespace_end: XmlNamespaceEndWrapper<'a>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called when an element is opened
+    ///
+    /// # Errors
+    ///
+    /// On strict mode, an error stops the decoding of the document.
+    fn visit_xml_tag_start(&mut self, _tag_start: XmlTagStartWrapper<'a>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called when an element is closed
+    ///
+    /// # Errors
+    ///
+    /// On strict mode, an error stops the decoding of the document.
+    fn visit_xml_tag_end(&mut self, _tag_end: XmlTagEndWrapper<'a>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called for the text content between elements
+    ///
+    /// # Errors
+    ///
+    /// On strict mode, an error stops the decoding of the document.
+    fn visit_xml_text(&mut self, _text: XmlTextWrapper<'a>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called for the map of attribute resource ids of a binary XML
+    ///
+    /// # Errors
+    ///
+    /// On strict mode, an error stops the decoding of the document.
+    fn visit_resource(&mut self, _resource: ResourceWrapper<'a>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Chunks with an unknown type, or that do not belong to the document, with all their bytes
+    ///
+    /// # Errors
+    ///
+    /// On strict mode, an error stops the decoding of the document.
+    fn visit_unknown(&mut self, _header: &ChunkHeader, _raw: &'a [u8]) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Problems the visitor recovered from while visiting the last chunk. Their locations are
+    /// relative to the start of the chunk.
+    fn take_diagnostics(&mut self) -> Vec<AbxmlError> {
+        Vec::new()
+    }
 }
 
-/// Methods to decode a binary resource.arsc file or a binary xml file
+/// Methods to decode a binary resource.arsc file or a binary xml file. Errors are returned as
+/// `AbxmlError`s located on the chunk that could not be decoded.
 #[derive(Debug, Copy, Clone)]
 pub struct Executor;
 
 impl Executor {
     /// Given a valid `resources.arsc` file contents, it will call to the proper methods on the
-    /// given visitor.
+    /// given visitor. The problems skipped on lenient mode are logged as warnings.
     pub fn arsc<'a, V: ChunkVisitor<'a>>(buffer: &'a [u8], visitor: &mut V) -> Result<(), Error> {
+        let diagnostics = Self::arsc_with_options(buffer, visitor, ParseOptions::default())?;
+        warn_skipped("resources.arsc", &diagnostics);
+
+        Ok(())
+    }
+
+    /// Decodes a `resources.arsc` as `arsc` does, handling the problems as `options` says. It
+    /// returns the problems that were skipped on lenient mode.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the visitor fails or the table has problems that can not be skipped.
+    pub fn arsc_with_options<'a, V: ChunkVisitor<'a>>(
+        buffer: &'a [u8],
+        visitor: &mut V,
+        options: ParseOptions,
+    ) -> Result<Vec<Diagnostic>, Error> {
         let mut cursor = Cursor::new(buffer);
-        let token = cursor
-            .read_u16::<LittleEndian>()
-            .context("error reading first token")?;
-
-        if token != 0x2 {
-            bail!("file does not start with ARSC token: {:X}", token);
-        }
-
-        let header_size = cursor
-            .read_u16::<LittleEndian>()
-            .context("error reading header size")?;
-        let _chunk_size = cursor
-            .read_u32::<LittleEndian>()
-            .context("error reading chunk size")?;
-        let _package_amount = cursor
-            .read_u32::<LittleEndian>()
-            .context("error reading package amount")?;
-        // TODO: Avoid infinite loop
-        cursor.set_position(u64::from(header_size));
-
-        let stream = ChunkLoaderStream::new(cursor);
+        let table = ChunkContext::new(TOKEN_TABLE, 0);
+        skip_document_header(&mut cursor, TOKEN_TABLE).map_err(|e| locate(&e, 0, &[table]))?;
+
+        let mut report = Report::new(options);
+        let mut stream = ChunkLoaderStream::with_limits(cursor, options.get_limits());
         let mut origin = Origin::Global;
+        let mut path = vec![table];
+
+        while let Some(chunk) = stream.next() {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    report.fail(locate(&e, 0, &path))?;
+                    continue;
+                }
+            };
+            let header = match stream.get_header() {
+                Some(header) => *header,
+                None => continue,
+            };
+            let context = ChunkContext::new(header.get_token(), header.get_offset());
 
-        for c in stream {
-            match c.context("error reading next chunk")? {
+            let result = match chunk {
                 Chunk::StringTable(stw) => {
-                    visitor.visit_string_table(stw, origin);
+                    let result = visitor.visit_string_table(stw, origin);
                     origin = Origin::next(origin);
+
+                    result
                 }
                 Chunk::Package(pw) => {
-                    visitor.visit_package(pw);
-                }
-                Chunk::TableType(ttw) => {
-                    visitor.visit_table_type(ttw);
-                }
-                Chunk::TableTypeSpec(tsw) => {
-                    visitor.visit_type_spec(tsw);
+                    path.truncate(1);
+                    let result = visitor.visit_package(pw);
+                    path.push(context);
+
+                    result
                 }
+                Chunk::TableType(ttw) => visitor.visit_table_type(ttw),
+                Chunk::TableTypeSpec(tsw) => visitor.visit_type_spec(tsw),
                 _ => {
-                    warn!("Not expected chunk on ARSC");
+                    report.skip_unknown(&header, &path)?;
+                    let raw =
+                        &buffer[header.get_offset() as usize..header.get_chunk_end() as usize];
+
+                    visitor.visit_unknown(&header, raw)
                 }
+            };
+
+            let mut chunk_path = path.clone();
+            if chunk_path.last() != Some(&context) {
+                chunk_path.push(context);
             }
+
+            report.visited(visitor, result, header.get_offset(), &chunk_path)?;
         }
 
-        Ok(())
+        Ok(report.diagnostics)
     }
 
     /// Given a valid binary XML file contents, it will call to the proper methods on the
-    /// given visitor.
+    /// given visitor. The problems skipped on lenient mode are logged as warnings.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the visitor fails or the document has problems that can not be skipped.
     pub fn xml<'a, V: ChunkVisitor<'a>>(
-        mut cursor: Cursor<&'a [u8]>,
+        cursor: Cursor<&'a [u8]>,
         visitor: &mut V,
     ) -> Result<(), Error> {
-        let token = cursor
-            .read_u16::<LittleEndian>()
-            .context("error reading first token")?;
+        let diagnostics = Self::xml_with_options(cursor, visitor, ParseOptions::default())?;
+        warn_skipped("binary XML", &diagnostics);
 
-        if token != 0x3 {
-            bail!("document does not start with XML token: {:X}", token);
-        }
+        Ok(())
+    }
 
-        let header_size = cursor
-            .read_u16::<LittleEndian>()
-            .context("error reading header size")?;
-        let _chunk_size = cursor
-            .read_u32::<LittleEndian>()
-            .context("error reading chunk size")?;
-        cursor.set_position(u64::from(header_size));
-        let stream = ChunkLoaderStream::new(cursor);
+    /// Decodes a binary XML as `xml` does, handling the problems as `options` says. It returns
+    /// the problems that were skipped on lenient mode.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the visitor fails or the document has problems that can not be skipped.
+    pub fn xml_with_options<'a, V: ChunkVisitor<'a>>(
+        mut cursor: Cursor<&'a [u8]>,
+        visitor: &mut V,
+        options: ParseOptions,
+    ) -> Result<Vec<Diagnostic>, Error> {
+        let document = ChunkContext::new(TOKEN_XML, 0);
+        skip_document_header(&mut cursor, TOKEN_XML).map_err(|e| locate(&e, 0, &[document]))?;
 
-        for c in stream {
-            match c.context("error reading next chunk")? {
-                Chunk::StringTable(stw) => {
-                    visitor.visit_string_table(stw, Origin::Global);
-                }
-                Chunk::XmlNamespaceStart(xnsw) => {
-                    visitor.visit_xml_namespace_start(xnsw);
-                }
-                Chunk::XmlNamespaceEnd(xnsw) => {
-                    visitor.visit_xml_namespace_end(xnsw);
-                }
-                Chunk::XmlTagStart(xnsw) => {
-                    visitor.visit_xml_tag_start(xnsw);
-                }
-                Chunk::XmlTagEnd(xnsw) => {
-                    visitor.visit_xml_tag_end(xnsw);
-                }
-                Chunk::XmlText(xsnw) => {
-                    visitor.visit_xml_text(xsnw);
+        let buffer = *cursor.get_ref();
+        let mut report = Report::new(options);
+        let mut stream = ChunkLoaderStream::with_limits(cursor, options.get_limits());
+
+        while let Some(chunk) = stream.next() {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    report.fail(locate(&e, 0, &[document]))?;
+                    continue;
                 }
-                Chunk::Resource(rw) => {
-                    visitor.visit_resource(rw);
+            };
+            let header = match stream.get_header() {
+                Some(header) => *header,
+                None => continue,
+            };
+
+            let result = match chunk {
+                Chunk::StringTable(stw) => visitor.visit_string_table(stw, Origin::Global),
+                Chunk::XmlNamespaceStart(xnsw) => visitor.visit_xml_namespace_start(xnsw),
+                Chunk::XmlNamespaceEnd(xnsw) => visitor.visit_xml_namespace_end(xnsw),
+                Chunk::XmlTagStart(xnsw) => visitor.visit_xml_tag_start(xnsw),
+                Chunk::XmlTagEnd(xnsw) => visitor.visit_xml_tag_end(xnsw),
+                Chunk::XmlText(xsnw) => visitor.visit_xml_text(xsnw),
+                Chunk::Resource(rw) => visitor.visit_resource(rw),
+                _ => {
+                    report.skip_unknown(&header, &[document])?;
+                    let raw =
+                        &buffer[header.get_offset() as usize..header.get_chunk_end() as usize];
+
+                    visitor.visit_unknown(&header, raw)
                 }
-                _ => (),
-            }
+            };
+
+            let context = ChunkContext::new(header.get_token(), header.get_offset());
+            report.visited(visitor, result, header.get_offset(), &[document, context])?;
+        }
+
+        Ok(report.diagnostics)
+    }
+}
+
+/// Problems found by the `Executor` and its visitor while decoding a document
+struct Report {
+    options: ParseOptions,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    fn new(options: ParseOptions) -> Self {
+        Self {
+            options,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Returns the error if it is not recoverable or stores it otherwise
+    fn fail(&mut self, error: AbxmlError) -> Result<(), Error> {
+        if self.options.is_recoverable(&error) {
+            self.diagnostics.push(Diagnostic::new(error));
+
+            Ok(())
+        } else {
+            Err(error.into())
+        }
+    }
+
+    /// Collects the outcome of visiting the chunk that starts at `base`. `path` ends with the
+    /// visited chunk.
+    fn visited<'a, V: ChunkVisitor<'a>>(
+        &mut self,
+        visitor: &mut V,
+        result: Result<(), Error>,
+        base: u64,
+        path: &[ChunkContext],
+    ) -> Result<(), Error> {
+        for error in visitor.take_diagnostics() {
+            self.diagnostics
+                .push(Diagnostic::new(error.located(base, path)));
+        }
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) => self.fail(locate(&e, base, path)),
+        }
+    }
+
+    /// Chunks with an unknown type, or that do not belong to the document being decoded, are
+    /// skipped. They are only reported on lenient mode.
+    fn skip_unknown(&mut self, header: &ChunkHeader, path: &[ChunkContext]) -> Result<(), Error> {
+        if self.options.is_lenient() {
+            let mut path = path.to_vec();
+            path.push(ChunkContext::new(header.get_token(), header.get_offset()));
+
+            self.fail(AbxmlError::UnknownChunk {
+                location: Location::new(header.get_offset(), path),
+                token: header.get_token(),
+            })?;
         }
 
         Ok(())
@@ -159,3 +379,500 @@ impl Origin {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use byteorder::{LittleEndian, WriteBytesExt};
+
+    use super::{Executor, ModelVisitor, XmlVisitor};
+    use crate::{
+        anomalies::AnomalyReport,
+        chunks::{TOKEN_XML, TOKEN_XML_TAG_START},
+        dump::{dump_resources, dump_xml_tree},
+        errors::AbxmlError,
+        inspector::Inspection,
+        model::{
+            builder::Xml,
+            owned::{
+                AttributeBuf, ConfigurationBuf, OwnedBuf, StringTableBuf, XmlNamespaceEndBuf,
+                XmlNamespaceStartBuf, XmlTagEndBuf, XmlTagStartBuf,
+            },
+            AttributeValue, BinaryXmlDocument, ResourceId, ResourceTable, TableEntry,
+        },
+        options::{DecodeLimits, ParseOptions},
+        reader::BinaryXmlReader,
+    };
+
+    fn document(name: u32) -> Vec<u8> {
+        let mut xml = Xml::default();
+        let mut string_table = StringTableBuf::default();
+        string_table.add_string("manifest".to_string());
+
+        xml.push_owned(Box::new(string_table));
+        xml.push_owned(Box::new(XmlTagStartBuf::new(
+            1,
+            0xFFFF_FFFF,
+            0xFFFF_FFFF,
+            name,
+            0x0014_0014,
+            0,
+        )));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(name)));
+
+        xml.into_vec().unwrap()
+    }
+
+    fn decode(buffer: &[u8]) -> AbxmlError {
+        let model_visitor = ModelVisitor::default();
+        let options = ParseOptions::strict();
+        let mut visitor = XmlVisitor::with_options(model_visitor.get_resources(), options);
+        let error = Executor::xml_with_options(Cursor::new(buffer), &mut visitor, options)
+            .err()
+            .unwrap();
+
+        error.downcast::<AbxmlError>().unwrap()
+    }
+
+    #[test]
+    fn it_reports_documents_with_an_unexpected_token() {
+        match decode(&[2, 0, 12, 0, 12, 0, 0, 0]) {
+            AbxmlError::BadToken {
+                expected, found, ..
+            } => {
+                assert_eq!(TOKEN_XML, expected);
+                assert_eq!(2, found);
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn it_reports_truncated_chunks() {
+        let mut buffer = document(0);
+        let len = buffer.len();
+        buffer.truncate(len - 4);
+
+        match decode(&buffer) {
+            AbxmlError::TruncatedChunk {
+                location,
+                needed,
+                available,
+            } => {
+                assert_eq!(needed, available + 4);
+                assert_eq!(TOKEN_XML, location.get_path()[0].get_token());
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn it_reports_strings_out_of_range_with_the_chunk_path() {
+        match decode(&document(9)) {
+            AbxmlError::StringIndexOutOfRange {
+                location,
+                index,
+                len,
+            } => {
+                assert_eq!(9, index);
+                assert_eq!(1, len);
+
+                let path = location.get_path();
+                assert_eq!(2, path.len());
+                assert_eq!(TOKEN_XML_TAG_START, path[1].get_token());
+                assert!(location.get_offset() >= path[1].get_offset());
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    /// `<manifest><application android:label="@0x7f010000"/></manifest>`, with the given string
+    /// index as the name of the application
+    fn nested_document(child_name: u32) -> Vec<u8> {
+        let mut xml = Xml::default();
+        let mut string_table = StringTableBuf::default();
+        string_table.add_string("manifest".to_string());
+        string_table.add_string("application".to_string());
+        string_table.add_string("label".to_string());
+
+        let mut child =
+            XmlTagStartBuf::new(2, 0xFFFF_FFFF, 0xFFFF_FFFF, child_name, 0x0014_0014, 0);
+        child.add_attribute(AttributeBuf::new(
+            0xFFFF_FFFF,
+            2,
+            0xFFFF_FFFF,
+            1 << 24,
+            0x7F01_0000,
+        ));
+
+        xml.push_owned(Box::new(string_table));
+        xml.push_owned(Box::new(XmlTagStartBuf::new(
+            1,
+            0xFFFF_FFFF,
+            0xFFFF_FFFF,
+            0,
+            0x0014_0014,
+            0,
+        )));
+        xml.push_owned(Box::new(child));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(child_name)));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+
+        xml.into_vec().unwrap()
+    }
+
+    #[test]
+    fn it_keeps_the_raw_value_of_unresolved_references_on_lenient_mode() {
+        let buffer = nested_document(1);
+        let model_visitor = ModelVisitor::default();
+        let mut visitor =
+            XmlVisitor::with_options(model_visitor.get_resources(), ParseOptions::lenient());
+
+        let diagnostics =
+            Executor::xml_with_options(Cursor::new(&buffer), &mut visitor, ParseOptions::lenient())
+                .unwrap();
+
+        assert_eq!(1, diagnostics.len());
+        match diagnostics[0].get_error() {
+            AbxmlError::UnresolvedReference { location, .. } => {
+                assert_eq!(TOKEN_XML_TAG_START, location.get_path()[1].get_token());
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+
+        let root = visitor.get_root().as_ref().unwrap();
+        let application = &root.get_children()[0];
+        assert_eq!("@id/0x7f010000", application.get_attributes()["label"]);
+
+        let strict = ParseOptions::strict();
+        let mut visitor = XmlVisitor::with_options(model_visitor.get_resources(), strict);
+        assert!(Executor::xml_with_options(Cursor::new(&buffer), &mut visitor, strict).is_err());
+
+        // The entry points without options skip the problems, as they did before the modes
+        let mut visitor = XmlVisitor::new(model_visitor.get_resources());
+        assert!(Executor::xml(Cursor::new(&buffer), &mut visitor).is_ok());
+        let root = visitor.get_root().as_ref().unwrap();
+        assert_eq!(
+            "@id/0x7f010000",
+            root.get_children()[0].get_attributes()["label"]
+        );
+    }
+
+    #[test]
+    fn it_skips_the_elements_that_can_not_be_decoded_on_lenient_mode() {
+        let buffer = nested_document(9);
+        let model_visitor = ModelVisitor::default();
+        let mut visitor =
+            XmlVisitor::with_options(model_visitor.get_resources(), ParseOptions::lenient());
+
+        let diagnostics =
+            Executor::xml_with_options(Cursor::new(&buffer), &mut visitor, ParseOptions::lenient())
+                .unwrap();
+
+        assert_eq!(1, diagnostics.len());
+        match diagnostics[0].get_error() {
+            AbxmlError::StringIndexOutOfRange { index, .. } => assert_eq!(9, *index),
+            error => panic!("unexpected error: {}", error),
+        }
+
+        let root = visitor.get_root().as_ref().unwrap();
+        assert_eq!("manifest", root.get_tag().get_name().as_str());
+        assert!(root.get_children().is_empty());
+    }
+
+    #[test]
+    fn it_reports_unknown_chunks_only_on_lenient_mode() {
+        let mut buffer = document(0);
+        let mut unknown = Vec::new();
+        unknown.write_u16::<LittleEndian>(0x0999).unwrap();
+        unknown.write_u16::<LittleEndian>(8).unwrap();
+        unknown.write_u32::<LittleEndian>(8).unwrap();
+        buffer.extend(&unknown);
+        let len = buffer.len() as u32;
+        (&mut buffer[4..8]).write_u32::<LittleEndian>(len).unwrap();
+
+        let model_visitor = ModelVisitor::default();
+        let strict = ParseOptions::strict();
+        let mut visitor = XmlVisitor::with_options(model_visitor.get_resources(), strict);
+        assert!(Executor::xml_with_options(Cursor::new(&buffer), &mut visitor, strict).is_ok());
+
+        let mut visitor = XmlVisitor::new(model_visitor.get_resources());
+        let diagnostics =
+            Executor::xml_with_options(Cursor::new(&buffer), &mut visitor, ParseOptions::lenient())
+                .unwrap();
+
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            u64::from(len) - 8,
+            diagnostics[0].get_location().get_offset()
+        );
+    }
+
+    #[test]
+    fn it_stops_on_truncated_chunks_on_lenient_mode() {
+        let mut buffer = nested_document(1);
+        let len = buffer.len();
+        buffer.truncate(len - 4);
+
+        let model_visitor = ModelVisitor::default();
+        let mut visitor =
+            XmlVisitor::with_options(model_visitor.get_resources(), ParseOptions::lenient());
+        let diagnostics =
+            Executor::xml_with_options(Cursor::new(&buffer), &mut visitor, ParseOptions::lenient())
+                .unwrap();
+
+        match diagnostics.last().map(|diagnostic| diagnostic.get_error()) {
+            Some(AbxmlError::TruncatedChunk { .. }) => {}
+            _ => panic!("the last diagnostic should be the truncated chunk"),
+        }
+        assert_eq!(2, diagnostics.len());
+    }
+
+    #[test]
+    fn it_skips_string_tables_outside_of_packages_on_lenient_mode() {
+        let string_table = StringTableBuf::default().to_vec().unwrap();
+        let mut buffer = Vec::new();
+        buffer.write_u16::<LittleEndian>(2).unwrap();
+        buffer.write_u16::<LittleEndian>(12).unwrap();
+        buffer
+            .write_u32::<LittleEndian>(12 + 2 * string_table.len() as u32)
+            .unwrap();
+        buffer.write_u32::<LittleEndian>(0).unwrap();
+        buffer.extend(&string_table);
+        buffer.extend(&string_table);
+
+        let strict = ParseOptions::strict();
+        let mut visitor = ModelVisitor::with_options(strict);
+        assert!(Executor::arsc_with_options(&buffer, &mut visitor, strict).is_err());
+
+        let mut visitor = ModelVisitor::default();
+        assert!(Executor::arsc(&buffer, &mut visitor).is_ok());
+
+        let mut visitor = ModelVisitor::with_options(ParseOptions::lenient());
+        let diagnostics =
+            Executor::arsc_with_options(&buffer, &mut visitor, ParseOptions::lenient()).unwrap();
+
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            12 + string_table.len() as u64,
+            diagnostics[0].get_location().get_offset()
+        );
+    }
+
+    fn exceeded_limit(buffer: &[u8], limits: DecodeLimits) -> &'static str {
+        let options = ParseOptions::default().with_limits(limits);
+        let mut visitor = ModelVisitor::with_options(options);
+        let error = Executor::arsc_with_options(buffer, &mut visitor, options)
+            .err()
+            .unwrap();
+
+        match error.downcast::<AbxmlError>().unwrap() {
+            AbxmlError::LimitExceeded { limit, .. } => limit,
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn it_enforces_the_decode_limits_on_tables() {
+        let mut table = ResourceTable::default();
+        table.add_package(0x7F, "com.example").unwrap();
+        for name in &["first", "second", "third"] {
+            let id = table.add_resource(0x7F, "string", name).unwrap();
+            let value = TableEntry::Value(AttributeValue::String(name.to_string()));
+            table
+                .set_entry(id, &ConfigurationBuf::default(), value)
+                .unwrap();
+        }
+        let buffer = table.to_vec().unwrap();
+
+        let mut visitor = ModelVisitor::default();
+        assert!(Executor::arsc(&buffer, &mut visitor).is_ok());
+
+        let limits = DecodeLimits::default();
+        assert_eq!(
+            "chunk count",
+            exceeded_limit(&buffer, limits.with_max_chunks(2))
+        );
+        assert_eq!(
+            "entries per type",
+            exceeded_limit(&buffer, limits.with_max_entries_per_type(2))
+        );
+        assert_eq!(
+            "string length",
+            exceeded_limit(&buffer, limits.with_max_string_length(4))
+        );
+    }
+
+    #[test]
+    fn it_enforces_the_xml_depth_limit() {
+        let mut xml = Xml::default();
+        let mut string_table = StringTableBuf::default();
+        string_table.add_string("manifest".to_string());
+        string_table.add_string("application".to_string());
+        xml.push_owned(Box::new(string_table));
+        for name in 0..2 {
+            let tag = XmlTagStartBuf::new(1, 0xFFFF_FFFF, 0xFFFF_FFFF, name, 0x0014_0014, 0);
+            xml.push_owned(Box::new(tag));
+        }
+        xml.push_owned(Box::new(XmlTagEndBuf::new(1)));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+        let buffer = xml.into_vec().unwrap();
+        let options =
+            ParseOptions::default().with_limits(DecodeLimits::default().with_max_xml_depth(1));
+        let model_visitor = ModelVisitor::default();
+
+        let mut visitor = XmlVisitor::with_options(model_visitor.get_resources(), options);
+        let error = Executor::xml_with_options(Cursor::new(&buffer), &mut visitor, options)
+            .err()
+            .unwrap();
+        match error.downcast::<AbxmlError>().unwrap() {
+            AbxmlError::LimitExceeded { max, found, .. } => assert_eq!((1, 2), (max, found)),
+            error => panic!("unexpected error: {}", error),
+        }
+
+        // Limits are not skipped on lenient mode
+        let options = ParseOptions::lenient().with_limits(options.get_limits());
+        let mut visitor = XmlVisitor::with_options(model_visitor.get_resources(), options);
+        assert!(Executor::xml_with_options(Cursor::new(&buffer), &mut visitor, options).is_err());
+    }
+
+    #[test]
+    fn it_skips_secondary_string_tables_unless_strict() {
+        let mut xml = Xml::default();
+        for _ in 0..2 {
+            let mut string_table = StringTableBuf::default();
+            string_table.add_string("manifest".to_string());
+            xml.push_owned(Box::new(string_table));
+        }
+        xml.push_owned(Box::new(XmlTagStartBuf::new(
+            1,
+            0xFFFF_FFFF,
+            0xFFFF_FFFF,
+            0,
+            0x0014_0014,
+            0,
+        )));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(0)));
+        let buffer = xml.into_vec().unwrap();
+        let model_visitor = ModelVisitor::default();
+
+        let mut visitor = XmlVisitor::new(model_visitor.get_resources());
+        let diagnostics =
+            Executor::xml_with_options(Cursor::new(&buffer), &mut visitor, ParseOptions::default())
+                .unwrap();
+        assert_eq!(1, diagnostics.len());
+        assert!(visitor.into_string().unwrap().contains("<manifest"));
+
+        let (document, diagnostics) =
+            BinaryXmlDocument::from_bytes_with_options(&buffer, ParseOptions::default()).unwrap();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!("manifest", document.get_root().get_name());
+
+        let mut reader = BinaryXmlReader::new(&buffer, model_visitor.get_resources()).unwrap();
+        assert!(reader.by_ref().all(|event| event.is_ok()));
+        assert_eq!(1, reader.get_diagnostics().len());
+
+        let strict = ParseOptions::strict();
+        let mut visitor = XmlVisitor::with_options(model_visitor.get_resources(), strict);
+        assert!(Executor::xml_with_options(Cursor::new(&buffer), &mut visitor, strict).is_err());
+        assert!(BinaryXmlDocument::from_bytes_with_options(&buffer, strict).is_err());
+        let reader =
+            BinaryXmlReader::with_options(&buffer, model_visitor.get_resources(), strict).unwrap();
+        assert!(reader.into_iter().any(|event| event.is_err()));
+    }
+
+    /// Runs every decoder over the given bytes. They may fail, but they must not panic.
+    fn decode_everything(buffer: &[u8]) {
+        for options in &[ParseOptions::strict(), ParseOptions::lenient()] {
+            let mut model_visitor = ModelVisitor::with_options(*options);
+            if Executor::arsc_with_options(buffer, &mut model_visitor, *options).is_ok() {
+                let _ = dump_resources(model_visitor.get_resources(), 0x7F);
+            }
+
+            let resources = model_visitor.get_resources();
+            let mut visitor = XmlVisitor::with_options(resources, *options);
+            if Executor::xml_with_options(Cursor::new(buffer), &mut visitor, *options).is_ok() {
+                let _ = visitor.into_string();
+            }
+
+            if let Ok(reader) = BinaryXmlReader::new(buffer, resources) {
+                for _ in reader {}
+            }
+        }
+
+        if let Ok(table) = ResourceTable::from_bytes(buffer) {
+            let _ = table.to_vec();
+        }
+        if let Ok(document) = BinaryXmlDocument::from_bytes(buffer) {
+            let _ = document.to_vec();
+        }
+        let _ = dump_xml_tree(buffer);
+        let _ = Inspection::new(buffer).to_string();
+        let _ = AnomalyReport::analyze(buffer).to_string();
+    }
+
+    /// Truncates the document at every length and overwrites its words with values that are
+    /// usually out of range
+    fn mutate(buffer: &[u8], check: impl Fn(&[u8])) {
+        for len in 0..buffer.len() {
+            check(&buffer[..len]);
+        }
+
+        for position in (0..buffer.len()).step_by(2) {
+            for value in &[0x0000_0000_u32, 0x0000_7FFF, 0xFFFF_FFFF] {
+                let mut mutated = buffer.to_vec();
+                let end = (position + 4).min(mutated.len());
+                let bytes = value.to_le_bytes();
+                mutated[position..end].copy_from_slice(&bytes[..end - position]);
+
+                check(&mutated);
+            }
+        }
+    }
+
+    #[test]
+    fn it_does_not_panic_on_corrupted_tables() {
+        let default = ConfigurationBuf::default();
+        let catalan = ConfigurationBuf::from_qualifiers("ca").unwrap();
+
+        let mut table = ResourceTable::default();
+        table.add_package(0x7F, "com.example").unwrap();
+        let color = table.add_resource(0x7F, "attr", "color").unwrap();
+        let title = table.add_resource(0x7F, "string", "title").unwrap();
+        let theme = table.add_resource(0x7F, "style", "AppTheme").unwrap();
+        let value = |text: &str| TableEntry::Value(AttributeValue::String(text.to_string()));
+        let bag = |parent, items| TableEntry::Bag { parent, items };
+        table
+            .set_entry(color, &default, bag(ResourceId::default(), Vec::new()))
+            .unwrap();
+        table.set_entry(title, &default, value("Title")).unwrap();
+        table.set_entry(title, &catalan, value("Títol")).unwrap();
+        let items = vec![(color, AttributeValue::Reference(title))];
+        table.set_entry(theme, &default, bag(theme, items)).unwrap();
+
+        mutate(&table.to_vec().unwrap(), decode_everything);
+    }
+
+    #[test]
+    fn it_does_not_panic_on_corrupted_documents() {
+        let mut xml = Xml::default();
+        let mut string_table = StringTableBuf::default();
+        string_table.add_string("android".to_string());
+        string_table.add_string("http://schemas.android.com/apk/res/android".to_string());
+        string_table.add_string("manifest".to_string());
+        string_table.add_string("label".to_string());
+
+        let mut tag_start = XmlTagStartBuf::new(2, 0xFFFF_FFFF, 0xFFFF_FFFF, 2, 0x0014_0014, 0);
+        tag_start.add_attribute(AttributeBuf::new(1, 3, 0xFFFF_FFFF, 1 << 24, 0x7F01_0000));
+        tag_start.add_attribute(AttributeBuf::new(0xFFFF_FFFF, 0, 2, 3 << 24, 2));
+
+        xml.push_owned(Box::new(string_table));
+        xml.push_owned(Box::new(XmlNamespaceStartBuf::new(1, 0, 1)));
+        xml.push_owned(Box::new(tag_start));
+        xml.push_owned(Box::new(XmlTagEndBuf::new(2)));
+        xml.push_owned(Box::new(XmlNamespaceEndBuf::new(1, 0, 1)));
+
+        mutate(&xml.into_vec().unwrap(), decode_everything);
+    }
+}
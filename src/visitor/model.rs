@@ -1,29 +1,55 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    mem,
+    rc::Rc,
+};
 
-use failure::{format_err, Error, ResultExt};
-use log::error;
+use failure::{ensure, format_err, Error, ResultExt};
 
 use crate::{
     chunks::{
         PackageWrapper, StringTableCache, StringTableWrapper, TableTypeWrapper, TypeSpecWrapper,
     },
+    errors::{locate, AbxmlError, Location},
     model::{
-        owned::Entry, Entries, Identifier, Library as LibraryTrait, LibraryBuilder,
-        Resources as ResourcesTrait, StringTable as StringTableTrait, TypeSpec as TypeSpecTrait,
+        owned::{ConfigurationBuf, Entry},
+        BagValue, Entries, Library as LibraryTrait, LibraryBuilder, ResourceId, ResourceName,
+        Resources as ResourcesTrait, SpecFlags, StringTable as StringTableTrait, StyleValue,
+        TableType, Theme, TypeSpec as TypeSpecTrait, Value,
     },
+    options::ParseOptions,
 };
 
 use super::{ChunkVisitor, Origin};
 
+/// Maximum amount of references that `Resources::resolve_value` follows before giving up
+pub const MAX_REFERENCE_DEPTH: usize = 32;
+
+/// Id of the `android` package
+const FRAMEWORK_PACKAGE_ID: u8 = 0x01;
+
 #[derive(Default, Debug)]
 pub struct ModelVisitor<'a> {
-    package_mask: u32,
+    package_id: u8,
     resources: Resources<'a>,
     current_spec: Option<TypeSpecWrapper<'a>>,
     tables: HashMap<Origin, StringTableCache<StringTableWrapper<'a>>>,
+    options: ParseOptions,
+    diagnostics: Vec<AbxmlError>,
 }
 
 impl<'a> ModelVisitor<'a> {
+    /// Creates a visitor that handles the problems found on the tables as `options` says. On
+    /// lenient mode, entries whose name can not be read are loaded without it.
+    #[must_use]
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
     pub fn get_resources(&self) -> &'a Resources {
         &self.resources
     }
@@ -31,106 +57,155 @@ impl<'a> ModelVisitor<'a> {
     pub fn get_mut_resources(&mut self) -> &'a mut Resources {
         &mut self.resources
     }
+
+    pub(crate) fn set_missing_app_package(&mut self, missing_app_package: bool) {
+        self.resources.set_missing_app_package(missing_app_package);
+    }
+
+    /// Stores the names of the given entries, so they can be looked up later without scanning
+    /// all the packages
+    fn index_names(&mut self, package_id: u8, entries: &Entries) -> Result<(), Error> {
+        let mut names = Vec::new();
+
+        if let Some(package) = self.resources.get_package(package_id) {
+            let package_name = package.get_name().unwrap_or_default();
+
+            for (id, entry) in entries {
+                if self.resources.names.contains_key(id) {
+                    continue;
+                }
+
+                match Self::get_entry_name(package, &package_name, *id, entry) {
+                    Ok(name) => names.push((*id, name)),
+                    Err(e) => self
+                        .options
+                        .recover(locate(&e, 0, &[]), &mut self.diagnostics)?,
+                }
+            }
+        }
+
+        for (id, name) in names {
+            self.resources.ids.insert(name.clone(), id);
+            self.resources.names.insert(id, name);
+        }
+
+        Ok(())
+    }
+
+    fn get_entry_name(
+        package: &Library,
+        package_name: &str,
+        id: ResourceId,
+        entry: &Entry,
+    ) -> Result<ResourceName, Error> {
+        let spec = u32::from(id.get_type())
+            .checked_sub(1)
+            .ok_or_else(|| format_err!("invalid spec id on {}", id))
+            .and_then(|spec| package.get_spec_string(spec))
+            .context(format_err!("could not retrieve the type name of {}", id))?;
+        let key = package
+            .get_entries_string(entry.get_key())
+            .context(format_err!("could not retrieve the name of {}", id))?;
+
+        Ok(ResourceName::new(Some(package_name), &spec, &key))
+    }
 }
 
 impl<'a> ChunkVisitor<'a> for ModelVisitor<'a> {
-    fn visit_string_table(&mut self, string_table: StringTableWrapper<'a>, origin: Origin) {
+    fn visit_string_table(
+        &mut self,
+        string_table: StringTableWrapper<'a>,
+        origin: Origin,
+    ) -> Result<(), Error> {
         if let Origin::Global = origin {
             self.tables
                 .insert(origin, StringTableCache::new(string_table));
         } else {
-            let package_id = self.package_mask.get_package();
-
-            let st_res = self
-                .resources
-                .get_mut_package(package_id)
-                .and_then(|package| {
+            match self.resources.get_mut_package(self.package_id) {
+                Some(package) => {
                     package.set_string_table(StringTableCache::new(string_table), origin);
-                    Some(())
-                });
-
-            if st_res.is_none() {
-                error!("Could not retrieve target package");
+                }
+                None => self.options.recover(
+                    AbxmlError::Malformed {
+                        location: Location::default(),
+                        reason: "found a string table outside of a package".to_string(),
+                    },
+                    &mut self.diagnostics,
+                )?,
             }
         }
-    }
 
-    fn visit_package(&mut self, package: PackageWrapper<'a>) {
-        if let Ok(package_id) = package.get_id() {
-            self.package_mask = package_id << 24;
+        Ok(())
+    }
 
-            let package_id = self.package_mask.get_package();
-            let rp = Library::new(package);
+    fn visit_package(&mut self, package: PackageWrapper<'a>) -> Result<(), Error> {
+        // Shared libraries are compiled with package id `0`, which is only assigned at runtime.
+        // They are loaded with that id, so their own references, which also use `0`, resolve
+        // against them.
+        let package_id = package.get_id().context("could not read the package id")? as u8;
+        self.package_id = package_id;
 
-            self.resources.push_package(package_id, rp);
+        let mut library = Library::new(package);
 
-            if self.tables.contains_key(&Origin::Global) {
-                if let Some(st) = self.tables.remove(&Origin::Global) {
-                    let set_result =
-                        self.resources
-                            .get_mut_package(package_id)
-                            .and_then(|package| {
-                                package.set_string_table(st, Origin::Global);
+        if let Some(st) = self.tables.remove(&Origin::Global) {
+            library.set_string_table(st, Origin::Global);
+        }
 
-                                Some(())
-                            });
+        self.resources.push_package(package_id, library);
 
-                    if set_result.is_none() {
-                        error!(
-                            "could not set the string table because it refers to a non-existing \
-                             package"
-                        );
-                    }
-                }
-            }
-        }
+        Ok(())
     }
 
-    fn visit_table_type(&mut self, table_type: TableTypeWrapper<'a>) {
+    fn visit_table_type(&mut self, table_type: TableTypeWrapper<'a>) -> Result<(), Error> {
         let mut entries = Entries::new();
 
         if let Some(ts) = &self.current_spec {
-            let mask = ts
-                .get_id()
-                .and_then(|id| Ok(self.package_mask | (u32::from(id) << 16)))
-                .unwrap_or(0);
-
-            let entries_result = table_type.get_entries();
+            let type_id = ts.get_id()? as u8;
 
-            match entries_result {
-                Ok(ventries) => {
-                    for e in &ventries {
-                        let id = mask | e.get_id();
+            for e in table_type
+                .get_entries()
+                .context("could not read the entries")?
+            {
+                let id = ResourceId::new(self.package_id, type_id, e.get_id().get_entry());
 
-                        if !e.is_empty() {
-                            entries.insert(id, e.clone());
-                        }
-                    }
+                if !e.is_empty() {
+                    entries.insert(id, e);
                 }
-                Err(err) => error!("Error visiting table_type: {}", err),
             }
         }
 
-        let package_id = self.package_mask.get_package();
+        let package_id = self.package_id;
+        self.index_names(package_id, &entries)?;
 
-        self.resources
+        let config = table_type
+            .get_configuration()
+            .and_then(|config| config.to_buffer())
+            .context("could not decode the configuration of the entries")?;
+
+        let package = self
+            .resources
             .get_mut_package(package_id)
-            .and_then(|package| {
-                package.add_entries(entries);
-                Some(())
-            });
+            .ok_or_else(|| format_err!("found a type outside of a package"))?;
+        package.add_config_entries(config, &entries);
+        package.add_entries(entries);
+
+        Ok(())
     }
 
-    fn visit_type_spec(&mut self, type_spec: TypeSpecWrapper<'a>) {
+    fn visit_type_spec(&mut self, type_spec: TypeSpecWrapper<'a>) -> Result<(), Error> {
         self.current_spec = Some(type_spec.clone());
-        let package_id = (self.package_mask >> 24) as u8;
-        if let Some(package) = self.resources.get_mut_package(package_id) {
-            let _ = package
-                .add_type_spec(type_spec)
-                .map_err(|e| error!("Could not add type spec: {}", e));
-        } else {
-            error!("Type spec refers to a non existing package");
-        }
+
+        self.resources
+            .get_mut_package(self.package_id)
+            .ok_or_else(|| format_err!("type spec refers to a non existing package"))?
+            .add_type_spec(type_spec)
+            .context("could not add type spec")?;
+
+        Ok(())
+    }
+
+    fn take_diagnostics(&mut self) -> Vec<AbxmlError> {
+        mem::take(&mut self.diagnostics)
     }
 }
 
@@ -140,16 +215,293 @@ pub type RefPackage<'a> = Rc<RefCell<Library<'a>>>;
 pub struct Resources<'a> {
     packages: HashMap<u8, Library<'a>>,
     main_package: Option<u8>,
+    /// First package loaded that is not the framework
+    app_package: Option<u8>,
+    names: HashMap<ResourceId, ResourceName>,
+    ids: HashMap<ResourceName, ResourceId>,
+    /// If the APK has no resources of its own, only the framework ones
+    missing_app_package: bool,
 }
 
 impl<'a> Resources<'a> {
+    /// Returns the id of the resource with the given name (`string/app_name`,
+    /// `android:attr/layout_width`...). If the name has no package, it is searched on the app
+    /// package, as `get_app_package` returns.
+    ///
+    /// # Errors
+    ///
+    /// It fails if the name is not valid or there is no resource with that name.
+    pub fn find_id(&self, name: &str) -> Result<ResourceId, Error> {
+        let mut name: ResourceName = name.parse()?;
+
+        if name.get_package().is_none() {
+            let package = self
+                .get_app_package()
+                .and_then(LibraryTrait::get_name)
+                .ok_or_else(|| format_err!("could not find the app package"))?;
+
+            name = name.with_package(&package);
+        }
+
+        self.ids
+            .get(&name)
+            .copied()
+            .ok_or_else(|| format_err!("could not find resource {}", name))
+    }
+
+    /// Returns the name of the resource with the given id
+    #[must_use]
+    pub fn name_of(&self, id: ResourceId) -> Option<&ResourceName> {
+        self.names.get(&id)
+    }
+
+    /// Returns the ids and names of all the resources
+    pub fn get_names(&self) -> impl Iterator<Item = (ResourceId, &ResourceName)> {
+        self.names.iter().map(|(id, name)| (*id, name))
+    }
+
+    /// Returns the flags of the resource: if it is public and the configuration axes its values
+    /// vary over
+    ///
+    /// # Errors
+    ///
+    /// It fails if the package or the type of the resource are not loaded.
+    pub fn get_spec_flags(&self, id: ResourceId) -> Result<SpecFlags, Error> {
+        self.get_package(id.get_package())
+            .ok_or_else(|| format_err!("could not find package of {}", id))?
+            .get_spec_flags(id)
+    }
+
+    /// Returns the final value of the resource with the given id on a device with the given
+    /// configuration. References to other resources are followed and strings are resolved into
+    /// text. References to bags (styles, arrays...) and to attributes can not be reduced to a
+    /// single value, so they are returned as they are.
+    ///
+    /// # Errors
+    ///
+    /// It fails if a resource on the chain is not loaded or has no value for the configuration, or
+    /// if the chain has a cycle or too many references.
+    pub fn resolve_value(&self, id: ResourceId, config: &ConfigurationBuf) -> Result<Value, Error> {
+        let mut visited = HashSet::new();
+        let mut current = id;
+
+        loop {
+            ensure!(
+                visited.insert(current),
+                "reference cycle found resolving {}",
+                id
+            );
+            ensure!(
+                visited.len() <= MAX_REFERENCE_DEPTH,
+                "too many references resolving {}",
+                id
+            );
+
+            let package = self
+                .get_package(current.get_package())
+                .ok_or_else(|| format_err!("could not find package of {}", current))?;
+            let entry = package.get_config_entry(current, config).ok_or_else(|| {
+                format_err!("could not find entry {} for the configuration", current)
+            })?;
+
+            if let Entry::Complex(_) = entry {
+                return Ok(Value::ReferenceId(current));
+            }
+
+            let simple = entry.simple()?;
+
+            match Value::create(simple.get_type(), simple.get_value())? {
+                Value::ReferenceId(next) | Value::DynamicReferenceId(next) if !next.is_null() => {
+                    current = next;
+                }
+                Value::StringReference(index) => {
+                    return Ok(Value::String((*package.get_global_string(index)?).clone()));
+                }
+                value => return Ok(value),
+            }
+        }
+    }
+
+    /// Returns all the attributes defined by the given theme on a device with the given
+    /// configuration. Attributes are inherited from the explicit parents, including the ones of
+    /// the framework, or from the implicit parents given by the dotted names (`Theme.App` is the
+    /// parent of `Theme.App.Dark`).
+    ///
+    /// # Errors
+    ///
+    /// It fails if the theme or one of its parents is not loaded or is not a bag.
+    pub fn get_theme(&self, id: ResourceId, config: &ConfigurationBuf) -> Result<Theme, Error> {
+        let mut theme = Theme::new(id);
+        let mut visited = HashSet::new();
+        let mut current = Some(id);
+
+        while let Some(style) = current {
+            ensure!(
+                visited.insert(style),
+                "style inheritance cycle found resolving {}",
+                id
+            );
+            ensure!(
+                visited.len() <= MAX_REFERENCE_DEPTH,
+                "too many parents resolving {}",
+                id
+            );
+
+            if style != id {
+                theme.push_parent(style);
+            }
+
+            let package = self
+                .get_package(style.get_package())
+                .ok_or_else(|| format_err!("could not find package of style {}", style))?;
+            let bag = package
+                .get_config_entry(style, config)
+                .ok_or_else(|| format_err!("could not find style {} for the configuration", style))?
+                .complex()
+                .context(format_err!("{} is not a style", style))?;
+            let style_value = StyleValue::from_entry(bag, package.get_global_strings()?)?;
+
+            for (attribute, value) in style_value.get_items() {
+                theme.inherit(*attribute, value.clone());
+            }
+
+            current = self.get_style_parent(style, style_value.get_parent());
+        }
+
+        Ok(theme)
+    }
+
+    /// Returns the typed view of the bag (style, array, plurals...) with the given id on a device
+    /// with the given configuration
+    ///
+    /// # Errors
+    ///
+    /// It fails if the resource is not loaded, it is not a bag or the bag can not be decoded.
+    pub fn get_bag(&self, id: ResourceId, config: &ConfigurationBuf) -> Result<BagValue, Error> {
+        let package = self
+            .get_package(id.get_package())
+            .ok_or_else(|| format_err!("could not find package of {}", id))?;
+        let bag = package
+            .get_config_entry(id, config)
+            .ok_or_else(|| format_err!("could not find entry {} for the configuration", id))?
+            .complex()?;
+        let type_name = u32::from(id.get_type())
+            .checked_sub(1)
+            .ok_or_else(|| format_err!("invalid type on {}", id))
+            .and_then(|spec| package.get_spec_string(spec))?;
+
+        BagValue::new(&type_name, bag, package.get_global_strings()?)
+    }
+
+    /// Resolves the references to theme attributes (`?attr/colorPrimary`) against the given
+    /// theme. References to other resources are followed as on `resolve_value`.
+    ///
+    /// # Errors
+    ///
+    /// It fails if an attribute is not defined by the theme, the attribute references have a cycle
+    /// or a reference can not be resolved.
+    pub fn resolve_in_theme(
+        &self,
+        value: Value,
+        theme: &Theme,
+        config: &ConfigurationBuf,
+    ) -> Result<Value, Error> {
+        let mut visited = HashSet::new();
+        let mut current = value;
+
+        loop {
+            match current {
+                Value::AttributeReferenceId(attribute)
+                | Value::DynamicAttributeReferenceId(attribute) => {
+                    ensure!(
+                        visited.insert(attribute),
+                        "attribute reference cycle found on {}",
+                        attribute
+                    );
+
+                    current = theme.get_attribute(attribute).cloned().ok_or_else(|| {
+                        format_err!(
+                            "theme {} does not define the attribute {}",
+                            theme.get_id(),
+                            attribute
+                        )
+                    })?;
+                }
+                Value::ReferenceId(id) | Value::DynamicReferenceId(id) if !id.is_null() => {
+                    match self.resolve_value(id, config)? {
+                        attribute @ (Value::AttributeReferenceId(_)
+                        | Value::DynamicAttributeReferenceId(_)) => current = attribute,
+                        value => return Ok(value),
+                    }
+                }
+                value => return Ok(value),
+            }
+        }
+    }
+
+    /// Returns the explicit parent of the style or, if it has none, the one implied by its name
+    fn get_style_parent(&self, style: ResourceId, parent: ResourceId) -> Option<ResourceId> {
+        if !parent.is_null() {
+            return Some(parent);
+        }
+
+        let name = self.name_of(style)?;
+        let separator = name.get_entry().rfind('.')?;
+        let parent_name = ResourceName::new(
+            name.get_package(),
+            name.get_type(),
+            &name.get_entry()[..separator],
+        );
+
+        self.ids.get(&parent_name).copied()
+    }
+
+    /// Returns the package of the app: the first one loaded that is not the framework. The
+    /// framework is loaded before the app, so, if it is the only one loaded, it is returned
+    /// instead.
+    #[must_use]
+    pub fn get_app_package(&self) -> Option<&Library<'a>> {
+        match self.app_package {
+            Some(package_id) => self.packages.get(&package_id),
+            None => self.get_main_package(),
+        }
+    }
+
     pub fn push_package(&mut self, package_id: u8, package: Library<'a>) {
         if self.packages.is_empty() {
             self.main_package = Some(package_id);
         }
 
+        if package_id != FRAMEWORK_PACKAGE_ID && self.app_package.is_none() {
+            self.app_package = Some(package_id);
+        }
+
         self.packages.insert(package_id, package);
     }
+
+    pub(crate) fn get_package_count(&self) -> usize {
+        self.packages.len()
+    }
+
+    pub(crate) fn set_missing_app_package(&mut self, missing_app_package: bool) {
+        self.missing_app_package = missing_app_package;
+    }
+
+    /// Raw text (`@0x7f010000`) of a reference that can not be resolved because the APK has no
+    /// resources of its own. Those references are expected, so they are not reported as problems.
+    pub(crate) fn format_missing_reference(&self, value: &Value) -> Option<String> {
+        let (prefix, id) = match value {
+            Value::ReferenceId(id) | Value::DynamicReferenceId(id) => ("@", id),
+            Value::AttributeReferenceId(id) | Value::DynamicAttributeReferenceId(id) => ("?", id),
+            _ => return None,
+        };
+
+        if self.missing_app_package && !self.packages.contains_key(&id.get_package()) {
+            Some(format!("{prefix}{id}"))
+        } else {
+            None
+        }
+    }
 }
 
 impl<'a> ResourcesTrait<'a> for Resources<'a> {
@@ -189,6 +541,7 @@ pub struct Library<'a> {
     spec_string_table: Option<StringTableCache<StringTableWrapper<'a>>>,
     entries_string_table: Option<StringTableCache<StringTableWrapper<'a>>>,
     entries: Entries,
+    config_entries: HashMap<ResourceId, Vec<(Rc<ConfigurationBuf>, Entry)>>,
 }
 
 impl<'a> Library<'a> {
@@ -200,9 +553,93 @@ impl<'a> Library<'a> {
             spec_string_table: None,
             entries_string_table: None,
             entries: Entries::default(),
+            config_entries: HashMap::new(),
         }
     }
 
+    /// Stores the entries defined for the given configuration
+    pub fn add_config_entries(&mut self, config: ConfigurationBuf, entries: &Entries) {
+        let config = Rc::new(config);
+
+        for (id, entry) in entries {
+            self.config_entries
+                .entry(*id)
+                .or_default()
+                .push((Rc::clone(&config), entry.clone()));
+        }
+    }
+
+    /// Returns the entry whose configuration best matches the given device configuration
+    pub fn get_config_entry(&self, id: ResourceId, device: &ConfigurationBuf) -> Option<&Entry> {
+        let mut best: Option<&(Rc<ConfigurationBuf>, Entry)> = None;
+
+        for candidate in self.config_entries.get(&id)? {
+            if !candidate.0.matches(device) {
+                continue;
+            }
+
+            let is_better = match best {
+                Some((config, _)) => candidate.0.is_better_than(config, device),
+                None => true,
+            };
+
+            if is_better {
+                best = Some(candidate);
+            }
+        }
+
+        best.map(|(_, entry)| entry)
+    }
+
+    /// Returns the entries of the resource on every configuration it has a value for, in the
+    /// order they were found
+    pub fn get_config_entries(&self, id: ResourceId) -> &[(Rc<ConfigurationBuf>, Entry)] {
+        self.config_entries.get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the type specs of the package, in no particular order
+    pub fn get_type_specs(&self) -> impl Iterator<Item = &TypeSpecWrapper<'a>> {
+        self.specs.values()
+    }
+
+    /// Returns the string with the given index on the global string table of the package, where
+    /// the string values are stored
+    ///
+    /// # Errors
+    ///
+    /// It fails if the package has no global string table or the index is not on it.
+    pub fn get_global_string(&self, str_id: u32) -> Result<Rc<String>, Error> {
+        let out_string = self
+            .get_global_strings()?
+            .get_string(str_id)
+            .context(format_err!(
+                "could not find string {} on global string table",
+                str_id
+            ))?;
+
+        Ok(out_string)
+    }
+
+    /// Returns the flags of the resource on the type spec
+    ///
+    /// # Errors
+    ///
+    /// It fails if the type spec of the resource is not loaded.
+    pub fn get_spec_flags(&self, id: ResourceId) -> Result<SpecFlags, Error> {
+        self.specs
+            .get(&u32::from(id.get_type()))
+            .ok_or_else(|| format_err!("could not find the type spec of {}", id))?
+            .get_spec_flags(u32::from(id.get_entry()))
+    }
+
+    pub(crate) fn get_global_strings(
+        &self,
+    ) -> Result<&StringTableCache<StringTableWrapper<'a>>, Error> {
+        self.string_table
+            .as_ref()
+            .ok_or_else(|| format_err!("the package does not have a global string table"))
+    }
+
     fn get_spec_as_str(&self, spec_id: u32) -> Result<String, Error> {
         if self.specs.get(&(spec_id)).is_some() {
             if let Some(spec_string_table) = &self.spec_string_table {
@@ -223,12 +660,12 @@ impl<'a> LibraryTrait for Library<'a> {
 
     fn format_reference(
         &self,
-        id: u32,
+        id: ResourceId,
         key: u32,
         namespace: Option<String>,
         prefix: &str,
     ) -> Result<String, Error> {
-        let spec_id = u32::from(id.get_spec());
+        let spec_id = u32::from(id.get_type());
         let spec_str = self
             .get_spec_as_str(spec_id)
             .context(format_err!("could not find spec: {}", spec_id))?;
@@ -249,7 +686,7 @@ impl<'a> LibraryTrait for Library<'a> {
         }
     }
 
-    fn get_entry(&self, id: u32) -> Result<&Entry, Error> {
+    fn get_entry(&self, id: ResourceId) -> Result<&Entry, Error> {
         self.entries
             .get(&id)
             .ok_or_else(|| format_err!("could not find entry"))
@@ -305,3 +742,363 @@ impl<'a> LibraryBuilder<'a> for Library<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ModelVisitor;
+    use crate::{
+        decoder::Decoder,
+        model::{
+            owned::ConfigurationBuf, AttributeValue, BagValue, ConfigChanges, Library, ResourceId,
+            ResourceTable, TableEntry, Value,
+        },
+        visitor::Executor,
+    };
+
+    fn example_arsc() -> Vec<u8> {
+        let mut table = ResourceTable::default();
+        table.add_package(0x7F, "com.example").unwrap();
+
+        for (type_name, name) in &[
+            ("string", "app_name"),
+            ("string", "title"),
+            ("bool", "flag"),
+        ] {
+            let id = table.add_resource(0x7F, type_name, name).unwrap();
+            let value = TableEntry::Value(AttributeValue::Boolean(true));
+            table
+                .set_entry(id, &ConfigurationBuf::default(), value)
+                .unwrap();
+        }
+
+        table.to_vec().unwrap()
+    }
+
+    #[test]
+    fn it_can_find_the_id_of_a_resource_name() {
+        let content = example_arsc();
+        let decoder = Decoder::from_buffer(&content).unwrap();
+        let resources = decoder.get_resources();
+
+        assert_eq!(
+            ResourceId::new(0x7F, 1, 1),
+            resources.find_id("string/title").unwrap()
+        );
+        assert_eq!(
+            ResourceId::new(0x7F, 2, 0),
+            resources.find_id("@com.example:bool/flag").unwrap()
+        );
+        assert!(resources.find_id("string/missing").is_err());
+        assert!(resources.find_id("android:string/title").is_err());
+    }
+
+    #[test]
+    fn it_can_find_the_name_of_a_resource_id() {
+        let content = example_arsc();
+        let decoder = Decoder::from_buffer(&content).unwrap();
+        let name = decoder
+            .get_resources()
+            .name_of(ResourceId::new(0x7F, 1, 0))
+            .unwrap();
+
+        assert_eq!(Some("com.example"), name.get_package());
+        assert_eq!("string", name.get_type());
+        assert_eq!("app_name", name.get_entry());
+        assert!(decoder
+            .get_resources()
+            .name_of(ResourceId::new(0x7F, 1, 5))
+            .is_none());
+    }
+
+    fn references_arsc() -> Vec<u8> {
+        let mut table = ResourceTable::default();
+        table.add_package(0x7F, "com.example").unwrap();
+        let default = ConfigurationBuf::default();
+        let mut catalan = ConfigurationBuf::default();
+        catalan.set_language("ca");
+
+        let greeting = table.add_resource(0x7F, "string", "greeting").unwrap();
+        let alias = table.add_resource(0x7F, "string", "alias").unwrap();
+        let first = table.add_resource(0x7F, "string", "first").unwrap();
+        let second = table.add_resource(0x7F, "string", "second").unwrap();
+
+        let text = |text: &str| TableEntry::Value(AttributeValue::String(text.to_string()));
+        let reference = |id| TableEntry::Value(AttributeValue::Reference(id));
+
+        table.set_entry(greeting, &default, text("Hello")).unwrap();
+        table.set_entry(greeting, &catalan, text("Hola")).unwrap();
+        table
+            .set_entry(alias, &default, reference(greeting))
+            .unwrap();
+        table.set_entry(first, &default, reference(second)).unwrap();
+        table.set_entry(second, &default, reference(first)).unwrap();
+
+        table.to_vec().unwrap()
+    }
+
+    #[test]
+    fn it_resolves_references_to_the_final_value() {
+        let content = references_arsc();
+        let decoder = Decoder::from_buffer(&content).unwrap();
+        let resources = decoder.get_resources();
+        let alias = resources.find_id("string/alias").unwrap();
+
+        match resources.resolve_value(alias, &ConfigurationBuf::default()) {
+            Ok(Value::String(text)) => assert_eq!("Hello", text),
+            other => panic!("unexpected value: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_loads_shared_libraries_with_their_own_package_id() {
+        let mut table = ResourceTable::default();
+        table.add_package(0, "com.example.library").unwrap();
+        let flag = table.add_resource(0, "bool", "flag").unwrap();
+        let alias = table.add_resource(0, "bool", "alias").unwrap();
+        let default = ConfigurationBuf::default();
+        table
+            .set_entry(
+                flag,
+                &default,
+                TableEntry::Value(AttributeValue::Boolean(true)),
+            )
+            .unwrap();
+        table
+            .set_entry(
+                alias,
+                &default,
+                TableEntry::Value(AttributeValue::Reference(flag)),
+            )
+            .unwrap();
+        let content = table.to_vec().unwrap();
+
+        let decoder = Decoder::from_buffer(&content).unwrap();
+        let resources = decoder.get_resources();
+
+        assert_eq!(0, alias.get_package());
+        assert_eq!(
+            alias,
+            resources.find_id("com.example.library:bool/alias").unwrap()
+        );
+        match resources.resolve_value(alias, &default) {
+            Ok(Value::Boolean(value)) => assert!(value),
+            other => panic!("unexpected value: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_resolves_the_value_of_the_best_matching_configuration() {
+        let content = references_arsc();
+        let decoder = Decoder::from_buffer(&content).unwrap();
+        let resources = decoder.get_resources();
+        let alias = resources.find_id("string/alias").unwrap();
+        let mut catalan = ConfigurationBuf::default();
+        catalan.set_language("ca");
+        let mut english = ConfigurationBuf::default();
+        english.set_language("en");
+
+        let text = |config| match resources.resolve_value(alias, config).unwrap() {
+            Value::String(text) => text,
+            other => panic!("unexpected value: {:?}", other),
+        };
+
+        assert_eq!("Hola", text(&catalan));
+        assert_eq!("Hello", text(&english));
+    }
+
+    #[test]
+    fn it_returns_the_flags_of_the_type_specs() {
+        let mut table = ResourceTable::from_bytes(&references_arsc()).unwrap();
+        let alias = ResourceId::new(0x7F, 1, 1);
+        table.set_public(alias, true).unwrap();
+        let content = table.to_vec().unwrap();
+        let decoder = Decoder::from_buffer(&content).unwrap();
+        let resources = decoder.get_resources();
+
+        let localized: Vec<String> = resources
+            .get_names()
+            .filter(|(id, _)| {
+                resources.get_spec_flags(*id).unwrap().get_config_changes() == ConfigChanges::LOCALE
+            })
+            .map(|(_, name)| name.get_entry().to_string())
+            .collect();
+
+        assert_eq!(vec!["greeting".to_string()], localized);
+        assert!(resources.get_spec_flags(alias).unwrap().is_public());
+        assert!(!resources
+            .get_spec_flags(resources.find_id("string/greeting").unwrap())
+            .unwrap()
+            .is_public());
+    }
+
+    #[test]
+    fn it_detects_reference_cycles() {
+        let content = references_arsc();
+        let decoder = Decoder::from_buffer(&content).unwrap();
+        let resources = decoder.get_resources();
+        let first = resources.find_id("string/first").unwrap();
+
+        assert!(resources
+            .resolve_value(first, &ConfigurationBuf::default())
+            .is_err());
+        assert!(resources
+            .resolve_value(ResourceId::new(0x7F, 1, 9), &ConfigurationBuf::default())
+            .is_err());
+    }
+
+    fn themes_arsc() -> (Vec<u8>, Vec<u8>) {
+        let default = ConfigurationBuf::default();
+        let bag = |parent, items| TableEntry::Bag { parent, items };
+
+        let mut android = ResourceTable::default();
+        android.add_package(0x01, "android").unwrap();
+        let color_primary = android.add_resource(0x01, "attr", "colorPrimary").unwrap();
+        let text_color = android.add_resource(0x01, "attr", "textColor").unwrap();
+        let theme = android.add_resource(0x01, "style", "Theme").unwrap();
+        for attribute in &[color_primary, text_color] {
+            android
+                .set_entry(*attribute, &default, bag(ResourceId::default(), Vec::new()))
+                .unwrap();
+        }
+        let items = vec![
+            (color_primary, AttributeValue::Integer(1)),
+            (text_color, AttributeValue::Integer(2)),
+        ];
+        android
+            .set_entry(theme, &default, bag(ResourceId::default(), items))
+            .unwrap();
+
+        let mut app = ResourceTable::default();
+        app.add_package(0x7F, "com.example").unwrap();
+        let accent = app.add_resource(0x7F, "string", "accent").unwrap();
+        let app_theme = app.add_resource(0x7F, "style", "AppTheme").unwrap();
+        let dark_theme = app.add_resource(0x7F, "style", "AppTheme.Dark").unwrap();
+        let accent_value = AttributeValue::String("Accent".to_string());
+        app.set_entry(accent, &default, TableEntry::Value(accent_value))
+            .unwrap();
+        let items = vec![(color_primary, AttributeValue::Reference(accent))];
+        app.set_entry(app_theme, &default, bag(theme, items))
+            .unwrap();
+        let items = vec![(
+            text_color,
+            AttributeValue::AttributeReference(color_primary),
+        )];
+        app.set_entry(dark_theme, &default, bag(ResourceId::default(), items))
+            .unwrap();
+
+        (android.to_vec().unwrap(), app.to_vec().unwrap())
+    }
+
+    #[test]
+    fn it_finds_the_names_without_package_on_the_app_package() {
+        let (android, app) = themes_arsc();
+        let mut visitor = ModelVisitor::default();
+        Executor::arsc(&android, &mut visitor).unwrap();
+
+        // Only the framework is loaded
+        let theme = visitor.get_resources().find_id("style/Theme").unwrap();
+        assert_eq!(ResourceId::new(0x01, 2, 0), theme);
+
+        Executor::arsc(&app, &mut visitor).unwrap();
+        let resources = visitor.get_resources();
+
+        assert_eq!(
+            Some("com.example".to_string()),
+            resources.get_app_package().and_then(Library::get_name)
+        );
+        assert_eq!(
+            ResourceId::new(0x7F, 2, 0),
+            resources.find_id("style/AppTheme").unwrap()
+        );
+        assert_eq!(theme, resources.find_id("android:style/Theme").unwrap());
+        assert!(resources.find_id("style/Theme").is_err());
+    }
+
+    #[test]
+    fn it_flattens_the_attributes_of_a_theme() {
+        let (android, app) = themes_arsc();
+        let mut visitor = ModelVisitor::default();
+        Executor::arsc(&android, &mut visitor).unwrap();
+        Executor::arsc(&app, &mut visitor).unwrap();
+        let resources = visitor.get_resources();
+        let config = ConfigurationBuf::default();
+
+        let dark = resources
+            .find_id("com.example:style/AppTheme.Dark")
+            .unwrap();
+        let theme = resources.get_theme(dark, &config).unwrap();
+
+        assert_eq!(
+            &[
+                resources.find_id("com.example:style/AppTheme").unwrap(),
+                resources.find_id("android:style/Theme").unwrap(),
+            ],
+            theme.get_parents()
+        );
+        assert_eq!(2, theme.get_attributes().len());
+        assert_eq!(
+            Some(&Value::ReferenceId(
+                resources.find_id("com.example:string/accent").unwrap()
+            )),
+            theme.get_attribute(resources.find_id("android:attr/colorPrimary").unwrap())
+        );
+    }
+
+    #[test]
+    fn it_resolves_attribute_references_against_a_theme() {
+        let (android, app) = themes_arsc();
+        let mut visitor = ModelVisitor::default();
+        Executor::arsc(&android, &mut visitor).unwrap();
+        Executor::arsc(&app, &mut visitor).unwrap();
+        let resources = visitor.get_resources();
+        let config = ConfigurationBuf::default();
+
+        let text_color = resources.find_id("android:attr/textColor").unwrap();
+        let dark = resources
+            .find_id("com.example:style/AppTheme.Dark")
+            .unwrap();
+        let dark = resources.get_theme(dark, &config).unwrap();
+        let base = resources.find_id("android:style/Theme").unwrap();
+        let base = resources.get_theme(base, &config).unwrap();
+
+        let resolve = |theme| {
+            resources
+                .resolve_in_theme(Value::AttributeReferenceId(text_color), theme, &config)
+                .unwrap()
+        };
+
+        assert_eq!(Value::String("Accent".to_string()), resolve(&dark));
+        assert_eq!(Value::Integer(2), resolve(&base));
+
+        let missing = Value::AttributeReferenceId(ResourceId::new(0x01, 1, 9));
+        assert!(resources.resolve_in_theme(missing, &dark, &config).is_err());
+    }
+
+    #[test]
+    fn it_returns_typed_views_of_bags() {
+        let (android, app) = themes_arsc();
+        let mut visitor = ModelVisitor::default();
+        Executor::arsc(&android, &mut visitor).unwrap();
+        Executor::arsc(&app, &mut visitor).unwrap();
+        let resources = visitor.get_resources();
+        let config = ConfigurationBuf::default();
+
+        let app_theme = resources.find_id("com.example:style/AppTheme").unwrap();
+        match resources.get_bag(app_theme, &config).unwrap() {
+            BagValue::Style(style) => assert_eq!(
+                resources.find_id("android:style/Theme").unwrap(),
+                style.get_parent()
+            ),
+            other => panic!("unexpected bag: {:?}", other),
+        }
+
+        let text_color = resources.find_id("android:attr/textColor").unwrap();
+        match resources.get_bag(text_color, &config).unwrap() {
+            BagValue::Attr(attr) => assert!(attr.get_symbols().is_empty()),
+            other => panic!("unexpected bag: {:?}", other),
+        }
+
+        let accent = resources.find_id("com.example:string/accent").unwrap();
+        assert!(resources.get_bag(accent, &config).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use failure::Error;
+
 use super::{ChunkVisitor, Origin};
 use crate::{
     chunks::{PackageWrapper, StringTableWrapper, TableTypeWrapper, TypeSpecWrapper},
@@ -9,28 +11,40 @@ use crate::{
 pub struct PrintVisitor;
 
 impl<'a> ChunkVisitor<'a> for PrintVisitor {
-    fn visit_string_table(&mut self, string_table: StringTableWrapper, origin: Origin) {
+    fn visit_string_table(
+        &mut self,
+        string_table: StringTableWrapper,
+        origin: Origin,
+    ) -> Result<(), Error> {
         println!("String Table!");
         println!(
             "\tLength ({:?}): {} ",
             origin,
             string_table.get_strings_len()
         );
+
+        Ok(())
     }
 
-    fn visit_package(&mut self, package: PackageWrapper) {
+    fn visit_package(&mut self, package: PackageWrapper) -> Result<(), Error> {
         println!("Package!");
-        println!("\tId: {}", package.get_id().unwrap());
-        println!("\tName: {}", package.get_name().unwrap());
+        println!("\tId: {}", package.get_id()?);
+        println!("\tName: {}", package.get_name()?);
+
+        Ok(())
     }
 
-    fn visit_table_type(&mut self, table_type: TableTypeWrapper) {
+    fn visit_table_type(&mut self, table_type: TableTypeWrapper) -> Result<(), Error> {
         println!("Table type!");
-        println!("\tId: {}", table_type.get_id().unwrap());
+        println!("\tId: {}", table_type.get_id()?);
+
+        Ok(())
     }
 
-    fn visit_type_spec(&mut self, type_spec: TypeSpecWrapper) {
+    fn visit_type_spec(&mut self, type_spec: TypeSpecWrapper) -> Result<(), Error> {
         println!("Type spec!");
-        println!("\tId: {}", type_spec.get_id().unwrap());
+        println!("\tId: {}", type_spec.get_id()?);
+
+        Ok(())
     }
 }
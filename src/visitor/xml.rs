@@ -1,7 +1,6 @@
-use std::{cmp::Ordering, collections::HashMap, rc::Rc};
+use std::{cmp::Ordering, collections::HashMap, mem, rc::Rc};
 
-use failure::{format_err, Error, ResultExt};
-use log::{error, info, warn};
+use failure::{ensure, format_err, Error, ResultExt};
 
 use super::{ChunkVisitor, Origin};
 use crate::{
@@ -10,10 +9,12 @@ use crate::{
         XmlNamespaceStartWrapper, XmlTagEndWrapper, XmlTagStartWrapper,
     },
     encoder::Xml,
+    errors::{locate, AbxmlError, Location},
     model::{
-        owned::SimpleEntry, AttributeTrait, Element, ElementContainer, Identifier, Library,
-        NamespaceStart, Namespaces, Resources as ResourceTrait, StringTable, Tag, TagStart, Value,
+        AttrSymbol, AttrValue, AttributeTrait, Element, ElementContainer, Library, NamespaceStart,
+        Namespaces, ResourceId, Resources as ResourceTrait, StringTable, Tag, TagStart, Value,
     },
+    options::ParseOptions,
     visitor::model::Resources,
 };
 
@@ -25,17 +26,32 @@ pub struct XmlVisitor<'a> {
     res: Vec<u32>,
     resources: &'a Resources<'a>,
     namespace_prefixes: Vec<Rc<String>>,
+    options: ParseOptions,
+    diagnostics: Vec<AbxmlError>,
+    /// For each open tag, if it was added to the container
+    open_tags: Vec<bool>,
 }
 
 impl<'a> XmlVisitor<'a> {
     pub fn new(resources: &'a Resources<'a>) -> Self {
+        Self::with_options(resources, ParseOptions::default())
+    }
+
+    /// Creates a visitor that handles the problems found on the document as `options` says. On
+    /// lenient mode, elements that can not be decoded are skipped (their children are added to
+    /// their parent) and attributes that can not be resolved keep their raw value.
+    #[must_use]
+    pub fn with_options(resources: &'a Resources<'a>, options: ParseOptions) -> Self {
         Self {
             main_string_table: None,
             namespaces: Namespaces::default(),
-            container: ElementContainer::default(),
+            container: ElementContainer::with_limits(options.get_limits()),
             res: Vec::new(),
             resources,
             namespace_prefixes: Vec::new(),
+            options,
+            diagnostics: Vec::new(),
+            open_tags: Vec::new(),
         }
     }
 
@@ -47,6 +63,10 @@ impl<'a> XmlVisitor<'a> {
         self.container.get_root()
     }
 
+    pub fn into_root(self) -> Option<Element> {
+        self.container.into_root()
+    }
+
     pub fn get_string_table(&self) -> &Option<StringTableCache<StringTableWrapper<'a>>> {
         &self.main_string_table
     }
@@ -61,26 +81,29 @@ impl<'a> XmlVisitor<'a> {
 
     // TODO: Convert to TryInto once it will be stable
     pub fn into_string(self) -> Result<String, Error> {
-        if let Some(root) = self.get_root() {
-            if self.get_string_table().is_some() {
-                let res =
-                    Xml::encode(self.get_namespaces(), root).context("could note encode XML")?;
-                return Ok(res);
-            } else {
-                warn!("No string table found");
-            }
-        } else {
-            warn!("No root on target XML");
-        }
+        let root = self
+            .get_root()
+            .as_ref()
+            .ok_or_else(|| format_err!("could not decode XML: no root element found"))?;
+        ensure!(
+            self.get_string_table().is_some(),
+            "could not decode XML: no string table found"
+        );
+
+        let res = Xml::encode(self.get_namespaces(), root).context("could note encode XML")?;
 
-        Err(format_err!("could not decode XML"))
+        Ok(res)
     }
 
-    fn build_element(&self, tag_start: &XmlTagStartWrapper) -> Result<Element, Error> {
+    fn build_element(
+        &self,
+        tag_start: &XmlTagStartWrapper,
+        diagnostics: &mut Vec<AbxmlError>,
+    ) -> Result<Element, Error> {
         match &self.main_string_table {
             Some(string_table) => {
                 let (tag, attributes) = self
-                    .get_element_data(&string_table, tag_start)
+                    .get_element_data(&string_table, tag_start, diagnostics)
                     .context("could not get element data")?;
                 Ok(Element::new(tag, attributes))
             }
@@ -92,6 +115,7 @@ impl<'a> XmlVisitor<'a> {
         &self,
         string_table: &StringTableCache<StringTableWrapper<'a>>,
         tag_start: &XmlTagStartWrapper,
+        diagnostics: &mut Vec<AbxmlError>,
     ) -> Result<(Tag, HashMap<String, String>), Error> {
         let name_index = tag_start
             .get_element_name_index()
@@ -112,47 +136,50 @@ impl<'a> XmlVisitor<'a> {
                 .get_attribute(i)
                 .context(format_err!("could not read attribute {} ", i))?;
 
+            let location = Location::relative(36 + u64::from(i) * 20);
+
             let namespace_index = current_attribute.get_namespace()?;
             if namespace_index != 0xFFFF_FFFF {
                 let namespace = (*string_table.get_string(namespace_index)?).clone();
-                let prefix = self
-                    .namespaces
-                    .get(&namespace)
-                    .ok_or_else(|| format_err!("namespace not found"))?;
-                final_name.push_str(prefix);
-                final_name.push(':');
+
+                match self.namespaces.get(&namespace) {
+                    Some(prefix) => {
+                        final_name.push_str(prefix);
+                        final_name.push(':');
+                    }
+                    None => self.options.recover(
+                        AbxmlError::Malformed {
+                            location: location.clone(),
+                            reason: format!("namespace {namespace} is not declared"),
+                        },
+                        diagnostics,
+                    )?,
+                }
             }
 
             let name_index = current_attribute.get_name()?;
             let name = string_table.get_string(name_index)?;
             final_name.push_str((*name).as_str());
 
-            let current_value = current_attribute.get_value()?;
-            let value = match current_value {
-                Value::StringReference(index) => (*string_table.get_string(index)?).clone(),
-                Value::ReferenceId(id) => {
-                    AttributeHelper::resolve_reference(self.resources, id, "@")
-                        .context("could not resolve reference")?
-                }
-                Value::AttributeReferenceId(id) => {
-                    AttributeHelper::resolve_reference(self.resources, id, "?")
-                        .context("could not resolve attribute reference")?
-                }
-                Value::Integer(value) | Value::Flags(value) => {
-                    let flag_resolution = AttributeHelper::resolve_flags(
-                        &current_attribute,
-                        value,
-                        &self.res,
-                        self.resources,
-                    );
-
-                    if let Some(flag_resolution) = flag_resolution {
-                        flag_resolution
+            let value = match AttributeHelper::format_value(
+                &current_attribute,
+                string_table,
+                &self.res,
+                self.resources,
+            ) {
+                Ok(value) => value,
+                Err(e) => {
+                    let raw_value = current_attribute.get_value()?;
+
+                    if let Some(reference) = self.resources.format_missing_reference(&raw_value) {
+                        reference
                     } else {
-                        current_attribute.get_value()?.to_string()
+                        self.options
+                            .recover(locate(&e, location.get_offset(), &[]), diagnostics)?;
+
+                        raw_value.to_string()
                     }
                 }
-                _ => current_value.to_string(),
             };
 
             attributes.insert(final_name, value);
@@ -163,98 +190,168 @@ impl<'a> XmlVisitor<'a> {
 }
 
 impl<'a> ChunkVisitor<'a> for XmlVisitor<'a> {
-    fn visit_string_table(&mut self, string_table: StringTableWrapper<'a>, _: Origin) {
+    fn visit_string_table(
+        &mut self,
+        string_table: StringTableWrapper<'a>,
+        _: Origin,
+    ) -> Result<(), Error> {
         if self.main_string_table.is_some() {
-            error!("Secondary table!");
+            self.options.recover(
+                AbxmlError::Malformed {
+                    location: Location::default(),
+                    reason: "secondary string tables are not supported".to_string(),
+                },
+                &mut self.diagnostics,
+            )
         } else {
             self.main_string_table = Some(StringTableCache::new(string_table));
+
+            Ok(())
         }
     }
 
-    fn visit_xml_namespace_start(&mut self, namespace_start: XmlNamespaceStartWrapper<'a>) {
+    fn visit_xml_namespace_start(
+        &mut self,
+        namespace_start: XmlNamespaceStartWrapper<'a>,
+    ) -> Result<(), Error> {
         if let Some(ref mut string_table) = self.main_string_table {
-            if let (Ok(namespace), Ok(prefix)) = (
-                namespace_start.get_namespace(string_table),
-                namespace_start.get_prefix(string_table),
-            ) {
-                self.namespaces
-                    .insert((*namespace).clone(), (*prefix).clone());
-                self.namespace_prefixes.push(namespace.clone());
-            } else {
-                error!("Error reading namespace from the string table");
-            }
+            let namespace = namespace_start
+                .get_namespace(string_table)
+                .context("could not read the namespace from the string table")?;
+            let prefix = namespace_start
+                .get_prefix(string_table)
+                .context("could not read the prefix from the string table")?;
+
+            self.namespaces
+                .insert((*namespace).clone(), (*prefix).clone());
+            self.namespace_prefixes.push(namespace);
         }
+
+        Ok(())
     }
 
-    fn visit_xml_tag_start(&mut self, tag_start: XmlTagStartWrapper<'a>) {
-        let element_result = self.build_element(&tag_start);
-        if let Ok(element) = element_result {
-            self.container.start_element(element);
-        } else {
-            error!("Could not build a XML element")
+    fn visit_xml_tag_start(&mut self, tag_start: XmlTagStartWrapper<'a>) -> Result<(), Error> {
+        let mut diagnostics = Vec::new();
+        let result = self.build_element(&tag_start, &mut diagnostics);
+        self.diagnostics.append(&mut diagnostics);
+
+        match result.and_then(|element| self.container.start_element(element)) {
+            Ok(()) => self.open_tags.push(true),
+            Err(e) => {
+                let e = e.context("could not build a XML element").into();
+                self.options
+                    .recover(locate(&e, 0, &[]), &mut self.diagnostics)?;
+                self.open_tags.push(false);
+            }
         }
+
+        Ok(())
     }
 
-    fn visit_xml_tag_end(&mut self, _: XmlTagEndWrapper<'a>) {
-        self.container.end_element()
+    fn visit_xml_tag_end(&mut self, _: XmlTagEndWrapper<'a>) -> Result<(), Error> {
+        match self.open_tags.pop() {
+            Some(false) => Ok(()),
+            _ => self.container.end_element(),
+        }
     }
 
-    fn visit_xml_namespace_end(&mut self, _: XmlNamespaceEndWrapper<'a>) {
+    fn visit_xml_namespace_end(&mut self, _: XmlNamespaceEndWrapper<'a>) -> Result<(), Error> {
         let _ = self.namespace_prefixes.pop();
+
+        Ok(())
     }
 
-    fn visit_resource(&mut self, resource: ResourceWrapper<'a>) {
-        if let Ok(res) = resource.get_resources() {
-            self.res = res;
-        }
+    fn visit_resource(&mut self, resource: ResourceWrapper<'a>) -> Result<(), Error> {
+        self.res = resource
+            .get_resources()
+            .context("could not read the resource ids")?;
+
+        Ok(())
+    }
+
+    fn take_diagnostics(&mut self) -> Vec<AbxmlError> {
+        mem::take(&mut self.diagnostics)
     }
 }
 
 pub struct AttributeHelper;
 
 impl AttributeHelper {
+    /// Formats the value of the given attribute as it should appear on a text XML, resolving
+    /// strings, references and flags.
+    #[allow(clippy::cast_sign_loss)]
+    pub fn format_value<'a, R: ResourceTrait<'a>, A: AttributeTrait, S: StringTable>(
+        attribute: &A,
+        string_table: &S,
+        xml_resources: &[u32],
+        resources: &R,
+    ) -> Result<String, Error> {
+        let current_value = attribute.get_value()?;
+        let value = match current_value {
+            Value::StringReference(index) => (*string_table.get_string(index)?).clone(),
+            Value::ReferenceId(id) | Value::DynamicReferenceId(id) => {
+                Self::resolve_reference(resources, id, "@")
+                    .context("could not resolve reference")?
+            }
+            Value::AttributeReferenceId(id) | Value::DynamicAttributeReferenceId(id) => {
+                Self::resolve_reference(resources, id, "?")
+                    .context("could not resolve attribute reference")?
+            }
+            Value::Integer(value) => {
+                Self::resolve_flags(attribute, value as u32, xml_resources, resources)?
+                    .unwrap_or_else(|| current_value.to_string())
+            }
+            Value::IntegerHex(value) => {
+                Self::resolve_flags(attribute, value, xml_resources, resources)?
+                    .unwrap_or_else(|| current_value.to_string())
+            }
+            _ => current_value.to_string(),
+        };
+
+        Ok(value)
+    }
+
     pub fn resolve_reference<'a, R: ResourceTrait<'a>>(
         resources: &R,
-        id: u32,
+        id: ResourceId,
         prefix: &str,
     ) -> Result<String, Error> {
-        let res_id = id;
-        let package_id = id.get_package();
-
-        if id == 0 {
+        if id.is_null() {
             return Ok("@null".to_string());
         }
 
+        let package_id = id.get_package();
+
         let is_main = resources.is_main_package(package_id);
+        let unresolved = || AbxmlError::UnresolvedReference {
+            location: Location::default(),
+            id,
+        };
         let package = resources
             .get_package(package_id)
-            .ok_or_else(|| format_err!("package not found"))?;
-
-        let entry_key = package.get_entry(res_id).and_then(|e| Ok(e.get_key())).ok();
+            .ok_or_else(|| Error::from(unresolved()).context("package not found"))?;
+        let key = package.get_entry(id).map_err(|_| unresolved())?.get_key();
+        let namespace = if is_main { None } else { package.get_name() };
 
-        if let Some(key) = entry_key {
-            let namespace = if is_main { None } else { package.get_name() };
-            return package.format_reference(id, key, namespace, prefix);
-        }
-
-        Err(format_err!("error resolving reference"))
+        package.format_reference(id, key, namespace, prefix)
     }
 
+    /// Names of the enum or flag values of the attribute that match `flags`, if the attribute
+    /// has any. It fails if the names of the matching values can not be read.
     pub fn resolve_flags<'a, R: ResourceTrait<'a>, A: AttributeTrait>(
         attribute: &A,
         flags: u32,
         xml_resources: &[u32],
         resources: &R,
-    ) -> Option<String> {
+    ) -> Result<Option<String>, Error> {
         // Check if it's the special value in which the integer is an Enum
         // In that case, we return a crafted string instead of the integer itself
-        let name_index = attribute.get_name().unwrap();
-        if name_index < xml_resources.len() as u32 {
-            Self::search_values(flags, name_index, xml_resources, resources)
-        } else {
-            let str = format!("@flags:{}", flags);
-
-            Some(str.to_string())
+        match attribute.get_name() {
+            Ok(name_index) if name_index < xml_resources.len() as u32 => {
+                Self::search_values(flags, name_index, xml_resources, resources)
+            }
+            Ok(_) => Ok(Some(format!("@flags:{flags}"))),
+            Err(_) => Ok(None),
         }
     }
 
@@ -263,97 +360,94 @@ impl AttributeHelper {
         name_index: u32,
         xml_resources: &[u32],
         resources: &R,
-    ) -> Option<String> {
+    ) -> Result<Option<String>, Error> {
         let entry_ref = match xml_resources.get(name_index as usize) {
-            Some(entry_ref) => entry_ref,
-            None => return None,
+            Some(entry_ref) => ResourceId::from(*entry_ref),
+            None => return Ok(None),
         };
 
-        let package_id = entry_ref.get_package();
-        resources
-            .get_package(package_id)
-            .and_then(|package| Self::search_flags(flags, *entry_ref, package))
+        match resources.get_package(entry_ref.get_package()) {
+            Some(package) => Self::search_flags(flags, entry_ref, package),
+            None => Ok(None),
+        }
     }
 
-    fn search_flags(flags: u32, entry_ref: u32, package: &dyn Library) -> Option<String> {
-        let str_indexes = Self::get_strings(flags, entry_ref, package);
-        let str_strs: Vec<String> = str_indexes
+    fn search_flags(
+        flags: u32,
+        entry_ref: ResourceId,
+        package: &dyn Library,
+    ) -> Result<Option<String>, Error> {
+        let str_indexes = Self::get_strings(flags, entry_ref, package)?;
+        let str_strs = str_indexes
             .iter()
             .map(|si| {
-                if let Ok(str) = package.get_entries_string(*si) {
-                    (*str).clone()
-                } else {
-                    error!("Key not found on the string table");
-                    String::new()
-                }
+                package
+                    .get_entries_string(*si)
+                    .map(|str| (*str).clone())
+                    .context(format_err!(
+                        "could not read the name of a value of {}",
+                        entry_ref
+                    ))
             })
-            .collect();
+            .collect::<Result<Vec<String>, _>>()?;
 
         if str_strs.is_empty() {
-            None
+            Ok(None)
         } else {
             let final_string = str_strs.join("|");
-            Some(final_string)
+            Ok(Some(final_string))
         }
     }
 
-    fn get_strings(flags: u32, entry_ref: u32, package: &dyn Library) -> Vec<u32> {
+    fn get_strings(
+        flags: u32,
+        entry_ref: ResourceId,
+        package: &dyn Library,
+    ) -> Result<Vec<u32>, Error> {
         use crate::model::owned::Entry;
 
         let mut strs = Vec::new();
         let mut masks = Vec::new();
 
-        let inner_entries = package
+        let mut symbols = package
             .get_entry(entry_ref)
             .and_then(Entry::complex)
-            .and_then(|c| Ok(c.get_entries().to_vec()))
+            .and_then(|c| Ok(AttrValue::from_entry(c).get_symbols().to_vec()))
             .unwrap_or_else(|_| Vec::new());
 
-        let mut sorted = inner_entries.to_vec();
+        symbols.sort_by(Self::compare_symbols);
 
-        sorted.sort_by(Self::compare_entries);
-
-        for ie in sorted {
-            let mask = ie.get_value();
+        for symbol in symbols {
+            let mask = symbol.get_value();
             if (mask & flags) == mask {
-                let maybe_entry = package.get_entry(ie.get_id());
-
-                if let Ok(entry) = maybe_entry {
-                    let mut has_to_add = true;
-
-                    for s in &masks {
-                        if mask & s == mask {
-                            has_to_add = false;
-                            break;
-                        }
-                    }
-
-                    if has_to_add {
-                        entry
-                            .simple()
-                            .and_then(|s| Ok(s.get_key()))
-                            .and_then(|key| {
-                                strs.push(key);
-                                masks.push(mask);
-                                Ok(())
-                            })
-                            .unwrap_or_else(|_| {
-                                error!(
-                                    "Value should be added but there was an issue reading \
-                                     the entry"
-                                );
-                            });
-                    }
-                } else {
-                    info!("Some entry matched but could not found on entries");
+                let entry = package.get_entry(symbol.get_name()).context(format_err!(
+                    "value {} of {} is not on the entries",
+                    symbol.get_name(),
+                    entry_ref
+                ))?;
+
+                let has_to_add = masks.iter().all(|s| mask & s != mask);
+
+                if has_to_add {
+                    let key = entry
+                        .simple()
+                        .context(format_err!(
+                            "could not read value {} of {}",
+                            symbol.get_name(),
+                            entry_ref
+                        ))?
+                        .get_key();
+                    strs.push(key);
+                    masks.push(mask);
                 }
             }
         }
 
-        strs
+        Ok(strs)
     }
 
-    fn compare_entries(a: &SimpleEntry, b: &SimpleEntry) -> Ordering {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn compare_symbols(a: &AttrSymbol, b: &AttrSymbol) -> Ordering {
         let id_a = a.get_value();
         let id_b = b.get_value();
 
@@ -387,7 +481,7 @@ mod tests {
     use crate::{
         model::{
             owned::{AttributeBuf, ComplexEntry, Entry, SimpleEntry},
-            Entries, Library, LibraryBuilder, Resources, StringTable, TypeSpec,
+            Entries, Library, LibraryBuilder, ResourceId, Resources, StringTable, TypeSpec,
         },
         test::FakeStringTable,
         visitor::Origin,
@@ -426,13 +520,13 @@ mod tests {
             let entry_ce1 = Entry::Complex(complex_entry1);
 
             let mut entries = Entries::new();
-            entries.insert((1 << 24) | 1, entry1);
-            entries.insert((2 << 24) | 1, entry2);
-            entries.insert((2 << 24) | 2, entry3);
-            entries.insert((2 << 24) | 3, entry_ce1);
-            entries.insert((2 << 24) | 4, entry4);
-            entries.insert((2 << 24) | 5, entry5);
-            entries.insert((2 << 24) | 6, entry6);
+            entries.insert(ResourceId::new(1, 0, 1), entry1);
+            entries.insert(ResourceId::new(2, 0, 1), entry2);
+            entries.insert(ResourceId::new(2, 0, 2), entry3);
+            entries.insert(ResourceId::new(2, 0, 3), entry_ce1);
+            entries.insert(ResourceId::new(2, 0, 4), entry4);
+            entries.insert(ResourceId::new(2, 0, 5), entry5);
+            entries.insert(ResourceId::new(2, 0, 6), entry6);
 
             Self { entries }
         }
@@ -445,21 +539,21 @@ mod tests {
 
         fn format_reference(
             &self,
-            id: u32,
+            id: ResourceId,
             _: u32,
             namespace: Option<String>,
             _: &str,
         ) -> Result<String, Error> {
-            if id == (1 << 24) | 1 && namespace.is_none() {
+            if id == ResourceId::new(1, 0, 1) && namespace.is_none() {
                 Ok("reference#1".to_string())
-            } else if id == (2 << 24) | 1 && namespace.is_some() {
+            } else if id == ResourceId::new(2, 0, 1) && namespace.is_some() {
                 Ok("NS:reference#2".to_string())
             } else {
                 Err(format_err!("could not format"))
             }
         }
 
-        fn get_entry(&self, id: u32) -> Result<&Entry, Error> {
+        fn get_entry(&self, id: ResourceId) -> Result<&Entry, Error> {
             self.entries
                 .get(&id)
                 .ok_or_else(|| format_err!("could not find entry"))
@@ -549,7 +643,8 @@ mod tests {
     fn it_resolves_to_null_if_id_is_0() {
         let resources = FakeResources::fake();
 
-        let reference = AttributeHelper::resolve_reference(&resources, 0, "prefix");
+        let reference =
+            AttributeHelper::resolve_reference(&resources, ResourceId::from(0), "prefix");
 
         assert_eq!("@null", reference.unwrap());
     }
@@ -558,7 +653,8 @@ mod tests {
     fn it_returns_error_if_the_provided_id_is_related_to_a_non_existing_package() {
         let resources = FakeResources::fake();
 
-        let reference = AttributeHelper::resolve_reference(&resources, 3 << 24, "prefix");
+        let reference =
+            AttributeHelper::resolve_reference(&resources, ResourceId::new(3, 0, 0), "prefix");
 
         assert!(reference.is_err());
         assert_eq!("package not found", reference.err().unwrap().to_string());
@@ -568,7 +664,8 @@ mod tests {
     fn it_resolves_a_reference_without_namespace() {
         let resources = FakeResources::fake();
 
-        let reference = AttributeHelper::resolve_reference(&resources, (1 << 24) | 1, "prefix");
+        let reference =
+            AttributeHelper::resolve_reference(&resources, ResourceId::new(1, 0, 1), "prefix");
 
         assert_eq!("reference#1", reference.unwrap());
     }
@@ -577,7 +674,8 @@ mod tests {
     fn it_resolves_a_reference_with_namespace() {
         let resources = FakeResources::fake();
 
-        let result = AttributeHelper::resolve_reference(&resources, (2 << 24) | 1, "prefix");
+        let result =
+            AttributeHelper::resolve_reference(&resources, ResourceId::new(2, 0, 1), "prefix");
 
         assert_eq!("NS:reference#2", result.unwrap());
     }
@@ -591,7 +689,7 @@ mod tests {
 
         let result = AttributeHelper::resolve_flags(&attribute, 567, &resc, &resources);
 
-        assert_eq!(default_flags, result.unwrap());
+        assert_eq!(default_flags, result.unwrap().unwrap());
     }
 
     #[test]
@@ -604,7 +702,7 @@ mod tests {
 
         let result = AttributeHelper::resolve_flags(&attribute, flags, &resc, &resources);
 
-        assert_eq!("left", result.unwrap());
+        assert_eq!("left", result.unwrap().unwrap());
     }
 
     #[test]
@@ -617,6 +715,6 @@ mod tests {
 
         let result = AttributeHelper::resolve_flags(&attribute, flags, &resc, &resources);
 
-        assert_eq!("left|right", result.unwrap());
+        assert_eq!("left|right", result.unwrap().unwrap());
     }
 }